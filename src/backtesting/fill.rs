@@ -0,0 +1,328 @@
+use crate::types::{Candle, ExecutedTrade, FailedTrade, FutureTrade, ReasonCode, Side, Trade};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+
+/// Seed used for random rejections when none is configured
+const DEFAULT_SEED: u64 = 0;
+
+/// Simulates how a proposed trade is filled by the market during backtesting
+pub trait FillModel {
+    /// Attempt to fill a trade
+    ///
+    /// # Arguments
+    /// * `trade` - The trade proposed on `candle`
+    /// * `candle` - The candle on which the trade decision was made
+    /// * `next_candle` - The following candle, if any
+    ///
+    /// # Returns
+    /// * `Ok` - The (possibly partially) filled trade
+    /// * `Err` - The trade was rejected
+    fn fill(
+        &mut self,
+        trade: FutureTrade,
+        candle: &Candle,
+        next_candle: Option<&Candle>,
+    ) -> Result<ExecutedTrade, FailedTrade>;
+}
+
+/// Price adjustment applied against the trader
+///
+/// Buys are filled at a higher price and sells at a lower price.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Slippage {
+    #[default]
+    None,
+    /// A fixed amount of quote currency per unit
+    Fixed { amount: Decimal },
+    /// A percentage of the price. `0.1` is 0.1%.
+    Percentage { percentage: Decimal },
+    /// A fraction of the price proportional to the share of candle volume consumed by the trade
+    ///
+    /// The price is adjusted by `factor * quantity / volume`.
+    VolumeProportional { factor: Decimal },
+}
+
+impl Slippage {
+    /// Apply slippage to a price
+    fn apply(&self, price: Decimal, side: Side, quantity: Decimal, volume: Decimal) -> Decimal {
+        let adjustment = match self {
+            Slippage::None => Decimal::ZERO,
+            Slippage::Fixed { amount } => *amount,
+            Slippage::Percentage { percentage } => price * percentage / dec!(100),
+            Slippage::VolumeProportional { factor } => {
+                if volume.is_zero() {
+                    Decimal::ZERO
+                } else {
+                    price * factor * quantity / volume
+                }
+            }
+        };
+        match side {
+            Side::Buy => price + adjustment,
+            Side::Sell => price - adjustment,
+        }
+    }
+}
+
+/// Determines at which price a trade is executed
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionPrice {
+    /// Execute at the close price of the candle the decision was made on
+    #[default]
+    Close,
+    /// Execute at the open price of the following candle
+    ///
+    /// This avoids trading at a price which was already used to make the decision.
+    NextOpen,
+}
+
+/// Configuration for [`SimulatedFill`]
+///
+/// Meant to be read from the `[fill]` section of the backtesting TOML config file. The defaults fill
+/// every trade completely at the candle close.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct FillConfig {
+    #[serde(default)]
+    pub slippage: Slippage,
+
+    #[serde(default)]
+    pub execution: ExecutionPrice,
+
+    /// Maximum fraction of the execution candle volume that a single trade may fill
+    #[serde(default)]
+    pub max_volume_fraction: Option<Decimal>,
+
+    /// Probability in the range `[0, 1]` that the market rejects a trade
+    #[serde(default)]
+    pub rejection_probability: f64,
+
+    /// Seed for random rejections so that backtests are reproducible
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// A [`FillModel`] which applies slippage, execution delay, volume caps and random rejections
+pub struct SimulatedFill {
+    config: FillConfig,
+    rng: StdRng,
+}
+
+impl SimulatedFill {
+    pub fn new(config: FillConfig) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed.unwrap_or(DEFAULT_SEED));
+        Self { config, rng }
+    }
+}
+
+impl Default for SimulatedFill {
+    fn default() -> Self {
+        Self::new(FillConfig::default())
+    }
+}
+
+impl FillModel for SimulatedFill {
+    fn fill(
+        &mut self,
+        trade: FutureTrade,
+        candle: &Candle,
+        next_candle: Option<&Candle>,
+    ) -> Result<ExecutedTrade, FailedTrade> {
+        let (price, volume, time) = match self.config.execution {
+            ExecutionPrice::Close => (candle.close, candle.volume, candle.time),
+            ExecutionPrice::NextOpen => match next_candle {
+                Some(next) => (next.open, next.volume, next.time),
+                None => {
+                    return Err(FailedTrade::with_future_trade(
+                        ReasonCode::MarketRejection,
+                        trade,
+                    ))
+                }
+            },
+        };
+
+        if self.config.rejection_probability > 0.0
+            && self
+                .rng
+                .gen_bool(self.config.rejection_probability.min(1.0))
+        {
+            return Err(FailedTrade::with_future_trade(
+                ReasonCode::MarketRejection,
+                trade,
+            ));
+        }
+
+        let quantity = match self.config.max_volume_fraction {
+            Some(fraction) => trade.get_quantity().min(volume * fraction),
+            None => trade.get_quantity(),
+        };
+        if quantity <= Decimal::ZERO {
+            return Err(FailedTrade::with_future_trade(
+                ReasonCode::MarketRejection,
+                trade,
+            ));
+        }
+
+        let side = trade.get_side();
+        let price = self.config.slippage.apply(price, side, quantity, volume);
+
+        let order_type = trade.get_order_type();
+        Ok(
+            ExecutedTrade::with_calculated_notional(time.to_string(), side, price, quantity, time)
+                .with_order_type(order_type),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDateTime};
+
+    fn create_candles() -> (Candle, Candle) {
+        let time = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let candle = Candle {
+            time,
+            open: dec!(95),
+            high: dec!(110),
+            low: dec!(90),
+            close: dec!(100),
+            volume: dec!(10),
+        };
+        let next = Candle {
+            time: time + Duration::minutes(1),
+            open: dec!(102),
+            high: dec!(110),
+            low: dec!(90),
+            close: dec!(105),
+            volume: dec!(20),
+        };
+        (candle, next)
+    }
+
+    fn create_trade(side: Side, quantity: Decimal) -> FutureTrade {
+        let (candle, _) = create_candles();
+        FutureTrade::new(side, candle.close, quantity, candle.time)
+    }
+
+    #[test]
+    fn test_default_fills_at_close() {
+        let (candle, next) = create_candles();
+        let mut model = SimulatedFill::default();
+
+        let trade = model
+            .fill(create_trade(Side::Buy, dec!(2)), &candle, Some(&next))
+            .unwrap();
+        assert_eq!(trade.get_price(), dec!(100));
+        assert_eq!(trade.get_quantity(), dec!(2));
+        assert_eq!(trade.get_notional_value(), dec!(200));
+        assert_eq!(*trade.get_timestamp(), candle.time);
+    }
+
+    #[test]
+    fn test_next_open_execution() {
+        let (candle, next) = create_candles();
+        let mut model = SimulatedFill::new(FillConfig {
+            execution: ExecutionPrice::NextOpen,
+            ..Default::default()
+        });
+
+        let trade = model
+            .fill(create_trade(Side::Buy, dec!(2)), &candle, Some(&next))
+            .unwrap();
+        assert_eq!(trade.get_price(), dec!(102));
+        assert_eq!(*trade.get_timestamp(), next.time);
+
+        // there is no next candle at the end of the data
+        let result = model.fill(create_trade(Side::Buy, dec!(2)), &candle, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_slippage() {
+        let (candle, _) = create_candles();
+
+        let price = Slippage::Fixed { amount: dec!(0.5) }.apply(
+            candle.close,
+            Side::Buy,
+            dec!(1),
+            candle.volume,
+        );
+        assert_eq!(price, dec!(100.5));
+
+        let price = Slippage::Percentage {
+            percentage: dec!(1),
+        }
+        .apply(candle.close, Side::Sell, dec!(1), candle.volume);
+        assert_eq!(price, dec!(99));
+
+        // consuming 20% of the volume with a factor of 0.1 moves the price by 2%
+        let price = Slippage::VolumeProportional { factor: dec!(0.1) }.apply(
+            candle.close,
+            Side::Buy,
+            dec!(2),
+            candle.volume,
+        );
+        assert_eq!(price, dec!(102));
+    }
+
+    #[test]
+    fn test_partial_fill() {
+        let (candle, next) = create_candles();
+        let mut model = SimulatedFill::new(FillConfig {
+            max_volume_fraction: Some(dec!(0.1)),
+            ..Default::default()
+        });
+
+        let trade = model
+            .fill(create_trade(Side::Sell, dec!(5)), &candle, Some(&next))
+            .unwrap();
+        assert_eq!(trade.get_quantity(), dec!(1));
+        assert_eq!(trade.get_notional_value(), dec!(100));
+    }
+
+    #[test]
+    fn test_random_rejections_are_reproducible() {
+        let (candle, next) = create_candles();
+        let config = FillConfig {
+            rejection_probability: 0.5,
+            seed: Some(42),
+            ..Default::default()
+        };
+
+        let run = |config: FillConfig| {
+            let mut model = SimulatedFill::new(config);
+            (0..50)
+                .map(|_| {
+                    model
+                        .fill(create_trade(Side::Buy, dec!(1)), &candle, Some(&next))
+                        .is_ok()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = run(config.clone());
+        let second = run(config);
+        assert_eq!(first, second);
+        assert!(first.iter().any(|x| *x));
+        assert!(first.iter().any(|x| !*x));
+    }
+
+    #[test]
+    fn test_always_reject() {
+        let (candle, next) = create_candles();
+        let mut model = SimulatedFill::new(FillConfig {
+            rejection_probability: 1.0,
+            ..Default::default()
+        });
+
+        let failed = model
+            .fill(create_trade(Side::Buy, dec!(1)), &candle, Some(&next))
+            .unwrap_err();
+        assert_eq!(failed.get_reason(), ReasonCode::MarketRejection);
+    }
+}
@@ -0,0 +1,681 @@
+mod fill;
+mod optimizer;
+mod report;
+mod walk_forward;
+
+pub use fill::{FillConfig, FillModel, SimulatedFill};
+pub use optimizer::{Objective, Optimizer, OptimizerError};
+pub use report::BacktestReport;
+pub use walk_forward::WalkForwardConfig;
+
+use crate::manager::{PositionManager, PositionManagerConfig, PositionManagerError, TradeDecision};
+use crate::markets::utils::save_candles;
+use crate::metrics;
+use crate::portfolio::{
+    AssetHandlers, CapitalHandlers, EquityHandlers, Portfolio, PortfolioArgs, PositionHandlers,
+    TradeHandlers,
+};
+use crate::processor::CandleProcessor;
+use crate::risk::{calculate_risk, RiskCalculationErrors, RiskConfig};
+use crate::strategies::{Strategy, StrategyConfig};
+use crate::types::{
+    candles_per_year, Candle, FailedTrade, FutureTrade, MarketData, MarketDataError, OrderType,
+    ReasonCode, Side, Signal, Trade, DB_PATH,
+};
+use crate::utils;
+use crate::utils::{
+    check_candle_alignment, closed_candles, extract_candles_from_df, frequency_to_duration,
+    print_candle_statistics, trim_candles, AlignmentError,
+};
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDateTime};
+use log::info;
+use polars::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const CANDLE_TRIM_SIZE: IdxSize = 100;
+
+/// Total configuration for backtesting
+///
+/// Meant to be read from a TOML config file
+#[derive(Deserialize, Debug)]
+pub struct BacktestingConfig {
+    pub portfolio: PortfolioArgs,
+    pub risk: PositionManagerConfig,
+    pub trading: TradingConfig,
+    #[serde(default)]
+    pub fill: FillConfig,
+    #[serde(default)]
+    pub strategy: StrategyConfig,
+}
+
+impl BacktestingConfig {
+    /// Read the configuration from a TOML file
+    pub fn from_file<P: AsRef<Path>>(config_path: P) -> Result<Self, BacktestingErrors> {
+        let config_str = std::fs::read_to_string(config_path)
+            .map_err(|e| BacktestingErrors::ConfigError(e.to_string()))?;
+        toml::from_str(&config_str).map_err(|e| BacktestingErrors::ConfigError(e.to_string()))
+    }
+}
+
+/// Contains trading config data for backtesting
+///
+/// Meant to be read from a TOML config file
+#[derive(Deserialize, Debug)]
+pub struct TradingConfig {
+    pub frequency: String,
+    pub trading_asset: String,
+    pub market_asset: String,
+
+    /// Order type used to submit trades. Backtests charge fees for this order type.
+    #[serde(default)]
+    pub order_type: OrderType,
+}
+
+#[derive(Debug)]
+pub enum BacktestingErrors {
+    APIError(String),
+    ConfigError(String),
+    CandleError(MarketDataError),
+    /// Raised when unable to extract signals from trading asset data
+    SignalExtractionError,
+    AlignmentError(AlignmentError),
+
+    RiskCalculationError(RiskCalculationErrors),
+    DecisionError(PositionManagerError),
+    /// Raised when candles or indicators cannot be written
+    SaveError(String),
+}
+
+/// Summary of a backtesting run used to compare runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestMetrics {
+    /// Mark-to-market equity at the end of the run minus equity at the start
+    pub net_profit: f64,
+    /// Annualized Sharpe ratio of per-candle equity returns
+    pub sharpe_ratio: f64,
+    /// Largest decline of equity from a previous peak. `0.12` is 12%.
+    pub max_drawdown: f64,
+    /// Number of executed trades
+    pub trades: usize,
+}
+
+impl BacktestMetrics {
+    /// Calculate metrics from the mark-to-market equity of each candle
+    ///
+    /// # Arguments
+    /// * `equity` - Equity of each candle
+    /// * `trades` - Number of executed trades
+    /// * `periods_per_year` - Number of candles in a year. Used to annualize the Sharpe ratio.
+    pub fn from_equity(equity: &[Decimal], trades: usize, periods_per_year: Decimal) -> Self {
+        let net_profit = match (equity.first(), equity.last()) {
+            (Some(first), Some(last)) => (last - first).to_f64().unwrap_or_default(),
+            _ => 0.0,
+        };
+        let returns = metrics::returns(equity);
+        let sharpe_ratio = metrics::sharpe_ratio(&returns, Decimal::ZERO, periods_per_year);
+
+        Self {
+            net_profit,
+            sharpe_ratio: sharpe_ratio.to_f64().unwrap_or_default(),
+            max_drawdown: metrics::max_drawdown(equity).to_f64().unwrap_or_default(),
+            trades,
+        }
+    }
+}
+
+pub struct BacktestingRuntime {
+    strategy: Strategy,
+    portfolio_args: PortfolioArgs,
+    manager_config: PositionManagerConfig,
+    trading_config: TradingConfig,
+
+    /// Simulates market conditions when executing trades
+    fill_model: Box<dyn FillModel>,
+
+    /// Path to the sqlite candle store
+    db_path: PathBuf,
+
+    /// Only candles within this range are used. Unbounded when `None`.
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+
+    /// Candles before this time only provide history for indicators and are not traded
+    trading_start: Option<NaiveDateTime>,
+
+    /// Mark-to-market equity of the last run
+    equity_curve: Vec<(NaiveDateTime, Decimal)>,
+
+    /// Performance of the last run
+    report: Option<BacktestReport>,
+
+    /// Log statistics and the performance report after each run
+    log_statistics: bool,
+
+    /// Global candle references
+    market_candle_data: Option<MarketData>,
+    trading_candle_data: Option<MarketData>,
+
+    /// Usable candles for market data
+    market_candles: Option<DataFrame>,
+
+    /// Usable candles for trading data
+    trading_candles: Option<DataFrame>,
+}
+
+impl BacktestingRuntime {
+    /// Create a runtime from an already parsed [`BacktestingConfig`]
+    pub fn from_backtesting_config(config: BacktestingConfig, strategy: Strategy) -> Self {
+        BacktestingRuntime {
+            portfolio_args: config.portfolio,
+            strategy,
+            manager_config: config.risk,
+            trading_config: config.trading,
+            fill_model: Box::new(SimulatedFill::new(config.fill)),
+            db_path: PathBuf::from(DB_PATH),
+            start: None,
+            end: None,
+            trading_start: None,
+            equity_curve: Vec::new(),
+            report: None,
+            log_statistics: false,
+            market_candle_data: None,
+            trading_candle_data: None,
+            market_candles: None,
+            trading_candles: None,
+        }
+    }
+
+    /// Builder method for the path of the sqlite candle store
+    pub fn with_db_path<P: Into<PathBuf>>(mut self, db_path: P) -> Self {
+        self.db_path = db_path.into();
+        self
+    }
+
+    /// Builder method for limiting the candles used to a date range
+    pub fn with_date_range(
+        mut self,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Builder method for logging statistics and the performance report after each run
+    ///
+    /// Disabled by default so that runs of an [`Optimizer`] are not logged.
+    pub fn with_statistics(mut self, log_statistics: bool) -> Self {
+        self.log_statistics = log_statistics;
+        self
+    }
+
+    /// Builder method for warming up indicators before trading begins
+    ///
+    /// Candles before `trading_start` are processed by the strategy but no trades are made and equity is not
+    /// recorded. Used to evaluate a window of candles without starting indicators from scratch.
+    pub fn with_trading_start(mut self, trading_start: Option<NaiveDateTime>) -> Self {
+        self.trading_start = trading_start;
+        self
+    }
+
+    pub fn load_candles(self) -> Result<Self, BacktestingErrors> {
+        info!("******************************************\nLoading Candles");
+        // load candle data
+        let load = |asset: &str| {
+            MarketData::from_db_path(&self.db_path, asset).map_err(BacktestingErrors::CandleError)
+        };
+        let market = load(&self.trading_config.market_asset)?;
+        let trading = load(&self.trading_config.trading_asset)?;
+
+        self.with_market_data(trading, market)
+    }
+
+    /// Use candles which have already been loaded from the candle store
+    ///
+    /// Allows several runtimes to share candles without reading the candle store again. Candles outside of
+    /// the date range are dropped.
+    ///
+    /// # Arguments
+    /// * `trading` - Candles of the trading asset
+    /// * `market` - Candles of the market asset
+    pub fn with_market_data(
+        mut self,
+        trading: MarketData,
+        market: MarketData,
+    ) -> Result<Self, BacktestingErrors> {
+        self.market_candle_data = market.with_date_range(self.start, self.end).into();
+        self.trading_candle_data = trading.with_date_range(self.start, self.end).into();
+
+        let trading_candles = self.get_trading_asset()?.to_owned();
+
+        // ensure that candles are available for every interval used by the strategy
+        for interval in self.strategy.intervals() {
+            self.get_trading_interval(interval)?;
+        }
+
+        // populate market and trading candles
+        self.trading_candles = trading_candles.into();
+        self.market_candles = self.get_market_asset()?.to_owned().into();
+
+        info!("Finished loading candles");
+
+        Ok(self)
+    }
+
+    fn get_trading_asset(&self) -> Result<&DataFrame, BacktestingErrors> {
+        if let Some(data) = self.trading_candle_data.as_ref() {
+            data.get_candles(&self.trading_config.frequency)
+                .map_err(|e| BacktestingErrors::CandleError(e))
+        } else {
+            Err(BacktestingErrors::APIError(
+                "`trading_candle_data` is None".to_string(),
+            ))
+        }
+    }
+
+    /// Get trading asset candles of an interval other than the trading frequency
+    fn get_trading_interval(&self, interval: &str) -> Result<&DataFrame, BacktestingErrors> {
+        if let Some(data) = self.trading_candle_data.as_ref() {
+            data.get_candles(&interval.to_string())
+                .map_err(BacktestingErrors::CandleError)
+        } else {
+            Err(BacktestingErrors::APIError(
+                "`trading_candle_data` is None".to_string(),
+            ))
+        }
+    }
+
+    fn get_market_asset(&self) -> Result<&DataFrame, BacktestingErrors> {
+        if let Some(data) = self.market_candle_data.as_ref() {
+            data.get_candles(&self.trading_config.frequency)
+                .map_err(|e| BacktestingErrors::CandleError(e))
+        } else {
+            Err(BacktestingErrors::APIError(
+                "`market_candle_data` is None".to_string(),
+            ))
+        }
+    }
+
+    /// Run the backtesting simulation
+    ///
+    /// # Returns
+    /// Metrics for comparing the run against other runs
+    pub fn run(&mut self) -> Result<BacktestMetrics, BacktestingErrors> {
+        // ensure that candles are set
+        if self.trading_candles.is_none() || self.market_candles.is_none() {
+            return Err(BacktestingErrors::APIError(
+                "Candle data is None".to_string(),
+            ));
+        }
+
+        // ensure that the market data and historical data are sorted by timestamp
+        info!("Checking candle data and market data alignment");
+        let _ = check_candle_alignment(
+            self.trading_candles.as_ref().unwrap(),
+            self.market_candles.as_ref().unwrap(),
+        )
+        .map_err(|e| BacktestingErrors::AlignmentError(e));
+
+        let mut portfolio = self.initialize_portfolio()?;
+
+        // initialize position manager
+        let mut position_manager = PositionManager::new(self.manager_config.clone());
+        let risk_config = RiskConfig::new(
+            &self.trading_config.frequency,
+            self.manager_config.value_at_risk.clone(),
+        )
+        .map_err(|e| BacktestingErrors::ConfigError(format!("{:?}", e)))?
+        .with_risk_free_rate(self.manager_config.risk_free_rate)
+        .with_returns_source(self.manager_config.returns_source);
+        // frequencies without a number of candles in a year were rejected by the risk config
+        let periods_per_year =
+            Decimal::from(candles_per_year(&self.trading_config.frequency).unwrap_or_default());
+        // risk metrics may use a longer window than the strategy
+        let risk_window = CANDLE_TRIM_SIZE.max(risk_config.value_at_risk.lookback as IdxSize);
+
+        let candle_rows = extract_candles_from_df(self.trading_candles.as_ref().unwrap()).unwrap();
+
+        // candles of the intervals that indicators are bound to
+        let timeframe_candles = self
+            .strategy
+            .intervals()
+            .into_iter()
+            .map(|interval| {
+                let duration = frequency_to_duration(interval).ok_or(
+                    BacktestingErrors::ConfigError(format!("Invalid interval '{}'", interval)),
+                )?;
+                let candles = self.get_trading_interval(interval)?.clone();
+                Ok((interval.to_string(), duration, candles))
+            })
+            .collect::<Result<Vec<_>, BacktestingErrors>>()?;
+
+        // begin trading simulation
+        self.strategy.reset();
+        let mut equity = Vec::with_capacity(candle_rows.len() + 1);
+        let mut exposed_candles = 0;
+        let mut first_price = None;
+        let start_time = Instant::now();
+        for (idx, candle) in candle_rows.iter().enumerate() {
+            // signals are generated from the candles preceding the current candle, so the previous candle is
+            // ingested by indicators which support incremental updates
+            let streamed_signal = idx
+                .checked_sub(1)
+                .and_then(|previous| self.strategy.process_new_candle(&candle_rows[previous]));
+
+            if self
+                .trading_start
+                .is_some_and(|trading_start| candle.time < trading_start)
+            {
+                continue;
+            }
+
+            // mark-to-market value before trading on the candle
+            equity.push((candle.time, portfolio.update_equity(candle.close)));
+            if portfolio.get_assets() > Decimal::ZERO {
+                exposed_candles += 1;
+            }
+            first_price.get_or_insert(candle.close);
+
+            let trimmed_trading_candles = trim_candles(
+                self.trading_candles.as_ref().unwrap(),
+                candle.time,
+                CANDLE_TRIM_SIZE,
+            );
+            if trimmed_trading_candles.height() == 0 {
+                continue;
+            }
+            let signal = match streamed_signal {
+                Some(signal) => signal,
+                None => {
+                    // only candles which closed before the current candle are visible
+                    let timeframes = timeframe_candles
+                        .iter()
+                        .map(|(interval, duration, candles)| {
+                            let closed =
+                                closed_candles(candles, candle.time, *duration, CANDLE_TRIM_SIZE);
+                            (interval.clone(), closed)
+                        })
+                        .collect();
+                    self.strategy
+                        .process_timeframes(&trimmed_trading_candles, &timeframes)
+                }
+            }
+            .map_err(|_| BacktestingErrors::SignalExtractionError)?;
+
+            let trimmed_candles = if risk_window == CANDLE_TRIM_SIZE {
+                extract_candles_from_df(&trimmed_trading_candles).unwrap()
+            } else {
+                let risk_candles = trim_candles(
+                    self.trading_candles.as_ref().unwrap(),
+                    candle.time,
+                    risk_window,
+                );
+                extract_candles_from_df(&risk_candles).unwrap()
+            };
+
+            // trim market data
+            let trimmed_market = trim_candles(
+                &self.market_candles.as_ref().unwrap(),
+                candle.time,
+                risk_window,
+            );
+            let trimmed_market = extract_candles_from_df(&trimmed_market).unwrap();
+
+            // calculate current portfolio risk metrics
+            let risk = calculate_risk(&portfolio, &trimmed_market, &trimmed_candles, &risk_config)
+                .map_err(|e| {
+                    info!("Error calculating risk: {:?}", e);
+                    BacktestingErrors::RiskCalculationError(e)
+                })?;
+
+            let current_price = candle.close;
+
+            // make decision based on risk, signals and current market conditions
+            let decision = position_manager
+                .make_decision(&mut portfolio, &risk, &signal, current_price, candle.time)
+                .map_err(|e| {
+                    info!("Error making decision: {:?}", e);
+                    BacktestingErrors::DecisionError(e)
+                })?;
+
+            let trade = match decision {
+                TradeDecision::ExecuteBuy(quantity) => {
+                    FutureTrade::new(Side::Buy, current_price, quantity, candle.time)
+                }
+                TradeDecision::ExecuteSell(quantity, trade_ids) => {
+                    info!("Closing positions: {:?}", trade_ids);
+                    FutureTrade::new(Side::Sell, current_price, quantity, candle.time)
+                }
+                TradeDecision::DoNothing => continue,
+            }
+            .with_order_type(self.trading_config.order_type);
+
+            // attempt trades
+            match self
+                .fill_model
+                .fill(trade.clone(), candle, candle_rows.get(idx + 1))
+            {
+                Ok(executed) => {
+                    let cost = portfolio.cost_including_fee(
+                        executed.get_notional_value(),
+                        Side::Buy,
+                        executed.get_order_type(),
+                    );
+                    if executed.get_side() == Side::Buy && cost > portfolio.available_capital() {
+                        info!("Insufficient capital to fill buy: {:?}", executed);
+                        portfolio.add_failed_trade(FailedTrade::with_future_trade(
+                            ReasonCode::InsufficientFunds,
+                            trade,
+                        ));
+                    } else {
+                        portfolio.add_executed_trade(executed);
+                    }
+                }
+                Err(failed) => {
+                    info!("Trade rejected by market: {:?}", failed.get_reason());
+                    portfolio.add_failed_trade(failed);
+                }
+            }
+        }
+        let elapsed = start_time.elapsed();
+
+        // the final sample is the value after trading on the last candle
+        let candle_duration = frequency_to_duration(&self.trading_config.frequency)
+            .and_then(|duration| ChronoDuration::from_std(duration).ok())
+            .unwrap_or_default();
+        self.report = None;
+        if let (Some(candle), Some(first_price)) = (candle_rows.last(), first_price) {
+            equity.push((
+                candle.time + candle_duration,
+                portfolio.available_capital() + portfolio.get_assets() * candle.close,
+            ));
+            self.report = BacktestReport::new(
+                &equity,
+                &portfolio,
+                exposed_candles,
+                (first_price, candle.close),
+                periods_per_year,
+            );
+        }
+
+        if self.log_statistics {
+            self.print_statistics(elapsed, &portfolio);
+        }
+
+        let values = equity.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+        self.equity_curve = equity;
+        Ok(BacktestMetrics::from_equity(
+            &values,
+            portfolio.get_executed_trades().len(),
+            periods_per_year,
+        ))
+    }
+
+    /// Time and mark-to-market equity of every traded candle of the last run
+    ///
+    /// Each sample is the value of the portfolio before trading on the candle. The final sample is the value after
+    /// trading on the last candle and is timed at its close.
+    pub fn equity_curve(&self) -> &[(NaiveDateTime, Decimal)] {
+        &self.equity_curve
+    }
+
+    /// Create a portfolio from the [`PortfolioArgs`]
+    ///
+    /// # Arguments
+    /// * `candles` - The historical candles used to initialize the portfolio. Used to extract the starting time.
+    fn initialize_portfolio(&self) -> Result<Portfolio, BacktestingErrors> {
+        if let Some(candles) = self.trading_candles.as_ref() {
+            let start_time = candles
+                .column("time")
+                .unwrap()
+                .datetime()
+                .unwrap()
+                .get(0)
+                .unwrap();
+            let start_time = DateTime::from_timestamp_millis(start_time)
+                .unwrap()
+                .naive_utc();
+            Ok(Portfolio::from_args(&self.portfolio_args, start_time))
+        } else {
+            Err(BacktestingErrors::APIError(
+                "Trading candles are None".to_string(),
+            ))
+        }
+    }
+
+    /// Print statistics about the backtesting run
+    ///
+    /// # Arguments
+    /// * `candles` - Only candle length is used, so any candle [`DataFrame`] can be passed.
+    /// * `duration` - The duration of the backtesting run
+    /// * `portfolio` - The portfolio after the backtesting run
+    fn print_statistics(&self, duration: Duration, portfolio: &Portfolio) {
+        info!("******************************************\nBacktesting Statistics");
+        // print basic statistics
+        print_portfolio(portfolio, self.portfolio_args.capital);
+        if let Some(report) = self.report.as_ref() {
+            info!("Performance report:\n{}", report);
+        }
+
+        let candles = self.trading_candles.as_ref().unwrap();
+
+        print_candle_statistics(candles);
+
+        let candle_len = candles.height();
+        info!(
+            r#"Finished processing {:?} rows in {:?}
+Avg. processing time per row: {:?}"#,
+            candle_len,
+            duration,
+            duration / candle_len as u32
+        );
+    }
+
+    /// Save candles and indicators as CSV
+    ///
+    /// # Arguments
+    /// * `path` - The directory to save the data
+    pub fn save_data<P: Into<PathBuf>>(&mut self, path: P) -> Result<(), BacktestingErrors> {
+        let path = path.into();
+        let save_error = |e: &dyn std::fmt::Display| BacktestingErrors::SaveError(e.to_string());
+
+        // check that the path is not a file, and exists
+        if path.is_file() {
+            return Err(BacktestingErrors::SaveError(
+                "Path is a file, expected a directory".to_string(),
+            ));
+        } else if !path.exists() {
+            std::fs::create_dir_all(&path).map_err(|e| save_error(&e))?;
+        }
+
+        let (trading_candles, market_candles) =
+            match (self.trading_candles.as_mut(), self.market_candles.as_mut()) {
+                (Some(trading), Some(market)) => (trading, market),
+                _ => {
+                    return Err(BacktestingErrors::APIError(
+                        "Candle data is None".to_string(),
+                    ))
+                }
+            };
+
+        // save trading assets
+        let filename = format!(
+            "{}_{}.csv",
+            self.trading_config.trading_asset, self.trading_config.frequency
+        );
+        let trading_candles_path = path.join(filename);
+        save_candles(trading_candles, trading_candles_path.to_str().unwrap())
+            .map_err(|e| save_error(&e))?;
+
+        // save market data
+        let filename = format!(
+            "{}_{}.csv",
+            self.trading_config.market_asset, self.trading_config.frequency
+        );
+        let market_candles_path = path.join(filename);
+        save_candles(market_candles, market_candles_path.to_str().unwrap())
+            .map_err(|e| save_error(&e))?;
+
+        // save performance report
+        if let Some(report) = self.report.as_ref() {
+            let json = report.to_json().map_err(|e| save_error(&e))?;
+            std::fs::write(path.join("report.json"), json).map_err(|e| save_error(&e))?;
+        }
+
+        // save indicators
+        self.strategy
+            .save_indicators(self.trading_candles.as_ref().unwrap(), path);
+        Ok(())
+    }
+}
+
+pub fn print_portfolio(portfolio: &Portfolio, starting_capital: Decimal) {
+    info!(
+        r#"Number of open positions: {}
+Total open quantity: {}
+Total open value: {}
+Total executed positions: {}
+Total failed trades: {}
+Fees paid: {}
+Profit: {}"#,
+        portfolio.get_open_positions().len(),
+        portfolio.total_open_quantity(),
+        portfolio.total_position_value(),
+        portfolio.get_executed_trades().len(),
+        portfolio.get_failed_trades().len(),
+        portfolio.total_fees(),
+        portfolio.available_capital() - starting_capital
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_metrics_from_equity() {
+        let equity = [dec!(100), dec!(110), dec!(99), dec!(120)];
+        let metrics = BacktestMetrics::from_equity(&equity, 3, dec!(1));
+
+        assert_eq!(metrics.net_profit, 20.0);
+        assert_eq!(metrics.trades, 3);
+        // equity fell from 110 to 99
+        assert!((metrics.max_drawdown - 0.1).abs() < 1e-9);
+        assert!(metrics.sharpe_ratio > 0.0);
+
+        // the Sharpe ratio is annualized
+        let annualized = BacktestMetrics::from_equity(&equity, 3, dec!(4));
+        assert!((annualized.sharpe_ratio - metrics.sharpe_ratio * 2.0).abs() < 1e-9);
+
+        let metrics = BacktestMetrics::from_equity(&[dec!(100)], 0, dec!(1));
+        assert_eq!(metrics.net_profit, 0.0);
+        assert_eq!(metrics.sharpe_ratio, 0.0);
+        assert_eq!(metrics.max_drawdown, 0.0);
+    }
+}
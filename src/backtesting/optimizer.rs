@@ -0,0 +1,944 @@
+use crate::backtesting::{
+    BacktestMetrics, BacktestingConfig, BacktestingRuntime, WalkForwardConfig,
+};
+use crate::markets::utils::save_candles;
+use crate::types::{MarketData, DB_PATH};
+use chrono::NaiveDateTime;
+use log::info;
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use thiserror::Error;
+use toml::Value;
+
+/// Seed used for random search when none is configured
+const DEFAULT_SEED: u64 = 42;
+
+/// Name of the config file section which configures the optimizer
+const OPTIMIZE_SECTION: &str = "optimize";
+
+#[derive(Error, Debug)]
+pub enum OptimizerError {
+    #[error("Invalid config: {0}")]
+    Config(String),
+    #[error("No parameters to optimize")]
+    NoParameters,
+    #[error("Invalid parameter '{path}': {reason}")]
+    InvalidParameter { path: String, reason: String },
+    #[error("Could not load candles: {0}")]
+    Candles(String),
+    #[error("Could not save results: {0}")]
+    Save(String),
+}
+
+/// How parameter combinations are chosen
+///
+/// In TOML, `search = "grid"` or `search = { random = { samples = 50, seed = 7 } }`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMethod {
+    /// Every combination of parameter values
+    #[default]
+    Grid,
+    /// A fixed number of randomly sampled combinations
+    Random {
+        samples: usize,
+        /// Seed so that searches are reproducible
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+}
+
+/// Metric used to rank backtesting runs
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    /// Highest net profit
+    #[default]
+    NetProfit,
+    /// Highest Sharpe ratio
+    SharpeRatio,
+    /// Lowest maximum drawdown
+    MaxDrawdown,
+}
+
+impl Objective {
+    /// Value of the metric
+    pub fn value(&self, metrics: &BacktestMetrics) -> f64 {
+        match self {
+            Objective::NetProfit => metrics.net_profit,
+            Objective::SharpeRatio => metrics.sharpe_ratio,
+            Objective::MaxDrawdown => metrics.max_drawdown,
+        }
+    }
+
+    /// Score of a run where a higher score is better
+    pub fn score(&self, metrics: &BacktestMetrics) -> f64 {
+        match self {
+            Objective::MaxDrawdown => -self.value(metrics),
+            _ => self.value(metrics),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Objective::NetProfit => "net_profit",
+            Objective::SharpeRatio => "sharpe_ratio",
+            Objective::MaxDrawdown => "max_drawdown",
+        }
+    }
+}
+
+/// Values searched for a single parameter
+///
+/// Either a list of `values`, or a range from `start` to `end` (inclusive). Grid search requires a `step` for
+/// ranges, while random search samples ranges without a `step` uniformly. Integer bounds produce integer
+/// values.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ParameterRange {
+    /// Dotted path of the parameter in the backtesting config (ie: "risk.stop_loss_percentage" or
+    /// "strategy.indicators.0.period")
+    pub path: String,
+    pub values: Option<Vec<Value>>,
+    pub start: Option<Value>,
+    pub end: Option<Value>,
+    pub step: Option<Value>,
+}
+
+/// Draws values of a [`ParameterRange`] for random search
+enum Sampler {
+    Choice(Vec<Value>),
+    Integer(i64, i64),
+    Float(f64, f64),
+}
+
+impl Sampler {
+    fn sample(&self, rng: &mut StdRng) -> Value {
+        match self {
+            Sampler::Choice(values) => values[rng.gen_range(0..values.len())].clone(),
+            Sampler::Integer(start, end) => Value::Integer(rng.gen_range(*start..=*end)),
+            Sampler::Float(start, end) => Value::Float(rng.gen_range(*start..=*end)),
+        }
+    }
+}
+
+impl ParameterRange {
+    /// Create a range which searches the given values
+    #[cfg(test)]
+    pub fn values<S: Into<String>>(path: S, values: Vec<Value>) -> Self {
+        Self {
+            path: path.into(),
+            values: Some(values),
+            start: None,
+            end: None,
+            step: None,
+        }
+    }
+
+    /// Create a range from `start` to `end` (inclusive) in increments of `step`
+    #[cfg(test)]
+    pub fn range<S, V>(path: S, start: V, end: V, step: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Value>,
+    {
+        Self {
+            path: path.into(),
+            values: None,
+            start: Some(start.into()),
+            end: Some(end.into()),
+            step: Some(step.into()),
+        }
+    }
+
+    fn invalid(&self, reason: &str) -> OptimizerError {
+        OptimizerError::InvalidParameter {
+            path: self.path.clone(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Start and end of the range and whether both are integers
+    fn bounds(&self) -> Result<(f64, f64, bool), OptimizerError> {
+        let (Some(start), Some(end)) = (&self.start, &self.end) else {
+            return Err(self.invalid("either `values` or `start` and `end` are required"));
+        };
+        let integer = start.is_integer() && end.is_integer();
+        let (Some(start), Some(end)) = (as_number(start), as_number(end)) else {
+            return Err(self.invalid("`start` and `end` must be numbers"));
+        };
+        if start > end {
+            return Err(self.invalid("`start` must not be greater than `end`"));
+        }
+        Ok((start, end, integer))
+    }
+
+    /// Every value searched by a grid search
+    fn grid_values(&self) -> Result<Vec<Value>, OptimizerError> {
+        if let Some(values) = &self.values {
+            if self.start.is_some() || self.end.is_some() || self.step.is_some() {
+                return Err(self.invalid("`values` cannot be combined with a range"));
+            }
+            if values.is_empty() {
+                return Err(self.invalid("`values` must not be empty"));
+            }
+            return Ok(values.clone());
+        }
+
+        let (start, end, integer) = self.bounds()?;
+        let Some(step) = &self.step else {
+            return Err(self.invalid("`step` is required for grid search"));
+        };
+        let integer = integer && step.is_integer();
+        let step = as_number(step)
+            .filter(|step| *step > 0.0)
+            .ok_or_else(|| self.invalid("`step` must be a number greater than 0"))?;
+
+        // a small tolerance includes `end` despite floating point error
+        let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+        Ok((0..count)
+            .map(|i| {
+                let value = start + step * i as f64;
+                if integer {
+                    Value::Integer(value.round() as i64)
+                } else {
+                    Value::Float((value * 1e10).round() / 1e10)
+                }
+            })
+            .collect())
+    }
+
+    fn sampler(&self) -> Result<Sampler, OptimizerError> {
+        if self.values.is_some() || self.step.is_some() {
+            return Ok(Sampler::Choice(self.grid_values()?));
+        }
+        let (start, end, integer) = self.bounds()?;
+        if integer {
+            Ok(Sampler::Integer(start as i64, end as i64))
+        } else {
+            Ok(Sampler::Float(start, end))
+        }
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(value) => Some(*value as f64),
+        Value::Float(value) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Set the value at a dotted path of a TOML document
+///
+/// Missing tables are created, but array elements must already exist.
+fn set_path(document: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    let mut segments = path.split('.').peekable();
+    let mut current = document;
+    while let Some(segment) = segments.next() {
+        let last = segments.peek().is_none();
+        current = match current {
+            Value::Table(table) => {
+                if last {
+                    table.insert(segment.to_string(), value);
+                    return Ok(());
+                }
+                table
+                    .entry(segment)
+                    .or_insert_with(|| Value::Table(Default::default()))
+            }
+            Value::Array(array) => {
+                let element = segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| array.get_mut(index))
+                    .ok_or(format!("'{}' is not an element of the array", segment))?;
+                if last {
+                    *element = value;
+                    return Ok(());
+                }
+                element
+            }
+            _ => return Err(format!("'{}' is not a table or an array", segment)),
+        };
+    }
+    Err("path is empty".to_string())
+}
+
+/// Configuration of a parameter search
+///
+/// Meant to be read from the `[optimize]` section of the backtesting config file:
+///
+/// ```toml
+/// [optimize]
+/// search = { random = { samples = 50, seed = 7 } }
+/// objective = "sharpe_ratio"
+/// walk_forward = { rolling = { in_sample = 1000, out_of_sample = 250 } }
+///
+/// [[optimize.parameters]]
+/// path = "strategy.indicators.0.period"
+/// start = 10
+/// end = 30
+/// step = 5
+///
+/// [[optimize.parameters]]
+/// path = "risk.stop_loss_percentage"
+/// values = [0.5, 1.0, 2.0]
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OptimizerConfig {
+    #[serde(default)]
+    pub search: SearchMethod,
+    #[serde(default)]
+    pub objective: Objective,
+    /// Number of backtests run in parallel. Defaults to the number of available CPUs.
+    pub threads: Option<usize>,
+    pub parameters: Vec<ParameterRange>,
+    /// Windows used by [`Optimizer::walk_forward`]
+    pub walk_forward: Option<WalkForwardConfig>,
+}
+
+/// A single backtesting run of an [`Optimizer`]
+#[derive(Debug, Clone)]
+pub struct OptimizationRun {
+    /// Parameter paths and the values used for the run
+    pub parameters: Vec<(String, Value)>,
+    /// Metrics of the run, or the reason the run failed
+    pub metrics: Result<BacktestMetrics, String>,
+}
+
+/// Runs of an [`Optimizer`] ranked by the objective
+///
+/// Failed runs are ranked last.
+#[derive(Debug)]
+pub struct OptimizationResults {
+    objective: Objective,
+    paths: Vec<String>,
+    runs: Vec<OptimizationRun>,
+}
+
+impl OptimizationResults {
+    pub fn objective(&self) -> Objective {
+        self.objective
+    }
+
+    /// The best run which did not fail
+    pub fn best(&self) -> Option<&OptimizationRun> {
+        self.runs.first().filter(|run| run.metrics.is_ok())
+    }
+
+    /// Table of ranked runs with a column for every parameter and metric
+    pub fn to_dataframe(&self) -> PolarsResult<DataFrame> {
+        let mut columns = vec![Series::new(
+            "rank",
+            (1..=self.runs.len() as u32).collect::<Vec<_>>(),
+        )];
+        for (idx, path) in self.paths.iter().enumerate() {
+            let values = self
+                .runs
+                .iter()
+                .map(|run| match &run.parameters[idx].1 {
+                    Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                })
+                .collect::<Vec<_>>();
+            columns.push(Series::new(path, values));
+        }
+
+        let metric = |f: fn(&BacktestMetrics) -> f64| {
+            self.runs
+                .iter()
+                .map(|run| run.metrics.as_ref().ok().map(f))
+                .collect::<Vec<_>>()
+        };
+        columns.push(Series::new("net_profit", metric(|m| m.net_profit)));
+        columns.push(Series::new("sharpe_ratio", metric(|m| m.sharpe_ratio)));
+        columns.push(Series::new("max_drawdown", metric(|m| m.max_drawdown)));
+        columns.push(Series::new(
+            "trades",
+            self.runs
+                .iter()
+                .map(|run| run.metrics.as_ref().ok().map(|m| m.trades as u64))
+                .collect::<Vec<_>>(),
+        ));
+        columns.push(Series::new(
+            "error",
+            self.runs
+                .iter()
+                .map(|run| run.metrics.as_ref().err().cloned())
+                .collect::<Vec<_>>(),
+        ));
+
+        DataFrame::new(columns)
+    }
+
+    /// Write the ranked runs as CSV
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), OptimizerError> {
+        let path = path.as_ref();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent).map_err(|e| OptimizerError::Save(e.to_string()))?;
+        }
+        let path = path
+            .to_str()
+            .ok_or(OptimizerError::Save("Invalid path".to_string()))?;
+
+        let mut df = self
+            .to_dataframe()
+            .map_err(|e| OptimizerError::Save(e.to_string()))?;
+        save_candles(&mut df, path).map_err(|e| OptimizerError::Save(e.to_string()))
+    }
+}
+
+/// A combination of parameter values applied to the backtesting config
+pub(super) struct Combination {
+    parameters: Vec<(String, Value)>,
+    document: Value,
+}
+
+/// Searches parameters of a backtesting config for the best [`Objective`]
+///
+/// Every combination of parameter values is applied to the backtesting config and run by its own
+/// [`BacktestingRuntime`]. Candles are loaded once and shared by all runs, which are spread across threads.
+pub struct Optimizer {
+    /// The backtesting config file as a TOML document
+    pub(super) document: Value,
+    pub(super) config: OptimizerConfig,
+
+    /// Path to the sqlite candle store
+    db_path: PathBuf,
+
+    /// Only candles within this range are used. Unbounded when `None`.
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+
+    /// Candles of the trading and market assets. Loaded from the candle store when `None`.
+    market_data: Option<(MarketData, MarketData)>,
+}
+
+impl Optimizer {
+    /// Create an optimizer for a backtesting config document
+    ///
+    /// # Arguments
+    /// * `document` - The backtesting config as a TOML document
+    /// * `config` - The parameters to search
+    pub fn new(document: Value, config: OptimizerConfig) -> Self {
+        Self {
+            document,
+            config,
+            db_path: PathBuf::from(DB_PATH),
+            start: None,
+            end: None,
+            market_data: None,
+        }
+    }
+
+    /// Read the backtesting config and the `[optimize]` section from a TOML file
+    pub fn from_file<P: AsRef<Path>>(config_path: P) -> Result<Self, OptimizerError> {
+        let config_str = std::fs::read_to_string(config_path)
+            .map_err(|e| OptimizerError::Config(e.to_string()))?;
+        let mut document = config_str
+            .parse::<toml::Table>()
+            .map_err(|e| OptimizerError::Config(e.to_string()))?;
+
+        let config = document
+            .remove(OPTIMIZE_SECTION)
+            .ok_or(OptimizerError::Config(format!(
+                "missing [{}] section",
+                OPTIMIZE_SECTION
+            )))?
+            .try_into::<OptimizerConfig>()
+            .map_err(|e| OptimizerError::Config(e.to_string()))?;
+
+        Ok(Self::new(Value::Table(document), config))
+    }
+
+    /// Builder method for the path of the sqlite candle store
+    pub fn with_db_path<P: Into<PathBuf>>(mut self, db_path: P) -> Self {
+        self.db_path = db_path.into();
+        self
+    }
+
+    /// Builder method for limiting the candles used to a date range
+    pub fn with_date_range(
+        mut self,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Use candles which have already been loaded instead of reading the candle store
+    #[cfg(test)]
+    pub fn with_market_data(mut self, trading: MarketData, market: MarketData) -> Self {
+        self.market_data = Some((trading, market));
+        self
+    }
+
+    /// Set a value of the backtesting config which is used by every run
+    pub fn set<V: Into<Value>>(&mut self, path: &str, value: V) -> Result<(), OptimizerError> {
+        set_path(&mut self.document, path, value.into()).map_err(|reason| {
+            OptimizerError::InvalidParameter {
+                path: path.to_string(),
+                reason,
+            }
+        })
+    }
+
+    /// Paths of the searched parameters
+    pub(super) fn paths(&self) -> Vec<String> {
+        self.config
+            .parameters
+            .iter()
+            .map(|parameter| parameter.path.clone())
+            .collect()
+    }
+
+    /// Parameter values of every run
+    fn parameter_values(&self) -> Result<Vec<Vec<Value>>, OptimizerError> {
+        let parameters = &self.config.parameters;
+        if parameters.is_empty() {
+            return Err(OptimizerError::NoParameters);
+        }
+
+        match &self.config.search {
+            SearchMethod::Grid => {
+                let mut combinations = vec![vec![]];
+                for parameter in parameters.iter() {
+                    let values = parameter.grid_values()?;
+                    combinations = combinations
+                        .into_iter()
+                        .flat_map(|combination| {
+                            values.iter().map(move |value| {
+                                let mut combination = combination.clone();
+                                combination.push(value.clone());
+                                combination
+                            })
+                        })
+                        .collect();
+                }
+                Ok(combinations)
+            }
+            SearchMethod::Random { samples, seed } => {
+                let samplers = parameters
+                    .iter()
+                    .map(|parameter| parameter.sampler())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut rng = StdRng::seed_from_u64(seed.unwrap_or(DEFAULT_SEED));
+                Ok((0..*samples)
+                    .map(|_| {
+                        samplers
+                            .iter()
+                            .map(|sampler| sampler.sample(&mut rng))
+                            .collect()
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Apply the parameter values of every run to the backtesting config
+    pub(super) fn combinations(&self) -> Result<Vec<Combination>, OptimizerError> {
+        let paths = self.paths();
+
+        self.parameter_values()?
+            .into_iter()
+            .map(|values| {
+                let parameters = paths.iter().cloned().zip(values).collect::<Vec<_>>();
+                Ok(Combination {
+                    document: self.document_with(&parameters)?,
+                    parameters,
+                })
+            })
+            .collect()
+    }
+
+    /// The backtesting config with the given parameter values applied
+    pub(super) fn document_with(
+        &self,
+        parameters: &[(String, Value)],
+    ) -> Result<Value, OptimizerError> {
+        let mut document = self.document.clone();
+        for (path, value) in parameters.iter() {
+            set_path(&mut document, path, value.clone()).map_err(|reason| {
+                OptimizerError::InvalidParameter {
+                    path: path.clone(),
+                    reason,
+                }
+            })?;
+        }
+        Ok(document)
+    }
+
+    /// The backtesting config without any searched parameters applied
+    pub(super) fn backtesting_config(&self) -> Result<BacktestingConfig, OptimizerError> {
+        self.document
+            .clone()
+            .try_into::<BacktestingConfig>()
+            .map_err(|e| OptimizerError::Config(e.to_string()))
+    }
+
+    /// Load candles of the trading and market assets of the backtesting config
+    pub(super) fn load_market_data(&self) -> Result<(MarketData, MarketData), OptimizerError> {
+        let (trading, market) = match &self.market_data {
+            Some(data) => data.clone(),
+            None => {
+                let config = self.backtesting_config()?;
+                let load = |asset: &str| {
+                    MarketData::from_db_path(&self.db_path, asset)
+                        .map_err(|e| OptimizerError::Candles(format!("{:?}", e)))
+                };
+                (
+                    load(&config.trading.trading_asset)?,
+                    load(&config.trading.market_asset)?,
+                )
+            }
+        };
+        Ok((
+            trading.with_date_range(self.start, self.end),
+            market.with_date_range(self.start, self.end),
+        ))
+    }
+
+    /// Run a backtest with the given backtesting config document
+    ///
+    /// # Returns
+    /// Metrics and the equity curve of the run, or the reason the run failed
+    pub(super) fn backtest(
+        document: Value,
+        trading: &MarketData,
+        market: &MarketData,
+        trading_start: Option<NaiveDateTime>,
+    ) -> Result<(BacktestMetrics, Vec<(NaiveDateTime, Decimal)>), String> {
+        let config = document
+            .try_into::<BacktestingConfig>()
+            .map_err(|e| e.to_string())?;
+        let strategy = config.strategy.build().map_err(|e| e.to_string())?;
+        let mut runtime = BacktestingRuntime::from_backtesting_config(config, strategy)
+            .with_trading_start(trading_start)
+            .with_market_data(trading.clone(), market.clone())
+            .map_err(|e| format!("{:?}", e))?;
+        let metrics = runtime.run().map_err(|e| format!("{:?}", e))?;
+        Ok((metrics, runtime.equity_curve().to_vec()))
+    }
+
+    /// Run a backtest for every combination of parameter values
+    ///
+    /// Runs which fail, such as when a combination of parameters is invalid, are recorded in the results
+    /// instead of stopping the search.
+    pub fn run(&self) -> Result<OptimizationResults, OptimizerError> {
+        let combinations = self.combinations()?;
+        let (trading, market) = self.load_market_data()?;
+        Ok(self.search(&combinations, &trading, &market, None))
+    }
+
+    /// Backtest every combination on the given candles and rank the runs
+    ///
+    /// # Arguments
+    /// * `combinations` - Parameter values applied to the backtesting config
+    /// * `trading` - Candles of the trading asset
+    /// * `market` - Candles of the market asset
+    /// * `trading_start` - Earlier candles only warm up indicators. See [`BacktestingRuntime::with_trading_start`].
+    pub(super) fn search(
+        &self,
+        combinations: &[Combination],
+        trading: &MarketData,
+        market: &MarketData,
+        trading_start: Option<NaiveDateTime>,
+    ) -> OptimizationResults {
+        let threads = self
+            .config
+            .threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, combinations.len().max(1));
+        info!(
+            "Running {} backtests on {} threads",
+            combinations.len(),
+            threads
+        );
+
+        let next = AtomicUsize::new(0);
+        let finished = Mutex::new(Vec::with_capacity(combinations.len()));
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(combination) = combinations.get(idx) else {
+                        break;
+                    };
+                    let metrics = Self::backtest(
+                        combination.document.clone(),
+                        trading,
+                        market,
+                        trading_start,
+                    )
+                    .map(|(metrics, _)| metrics);
+                    finished.lock().unwrap().push((idx, metrics));
+                });
+            }
+        });
+
+        // sort by index first so that ties keep the order of the combinations
+        let mut finished = finished.into_inner().unwrap();
+        finished.sort_by_key(|(idx, _)| *idx);
+        let objective = self.config.objective;
+        finished.sort_by(|(_, a), (_, b)| {
+            let score = |metrics: &Result<BacktestMetrics, String>| {
+                metrics
+                    .as_ref()
+                    .ok()
+                    .map(|metrics| objective.score(metrics))
+                    .filter(|score| !score.is_nan())
+                    .unwrap_or(f64::NEG_INFINITY)
+            };
+            score(b).total_cmp(&score(a))
+        });
+
+        let runs = finished
+            .into_iter()
+            .map(|(idx, metrics)| OptimizationRun {
+                parameters: combinations[idx].parameters.clone(),
+                metrics,
+            })
+            .collect();
+
+        OptimizationResults {
+            objective,
+            paths: self.paths(),
+            runs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+    use crate::utils::create_temp_dir;
+    use std::collections::HashMap;
+
+    const CONFIG: &str = r#"
+        [portfolio]
+        assets = 0.0
+        capital = 1000.0
+        threshold = 10.0
+
+        [risk]
+        max_position_size = 50.0
+        stop_loss_percentage = 1.0
+        take_profit_percentage = 1.0
+        max_beta = 10.0
+        var_limit = 100.0
+        min_sharpe_ratio = -100.0
+        unrealized_pnl_limit = 100.0
+
+        [trading]
+        frequency = "1m"
+        trading_asset = "BTC-USD"
+        market_asset = "ETH-USD"
+
+        [[strategy.indicators]]
+        name = "rsi"
+        period = 14
+
+        [optimize]
+        objective = "net_profit"
+
+        [[optimize.parameters]]
+        path = "strategy.indicators.0.period"
+        values = [0, 3, 5]
+
+        [[optimize.parameters]]
+        path = "risk.stop_loss_percentage"
+        start = 0.5
+        end = 1.0
+        step = 0.5
+    "#;
+
+    fn market_data(asset_name: &str) -> MarketData {
+        let closes = (0..200)
+            .map(|i| 100.0 + 10.0 * (i as f64 / 8.0).sin())
+            .collect::<Vec<_>>();
+        MarketData {
+            asset_name: asset_name.to_string(),
+            candles: HashMap::from([("1m".to_string(), create_candles(&closes))]),
+        }
+    }
+
+    fn optimizer() -> Optimizer {
+        let mut document = CONFIG.parse::<toml::Table>().unwrap();
+        let config = document
+            .remove(OPTIMIZE_SECTION)
+            .unwrap()
+            .try_into::<OptimizerConfig>()
+            .unwrap();
+        Optimizer::new(Value::Table(document), config)
+            .with_market_data(market_data("BTC-USD"), market_data("ETH-USD"))
+    }
+
+    #[test]
+    fn test_grid_values() {
+        let range = ParameterRange::range("period", 10, 20, 5);
+        assert_eq!(
+            range.grid_values().unwrap(),
+            vec![Value::Integer(10), Value::Integer(15), Value::Integer(20)]
+        );
+
+        // floating point error does not exclude the end
+        let range = ParameterRange::range("multiplier", 0.1, 0.3, 0.1);
+        assert_eq!(
+            range.grid_values().unwrap(),
+            vec![Value::Float(0.1), Value::Float(0.2), Value::Float(0.3)]
+        );
+
+        let range = ParameterRange::values("name", vec![Value::from("rsi")]);
+        assert_eq!(range.grid_values().unwrap(), vec![Value::from("rsi")]);
+
+        let invalid = |range: ParameterRange| {
+            matches!(
+                range.grid_values(),
+                Err(OptimizerError::InvalidParameter { .. })
+            )
+        };
+        assert!(invalid(ParameterRange::range("period", 10, 20, 0)));
+        assert!(invalid(ParameterRange::range("period", 20, 10, 5)));
+        assert!(invalid(ParameterRange::values("period", vec![])));
+        assert!(invalid(ParameterRange {
+            step: None,
+            ..ParameterRange::range("period", 10, 20, 5)
+        }));
+    }
+
+    #[test]
+    fn test_set_path() {
+        let mut document = Value::Table(CONFIG.parse::<toml::Table>().unwrap());
+
+        set_path(
+            &mut document,
+            "strategy.indicators.0.period",
+            Value::Integer(7),
+        )
+        .unwrap();
+        assert_eq!(
+            document["strategy"]["indicators"][0]["period"].as_integer(),
+            Some(7)
+        );
+
+        // missing tables are created
+        set_path(&mut document, "fill.slippage", Value::Float(0.1)).unwrap();
+        assert_eq!(document["fill"]["slippage"].as_float(), Some(0.1));
+
+        assert!(set_path(
+            &mut document,
+            "strategy.indicators.1.period",
+            Value::Integer(7)
+        )
+        .is_err());
+        assert!(set_path(&mut document, "trading.frequency.value", Value::Integer(7)).is_err());
+    }
+
+    #[test]
+    fn test_parameter_values() {
+        let mut optimizer = optimizer();
+        let values = optimizer.parameter_values().unwrap();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values[0], vec![Value::Integer(0), Value::Float(0.5)]);
+        assert_eq!(values[5], vec![Value::Integer(5), Value::Float(1.0)]);
+
+        optimizer.config.search = SearchMethod::Random {
+            samples: 10,
+            seed: Some(7),
+        };
+        optimizer.config.parameters[1].step = None;
+        let values = optimizer.parameter_values().unwrap();
+        assert_eq!(values.len(), 10);
+        assert_eq!(values, optimizer.parameter_values().unwrap());
+        for combination in values.iter() {
+            assert!([0, 3, 5].contains(&combination[0].as_integer().unwrap()));
+            let stop_loss = combination[1].as_float().unwrap();
+            assert!((0.5..=1.0).contains(&stop_loss));
+        }
+
+        optimizer.config.parameters.clear();
+        assert!(matches!(
+            optimizer.parameter_values(),
+            Err(OptimizerError::NoParameters)
+        ));
+    }
+
+    #[test]
+    fn test_run() {
+        let mut optimizer = optimizer();
+        optimizer.config.threads = Some(3);
+        let results = optimizer.run().unwrap();
+        assert_eq!(results.runs.len(), 6);
+
+        // runs are ranked by net profit and failed runs are last
+        let profits = results
+            .runs
+            .iter()
+            .filter_map(|run| run.metrics.as_ref().ok().map(|m| m.net_profit))
+            .collect::<Vec<_>>();
+        assert_eq!(profits.len(), 4);
+        assert!(profits.windows(2).all(|pair| pair[0] >= pair[1]));
+        for run in results.runs[4..].iter() {
+            assert_eq!(run.parameters[0].1, Value::Integer(0));
+            assert!(run.metrics.is_err());
+        }
+        assert_eq!(
+            results.best().unwrap().metrics.as_ref().unwrap().net_profit,
+            profits[0]
+        );
+
+        let df = results.to_dataframe().unwrap();
+        assert_eq!(
+            df.get_column_names(),
+            [
+                "rank",
+                "strategy.indicators.0.period",
+                "risk.stop_loss_percentage",
+                "net_profit",
+                "sharpe_ratio",
+                "max_drawdown",
+                "trades",
+                "error"
+            ]
+        );
+        assert_eq!(df.height(), 6);
+
+        let path = create_temp_dir(Path::new("optimizer_testing")).join("results.csv");
+        results.save_csv(&path).unwrap();
+        assert!(path.is_file());
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = create_temp_dir(Path::new("optimizer_config_testing")).join("config.toml");
+        std::fs::write(&path, CONFIG).unwrap();
+
+        let optimizer = Optimizer::from_file(&path).unwrap();
+        assert_eq!(optimizer.config.objective, Objective::NetProfit);
+        assert_eq!(optimizer.config.search, SearchMethod::Grid);
+        assert_eq!(optimizer.config.parameters.len(), 2);
+        assert!(optimizer.document.get(OPTIMIZE_SECTION).is_none());
+
+        // the remaining document is a valid backtesting config
+        assert!(optimizer
+            .document
+            .clone()
+            .try_into::<BacktestingConfig>()
+            .is_ok());
+    }
+}
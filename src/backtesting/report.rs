@@ -0,0 +1,338 @@
+use crate::backtesting::BacktestMetrics;
+use crate::metrics;
+use crate::portfolio::{Portfolio, TradeHandlers};
+use chrono::{Duration, NaiveDateTime};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::fmt;
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+const SECONDS_PER_DAY: f64 = 24.0 * 60.0 * 60.0;
+
+/// Performance of a backtesting run
+///
+/// Percentages are expressed as `12.0` for 12%. The Sharpe ratio and max drawdown are those of [`BacktestMetrics`],
+/// and ratios are annualized assuming candles trade around the clock.
+/// Win rate, profit factor and average win and loss are calculated from sells matched against earlier buys
+/// first-in-first-out and are net of the fees of both trades.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+    /// Time of the first traded candle
+    pub start: NaiveDateTime,
+    /// Time the last traded candle closed
+    pub end: NaiveDateTime,
+
+    /// Mark-to-market equity before trading on the first candle
+    pub starting_equity: f64,
+    /// Mark-to-market equity after trading on the last candle
+    pub final_equity: f64,
+    pub total_return: f64,
+    /// Compound annual growth rate
+    pub cagr: f64,
+
+    pub max_drawdown: f64,
+    /// Longest time spent below a previous equity peak
+    pub max_drawdown_days: f64,
+
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+
+    /// Number of executed trades
+    pub trades: usize,
+    /// Number of sells which closed earlier buys
+    pub closed_trades: usize,
+    pub win_rate: f64,
+    /// Gross profit over gross loss. `None` when there are no losing trades.
+    pub profit_factor: Option<f64>,
+    pub average_win: f64,
+    pub average_loss: f64,
+    /// Share of candles which began with assets held
+    pub exposure: f64,
+    pub fees: f64,
+
+    /// Return of holding the trading asset from the first to the last candle
+    pub buy_and_hold_return: f64,
+    /// Total return minus the buy-and-hold return
+    pub excess_return: f64,
+}
+
+impl BacktestReport {
+    /// Create a report from the results of a run
+    ///
+    /// # Arguments
+    /// * `equity` - Time and mark-to-market equity of each traded candle. The final sample is the equity after the
+    ///   last candle closed.
+    /// * `portfolio` - The portfolio at the end of the run
+    /// * `exposed_candles` - Number of traded candles which began with assets held
+    /// * `prices` - Close of the first and last traded candles
+    /// * `periods_per_year` - Number of candles in a year. Used to annualize ratios.
+    pub fn new(
+        equity: &[(NaiveDateTime, Decimal)],
+        portfolio: &Portfolio,
+        exposed_candles: usize,
+        prices: (Decimal, Decimal),
+        periods_per_year: Decimal,
+    ) -> Option<Self> {
+        let (start, first) = *equity.first()?;
+        let (end, last) = *equity.last()?;
+        let values = equity.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+        let starting_equity = first.to_f64().unwrap_or_default();
+        let final_equity = last.to_f64().unwrap_or_default();
+
+        let total_return = percent_change(starting_equity, final_equity);
+        let years = (end - start).num_seconds() as f64 / SECONDS_PER_YEAR;
+        let cagr = if years > 0.0 && starting_equity > 0.0 && final_equity >= 0.0 {
+            ((final_equity / starting_equity).powf(1.0 / years) - 1.0) * 100.0
+        } else {
+            0.0
+        };
+
+        let trades = portfolio.get_executed_trades().len();
+        let summary = BacktestMetrics::from_equity(&values, trades, periods_per_year);
+        let returns = metrics::returns(&values);
+        let sortino_ratio = metrics::sortino_ratio(&returns, Decimal::ZERO, periods_per_year);
+
+        let closed = portfolio
+            .closed_trade_pnl()
+            .iter()
+            .map(|pnl| pnl.to_f64().unwrap_or_default())
+            .collect::<Vec<_>>();
+        let wins = closed.iter().filter(|pnl| **pnl > 0.0).collect::<Vec<_>>();
+        let losses = closed.iter().filter(|pnl| **pnl < 0.0).collect::<Vec<_>>();
+        let gross_profit = wins.iter().copied().sum::<f64>();
+        let gross_loss = -losses.iter().copied().sum::<f64>();
+        let average = |values: &[&f64]| {
+            if values.is_empty() {
+                0.0
+            } else {
+                values.iter().copied().sum::<f64>() / values.len() as f64
+            }
+        };
+
+        let traded_candles = equity.len() - 1;
+        let buy_and_hold_return = percent_change(
+            prices.0.to_f64().unwrap_or_default(),
+            prices.1.to_f64().unwrap_or_default(),
+        );
+
+        Some(Self {
+            start,
+            end,
+            starting_equity,
+            final_equity,
+            total_return,
+            cagr,
+            max_drawdown: summary.max_drawdown * 100.0,
+            max_drawdown_days: drawdown_duration(equity).num_seconds() as f64 / SECONDS_PER_DAY,
+            sharpe_ratio: summary.sharpe_ratio,
+            sortino_ratio: sortino_ratio.to_f64().unwrap_or_default(),
+            trades,
+            closed_trades: closed.len(),
+            win_rate: if closed.is_empty() {
+                0.0
+            } else {
+                wins.len() as f64 / closed.len() as f64 * 100.0
+            },
+            profit_factor: (gross_loss > 0.0).then(|| gross_profit / gross_loss),
+            average_win: average(&wins),
+            average_loss: average(&losses),
+            exposure: if traded_candles == 0 {
+                0.0
+            } else {
+                exposed_candles as f64 / traded_candles as f64 * 100.0
+            },
+            fees: portfolio.total_fees().to_f64().unwrap_or_default(),
+            buy_and_hold_return,
+            excess_return: total_return - buy_and_hold_return,
+        })
+    }
+
+    /// Serialize the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl fmt::Display for BacktestReport {
+    /// Format the report as a table of metrics
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let profit_factor = self
+            .profit_factor
+            .map_or("n/a".to_string(), |factor| format!("{:.2}", factor));
+        let rows = [
+            ("Start", self.start.to_string()),
+            ("End", self.end.to_string()),
+            ("Starting equity", format!("{:.2}", self.starting_equity)),
+            ("Final equity", format!("{:.2}", self.final_equity)),
+            ("Total return", format!("{:.2}%", self.total_return)),
+            ("CAGR", format!("{:.2}%", self.cagr)),
+            (
+                "Buy and hold return",
+                format!("{:.2}%", self.buy_and_hold_return),
+            ),
+            ("Excess return", format!("{:.2}%", self.excess_return)),
+            ("Max drawdown", format!("{:.2}%", self.max_drawdown)),
+            (
+                "Max drawdown duration",
+                format!("{:.2} days", self.max_drawdown_days),
+            ),
+            ("Sharpe ratio", format!("{:.2}", self.sharpe_ratio)),
+            ("Sortino ratio", format!("{:.2}", self.sortino_ratio)),
+            ("Trades", self.trades.to_string()),
+            ("Closed trades", self.closed_trades.to_string()),
+            ("Win rate", format!("{:.2}%", self.win_rate)),
+            ("Profit factor", profit_factor),
+            ("Average win", format!("{:.2}", self.average_win)),
+            ("Average loss", format!("{:.2}", self.average_loss)),
+            ("Exposure", format!("{:.2}%", self.exposure)),
+            ("Fees paid", format!("{:.2}", self.fees)),
+        ];
+
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, value) in rows.iter() {
+            writeln!(f, "{:<width$}  {:>20}", name, value, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+fn percent_change(from: f64, to: f64) -> f64 {
+    if from == 0.0 {
+        0.0
+    } else {
+        (to / from - 1.0) * 100.0
+    }
+}
+
+/// Longest time spent below a previous equity peak
+fn drawdown_duration(equity: &[(NaiveDateTime, Decimal)]) -> Duration {
+    let values = equity.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+    let mut max_duration = Duration::zero();
+    let mut peak_time = None;
+    for ((time, _), drawdown) in equity.iter().zip(metrics::drawdowns(&values)) {
+        match peak_time {
+            Some(peak_time) if drawdown > Decimal::ZERO => {
+                max_duration = max_duration.max(*time - peak_time);
+            }
+            _ => peak_time = Some(*time),
+        }
+    }
+    max_duration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markets::SimplePercentageFee;
+    use crate::portfolio::AssetHandlers;
+    use crate::types::{ExecutedTrade, Side};
+    use rust_decimal_macros::dec;
+
+    fn time(minutes: i64) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap()
+            + Duration::minutes(minutes)
+    }
+
+    fn trade(side: Side, price: Decimal, quantity: Decimal, minutes: i64) -> ExecutedTrade {
+        ExecutedTrade::with_calculated_notional(
+            format!("{}", minutes),
+            side,
+            price,
+            quantity,
+            time(minutes),
+        )
+    }
+
+    #[test]
+    fn test_drawdown_duration() {
+        let equity = [
+            (time(0), dec!(100)),
+            (time(1), dec!(120)),
+            (time(2), dec!(90)),
+            (time(3), dec!(100)),
+            (time(4), dec!(130)),
+            (time(5), dec!(125)),
+        ];
+        assert_eq!(drawdown_duration(&equity), Duration::minutes(2));
+    }
+
+    #[test]
+    fn test_new() {
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), time(0));
+        portfolio.add_executed_trade(trade(Side::Buy, dec!(100), dec!(2), 0));
+        portfolio.add_executed_trade(trade(Side::Sell, dec!(110), dec!(1), 1));
+        portfolio.add_executed_trade(trade(Side::Sell, dec!(90), dec!(1), 2));
+        assert_eq!(portfolio.get_assets(), dec!(0));
+
+        let equity = [
+            (time(0), dec!(1000)),
+            (time(1), dec!(1020)),
+            (time(2), dec!(1010)),
+            (time(3), dec!(1000)),
+        ];
+        let report =
+            BacktestReport::new(&equity, &portfolio, 2, (dec!(100), dec!(90)), dec!(525600))
+                .unwrap();
+
+        assert_eq!(report.start, time(0));
+        assert_eq!(report.end, time(3));
+        assert_eq!(report.total_return, 0.0);
+        assert_eq!(report.cagr, 0.0);
+        assert_eq!(report.trades, 3);
+        assert_eq!(report.closed_trades, 2);
+        assert_eq!(report.win_rate, 50.0);
+        assert_eq!(report.profit_factor, Some(1.0));
+        assert_eq!(report.average_win, 10.0);
+        assert_eq!(report.average_loss, -10.0);
+        assert!((report.exposure - 200.0 / 3.0).abs() < 1e-9);
+        assert!((report.buy_and_hold_return + 10.0).abs() < 1e-9);
+        assert!((report.excess_return - 10.0).abs() < 1e-9);
+        assert!((report.max_drawdown - 20.0 / 1020.0 * 100.0).abs() < 1e-9);
+        assert!((report.max_drawdown_days - 2.0 / 1440.0).abs() < 1e-9);
+        // returns are positive on average, and losses deviate less than all returns
+        assert!(report.sharpe_ratio > 0.0);
+        assert!(report.sortino_ratio > report.sharpe_ratio);
+
+        // the same metrics that runs are ranked by
+        let values = equity.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+        let metrics = BacktestMetrics::from_equity(&values, 3, dec!(525600));
+        assert_eq!(report.sharpe_ratio, metrics.sharpe_ratio);
+        assert_eq!(report.max_drawdown, metrics.max_drawdown * 100.0);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["trades"], 3);
+        assert_eq!(json["win_rate"], 50.0);
+        assert_eq!(json["start"], "2024-01-01T00:00:00");
+
+        let table = report.to_string();
+        assert!(table.contains("Win rate"));
+        assert!(table.contains("50.00%"));
+
+        assert!(
+            BacktestReport::new(&[], &portfolio, 0, (dec!(100), dec!(90)), dec!(525600)).is_none()
+        );
+    }
+
+    #[test]
+    fn test_new_with_fees() {
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), time(0))
+            .add_fee_calculator(SimplePercentageFee::new(dec!(1)));
+        portfolio.add_executed_trade(trade(Side::Buy, dec!(100), dec!(2), 0));
+        // a gross profit of 1 is a loss after fees
+        portfolio.add_executed_trade(trade(Side::Sell, dec!(101), dec!(1), 1));
+        portfolio.add_executed_trade(trade(Side::Sell, dec!(120), dec!(1), 2));
+
+        let equity = [(time(0), dec!(1000)), (time(3), dec!(1000))];
+        let report =
+            BacktestReport::new(&equity, &portfolio, 2, (dec!(100), dec!(120)), dec!(525600))
+                .unwrap();
+
+        assert_eq!(report.closed_trades, 2);
+        assert_eq!(report.win_rate, 50.0);
+        assert!((report.average_win - 17.8).abs() < 1e-9);
+        assert!((report.average_loss + 1.01).abs() < 1e-9);
+        assert!((report.profit_factor.unwrap() - 17.8 / 1.01).abs() < 1e-9);
+        assert!((report.fees - 4.21).abs() < 1e-9);
+    }
+}
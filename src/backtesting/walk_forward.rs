@@ -0,0 +1,645 @@
+use crate::backtesting::{BacktestMetrics, Objective, Optimizer, OptimizerError, CANDLE_TRIM_SIZE};
+use crate::markets::utils::save_candles;
+use crate::types::{candles_per_year, MarketData};
+use chrono::NaiveDateTime;
+use log::info;
+use polars::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::ops::Range;
+use std::path::Path;
+use toml::Value;
+
+/// Number of candles before a window which warm up indicators
+const WARMUP_SIZE: usize = CANDLE_TRIM_SIZE as usize;
+
+/// How candles are divided into in-sample and out-of-sample windows
+///
+/// Window sizes are a number of candles of the trading frequency. In TOML, `walk_forward = { rolling = {
+/// in_sample = 1000, out_of_sample = 250 } }` or `walk_forward = { split = { train = 0.7 } }`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WalkForwardConfig {
+    /// In-sample windows of a fixed size which move forward by `out_of_sample` candles
+    Rolling {
+        in_sample: usize,
+        out_of_sample: usize,
+    },
+    /// In-sample windows which start at the first candle and grow by `out_of_sample` candles
+    Anchored {
+        in_sample: usize,
+        out_of_sample: usize,
+    },
+    /// A single train/test split where `train` is the fraction of candles used in-sample
+    Split { train: f64 },
+}
+
+/// Candle indices of an in-sample window and the out-of-sample window which follows it
+#[derive(Debug, Clone, PartialEq)]
+struct WindowRanges {
+    in_sample: Range<usize>,
+    out_of_sample: Range<usize>,
+}
+
+impl WalkForwardConfig {
+    /// Divide `candles` into windows
+    ///
+    /// Only complete windows are returned, so trailing candles which do not fill an out-of-sample window are
+    /// not evaluated.
+    fn windows(&self, candles: usize) -> Result<Vec<WindowRanges>, OptimizerError> {
+        let windows = match self {
+            WalkForwardConfig::Rolling {
+                in_sample,
+                out_of_sample,
+            }
+            | WalkForwardConfig::Anchored {
+                in_sample,
+                out_of_sample,
+            } => {
+                if *in_sample == 0 || *out_of_sample == 0 {
+                    return Err(OptimizerError::Config(
+                        "walk forward windows must contain at least 1 candle".to_string(),
+                    ));
+                }
+                let anchored = matches!(self, WalkForwardConfig::Anchored { .. });
+
+                let mut windows = vec![];
+                let mut start = 0;
+                while start + in_sample + out_of_sample <= candles {
+                    let end = start + in_sample;
+                    windows.push(WindowRanges {
+                        in_sample: if anchored { 0..end } else { start..end },
+                        out_of_sample: end..end + out_of_sample,
+                    });
+                    start += out_of_sample;
+                }
+                windows
+            }
+            WalkForwardConfig::Split { train } => {
+                if !(0.0..1.0).contains(train) {
+                    return Err(OptimizerError::Config(
+                        "`train` must be between 0 and 1".to_string(),
+                    ));
+                }
+                let split = (candles as f64 * train).round() as usize;
+                if split == 0 || split == candles {
+                    vec![]
+                } else {
+                    vec![WindowRanges {
+                        in_sample: 0..split,
+                        out_of_sample: split..candles,
+                    }]
+                }
+            }
+        };
+
+        if windows.is_empty() {
+            return Err(OptimizerError::Config(format!(
+                "{} candles are not enough for a walk forward window",
+                candles
+            )));
+        }
+        Ok(windows)
+    }
+}
+
+/// Results of a single walk forward window
+#[derive(Debug, Clone)]
+pub struct WalkForwardWindow {
+    /// Time of the first and last in-sample candles
+    pub in_sample: (NaiveDateTime, NaiveDateTime),
+    /// Time of the first and last out-of-sample candles
+    pub out_of_sample: (NaiveDateTime, NaiveDateTime),
+    /// Best parameters of the in-sample search. Empty when every in-sample run failed.
+    pub parameters: Vec<(String, Value)>,
+    /// Metrics of the best in-sample run
+    pub in_sample_metrics: Result<BacktestMetrics, String>,
+    /// Metrics of the best parameters on out-of-sample candles
+    pub out_of_sample_metrics: Result<BacktestMetrics, String>,
+}
+
+/// Per-window results of [`Optimizer::walk_forward`] and the stitched out-of-sample equity curve
+#[derive(Debug)]
+pub struct WalkForwardResults {
+    objective: Objective,
+    paths: Vec<String>,
+    windows: Vec<WalkForwardWindow>,
+    /// Out-of-sample equity of every window joined into a single curve
+    ///
+    /// Each window starts with the starting capital of the backtesting config, so the equity of a window is scaled
+    /// to begin where the previous window ended. Windows which failed are skipped.
+    equity_curve: Vec<(NaiveDateTime, Decimal)>,
+    /// Number of candles in a year
+    periods_per_year: Decimal,
+}
+
+impl WalkForwardResults {
+    pub fn windows(&self) -> &[WalkForwardWindow] {
+        &self.windows
+    }
+
+    /// Metrics of the stitched out-of-sample equity curve
+    pub fn out_of_sample_metrics(&self) -> BacktestMetrics {
+        let equity = self
+            .equity_curve
+            .iter()
+            .map(|(_, value)| *value)
+            .collect::<Vec<_>>();
+        let trades = self
+            .windows
+            .iter()
+            .filter_map(|window| window.out_of_sample_metrics.as_ref().ok())
+            .map(|metrics| metrics.trades)
+            .sum();
+        BacktestMetrics::from_equity(&equity, trades, self.periods_per_year)
+    }
+
+    /// Table of windows with the chosen parameters and in-sample and out-of-sample metrics
+    pub fn to_dataframe(&self) -> PolarsResult<DataFrame> {
+        let windows = &self.windows;
+        let mut columns = vec![
+            Series::new("window", (1..=windows.len() as u32).collect::<Vec<_>>()),
+            Series::new(
+                "in_sample_start",
+                windows.iter().map(|w| w.in_sample.0).collect::<Vec<_>>(),
+            ),
+            Series::new(
+                "in_sample_end",
+                windows.iter().map(|w| w.in_sample.1).collect::<Vec<_>>(),
+            ),
+            Series::new(
+                "out_of_sample_start",
+                windows
+                    .iter()
+                    .map(|w| w.out_of_sample.0)
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                "out_of_sample_end",
+                windows
+                    .iter()
+                    .map(|w| w.out_of_sample.1)
+                    .collect::<Vec<_>>(),
+            ),
+        ];
+        for path in self.paths.iter() {
+            let values = windows
+                .iter()
+                .map(|window| {
+                    window
+                        .parameters
+                        .iter()
+                        .find(|(parameter, _)| parameter == path)
+                        .map(|(_, value)| match value {
+                            Value::String(value) => value.clone(),
+                            value => value.to_string(),
+                        })
+                })
+                .collect::<Vec<_>>();
+            columns.push(Series::new(path, values));
+        }
+
+        let objective = self.objective;
+        columns.push(Series::new(
+            &format!("in_sample_{}", objective.as_str()),
+            windows
+                .iter()
+                .map(|w| {
+                    w.in_sample_metrics
+                        .as_ref()
+                        .ok()
+                        .map(|m| objective.value(m))
+                })
+                .collect::<Vec<_>>(),
+        ));
+
+        let metric = |f: fn(&BacktestMetrics) -> f64| {
+            windows
+                .iter()
+                .map(|w| w.out_of_sample_metrics.as_ref().ok().map(f))
+                .collect::<Vec<_>>()
+        };
+        columns.push(Series::new("net_profit", metric(|m| m.net_profit)));
+        columns.push(Series::new("sharpe_ratio", metric(|m| m.sharpe_ratio)));
+        columns.push(Series::new("max_drawdown", metric(|m| m.max_drawdown)));
+        columns.push(Series::new(
+            "trades",
+            windows
+                .iter()
+                .map(|w| {
+                    w.out_of_sample_metrics
+                        .as_ref()
+                        .ok()
+                        .map(|m| m.trades as u64)
+                })
+                .collect::<Vec<_>>(),
+        ));
+        columns.push(Series::new(
+            "error",
+            windows
+                .iter()
+                .map(|w| w.out_of_sample_metrics.as_ref().err().cloned())
+                .collect::<Vec<_>>(),
+        ));
+
+        DataFrame::new(columns)
+    }
+
+    /// Stitched out-of-sample equity curve with `time` and `equity` columns
+    pub fn equity_dataframe(&self) -> PolarsResult<DataFrame> {
+        df!(
+            "time" => self.equity_curve.iter().map(|(time, _)| *time).collect::<Vec<_>>(),
+            "equity" => self
+                .equity_curve
+                .iter()
+                .map(|(_, value)| value.to_f64().unwrap_or_default())
+                .collect::<Vec<_>>()
+        )
+    }
+
+    /// Write the windows to `windows.csv` and the stitched equity curve to `equity.csv` in `dir`
+    pub fn save_csv<P: AsRef<Path>>(&self, dir: P) -> Result<(), OptimizerError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|e| OptimizerError::Save(e.to_string()))?;
+
+        let save = |mut df: DataFrame, name: &str| {
+            let path = dir.join(name);
+            let path = path
+                .to_str()
+                .ok_or(OptimizerError::Save("Invalid path".to_string()))?;
+            save_candles(&mut df, path).map_err(|e| OptimizerError::Save(e.to_string()))
+        };
+        save(
+            self.to_dataframe()
+                .map_err(|e| OptimizerError::Save(e.to_string()))?,
+            "windows.csv",
+        )?;
+        save(
+            self.equity_dataframe()
+                .map_err(|e| OptimizerError::Save(e.to_string()))?,
+            "equity.csv",
+        )
+    }
+}
+
+/// Join out-of-sample equity curves
+///
+/// A curve begins at the time the previous curve ends, so the first sample of every curve after the first is
+/// replaced by the scaled remainder of the curve.
+fn stitch_equity(curves: &[Vec<(NaiveDateTime, Decimal)>]) -> Vec<(NaiveDateTime, Decimal)> {
+    let mut stitched: Vec<(NaiveDateTime, Decimal)> = vec![];
+    for curve in curves.iter() {
+        let Some((_, first)) = curve.first() else {
+            continue;
+        };
+        match stitched.last() {
+            Some((_, last)) if !first.is_zero() => {
+                let scale = *last / *first;
+                stitched.extend(
+                    curve
+                        .iter()
+                        .skip(1)
+                        .map(|(time, value)| (*time, *value * scale)),
+                );
+            }
+            Some(_) => stitched.extend(curve.iter().skip(1).cloned()),
+            None => stitched.extend(curve.iter().cloned()),
+        }
+    }
+    stitched
+}
+
+/// Only keep candles of `frequency` from `start` to `end` (inclusive)
+///
+/// Candles of other intervals are kept in full since indicators bound to them only see closed candles.
+fn window_data(
+    data: &MarketData,
+    frequency: &str,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<MarketData, OptimizerError> {
+    let mut data = data.clone();
+    let candles = data
+        .candles
+        .get_mut(frequency)
+        .ok_or(OptimizerError::Candles(format!(
+            "no {} candles for {}",
+            frequency, data.asset_name
+        )))?;
+    *candles = candles
+        .clone()
+        .lazy()
+        .filter(col("time").gt_eq(lit(start)))
+        .filter(col("time").lt_eq(lit(end)))
+        .collect()
+        .map_err(|e| OptimizerError::Candles(e.to_string()))?;
+    Ok(data)
+}
+
+/// Time of every candle of `frequency` in ascending order
+fn candle_times(data: &MarketData, frequency: &str) -> Result<Vec<NaiveDateTime>, OptimizerError> {
+    let candles = data
+        .get_candles(&frequency.to_string())
+        .map_err(|e| OptimizerError::Candles(format!("{:?}", e)))?;
+    let mut times = candles
+        .column("time")
+        .and_then(|time| time.datetime().cloned())
+        .map_err(|e| OptimizerError::Candles(e.to_string()))?
+        .as_datetime_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    times.sort();
+    Ok(times)
+}
+
+impl Optimizer {
+    /// Walk forward analysis of the searched parameters
+    ///
+    /// Candles are divided into windows by the `walk_forward` option of the `[optimize]` section. Parameters
+    /// are searched on the in-sample candles of each window and the best parameters are evaluated on the
+    /// out-of-sample candles which follow. Candles preceding a window warm up indicators but are not traded.
+    pub fn walk_forward(&self) -> Result<WalkForwardResults, OptimizerError> {
+        let walk_forward = self
+            .config
+            .walk_forward
+            .as_ref()
+            .ok_or(OptimizerError::Config(
+                "missing `walk_forward` in [optimize]".to_string(),
+            ))?;
+        let frequency = self.backtesting_config()?.trading.frequency;
+        let periods_per_year =
+            candles_per_year(&frequency)
+                .map(Decimal::from)
+                .ok_or(OptimizerError::Config(format!(
+                    "Unsupported frequency '{}'",
+                    frequency
+                )))?;
+        let combinations = self.combinations()?;
+        let (trading, market) = self.load_market_data()?;
+
+        let times = candle_times(&trading, &frequency)?;
+        let windows = walk_forward.windows(times.len())?;
+
+        // candles of a window along with warmup candles and the time trading begins
+        let slice = |range: &Range<usize>| -> Result<_, OptimizerError> {
+            let first = times[range.start.saturating_sub(WARMUP_SIZE)];
+            let last = times[range.end - 1];
+            Ok((
+                window_data(&trading, &frequency, first, last)?,
+                window_data(&market, &frequency, first, last)?,
+                times[range.start],
+            ))
+        };
+
+        let mut results = vec![];
+        let mut curves = vec![];
+        for (idx, window) in windows.iter().enumerate() {
+            info!(
+                "Walk forward window {} of {}: in-sample {} to {}, out-of-sample {} to {}",
+                idx + 1,
+                windows.len(),
+                times[window.in_sample.start],
+                times[window.in_sample.end - 1],
+                times[window.out_of_sample.start],
+                times[window.out_of_sample.end - 1]
+            );
+
+            let (trading_window, market_window, start) = slice(&window.in_sample)?;
+            let search = self.search(&combinations, &trading_window, &market_window, Some(start));
+            let best = search.best().cloned();
+
+            let (parameters, in_sample_metrics, out_of_sample) = match best {
+                Some(best) => {
+                    let document = self.document_with(&best.parameters)?;
+                    let (trading_window, market_window, start) = slice(&window.out_of_sample)?;
+                    let out_of_sample =
+                        Self::backtest(document, &trading_window, &market_window, Some(start));
+                    (best.parameters, best.metrics, out_of_sample)
+                }
+                None => {
+                    let error = "every in-sample run failed".to_string();
+                    (vec![], Err(error.clone()), Err(error))
+                }
+            };
+
+            let out_of_sample_metrics = match out_of_sample {
+                Ok((metrics, curve)) => {
+                    curves.push(curve);
+                    Ok(metrics)
+                }
+                Err(error) => Err(error),
+            };
+            results.push(WalkForwardWindow {
+                in_sample: (
+                    times[window.in_sample.start],
+                    times[window.in_sample.end - 1],
+                ),
+                out_of_sample: (
+                    times[window.out_of_sample.start],
+                    times[window.out_of_sample.end - 1],
+                ),
+                parameters,
+                in_sample_metrics,
+                out_of_sample_metrics,
+            });
+        }
+
+        Ok(WalkForwardResults {
+            objective: self.config.objective,
+            paths: self.paths(),
+            windows: results,
+            equity_curve: stitch_equity(&curves),
+            periods_per_year,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtesting::optimizer::{OptimizerConfig, ParameterRange};
+    use crate::indicators::create_candles;
+    use crate::utils::create_temp_dir;
+    use rust_decimal_macros::dec;
+    use std::collections::HashMap;
+
+    const CONFIG: &str = r#"
+        [portfolio]
+        assets = 0.0
+        capital = 1000.0
+        threshold = 10.0
+
+        [risk]
+        max_position_size = 50.0
+        stop_loss_percentage = 1.0
+        take_profit_percentage = 1.0
+        max_beta = 10.0
+        var_limit = 100.0
+        min_sharpe_ratio = -100.0
+        unrealized_pnl_limit = 100.0
+
+        [trading]
+        frequency = "1m"
+        trading_asset = "BTC-USD"
+        market_asset = "ETH-USD"
+
+        [[strategy.indicators]]
+        name = "rsi"
+        period = 14
+    "#;
+
+    fn time(minutes: i64) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap()
+            + chrono::Duration::minutes(minutes)
+    }
+
+    fn optimizer(walk_forward: WalkForwardConfig) -> Optimizer {
+        let closes = (0..400)
+            .map(|i| 100.0 + 10.0 * (i as f64 / 8.0).sin())
+            .collect::<Vec<_>>();
+        let data = |asset_name: &str| MarketData {
+            asset_name: asset_name.to_string(),
+            candles: HashMap::from([("1m".to_string(), create_candles(&closes))]),
+        };
+        let config = OptimizerConfig {
+            search: Default::default(),
+            objective: Objective::SharpeRatio,
+            threads: Some(2),
+            parameters: vec![ParameterRange::values(
+                "strategy.indicators.0.period",
+                vec![Value::Integer(3), Value::Integer(5)],
+            )],
+            walk_forward: Some(walk_forward),
+        };
+        let document = Value::Table(CONFIG.parse::<toml::Table>().unwrap());
+        Optimizer::new(document, config).with_market_data(data("BTC-USD"), data("ETH-USD"))
+    }
+
+    #[test]
+    fn test_windows() {
+        let rolling = WalkForwardConfig::Rolling {
+            in_sample: 4,
+            out_of_sample: 2,
+        };
+        assert_eq!(
+            rolling.windows(11).unwrap(),
+            vec![
+                WindowRanges {
+                    in_sample: 0..4,
+                    out_of_sample: 4..6
+                },
+                WindowRanges {
+                    in_sample: 2..6,
+                    out_of_sample: 6..8
+                },
+                WindowRanges {
+                    in_sample: 4..8,
+                    out_of_sample: 8..10
+                },
+            ]
+        );
+
+        let anchored = WalkForwardConfig::Anchored {
+            in_sample: 4,
+            out_of_sample: 2,
+        };
+        let windows = anchored.windows(8).unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[1].in_sample, 0..6);
+        assert_eq!(windows[1].out_of_sample, 6..8);
+
+        let split = WalkForwardConfig::Split { train: 0.7 };
+        assert_eq!(
+            split.windows(10).unwrap(),
+            vec![WindowRanges {
+                in_sample: 0..7,
+                out_of_sample: 7..10
+            }]
+        );
+
+        // not enough candles or invalid sizes
+        assert!(rolling.windows(5).is_err());
+        assert!(WalkForwardConfig::Split { train: 1.5 }.windows(10).is_err());
+        assert!(WalkForwardConfig::Rolling {
+            in_sample: 0,
+            out_of_sample: 2
+        }
+        .windows(10)
+        .is_err());
+    }
+
+    #[test]
+    fn test_stitch_equity() {
+        let curves = vec![
+            vec![(time(0), dec!(100)), (time(1), dec!(110))],
+            vec![(time(1), dec!(100)), (time(2), dec!(90))],
+            vec![],
+            vec![(time(2), dec!(50)), (time(3), dec!(60))],
+        ];
+        assert_eq!(
+            stitch_equity(&curves),
+            vec![
+                (time(0), dec!(100)),
+                (time(1), dec!(110)),
+                (time(2), dec!(99)),
+                (time(3), dec!(118.8)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_forward() {
+        let results = optimizer(WalkForwardConfig::Rolling {
+            in_sample: 150,
+            out_of_sample: 100,
+        })
+        .walk_forward()
+        .unwrap();
+
+        let windows = results.windows();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].in_sample, (time(0), time(149)));
+        assert_eq!(windows[0].out_of_sample, (time(150), time(249)));
+        assert_eq!(windows[1].in_sample, (time(100), time(249)));
+        assert_eq!(windows[1].out_of_sample, (time(250), time(349)));
+        for window in windows.iter() {
+            assert_eq!(window.parameters.len(), 1);
+            assert!(window.in_sample_metrics.is_ok());
+            assert!(window.out_of_sample_metrics.is_ok());
+        }
+
+        // out-of-sample equity is only recorded once trading begins and windows are joined end to start
+        let equity = &results.equity_curve;
+        assert_eq!(equity.first().unwrap().0, time(150));
+        assert_eq!(equity.last().unwrap().0, time(350));
+        assert_eq!(equity.len(), 201);
+        assert_eq!(equity.first().unwrap().1, dec!(1000));
+
+        let metrics = results.out_of_sample_metrics();
+        let net_profit = windows
+            .iter()
+            .map(|w| w.out_of_sample_metrics.as_ref().unwrap().net_profit)
+            .sum::<f64>();
+        assert!((metrics.net_profit - net_profit).abs() < net_profit.abs() * 0.05 + 1e-6);
+
+        let df = results.to_dataframe().unwrap();
+        assert_eq!(df.height(), 2);
+        assert!(df.get_column_names().contains(&"in_sample_sharpe_ratio"));
+
+        let dir = create_temp_dir(Path::new("walk_forward_testing"));
+        results.save_csv(&dir).unwrap();
+        assert!(dir.join("windows.csv").is_file());
+        assert!(dir.join("equity.csv").is_file());
+    }
+
+    #[test]
+    fn test_walk_forward_missing_config() {
+        let mut optimizer = optimizer(WalkForwardConfig::Split { train: 0.5 });
+        optimizer.config.walk_forward = None;
+        assert!(matches!(
+            optimizer.walk_forward(),
+            Err(OptimizerError::Config(_))
+        ));
+    }
+}
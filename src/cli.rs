@@ -0,0 +1,736 @@
+use crate::backtesting::{
+    BacktestingConfig, BacktestingErrors, BacktestingRuntime, Optimizer, OptimizerError,
+    TradingConfig,
+};
+use crate::live::{LiveConfig, LiveRuntime, LiveRuntimeErrors};
+use crate::markets::backfill::{Backfill, CandleStore};
+use crate::markets::manager::VALID_INTERVALS;
+use crate::markets::{CoinbaseClient, CoinbaseFeed, MarketError, PaperMarket};
+use crate::strategies::StrategyConfigError;
+use crate::types::{MarketData, DB_PATH};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use log::info;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+const DEFAULT_CONFIG_PATH: &str = "data/backtesting_config.toml";
+const DEFAULT_OUTPUT_DIR: &str = "data/backtesting";
+const DEFAULT_OPTIMIZATION_PATH: &str = "data/optimization.csv";
+const DEFAULT_WALK_FORWARD_DIR: &str = "data/walk_forward";
+
+/// Exit code for errors in the config file or command-line arguments
+const CONFIG_EXIT_CODE: u8 = 2;
+
+/// Number of feed events buffered while the strategy is processed
+const FEED_CHANNEL_SIZE: usize = 256;
+
+#[derive(Parser, Debug)]
+#[command(name = "tradr", version, about = "Backtest and run trading strategies")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run a strategy against candles in the sqlite candle store
+    Backtest(BacktestArgs),
+    /// Search strategy and risk parameters by backtesting every combination
+    Optimize(OptimizeArgs),
+    /// Search parameters on rolling in-sample windows and evaluate them on the out-of-sample windows which follow
+    WalkForward(WalkForwardArgs),
+    /// Download historical candles into the sqlite candle store
+    Backfill(BackfillArgs),
+    /// Replay candles from the sqlite candle store or CSV files against a simulated exchange
+    Paper(PaperArgs),
+    /// Trade on Coinbase
+    Live(LiveArgs),
+}
+
+/// Arguments shared by commands which read a TOML config file
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Path to the TOML config file
+    #[arg(short, long, default_value = DEFAULT_CONFIG_PATH)]
+    pub config: PathBuf,
+
+    /// Override the trading asset pair (ie: "BTC-USD")
+    #[arg(short, long)]
+    pub pair: Option<String>,
+
+    /// Override the candle frequency (ie: "1m", "1h")
+    #[arg(short, long)]
+    pub frequency: Option<String>,
+}
+
+impl ConfigArgs {
+    /// Apply the command-line overrides to the `[trading]` section of the config
+    fn apply(&self, trading: &mut TradingConfig) {
+        if let Some(pair) = &self.pair {
+            trading.trading_asset = pair.clone();
+        }
+        if let Some(frequency) = &self.frequency {
+            trading.frequency = frequency.clone();
+        }
+    }
+}
+
+/// Limits the candles used by a command
+#[derive(Args, Debug)]
+pub struct DateRangeArgs {
+    /// Time of the first candle (ie: "2024-01-01" or "2024-01-01 12:00")
+    #[arg(long, value_parser = parse_time)]
+    pub start: Option<NaiveDateTime>,
+
+    /// Time of the last candle
+    #[arg(long, value_parser = parse_time)]
+    pub end: Option<NaiveDateTime>,
+}
+
+#[derive(Args, Debug)]
+pub struct BacktestArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    #[command(flatten)]
+    pub range: DateRangeArgs,
+
+    /// Directory to save candles and indicators to
+    #[arg(short, long, default_value = DEFAULT_OUTPUT_DIR)]
+    pub output_dir: PathBuf,
+
+    /// Path to the sqlite candle store
+    #[arg(long, default_value = DB_PATH)]
+    pub db: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct OptimizeArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    #[command(flatten)]
+    pub range: DateRangeArgs,
+
+    /// Path of the CSV file to save ranked results to
+    #[arg(short, long, default_value = DEFAULT_OPTIMIZATION_PATH)]
+    pub output: PathBuf,
+
+    /// Path to the sqlite candle store
+    #[arg(long, default_value = DB_PATH)]
+    pub db: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct WalkForwardArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    #[command(flatten)]
+    pub range: DateRangeArgs,
+
+    /// Directory to save per-window results and the out-of-sample equity curve to
+    #[arg(short, long, default_value = DEFAULT_WALK_FORWARD_DIR)]
+    pub output_dir: PathBuf,
+
+    /// Path to the sqlite candle store
+    #[arg(long, default_value = DB_PATH)]
+    pub db: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct BackfillArgs {
+    /// Asset pairs to download (ie: "BTC-USD"). May be repeated.
+    #[arg(short, long, required = true)]
+    pub pair: Vec<String>,
+
+    /// Candle frequencies to download. May be repeated. Defaults to all frequencies.
+    #[arg(short, long)]
+    pub frequency: Vec<String>,
+
+    /// Time of the first candle (ie: "2024-01-01" or "2024-01-01 12:00")
+    #[arg(long, value_parser = parse_time)]
+    pub start: NaiveDateTime,
+
+    /// Time of the last candle. Defaults to now.
+    #[arg(long, value_parser = parse_time)]
+    pub end: Option<NaiveDateTime>,
+
+    /// Path to the sqlite candle store
+    #[arg(long, default_value = DB_PATH)]
+    pub db: PathBuf,
+
+    /// Minimum milliseconds between requests. Defaults to 150.
+    #[arg(long)]
+    pub request_interval: Option<u64>,
+
+    /// Number of times a failed request is retried. Defaults to 5.
+    #[arg(long)]
+    pub max_retries: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct PaperArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    #[command(flatten)]
+    pub range: DateRangeArgs,
+
+    /// Path to the sqlite candle store
+    #[arg(long, default_value = DB_PATH)]
+    pub db: PathBuf,
+
+    /// CSV file of candles at the trading frequency for an asset pair, as PAIR=PATH (ie: "BTC-USD=btc.csv"). May
+    /// be repeated. The whole file is replayed, and pairs without a CSV file are read from the candle store.
+    #[arg(long, value_parser = parse_csv_source)]
+    pub csv: Vec<(String, PathBuf)>,
+}
+
+#[derive(Args, Debug)]
+pub struct LiveArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    /// TOML file with Coinbase API credentials. Read from environment variables when omitted.
+    #[arg(long)]
+    pub credentials: Option<PathBuf>,
+
+    /// Log trading decisions without submitting orders
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Receive candles from the Coinbase websocket feed instead of polling
+    #[arg(long)]
+    pub stream: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("Backtesting failed: {0:?}")]
+    Backtesting(BacktestingErrors),
+    #[error("Trading failed: {0:?}")]
+    Live(LiveRuntimeErrors),
+    #[error(transparent)]
+    Market(#[from] MarketError),
+    #[error("Could not load candles: {0}")]
+    Candles(String),
+    #[error("Invalid strategy: {0}")]
+    Strategy(#[from] StrategyConfigError),
+    #[error("Optimization failed: {0}")]
+    Optimizer(#[from] OptimizerError),
+}
+
+impl From<BacktestingErrors> for CliError {
+    fn from(error: BacktestingErrors) -> Self {
+        CliError::Backtesting(error)
+    }
+}
+
+impl From<LiveRuntimeErrors> for CliError {
+    fn from(error: LiveRuntimeErrors) -> Self {
+        CliError::Live(error)
+    }
+}
+
+impl CliError {
+    /// Process exit code for the error
+    ///
+    /// Configuration errors exit with 2, all other errors exit with 1.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Backtesting(BacktestingErrors::ConfigError(_))
+            | CliError::Live(LiveRuntimeErrors::ConfigError(_))
+            | CliError::Live(LiveRuntimeErrors::InvalidFrequency(_))
+            | CliError::Strategy(_)
+            | CliError::Optimizer(OptimizerError::Config(_))
+            | CliError::Optimizer(OptimizerError::NoParameters)
+            | CliError::Optimizer(OptimizerError::InvalidParameter { .. }) => {
+                ExitCode::from(CONFIG_EXIT_CODE)
+            }
+            _ => ExitCode::FAILURE,
+        }
+    }
+}
+
+/// Parse a date (ie: "2024-01-01") or a date and time (ie: "2024-01-01 12:00")
+fn parse_time(s: &str) -> Result<NaiveDateTime, String> {
+    const FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"];
+    FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or(format!(
+            "invalid time '{}'. Expected YYYY-MM-DD or YYYY-MM-DD HH:MM",
+            s
+        ))
+}
+
+/// Parse a CSV candle file for an asset pair given as PAIR=PATH
+fn parse_csv_source(s: &str) -> Result<(String, PathBuf), String> {
+    s.split_once('=')
+        .filter(|(pair, path)| !pair.is_empty() && !path.is_empty())
+        .map(|(pair, path)| (pair.to_string(), PathBuf::from(path)))
+        .ok_or(format!("invalid CSV file '{}'. Expected PAIR=PATH", s))
+}
+
+impl Cli {
+    /// Run the selected command
+    pub async fn run(self) -> Result<(), CliError> {
+        match self.command {
+            Command::Backtest(args) => backtest(args),
+            Command::Optimize(args) => optimize(args),
+            Command::WalkForward(args) => walk_forward(args),
+            Command::Backfill(args) => backfill(args).await,
+            Command::Paper(args) => paper(args).await,
+            Command::Live(args) => live(args).await,
+        }
+    }
+}
+
+fn backtest(args: BacktestArgs) -> Result<(), CliError> {
+    let mut config = BacktestingConfig::from_file(&args.config.config)?;
+    args.config.apply(&mut config.trading);
+    let strategy = config.strategy.build()?;
+
+    let mut runtime = BacktestingRuntime::from_backtesting_config(config, strategy)
+        .with_db_path(args.db)
+        .with_date_range(args.range.start, args.range.end)
+        .with_statistics(true)
+        .load_candles()?;
+
+    info!("******************************************\nStarting to process");
+    runtime.run()?;
+
+    // Save runtime data
+    info!("******************************************\nSaving backtesting runtime data");
+    runtime.save_data(args.output_dir)?;
+    Ok(())
+}
+
+/// Read the optimizer config and apply the command-line overrides
+fn build_optimizer(
+    config: ConfigArgs,
+    range: DateRangeArgs,
+    db: PathBuf,
+) -> Result<Optimizer, CliError> {
+    let mut optimizer = Optimizer::from_file(&config.config)?
+        .with_db_path(db)
+        .with_date_range(range.start, range.end);
+    if let Some(pair) = config.pair {
+        optimizer.set("trading.trading_asset", pair)?;
+    }
+    if let Some(frequency) = config.frequency {
+        optimizer.set("trading.frequency", frequency)?;
+    }
+    Ok(optimizer)
+}
+
+fn optimize(args: OptimizeArgs) -> Result<(), CliError> {
+    let optimizer = build_optimizer(args.config, args.range, args.db)?;
+
+    let results = optimizer.run()?;
+    match results.best() {
+        Some(best) => info!(
+            "Best parameters by {}: {:?}",
+            results.objective().as_str(),
+            best.parameters
+        ),
+        None => info!("Every backtest failed"),
+    }
+
+    info!("Saving optimization results");
+    results.save_csv(args.output)?;
+    Ok(())
+}
+
+fn walk_forward(args: WalkForwardArgs) -> Result<(), CliError> {
+    let optimizer = build_optimizer(args.config, args.range, args.db)?;
+
+    let results = optimizer.walk_forward()?;
+    let metrics = results.out_of_sample_metrics();
+    info!(
+        "Out-of-sample over {} windows: net profit {:.2}, Sharpe ratio {:.4}, max drawdown {:.2}%, {} trades",
+        results.windows().len(),
+        metrics.net_profit,
+        metrics.sharpe_ratio,
+        metrics.max_drawdown * 100.0,
+        metrics.trades
+    );
+
+    info!("Saving walk forward results");
+    results.save_csv(args.output_dir)?;
+    Ok(())
+}
+
+async fn backfill(args: BackfillArgs) -> Result<(), CliError> {
+    let end = args.end.unwrap_or_else(|| Utc::now().naive_utc());
+    let frequencies = if args.frequency.is_empty() {
+        VALID_INTERVALS.iter().map(|f| f.to_string()).collect()
+    } else {
+        args.frequency
+    };
+
+    let client = CoinbaseClient::new();
+    let mut backfill = Backfill::new(&client, CandleStore::new(&args.db));
+    if let Some(request_interval) = args.request_interval {
+        backfill = backfill.with_request_interval(Duration::from_millis(request_interval));
+    }
+    if let Some(max_retries) = args.max_retries {
+        backfill = backfill.with_max_retries(max_retries);
+    }
+    for pair in args.pair.iter() {
+        for frequency in frequencies.iter() {
+            let written = backfill.run(pair, frequency, args.start, end).await?;
+            info!("Stored {} {} candles for {}", written, frequency, pair);
+        }
+    }
+    Ok(())
+}
+
+async fn paper(args: PaperArgs) -> Result<(), CliError> {
+    let mut config = LiveConfig::from_file(&args.config.config)?;
+    args.config.apply(&mut config.trading);
+    let strategy = config.strategy.build()?;
+
+    let mut market = PaperMarket::new(config.trading.frequency.clone());
+    if let Some(fees) = &config.portfolio.fees {
+        market = market.with_fee_calculator(fees.build());
+    }
+    for asset in [&config.trading.trading_asset, &config.trading.market_asset] {
+        let csv = args.csv.iter().find(|(pair, _)| pair == asset);
+        market = match csv {
+            Some((_, path)) => {
+                market.with_csv(asset.as_str(), config.trading.frequency.as_str(), path)
+            }
+            None => {
+                let data = MarketData::from_db_path(&args.db, asset.as_str())
+                    .map_err(|e| CliError::Candles(format!("{:?}", e)))?
+                    .with_date_range(args.range.start, args.range.end);
+                market.with_market_data(asset.as_str(), &data)
+            }
+        }
+        .map_err(|e| CliError::Candles(e.to_string()))?;
+    }
+
+    let mut runtime = LiveRuntime::from_live_config(config, strategy, market)?;
+    runtime.replay().await?;
+    Ok(())
+}
+
+async fn live(args: LiveArgs) -> Result<(), CliError> {
+    let mut config = LiveConfig::from_file(&args.config.config)?;
+    args.config.apply(&mut config.trading);
+    let strategy = config.strategy.build()?;
+
+    let mut client = match &args.credentials {
+        Some(path) => CoinbaseClient::from_credentials_file(path)?,
+        None => CoinbaseClient::from_env()?,
+    };
+    if args.dry_run {
+        client = client.disable_trades();
+    }
+
+    let mut intervals = strategy
+        .intervals()
+        .into_iter()
+        .map(|interval| interval.to_string())
+        .collect::<Vec<_>>();
+    intervals.push(config.trading.frequency.clone());
+    intervals.sort();
+    intervals.dedup();
+    let products = [
+        config.trading.trading_asset.clone(),
+        config.trading.market_asset.clone(),
+    ];
+
+    // charge the Coinbase fees unless others are configured so that profits aren't overstated
+    let default_fees = config.portfolio.fees.is_none();
+    let fee_calculator = client.fee_calculator();
+    let mut runtime = LiveRuntime::from_live_config(config, strategy, client)?;
+    if default_fees {
+        runtime = runtime.with_fee_calculator(fee_calculator);
+    }
+
+    if !args.stream {
+        runtime.run().await?;
+        return Ok(());
+    }
+
+    let intervals = intervals.iter().map(String::as_str).collect::<Vec<_>>();
+    let feed =
+        CoinbaseFeed::new(&[products[0].as_str(), products[1].as_str()]).with_intervals(&intervals);
+
+    let (sender, receiver) = mpsc::channel(FEED_CHANNEL_SIZE);
+    let feed = tokio::spawn(feed.run(sender));
+
+    let result = runtime.run_streaming(receiver).await;
+
+    // report why the feed stopped rather than only that it did
+    feed.abort();
+    if let Ok(Err(e)) = feed.await {
+        return Err(e.into());
+    }
+    result?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+    use std::path::Path;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("2024-01-02").unwrap(), time("2024-01-02 00:00"));
+        assert_eq!(
+            parse_time("2024-01-02 12:30").unwrap(),
+            time("2024-01-02 12:30")
+        );
+        assert_eq!(
+            parse_time("2024-01-02T12:30:00").unwrap(),
+            time("2024-01-02 12:30")
+        );
+        assert!(parse_time("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_parse_backtest() {
+        let cli = Cli::try_parse_from([
+            "tradr",
+            "backtest",
+            "--config",
+            "config.toml",
+            "--pair",
+            "ETH-USD",
+            "--start",
+            "2024-01-01",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Backtest(args) => {
+                assert_eq!(args.config.config, PathBuf::from("config.toml"));
+                assert_eq!(args.config.pair, Some("ETH-USD".to_string()));
+                assert_eq!(args.config.frequency, None);
+                assert_eq!(args.range.start, Some(time("2024-01-01 00:00")));
+                assert_eq!(args.range.end, None);
+                assert_eq!(args.output_dir, PathBuf::from(DEFAULT_OUTPUT_DIR));
+                assert_eq!(args.db, PathBuf::from(DB_PATH));
+            }
+            command => panic!("Unexpected command: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_parse_optimize() {
+        let cli = Cli::try_parse_from([
+            "tradr",
+            "optimize",
+            "--config",
+            "config.toml",
+            "--output",
+            "results.csv",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Optimize(args) => {
+                assert_eq!(args.config.config, PathBuf::from("config.toml"));
+                assert_eq!(args.output, PathBuf::from("results.csv"));
+                assert_eq!(args.range.start, None);
+                assert_eq!(args.db, PathBuf::from(DB_PATH));
+            }
+            command => panic!("Unexpected command: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_parse_walk_forward() {
+        let cli = Cli::try_parse_from(["tradr", "walk-forward", "--pair", "ETH-USD"]).unwrap();
+        match cli.command {
+            Command::WalkForward(args) => {
+                assert_eq!(args.config.config, PathBuf::from(DEFAULT_CONFIG_PATH));
+                assert_eq!(args.config.pair, Some("ETH-USD".to_string()));
+                assert_eq!(args.output_dir, PathBuf::from(DEFAULT_WALK_FORWARD_DIR));
+            }
+            command => panic!("Unexpected command: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_parse_paper() {
+        let cli = Cli::try_parse_from([
+            "tradr",
+            "paper",
+            "--csv",
+            "BTC-USD=data/btc.csv",
+            "--csv",
+            "ETH-USD=data/eth.csv",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Paper(args) => {
+                assert_eq!(
+                    args.csv,
+                    vec![
+                        ("BTC-USD".to_string(), PathBuf::from("data/btc.csv")),
+                        ("ETH-USD".to_string(), PathBuf::from("data/eth.csv")),
+                    ]
+                );
+                assert_eq!(args.db, PathBuf::from(DB_PATH));
+            }
+            command => panic!("Unexpected command: {:?}", command),
+        }
+
+        assert!(Cli::try_parse_from(["tradr", "paper", "--csv", "data/btc.csv"]).is_err());
+        assert!(Cli::try_parse_from(["tradr", "paper", "--csv", "BTC-USD="]).is_err());
+    }
+
+    #[test]
+    fn test_parse_backfill() {
+        let cli = Cli::try_parse_from([
+            "tradr",
+            "backfill",
+            "-p",
+            "BTC-USD",
+            "-p",
+            "ETH-USD",
+            "-f",
+            "1h",
+            "--start",
+            "2024-01-01",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Backfill(args) => {
+                assert_eq!(args.pair, vec!["BTC-USD", "ETH-USD"]);
+                assert_eq!(args.frequency, vec!["1h"]);
+                assert_eq!(args.start, time("2024-01-01 00:00"));
+                assert_eq!(args.request_interval, None);
+                assert_eq!(args.max_retries, None);
+            }
+            command => panic!("Unexpected command: {:?}", command),
+        }
+
+        let cli = Cli::try_parse_from([
+            "tradr",
+            "backfill",
+            "-p",
+            "BTC-USD",
+            "--start",
+            "2024-01-01",
+            "--request-interval",
+            "500",
+            "--max-retries",
+            "2",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Backfill(args) => {
+                assert_eq!(args.request_interval, Some(500));
+                assert_eq!(args.max_retries, Some(2));
+            }
+            command => panic!("Unexpected command: {:?}", command),
+        }
+
+        // pair and start are required
+        assert!(Cli::try_parse_from(["tradr", "backfill", "--start", "2024-01-01"]).is_err());
+        assert!(Cli::try_parse_from(["tradr", "backfill", "-p", "BTC-USD"]).is_err());
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut trading = TradingConfig {
+            frequency: "1m".to_string(),
+            trading_asset: "BTC-USD".to_string(),
+            market_asset: "ETH-USD".to_string(),
+            order_type: Default::default(),
+        };
+        let args = ConfigArgs {
+            config: PathBuf::from(DEFAULT_CONFIG_PATH),
+            pair: Some("SOL-USD".to_string()),
+            frequency: None,
+        };
+        args.apply(&mut trading);
+        assert_eq!(trading.trading_asset, "SOL-USD");
+        assert_eq!(trading.frequency, "1m");
+        assert_eq!(trading.market_asset, "ETH-USD");
+    }
+
+    #[tokio::test]
+    async fn test_missing_config_exit_code() {
+        let cli =
+            Cli::try_parse_from(["tradr", "backtest", "--config", "does/not/exist.toml"]).unwrap();
+        let error = cli.run().await.unwrap_err();
+        assert!(matches!(
+            error,
+            CliError::Backtesting(BacktestingErrors::ConfigError(_))
+        ));
+        assert_eq!(error.exit_code(), ExitCode::from(CONFIG_EXIT_CODE));
+    }
+
+    #[tokio::test]
+    async fn test_missing_optimize_section_exit_code() {
+        let path = create_temp_dir(Path::new("cli_optimize_testing")).join("config.toml");
+        std::fs::write(&path, "[trading]\nfrequency = \"1m\"\n").unwrap();
+
+        let cli =
+            Cli::try_parse_from(["tradr", "optimize", "--config", path.to_str().unwrap()]).unwrap();
+        let error = cli.run().await.unwrap_err();
+        assert!(matches!(
+            error,
+            CliError::Optimizer(OptimizerError::Config(_))
+        ));
+        assert_eq!(error.exit_code(), ExitCode::from(CONFIG_EXIT_CODE));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_strategy_exit_code() {
+        let config = r#"
+            [portfolio]
+            assets = 0.0
+            capital = 100.0
+            threshold = 10.0
+
+            [risk]
+            max_position_size = 50.0
+            stop_loss_percentage = 1.0
+            take_profit_percentage = 1.0
+            max_beta = 1.0
+            var_limit = 1.0
+            min_sharpe_ratio = 0.0
+            unrealized_pnl_limit = 1.0
+
+            [trading]
+            frequency = "1m"
+            trading_asset = "BTC-USD"
+            market_asset = "ETH-USD"
+
+            [[strategy.indicators]]
+            name = "unknown"
+        "#;
+        let path = create_temp_dir(Path::new("cli_testing")).join("config.toml");
+        std::fs::write(&path, config).unwrap();
+
+        let cli =
+            Cli::try_parse_from(["tradr", "backtest", "--config", path.to_str().unwrap()]).unwrap();
+        let error = cli.run().await.unwrap_err();
+        assert!(matches!(
+            error,
+            CliError::Strategy(StrategyConfigError::UnknownIndicator(..))
+        ));
+        assert_eq!(error.exit_code(), ExitCode::from(CONFIG_EXIT_CODE));
+    }
+}
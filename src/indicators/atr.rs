@@ -0,0 +1,152 @@
+use crate::indicators::{candle_bars, graph_from_columns, GraphProcessingError};
+use crate::processor::CandleProcessor;
+use crate::types::{ScoredSignal, Signal};
+use log::info;
+use polars::prelude::*;
+use ta::indicators::AverageTrueRange;
+use ta::Next;
+
+const DEFAULT_PERIOD: usize = 14;
+const DEFAULT_MULTIPLIER: f64 = 1.0;
+
+/// The Average True Range (ATR) indicator used as a volatility gate
+///
+/// Price changes smaller than `multiplier` times the ATR of the previous candle are treated as noise and
+/// signal a hold. Larger changes signal a buy when the price rises and a sell when the price falls.
+///
+/// Confidence grows with the amount the price change exceeds the gate, measured in ATRs.
+#[derive(Debug, Clone)]
+pub struct ATR {
+    period: usize,
+    multiplier: f64,
+}
+
+impl ATR {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            multiplier: DEFAULT_MULTIPLIER,
+        }
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    fn calculate_atr(&self, candles: &DataFrame) -> Result<Vec<f64>, GraphProcessingError> {
+        let mut atr = AverageTrueRange::new(self.period)
+            .map_err(|e| GraphProcessingError::InvalidParameter(e.to_string()))?;
+        Ok(candle_bars(candles)?
+            .iter()
+            .map(|bar| atr.next(bar))
+            .collect())
+    }
+}
+
+impl Default for ATR {
+    fn default() -> Self {
+        Self::new(DEFAULT_PERIOD)
+    }
+}
+
+impl CandleProcessor for ATR {
+    type ReturnType = ScoredSignal;
+    type ErrorType = GraphProcessingError;
+
+    fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
+        if candles.height() <= self.period {
+            return Ok(Signal::Hold.into());
+        }
+
+        let atr = self.calculate_atr(candles)?;
+        let closes = candle_bars(candles)?
+            .iter()
+            .map(|bar| bar.close)
+            .collect::<Vec<_>>();
+
+        let len = closes.len();
+        let change = closes[len - 1] - closes[len - 2];
+        let previous_atr = atr[len - 2];
+        let gate = previous_atr * self.multiplier;
+
+        if change.abs() <= gate {
+            return Ok(Signal::Hold.into());
+        }
+        let confidence = if previous_atr > 0.0 {
+            (change.abs() - gate) / previous_atr
+        } else {
+            1.0
+        };
+        Ok(ScoredSignal::new(Signal::from_score(change), confidence))
+    }
+
+    fn get_name(&self) -> &'static str {
+        "atr"
+    }
+
+    fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+        info!("Calculating ATR");
+
+        let atr = self.calculate_atr(candles).unwrap();
+        graph_from_columns(candles, vec![("atr", atr)]).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+
+    #[test]
+    fn test_default() {
+        let atr = ATR::default();
+        assert_eq!(atr.period, 14);
+        assert_eq!(atr.multiplier, 1.0);
+    }
+
+    #[test]
+    fn test_process_candle() {
+        let atr = ATR::new(3);
+
+        // every candle has a true range of 2
+        let mut closes = vec![100.0; 5];
+
+        // change is within the gate
+        closes.push(101.0);
+        let signal = atr.process_candle(&create_candles(&closes)).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+
+        // change of 3 exceeds the gate of 2 by half an ATR
+        *closes.last_mut().unwrap() = 103.0;
+        let signal = atr.process_candle(&create_candles(&closes)).unwrap();
+        assert_eq!(signal, ScoredSignal::new(Signal::Buy, 0.5));
+
+        *closes.last_mut().unwrap() = 90.0;
+        let signal = atr.process_candle(&create_candles(&closes)).unwrap();
+        assert_eq!(signal, ScoredSignal::new(Signal::Sell, 1.0));
+
+        // the gate widens with the multiplier
+        *closes.last_mut().unwrap() = 103.0;
+        let signal = atr
+            .clone()
+            .with_multiplier(2.0)
+            .process_candle(&create_candles(&closes))
+            .unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+    }
+
+    #[test]
+    fn test_get_raw_dataframe() {
+        let candles = create_candles(&[100.0, 100.0, 100.0, 104.0]);
+        let graph = ATR::new(3).get_raw_dataframe(&candles);
+
+        assert_eq!(graph.get_column_names(), ["time", "atr"]);
+
+        let atr = graph.column("atr").unwrap().f64().unwrap();
+        assert_eq!(atr.get(2), Some(2.0));
+
+        // true range is 5 and the EMA multiplier is 0.5
+        assert_eq!(atr.get(3), Some(3.5));
+    }
+}
@@ -1,8 +1,9 @@
-use crate::indicators::GraphProcessingError;
+use crate::indicators::{candle_value, GraphProcessingError};
 use crate::processor::CandleProcessor;
-use crate::types::Signal;
+use crate::types::{Candle, ScoredSignal, Signal};
 use log::info;
 use polars::prelude::*;
+use std::collections::VecDeque;
 
 const DEFAULT_PERIOD: usize = 20;
 const DEFAULT_MULTIPLIER: f64 = 2.0;
@@ -18,6 +19,60 @@ pub struct BBands {
     // Indicator / signal parameters
     threshold: f64,
     source_column: String,
+
+    // State used by `process_new_candle`
+    window: RollingWindow,
+}
+
+/// Lower, middle and upper band values for a single candle
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bands {
+    lower: f64,
+    middle: f64,
+    upper: f64,
+}
+
+/// Running mean and variance of the most recent values
+///
+/// Values are added and removed using Welford's algorithm so that each update is O(1) regardless of the
+/// window size.
+#[derive(Debug, Clone, Default)]
+struct RollingWindow {
+    values: VecDeque<f64>,
+    mean: f64,
+    /// Sum of squared differences from the mean
+    m2: f64,
+}
+
+impl RollingWindow {
+    /// Add a value, removing the oldest value once the window holds `period` values
+    fn push(&mut self, value: f64, period: usize) {
+        while self.values.len() >= period.max(1) {
+            let removed = self.values.pop_front().unwrap();
+            if self.values.is_empty() {
+                self.mean = 0.0;
+                self.m2 = 0.0;
+            } else {
+                let delta = removed - self.mean;
+                self.mean -= delta / self.values.len() as f64;
+                self.m2 -= delta * (removed - self.mean);
+            }
+        }
+
+        self.values.push_back(value);
+        let delta = value - self.mean;
+        self.mean += delta / self.values.len() as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Mean and sample standard deviation, or `None` until the window holds `period` values
+    fn mean_std(&self, period: usize) -> Option<(f64, f64)> {
+        if self.values.len() < period {
+            return None;
+        }
+        let variance = self.m2.max(0.0) / (self.values.len() - 1) as f64;
+        Some((self.mean, variance.sqrt()))
+    }
 }
 
 impl BBands {
@@ -27,9 +82,20 @@ impl BBands {
             multiplier,
             threshold: DEFAULT_THRESHOLD,
             source_column: String::from(DEFAULT_SOURCE_COL_NAME),
+            window: RollingWindow::default(),
         }
     }
 
+    pub fn with_period(mut self, period: usize) -> Self {
+        self.period = period;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
     pub fn with_threshold(mut self, threshold: f64) -> Self {
         self.threshold = threshold;
         self
@@ -70,17 +136,20 @@ impl BBands {
     /// This function uses a threshold to determine where the close price is relative to the bounds of the
     /// Bollinger Bands.
     ///
+    /// Confidence is the distance of the close price past the threshold band in standard deviations. A
+    /// close one standard deviation past the threshold band has full confidence.
+    ///
     /// # Arguments
     /// * `graph` - A subset of the indicator graph
     /// * `candles` - Candle data
     ///
     /// # Returns
-    /// A DataFrame with time and signals columns
+    /// The signal for the last candle
     fn extract_signal(
         &self,
         graph: &DataFrame,
         candles: &DataFrame,
-    ) -> Result<Signal, GraphProcessingError> {
+    ) -> Result<ScoredSignal, GraphProcessingError> {
         let graph = graph.tail(Some(1));
 
         let lower = graph
@@ -114,17 +183,62 @@ impl BBands {
             .get(0)
             .unwrap();
 
+        Ok(self.band_signal(
+            &Bands {
+                lower,
+                middle,
+                upper,
+            },
+            candle_price,
+        ))
+    }
+
+    /// Calculate signal from the band values and the close price of a candle
+    fn band_signal(&self, bands: &Bands, candle_price: f64) -> ScoredSignal {
+        let Bands {
+            lower,
+            middle,
+            upper,
+        } = *bands;
+
         let buy_threshold = middle - (middle - lower) * self.threshold;
         let sell_threshold = middle + (upper - middle) * self.threshold;
 
+        let std_dev = (middle - lower) / self.multiplier;
+        let confidence = |distance: f64| {
+            if std_dev > 0.0 {
+                distance / std_dev
+            } else {
+                1.0
+            }
+        };
+
         if candle_price < buy_threshold {
-            Ok(Signal::Buy)
+            ScoredSignal::new(Signal::Buy, confidence(buy_threshold - candle_price))
         } else if candle_price > sell_threshold {
-            Ok(Signal::Sell)
+            ScoredSignal::new(Signal::Sell, confidence(candle_price - sell_threshold))
         } else {
-            Ok(Signal::Hold)
+            Signal::Hold.into()
         }
     }
+
+    /// Add a candle to the rolling window and calculate the bands for it
+    ///
+    /// # Returns
+    /// The bands for the candle, or `None` until `period` candles have been ingested
+    fn update_bands(&mut self, candle: &Candle) -> Result<Option<Bands>, GraphProcessingError> {
+        let value = candle_value(candle, &self.source_column)?;
+        self.window.push(value, self.period);
+
+        Ok(self
+            .window
+            .mean_std(self.period)
+            .map(|(mean, std_dev)| Bands {
+                lower: mean - std_dev * self.multiplier,
+                middle: mean,
+                upper: mean + std_dev * self.multiplier,
+            }))
+    }
 }
 
 impl Default for BBands {
@@ -134,12 +248,12 @@ impl Default for BBands {
 }
 
 impl CandleProcessor for BBands {
-    type ReturnType = Signal;
+    type ReturnType = ScoredSignal;
     type ErrorType = GraphProcessingError;
 
     fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
         if candles.height() < self.period {
-            return Ok(Signal::Hold);
+            return Ok(Signal::Hold.into());
         }
 
         // check validity of row
@@ -163,6 +277,24 @@ impl CandleProcessor for BBands {
 
         self.calculate_bollinger_bands(candles).unwrap()
     }
+
+    fn process_new_candle(
+        &mut self,
+        candle: &Candle,
+    ) -> Option<Result<Self::ReturnType, Self::ErrorType>> {
+        let signal = self.update_bands(candle).and_then(|bands| match bands {
+            Some(bands) => {
+                let candle_price = candle_value(candle, DEFAULT_SOURCE_COL_NAME)?;
+                Ok(self.band_signal(&bands, candle_price))
+            }
+            None => Ok(Signal::Hold.into()),
+        });
+        Some(signal)
+    }
+
+    fn reset(&mut self) {
+        self.window = RollingWindow::default();
+    }
 }
 
 #[cfg(test)]
@@ -182,4 +314,94 @@ mod tests {
         assert_eq!(bb.period, 20);
         assert_eq!(bb.multiplier, 2.0);
     }
+
+    #[test]
+    fn test_signal_confidence() {
+        use crate::processor::CandleProcessor;
+        use crate::types::Signal;
+
+        // alternating closes have a mean of 100 and a standard deviation of ~1.03
+        let candles = |last: f64| {
+            let mut closes = (0..19)
+                .map(|i| if i % 2 == 0 { 99.0 } else { 101.0 })
+                .collect::<Vec<f64>>();
+            closes.push(last);
+            df![
+                "time" => (0..20).collect::<Vec<i64>>(),
+                "open" => &closes,
+                "high" => &closes,
+                "low" => &closes,
+                "close" => &closes,
+                "volume" => vec![1.0; 20]
+            ]
+            .unwrap()
+        };
+        let bb = super::BBands::default();
+
+        let signal = bb.process_candle(&candles(100.0)).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+
+        // barely past the lower band
+        let signal = bb.process_candle(&candles(97.5)).unwrap();
+        assert_eq!(signal.signal, Signal::Buy);
+        assert!(signal.confidence > 0.0 && signal.confidence < 0.5);
+
+        // far past the upper band
+        let signal = bb.process_candle(&candles(110.0)).unwrap();
+        assert_eq!(signal.signal, Signal::Sell);
+        assert_eq!(signal.confidence, 1.0);
+    }
+
+    #[test]
+    fn test_process_new_candle() {
+        use crate::indicators::create_candles;
+        use crate::processor::CandleProcessor;
+        use crate::utils::extract_candles_from_df;
+
+        let closes = (0..60)
+            .map(|i| 100.0 + (i as f64 * 0.7).sin() * 5.0 + i as f64 * 0.1)
+            .collect::<Vec<f64>>();
+        let candles = create_candles(&closes);
+        let mut bb = super::BBands::new(10, 2.0).with_threshold(0.5);
+
+        let graph = bb.get_raw_dataframe(&candles);
+        let lower = graph.column("lower").unwrap().f64().unwrap();
+        let middle = graph.column("middle").unwrap().f64().unwrap();
+        let upper = graph.column("upper").unwrap().f64().unwrap();
+
+        let close = |a: Option<f64>, b: f64| (a.unwrap() - b).abs() < 1e-9;
+
+        for (i, candle) in extract_candles_from_df(&candles)
+            .unwrap()
+            .iter()
+            .enumerate()
+        {
+            match bb.update_bands(candle).unwrap() {
+                Some(bands) => {
+                    assert!(close(lower.get(i), bands.lower));
+                    assert!(close(middle.get(i), bands.middle));
+                    assert!(close(upper.get(i), bands.upper));
+                }
+                None => assert!(i < 9 && middle.get(i).is_none()),
+            }
+        }
+
+        // streamed signals match the batch signals
+        bb.reset();
+        let mut buys_and_sells = 0;
+        for (i, candle) in extract_candles_from_df(&candles)
+            .unwrap()
+            .iter()
+            .enumerate()
+        {
+            let streamed = bb.process_new_candle(candle).unwrap().unwrap();
+            let batch = bb.process_candle(&candles.head(Some(i + 1))).unwrap();
+            assert_eq!(streamed.signal, batch.signal);
+            assert!((streamed.confidence - batch.confidence).abs() < 1e-9);
+            if streamed.signal != crate::types::Signal::Hold {
+                buys_and_sells += 1;
+            }
+        }
+        assert!(buys_and_sells > 0);
+    }
 }
@@ -0,0 +1,201 @@
+use crate::indicators::{candle_values, crossover, graph_from_columns, GraphProcessingError};
+use crate::processor::CandleProcessor;
+use crate::types::{ScoredSignal, Signal};
+use log::info;
+use polars::prelude::*;
+use ta::indicators::{ExponentialMovingAverage, SimpleMovingAverage};
+use ta::Next;
+
+const DEFAULT_FAST_PERIOD: usize = 12;
+const DEFAULT_SLOW_PERIOD: usize = 26;
+const DEFAULT_SOURCE_COL_NAME: &str = "close";
+
+/// Type of moving average used by [`MovingAverageCrossover`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MovingAverage {
+    Simple,
+    Exponential,
+}
+
+impl MovingAverage {
+    /// Calculate the moving average of the values
+    fn calculate(&self, period: usize, values: &[f64]) -> Result<Vec<f64>, GraphProcessingError> {
+        let invalid =
+            |e: ta::errors::TaError| GraphProcessingError::InvalidParameter(e.to_string());
+        match self {
+            MovingAverage::Simple => {
+                let mut sma = SimpleMovingAverage::new(period).map_err(invalid)?;
+                Ok(values.iter().map(|value| sma.next(*value)).collect())
+            }
+            MovingAverage::Exponential => {
+                let mut ema = ExponentialMovingAverage::new(period).map_err(invalid)?;
+                Ok(values.iter().map(|value| ema.next(*value)).collect())
+            }
+        }
+    }
+}
+
+/// Moving average crossover indicator
+///
+/// Signals a buy when the fast moving average crosses above the slow moving average and a sell when the
+/// fast moving average crosses below the slow moving average.
+#[derive(Debug, Clone)]
+pub struct MovingAverageCrossover {
+    average: MovingAverage,
+    fast_period: usize,
+    slow_period: usize,
+    source_column: String,
+}
+
+impl MovingAverageCrossover {
+    pub fn new(average: MovingAverage, fast_period: usize, slow_period: usize) -> Self {
+        Self {
+            average,
+            fast_period,
+            slow_period,
+            source_column: String::from(DEFAULT_SOURCE_COL_NAME),
+        }
+    }
+
+    /// Crossover of exponential moving averages with default periods
+    pub fn ema() -> Self {
+        Self::new(
+            MovingAverage::Exponential,
+            DEFAULT_FAST_PERIOD,
+            DEFAULT_SLOW_PERIOD,
+        )
+    }
+
+    /// Crossover of simple moving averages with default periods
+    pub fn sma() -> Self {
+        Self::new(
+            MovingAverage::Simple,
+            DEFAULT_FAST_PERIOD,
+            DEFAULT_SLOW_PERIOD,
+        )
+    }
+
+    pub fn with_fast_period(mut self, fast_period: usize) -> Self {
+        self.fast_period = fast_period;
+        self
+    }
+
+    pub fn with_slow_period(mut self, slow_period: usize) -> Self {
+        self.slow_period = slow_period;
+        self
+    }
+
+    pub fn fast_period(&self) -> usize {
+        self.fast_period
+    }
+
+    pub fn slow_period(&self) -> usize {
+        self.slow_period
+    }
+
+    pub fn with_source_column(mut self, source_column: String) -> Self {
+        self.source_column = source_column;
+        self
+    }
+
+    /// Calculate the fast and slow moving averages
+    fn calculate_averages(
+        &self,
+        candles: &DataFrame,
+    ) -> Result<(Vec<f64>, Vec<f64>), GraphProcessingError> {
+        let values = candle_values(candles, &self.source_column)?;
+        let fast = self.average.calculate(self.fast_period, &values)?;
+        let slow = self.average.calculate(self.slow_period, &values)?;
+        Ok((fast, slow))
+    }
+}
+
+impl CandleProcessor for MovingAverageCrossover {
+    type ReturnType = ScoredSignal;
+    type ErrorType = GraphProcessingError;
+
+    fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
+        if candles.height() <= self.slow_period {
+            return Ok(Signal::Hold.into());
+        }
+
+        let (fast, slow) = self.calculate_averages(candles)?;
+        Ok(crossover(&fast, &slow).into())
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self.average {
+            MovingAverage::Simple => "sma_crossover",
+            MovingAverage::Exponential => "ema_crossover",
+        }
+    }
+
+    fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+        info!("Calculating moving average crossover");
+
+        let (fast, slow) = self.calculate_averages(candles).unwrap();
+        let (fast_name, slow_name) = match self.average {
+            MovingAverage::Simple => ("sma_fast", "sma_slow"),
+            MovingAverage::Exponential => ("ema_fast", "ema_slow"),
+        };
+        graph_from_columns(candles, vec![(fast_name, fast), (slow_name, slow)]).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+
+    #[test]
+    fn test_new() {
+        let crossover = MovingAverageCrossover::ema()
+            .with_fast_period(5)
+            .with_slow_period(10);
+        assert_eq!(crossover.average, MovingAverage::Exponential);
+        assert_eq!(crossover.fast_period, 5);
+        assert_eq!(crossover.slow_period, 10);
+        assert_eq!(crossover.get_name(), "ema_crossover");
+        assert_eq!(MovingAverageCrossover::sma().get_name(), "sma_crossover");
+    }
+
+    #[test]
+    fn test_process_candle() {
+        let crossover = MovingAverageCrossover::new(MovingAverage::Simple, 2, 3);
+
+        // fast: 101.5 -> 100.5, slow: 101 -> 101
+        let candles = create_candles(&[100.0, 101.0, 102.0, 99.0]);
+        let signal = crossover.process_candle(&candles).unwrap();
+        assert_eq!(signal, ScoredSignal::from(Signal::Sell));
+
+        // fast: 100.5 -> 101.5, slow: 101 -> 101
+        let candles = create_candles(&[102.0, 101.0, 100.0, 103.0]);
+        let signal = crossover.process_candle(&candles).unwrap();
+        assert_eq!(signal, ScoredSignal::from(Signal::Buy));
+
+        // fast stays above slow
+        let candles = create_candles(&[100.0, 101.0, 102.0, 103.0]);
+        let signal = crossover.process_candle(&candles).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+
+        // not enough candles
+        let candles = create_candles(&[100.0, 101.0, 102.0]);
+        let signal = crossover.process_candle(&candles).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+    }
+
+    #[test]
+    fn test_get_raw_dataframe() {
+        let candles = create_candles(&[100.0, 101.0, 102.0, 99.0]);
+        let graph =
+            MovingAverageCrossover::new(MovingAverage::Simple, 2, 3).get_raw_dataframe(&candles);
+
+        assert_eq!(graph.get_column_names(), ["time", "sma_fast", "sma_slow"]);
+
+        let fast = graph.column("sma_fast").unwrap().f64().unwrap();
+        let slow = graph.column("sma_slow").unwrap().f64().unwrap();
+        assert_eq!(fast.get(3), Some(100.5));
+        assert_eq!(slow.get(2), Some(101.0));
+        assert_eq!(slow.get(3), Some(100.66666666666667));
+    }
+}
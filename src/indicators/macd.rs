@@ -0,0 +1,208 @@
+use crate::indicators::{candle_values, crossover, graph_from_columns, GraphProcessingError};
+use crate::processor::CandleProcessor;
+use crate::types::{ScoredSignal, Signal};
+use log::info;
+use polars::prelude::*;
+use ta::indicators::MovingAverageConvergenceDivergence;
+use ta::Next;
+
+const DEFAULT_FAST_PERIOD: usize = 12;
+const DEFAULT_SLOW_PERIOD: usize = 26;
+const DEFAULT_SIGNAL_PERIOD: usize = 9;
+const DEFAULT_SOURCE_COL_NAME: &str = "close";
+
+/// The Moving Average Convergence Divergence (MACD) indicator
+///
+/// Signals a buy when the MACD line crosses above the signal line and a sell when the MACD line crosses
+/// below the signal line.
+#[derive(Debug, Clone)]
+pub struct MACD {
+    fast_period: usize,
+    slow_period: usize,
+    signal_period: usize,
+    source_column: String,
+}
+
+/// Calculated MACD, signal line and histogram values
+struct MACDGraph {
+    macd: Vec<f64>,
+    signal: Vec<f64>,
+    histogram: Vec<f64>,
+}
+
+impl MACD {
+    pub fn new(fast_period: usize, slow_period: usize, signal_period: usize) -> Self {
+        Self {
+            fast_period,
+            slow_period,
+            signal_period,
+            source_column: String::from(DEFAULT_SOURCE_COL_NAME),
+        }
+    }
+
+    pub fn with_fast_period(mut self, fast_period: usize) -> Self {
+        self.fast_period = fast_period;
+        self
+    }
+
+    pub fn with_slow_period(mut self, slow_period: usize) -> Self {
+        self.slow_period = slow_period;
+        self
+    }
+
+    pub fn with_signal_period(mut self, signal_period: usize) -> Self {
+        self.signal_period = signal_period;
+        self
+    }
+
+    pub fn fast_period(&self) -> usize {
+        self.fast_period
+    }
+
+    pub fn slow_period(&self) -> usize {
+        self.slow_period
+    }
+
+    pub fn with_source_column(mut self, source_column: String) -> Self {
+        self.source_column = source_column;
+        self
+    }
+
+    fn calculate_macd(&self, candles: &DataFrame) -> Result<MACDGraph, GraphProcessingError> {
+        let mut macd = MovingAverageConvergenceDivergence::new(
+            self.fast_period,
+            self.slow_period,
+            self.signal_period,
+        )
+        .map_err(|e| GraphProcessingError::InvalidParameter(e.to_string()))?;
+
+        let mut graph = MACDGraph {
+            macd: Vec::with_capacity(candles.height()),
+            signal: Vec::with_capacity(candles.height()),
+            histogram: Vec::with_capacity(candles.height()),
+        };
+        for value in candle_values(candles, &self.source_column)? {
+            let output = macd.next(value);
+            graph.macd.push(output.macd);
+            graph.signal.push(output.signal);
+            graph.histogram.push(output.histogram);
+        }
+        Ok(graph)
+    }
+}
+
+impl Default for MACD {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_FAST_PERIOD,
+            DEFAULT_SLOW_PERIOD,
+            DEFAULT_SIGNAL_PERIOD,
+        )
+    }
+}
+
+impl CandleProcessor for MACD {
+    type ReturnType = ScoredSignal;
+    type ErrorType = GraphProcessingError;
+
+    fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
+        if candles.height() <= self.slow_period {
+            return Ok(Signal::Hold.into());
+        }
+
+        let graph = self.calculate_macd(candles)?;
+        Ok(crossover(&graph.macd, &graph.signal).into())
+    }
+
+    fn get_name(&self) -> &'static str {
+        "macd"
+    }
+
+    fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+        info!("Calculating MACD");
+
+        let graph = self.calculate_macd(candles).unwrap();
+        graph_from_columns(
+            candles,
+            vec![
+                ("macd", graph.macd),
+                ("macd_signal", graph.signal),
+                ("macd_histogram", graph.histogram),
+            ],
+        )
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+
+    /// Prices fall for 15 candles, then rise for 15 candles
+    fn v_shaped() -> Vec<f64> {
+        (0..30)
+            .map(|i| {
+                if i < 15 {
+                    100.0 - i as f64
+                } else {
+                    70.0 + i as f64
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default() {
+        let macd = MACD::default();
+        assert_eq!(macd.fast_period, 12);
+        assert_eq!(macd.slow_period, 26);
+        assert_eq!(macd.signal_period, 9);
+    }
+
+    #[test]
+    fn test_process_candle() {
+        let macd = MACD::new(3, 6, 3);
+        let candles = create_candles(&v_shaped());
+        let graph = macd.get_raw_dataframe(&candles);
+        let histogram = graph.column("macd_histogram").unwrap().f64().unwrap();
+
+        // a buy is signalled exactly when the histogram turns positive
+        let mut buys = 0;
+        for end in 7..=candles.height() {
+            let signal = macd.process_candle(&candles.head(Some(end))).unwrap();
+            let crossed =
+                histogram.get(end - 2).unwrap() <= 0.0 && histogram.get(end - 1).unwrap() > 0.0;
+            assert_eq!(signal.signal == Signal::Buy, crossed);
+            assert_ne!(signal.signal, Signal::Sell);
+            if crossed {
+                buys += 1;
+            }
+        }
+        assert_eq!(buys, 1);
+
+        // not enough candles
+        let signal = macd.process_candle(&candles.head(Some(6))).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+    }
+
+    #[test]
+    fn test_get_raw_dataframe() {
+        let candles = create_candles(&v_shaped());
+        let graph = MACD::new(3, 6, 3).get_raw_dataframe(&candles);
+
+        assert_eq!(
+            graph.get_column_names(),
+            ["time", "macd", "macd_signal", "macd_histogram"]
+        );
+        assert_eq!(graph.height(), candles.height());
+
+        let macd = graph.column("macd").unwrap().f64().unwrap();
+        let signal = graph.column("macd_signal").unwrap().f64().unwrap();
+        let histogram = graph.column("macd_histogram").unwrap().f64().unwrap();
+        for i in 0..graph.height() {
+            let expected = macd.get(i).unwrap() - signal.get(i).unwrap();
+            assert!((histogram.get(i).unwrap() - expected).abs() < 1e-9);
+        }
+    }
+}
@@ -19,16 +19,27 @@
 ///
 /// # Notes
 /// Due to the nature of candle data as it is received, there is no sorting that is performed internally.
+mod atr;
 mod bbands;
+mod crossover;
+mod macd;
+mod rsi;
+mod stochastic;
 mod vwap;
 
 // Re-exports
+pub use atr::ATR;
 pub use bbands::BBands;
+pub use crossover::MovingAverageCrossover;
+pub use macd::MACD;
+pub use rsi::RSI;
+pub use stochastic::Stochastic;
 pub use vwap::VWAP;
 
 use crate::processor::CandleProcessor;
-use crate::types::Signal;
+use crate::types::{Candle, ScoredSignal, Signal};
 use polars::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
 
 #[deprecated(since = "0.5.0", note = "Create a new error enum")]
 #[derive(Debug)]
@@ -57,6 +68,8 @@ pub enum GraphProcessingError {
     CandlesEmpty,
     DataFrameError(PolarsError),
     InsufficientCandleData,
+    /// Raised when an indicator function cannot be created with the given parameters
+    InvalidParameter(String),
 }
 
 #[deprecated(since = "0.5.0", note = "Create a new error enum")]
@@ -66,6 +79,131 @@ pub enum IndicatorProcessingError {
     SignalError(SignalProcessingError),
 }
 
+/// A single candle used as input for indicator functions from the TA library
+struct Bar {
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+impl ta::High for Bar {
+    fn high(&self) -> f64 {
+        self.high
+    }
+}
+
+impl ta::Low for Bar {
+    fn low(&self) -> f64 {
+        self.low
+    }
+}
+
+impl ta::Close for Bar {
+    fn close(&self) -> f64 {
+        self.close
+    }
+}
+
+/// Read a candle column as a vector of floats
+///
+/// # Arguments
+/// * `candles` - The DataFrame with the candle data
+/// * `column` - Name of the column to read
+fn candle_values(candles: &DataFrame, column: &str) -> Result<Vec<f64>, GraphProcessingError> {
+    candles
+        .column(column)
+        .map_err(|_| GraphProcessingError::InvalidCandleColumns)?
+        .f64()
+        .map_err(|_| GraphProcessingError::InvalidCandleColumns)?
+        .into_iter()
+        .map(|value| value.ok_or(GraphProcessingError::InvalidCandleColumns))
+        .collect()
+}
+
+/// Read a single candle field by its column name
+///
+/// Used when candles are ingested one at a time instead of as a DataFrame.
+fn candle_value(candle: &Candle, column: &str) -> Result<f64, GraphProcessingError> {
+    let value = match column {
+        "open" => candle.open,
+        "high" => candle.high,
+        "low" => candle.low,
+        "close" => candle.close,
+        "volume" => candle.volume,
+        _ => return Err(GraphProcessingError::InvalidCandleColumns),
+    };
+    value
+        .to_f64()
+        .ok_or(GraphProcessingError::InvalidCandleColumns)
+}
+
+/// Read the high, low and close columns as [`Bar`]s
+fn candle_bars(candles: &DataFrame) -> Result<Vec<Bar>, GraphProcessingError> {
+    let high = candle_values(candles, "high")?;
+    let low = candle_values(candles, "low")?;
+    let close = candle_values(candles, "close")?;
+    Ok(high
+        .into_iter()
+        .zip(low)
+        .zip(close)
+        .map(|((high, low), close)| Bar { high, low, close })
+        .collect())
+}
+
+/// Create an indicator graph from the candle "time" column and the given columns
+fn graph_from_columns(
+    candles: &DataFrame,
+    columns: Vec<(&str, Vec<f64>)>,
+) -> Result<DataFrame, GraphProcessingError> {
+    let mut series = vec![candles
+        .column("time")
+        .map_err(|_| GraphProcessingError::InvalidCandleColumns)?
+        .clone()];
+    series.extend(
+        columns
+            .into_iter()
+            .map(|(name, values)| Series::new(name, values)),
+    );
+    DataFrame::new(series).map_err(GraphProcessingError::DataFrameError)
+}
+
+/// Detect whether `fast` crossed `slow` on the last value
+///
+/// # Returns
+/// * [`Signal::Buy`] - If `fast` crossed above `slow`
+/// * [`Signal::Sell`] - If `fast` crossed below `slow`
+/// * [`Signal::Hold`] - Otherwise, or if there are less than two values
+fn crossover(fast: &[f64], slow: &[f64]) -> Signal {
+    let len = fast.len().min(slow.len());
+    if len < 2 {
+        return Signal::Hold;
+    }
+    let previous = fast[len - 2] - slow[len - 2];
+    let current = fast[len - 1] - slow[len - 1];
+
+    if previous <= 0.0 && current > 0.0 {
+        Signal::Buy
+    } else if previous >= 0.0 && current < 0.0 {
+        Signal::Sell
+    } else {
+        Signal::Hold
+    }
+}
+
+/// Signal for oscillators which are bounded between 0 and 100
+///
+/// Confidence grows with the distance past the oversold or overbought level. A value of 0 or 100 has full
+/// confidence.
+fn oscillator_signal(value: f64, oversold: f64, overbought: f64) -> ScoredSignal {
+    if value < oversold {
+        ScoredSignal::new(Signal::Buy, (oversold - value) / oversold)
+    } else if value > overbought {
+        ScoredSignal::new(Signal::Sell, (value - overbought) / (100.0 - overbought))
+    } else {
+        Signal::Hold.into()
+    }
+}
+
 /// Internal functions for indicators
 ///
 /// These functions are used to manage the stateful indicator object, and process the indicator
@@ -206,3 +344,51 @@ pub trait Indicator: IndicatorGraphHandler + IndicatorSignalHandler {
     /// Save indicator graph as CSV
     fn save_graph_as_csv(&mut self, path: &str) -> Result<(), PolarsError>;
 }
+
+/// Create candles from close prices for testing
+///
+/// Candles are one minute apart. High and low are 1 above and below the close.
+#[cfg(test)]
+pub(crate) fn create_candles(closes: &[f64]) -> DataFrame {
+    use chrono::{Duration, NaiveDateTime};
+
+    let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+    let times = (0..closes.len())
+        .map(|i| start + Duration::minutes(i as i64))
+        .collect::<Vec<_>>();
+    df![
+        "time" => times,
+        "open" => closes,
+        "high" => closes.iter().map(|c| c + 1.0).collect::<Vec<_>>(),
+        "low" => closes.iter().map(|c| c - 1.0).collect::<Vec<_>>(),
+        "close" => closes,
+        "volume" => vec![1.0; closes.len()]
+    ]
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossover() {
+        assert_eq!(crossover(&[1.0, 3.0], &[2.0, 2.0]), Signal::Buy);
+        assert_eq!(crossover(&[3.0, 1.0], &[2.0, 2.0]), Signal::Sell);
+        assert_eq!(crossover(&[3.0, 4.0], &[2.0, 2.0]), Signal::Hold);
+        assert_eq!(crossover(&[3.0], &[2.0]), Signal::Hold);
+    }
+
+    #[test]
+    fn test_oscillator_signal() {
+        let signal = oscillator_signal(15.0, 30.0, 70.0);
+        assert_eq!(signal.signal, Signal::Buy);
+        assert_eq!(signal.confidence, 0.5);
+
+        let signal = oscillator_signal(100.0, 30.0, 70.0);
+        assert_eq!(signal.signal, Signal::Sell);
+        assert_eq!(signal.confidence, 1.0);
+
+        assert_eq!(oscillator_signal(50.0, 30.0, 70.0).signal, Signal::Hold);
+    }
+}
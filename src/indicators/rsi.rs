@@ -0,0 +1,153 @@
+use crate::indicators::{
+    candle_values, graph_from_columns, oscillator_signal, GraphProcessingError,
+};
+use crate::processor::CandleProcessor;
+use crate::types::{ScoredSignal, Signal};
+use log::info;
+use polars::prelude::*;
+use ta::indicators::RelativeStrengthIndex;
+use ta::Next;
+
+const DEFAULT_PERIOD: usize = 14;
+const DEFAULT_OVERSOLD: f64 = 30.0;
+const DEFAULT_OVERBOUGHT: f64 = 70.0;
+const DEFAULT_SOURCE_COL_NAME: &str = "close";
+
+/// The Relative Strength Index (RSI) indicator
+///
+/// Signals a buy when the RSI is below the oversold level and a sell when the RSI is above the
+/// overbought level.
+#[derive(Debug, Clone)]
+pub struct RSI {
+    period: usize,
+
+    // Signal parameters
+    oversold: f64,
+    overbought: f64,
+    source_column: String,
+}
+
+impl RSI {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            oversold: DEFAULT_OVERSOLD,
+            overbought: DEFAULT_OVERBOUGHT,
+            source_column: String::from(DEFAULT_SOURCE_COL_NAME),
+        }
+    }
+
+    pub fn oversold(&self) -> f64 {
+        self.oversold
+    }
+
+    pub fn overbought(&self) -> f64 {
+        self.overbought
+    }
+
+    pub fn with_oversold(mut self, oversold: f64) -> Self {
+        self.oversold = oversold;
+        self
+    }
+
+    pub fn with_overbought(mut self, overbought: f64) -> Self {
+        self.overbought = overbought;
+        self
+    }
+
+    pub fn with_source_column(mut self, source_column: String) -> Self {
+        self.source_column = source_column;
+        self
+    }
+
+    fn calculate_rsi(&self, candles: &DataFrame) -> Result<Vec<f64>, GraphProcessingError> {
+        let mut rsi = RelativeStrengthIndex::new(self.period)
+            .map_err(|e| GraphProcessingError::InvalidParameter(e.to_string()))?;
+        Ok(candle_values(candles, &self.source_column)?
+            .into_iter()
+            .map(|value| rsi.next(value))
+            .collect())
+    }
+}
+
+impl Default for RSI {
+    fn default() -> Self {
+        Self::new(DEFAULT_PERIOD)
+    }
+}
+
+impl CandleProcessor for RSI {
+    type ReturnType = ScoredSignal;
+    type ErrorType = GraphProcessingError;
+
+    fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
+        if candles.height() <= self.period {
+            return Ok(Signal::Hold.into());
+        }
+
+        let rsi = self.calculate_rsi(candles)?;
+        let last = *rsi.last().unwrap();
+        Ok(oscillator_signal(last, self.oversold, self.overbought))
+    }
+
+    fn get_name(&self) -> &'static str {
+        "rsi"
+    }
+
+    fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+        info!("Calculating RSI");
+
+        let rsi = self.calculate_rsi(candles).unwrap();
+        graph_from_columns(candles, vec![("rsi", rsi)]).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+
+    #[test]
+    fn test_default() {
+        let rsi = RSI::default();
+        assert_eq!(rsi.period, 14);
+        assert_eq!(rsi.oversold, 30.0);
+        assert_eq!(rsi.overbought, 70.0);
+    }
+
+    #[test]
+    fn test_process_candle() {
+        let rsi = RSI::new(3);
+
+        // not enough candles
+        let candles = create_candles(&[100.0, 99.0, 98.0]);
+        assert_eq!(rsi.process_candle(&candles).unwrap().signal, Signal::Hold);
+
+        let falling = (0..10).map(|i| 100.0 - i as f64).collect::<Vec<_>>();
+        let signal = rsi.process_candle(&create_candles(&falling)).unwrap();
+        assert_eq!(signal.signal, Signal::Buy);
+        assert!(signal.confidence > 0.9);
+
+        let rising = (0..10).map(|i| 100.0 + i as f64).collect::<Vec<_>>();
+        let signal = rsi.process_candle(&create_candles(&rising)).unwrap();
+        assert_eq!(signal.signal, Signal::Sell);
+
+        let flat = [100.0, 101.0, 100.0, 101.0, 100.0, 101.0, 100.0, 101.0];
+        let signal = rsi.process_candle(&create_candles(&flat)).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+    }
+
+    #[test]
+    fn test_get_raw_dataframe() {
+        let candles = create_candles(&[100.0, 101.0, 102.0, 101.0, 103.0]);
+        let graph = RSI::new(3).get_raw_dataframe(&candles);
+
+        assert_eq!(graph.get_column_names(), ["time", "rsi"]);
+        assert_eq!(graph.height(), candles.height());
+
+        // the first value is seeded with equal gains and losses
+        let rsi = graph.column("rsi").unwrap().f64().unwrap();
+        assert_eq!(rsi.get(0), Some(50.0));
+        assert!(rsi.get(2).unwrap() > rsi.get(3).unwrap());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::indicators::{candle_bars, graph_from_columns, oscillator_signal, GraphProcessingError};
+use crate::processor::CandleProcessor;
+use crate::types::{ScoredSignal, Signal};
+use log::info;
+use polars::prelude::*;
+use ta::indicators::{ExponentialMovingAverage, FastStochastic};
+use ta::Next;
+
+const DEFAULT_PERIOD: usize = 14;
+const DEFAULT_SIGNAL_PERIOD: usize = 3;
+const DEFAULT_OVERSOLD: f64 = 20.0;
+const DEFAULT_OVERBOUGHT: f64 = 80.0;
+
+/// The Stochastic oscillator
+///
+/// %K is the position of the close within the high-low range of the last `period` candles and %D is the
+/// exponential moving average of %K over `signal_period` candles. Signals a buy when %D is below the
+/// oversold level and a sell when %D is above the overbought level.
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+    period: usize,
+    signal_period: usize,
+
+    // Signal parameters
+    oversold: f64,
+    overbought: f64,
+}
+
+impl Stochastic {
+    pub fn new(period: usize, signal_period: usize) -> Self {
+        Self {
+            period,
+            signal_period,
+            oversold: DEFAULT_OVERSOLD,
+            overbought: DEFAULT_OVERBOUGHT,
+        }
+    }
+
+    pub fn with_period(mut self, period: usize) -> Self {
+        self.period = period;
+        self
+    }
+
+    pub fn with_signal_period(mut self, signal_period: usize) -> Self {
+        self.signal_period = signal_period;
+        self
+    }
+
+    pub fn oversold(&self) -> f64 {
+        self.oversold
+    }
+
+    pub fn overbought(&self) -> f64 {
+        self.overbought
+    }
+
+    pub fn with_oversold(mut self, oversold: f64) -> Self {
+        self.oversold = oversold;
+        self
+    }
+
+    pub fn with_overbought(mut self, overbought: f64) -> Self {
+        self.overbought = overbought;
+        self
+    }
+
+    /// Calculate %K and %D
+    fn calculate_stochastic(
+        &self,
+        candles: &DataFrame,
+    ) -> Result<(Vec<f64>, Vec<f64>), GraphProcessingError> {
+        let invalid =
+            |e: ta::errors::TaError| GraphProcessingError::InvalidParameter(e.to_string());
+        let mut fast = FastStochastic::new(self.period).map_err(invalid)?;
+        let mut ema = ExponentialMovingAverage::new(self.signal_period).map_err(invalid)?;
+
+        let k = candle_bars(candles)?
+            .iter()
+            .map(|bar| fast.next(bar))
+            .collect::<Vec<_>>();
+        let d = k.iter().map(|value| ema.next(*value)).collect();
+        Ok((k, d))
+    }
+}
+
+impl Default for Stochastic {
+    fn default() -> Self {
+        Self::new(DEFAULT_PERIOD, DEFAULT_SIGNAL_PERIOD)
+    }
+}
+
+impl CandleProcessor for Stochastic {
+    type ReturnType = ScoredSignal;
+    type ErrorType = GraphProcessingError;
+
+    fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
+        if candles.height() < self.period + self.signal_period {
+            return Ok(Signal::Hold.into());
+        }
+
+        let (_, d) = self.calculate_stochastic(candles)?;
+        let last = *d.last().unwrap();
+        Ok(oscillator_signal(last, self.oversold, self.overbought))
+    }
+
+    fn get_name(&self) -> &'static str {
+        "stochastic"
+    }
+
+    fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+        info!("Calculating Stochastic oscillator");
+
+        let (k, d) = self.calculate_stochastic(candles).unwrap();
+        graph_from_columns(candles, vec![("stochastic_k", k), ("stochastic_d", d)]).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+
+    #[test]
+    fn test_default() {
+        let stochastic = Stochastic::default();
+        assert_eq!(stochastic.period, 14);
+        assert_eq!(stochastic.signal_period, 3);
+        assert_eq!(stochastic.oversold, 20.0);
+        assert_eq!(stochastic.overbought, 80.0);
+    }
+
+    #[test]
+    fn test_process_candle() {
+        let stochastic = Stochastic::new(3, 2);
+
+        let falling = (0..8).map(|i| 100.0 - 2.0 * i as f64).collect::<Vec<_>>();
+        let signal = stochastic
+            .process_candle(&create_candles(&falling))
+            .unwrap();
+        assert_eq!(signal.signal, Signal::Buy);
+
+        let rising = (0..8).map(|i| 100.0 + 2.0 * i as f64).collect::<Vec<_>>();
+        let signal = stochastic.process_candle(&create_candles(&rising)).unwrap();
+        assert_eq!(signal.signal, Signal::Sell);
+
+        // not enough candles
+        let signal = stochastic
+            .process_candle(&create_candles(&falling[..4]))
+            .unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+    }
+
+    #[test]
+    fn test_get_raw_dataframe() {
+        let candles = create_candles(&[100.0, 102.0, 104.0, 100.0]);
+        let graph = Stochastic::new(3, 2).get_raw_dataframe(&candles);
+
+        assert_eq!(
+            graph.get_column_names(),
+            ["time", "stochastic_k", "stochastic_d"]
+        );
+
+        // high of the last 3 candles is 105, low is 99 and the close is 100
+        let k = graph.column("stochastic_k").unwrap().f64().unwrap();
+        assert!((k.get(3).unwrap() - 100.0 / 6.0).abs() < 1e-9);
+        let d = graph.column("stochastic_d").unwrap().f64().unwrap();
+        assert_eq!(d.get(0), k.get(0));
+    }
+}
@@ -1,24 +1,81 @@
-use crate::indicators::GraphProcessingError;
+use crate::indicators::{candle_value, GraphProcessingError};
 use crate::processor::CandleProcessor;
-use crate::types::Signal;
+use crate::types::{Candle, ScoredSignal, Signal};
 use chrono::{DateTime, NaiveDateTime};
 use log::info;
 use polars::prelude::*;
+use std::collections::VecDeque;
+
+const DEFAULT_WINDOW: usize = 5;
+
+/// Percentage distance from the VWAP line at which a signal has full confidence. `1.0` is 1%.
+const FULL_CONFIDENCE_DISTANCE: f64 = 1.0;
 
 /// The Volume Weighted Average Price (VWAP) indicator
 pub struct VWAP {
     /// The window size for the VWAP calculation
     window: usize,
+
+    /// State used by `process_new_candle`
+    sums: RollingSums,
+}
+
+/// Running sums of typical price * volume and volume over the most recent candles
+#[derive(Default)]
+struct RollingSums {
+    values: VecDeque<(f64, f64)>,
+    tp_vol: f64,
+    volume: f64,
+}
+
+impl RollingSums {
+    /// Add a candle, removing the oldest candle once the window holds `window` candles
+    ///
+    /// # Returns
+    /// The VWAP of the candles in the window
+    fn push(&mut self, tp_vol: f64, volume: f64, window: usize) -> f64 {
+        while self.values.len() >= window.max(1) {
+            let (removed_tp_vol, removed_volume) = self.values.pop_front().unwrap();
+            self.tp_vol -= removed_tp_vol;
+            self.volume -= removed_volume;
+        }
+
+        self.values.push_back((tp_vol, volume));
+        self.tp_vol += tp_vol;
+        self.volume += volume;
+
+        self.tp_vol / self.volume
+    }
 }
 
 impl VWAP {
     pub fn new(window: usize) -> Self {
-        Self { window }
+        Self {
+            window,
+            sums: RollingSums::default(),
+        }
+    }
+
+    /// Add a candle to the rolling window and calculate the VWAP
+    fn update_vwap(&mut self, candle: &Candle) -> Result<f64, GraphProcessingError> {
+        let high = candle_value(candle, "high")?;
+        let low = candle_value(candle, "low")?;
+        let close = candle_value(candle, "close")?;
+        let volume = candle_value(candle, "volume")?;
+
+        let typical_price = (high + low + close) / 3.0;
+        Ok(self.sums.push(typical_price * volume, volume, self.window))
+    }
+}
+
+impl Default for VWAP {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
     }
 }
 
 impl CandleProcessor for VWAP {
-    type ReturnType = Signal;
+    type ReturnType = ScoredSignal;
     type ErrorType = GraphProcessingError;
 
     fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
@@ -41,14 +98,7 @@ impl CandleProcessor for VWAP {
             .get(graph.height() - 1)
             .unwrap();
 
-        let signal = if current_price > last_vwap {
-            Signal::Buy
-        } else if current_price < last_vwap {
-            Signal::Sell
-        } else {
-            Signal::Hold
-        };
-        Ok(signal)
+        Ok(vwap_signal(current_price, last_vwap))
     }
 
     fn get_name(&self) -> &'static str {
@@ -109,6 +159,36 @@ impl CandleProcessor for VWAP {
         ])
         .unwrap()
     }
+
+    fn process_new_candle(
+        &mut self,
+        candle: &Candle,
+    ) -> Option<Result<Self::ReturnType, Self::ErrorType>> {
+        let signal = self.update_vwap(candle).and_then(|vwap| {
+            let current_price = candle_value(candle, "close")?;
+            Ok(vwap_signal(current_price, vwap))
+        });
+        Some(signal)
+    }
+
+    fn reset(&mut self) {
+        self.sums = RollingSums::default();
+    }
+}
+
+/// Calculate signal from the close price and the VWAP
+fn vwap_signal(current_price: f64, last_vwap: f64) -> ScoredSignal {
+    let signal = if current_price > last_vwap {
+        Signal::Buy
+    } else if current_price < last_vwap {
+        Signal::Sell
+    } else {
+        Signal::Hold
+    };
+
+    // confidence grows with the distance of the close price from the VWAP line
+    let distance = (current_price - last_vwap).abs() / last_vwap * 100.0;
+    ScoredSignal::new(signal, distance / FULL_CONFIDENCE_DISTANCE)
 }
 
 fn calculate_vwap(candles: &DataFrame, window: usize) -> Result<DataFrame, PolarsError> {
@@ -143,3 +223,56 @@ fn calculate_vwap(candles: &DataFrame, window: usize) -> Result<DataFrame, Polar
         ])
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+    use crate::utils::extract_candles_from_df;
+
+    /// Candles with varying closes and volumes
+    fn candles() -> DataFrame {
+        let closes = (0..30)
+            .map(|i| 100.0 + (i as f64 * 0.5).cos() * 3.0)
+            .collect::<Vec<f64>>();
+        let mut candles = create_candles(&closes);
+        let volume = (0..30)
+            .map(|i| 1.0 + (i % 7) as f64 * 0.25)
+            .collect::<Vec<f64>>();
+        candles
+            .replace("volume", Series::new("volume", volume))
+            .unwrap();
+        candles
+    }
+
+    #[test]
+    fn test_process_new_candle() {
+        let candles = candles();
+        let mut vwap = VWAP::new(5);
+
+        let graph = vwap.get_raw_dataframe(&candles);
+        let expected = graph.column("vwap").unwrap().f64().unwrap();
+
+        for (i, candle) in extract_candles_from_df(&candles)
+            .unwrap()
+            .iter()
+            .enumerate()
+        {
+            let value = vwap.update_vwap(candle).unwrap();
+            assert!((expected.get(i).unwrap() - value).abs() < 1e-9);
+        }
+
+        // streamed signals match the batch signals
+        vwap.reset();
+        for (i, candle) in extract_candles_from_df(&candles)
+            .unwrap()
+            .iter()
+            .enumerate()
+        {
+            let streamed = vwap.process_new_candle(candle).unwrap().unwrap();
+            let batch = vwap.process_candle(&candles.head(Some(i + 1))).unwrap();
+            assert_eq!(streamed.signal, batch.signal);
+            assert!((streamed.confidence - batch.confidence).abs() < 1e-9);
+        }
+    }
+}
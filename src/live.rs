@@ -0,0 +1,1042 @@
+use crate::backtesting::{print_portfolio, TradingConfig, CANDLE_TRIM_SIZE};
+use crate::manager::{PositionManager, PositionManagerConfig, PositionManagerError, TradeDecision};
+use crate::markets::feed::FeedEvent;
+use crate::markets::manager::CandleManager;
+use crate::markets::{BaseMarket, FeeCalculator, MarketError, OrderTracker, PaperMarket};
+use crate::portfolio::{EquityHandlers, Portfolio, PortfolioArgs, TradeHandlers};
+use crate::risk::{calculate_risk, RiskCalculationErrors, RiskConfig};
+use crate::strategies::{Strategy, StrategyConfig};
+use crate::types::{FailedTrade, FutureTrade, OrderStatus, Side};
+use crate::utils::{closed_candles, extract_candles_from_df, frequency_to_duration};
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use log::{debug, info, warn};
+use polars::prelude::*;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+
+/// Total configuration for live trading
+///
+/// Uses the same layout as the backtesting TOML config file
+#[derive(Deserialize, Debug)]
+pub struct LiveConfig {
+    pub portfolio: PortfolioArgs,
+    pub risk: PositionManagerConfig,
+    pub trading: TradingConfig,
+    #[serde(default)]
+    pub strategy: StrategyConfig,
+}
+
+impl LiveConfig {
+    /// Read the configuration from a TOML file
+    pub fn from_file<P: AsRef<Path>>(config_path: P) -> Result<Self, LiveRuntimeErrors> {
+        let config_str = std::fs::read_to_string(config_path)
+            .map_err(|e| LiveRuntimeErrors::ConfigError(e.to_string()))?;
+        toml::from_str(&config_str).map_err(|e| LiveRuntimeErrors::ConfigError(e.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum LiveRuntimeErrors {
+    ConfigError(String),
+    /// Raised when the configured frequency cannot be converted to a polling interval
+    InvalidFrequency(String),
+    MarketError(MarketError),
+    /// Raised when unable to extract signals from trading asset data
+    SignalExtractionError,
+
+    RiskCalculationError(RiskCalculationErrors),
+    DecisionError(PositionManagerError),
+}
+
+/// Drives a [`Strategy`] and [`PositionManager`] against a live [`BaseMarket`]
+///
+/// Every poll interval the latest candles are fetched from the market. When new candles are
+/// available, the trimmed candle window is passed through the strategy, portfolio risk is
+/// calculated, and the resulting [`TradeDecision`] is submitted to the market. Executed and failed
+/// orders are recorded in the internal [`Portfolio`]. Orders which are left resting on the book,
+/// such as stop orders, are checked on every new candle and recorded once they are done.
+///
+/// Candles can instead be pushed by a streaming feed with [`LiveRuntime::run_streaming`].
+pub struct LiveRuntime<M: BaseMarket> {
+    strategy: Strategy,
+    market: M,
+    portfolio: Portfolio,
+    position_manager: PositionManager,
+    trading_config: TradingConfig,
+    risk_config: RiskConfig,
+
+    starting_capital: Decimal,
+    poll_interval: Duration,
+
+    /// Time of the latest candle passed through the strategy
+    last_processed: Option<NaiveDateTime>,
+
+    /// Ids of orders resting on the book, with the trade each order was submitted for
+    pending_orders: Vec<(String, FutureTrade)>,
+}
+
+impl<M: BaseMarket> LiveRuntime<M> {
+    pub fn new(
+        strategy: Strategy,
+        market: M,
+        portfolio_args: PortfolioArgs,
+        manager_config: PositionManagerConfig,
+        trading_config: TradingConfig,
+    ) -> Result<Self, LiveRuntimeErrors> {
+        let poll_interval = frequency_to_duration(&trading_config.frequency).ok_or(
+            LiveRuntimeErrors::InvalidFrequency(trading_config.frequency.clone()),
+        )?;
+        let risk_config = RiskConfig::new(
+            &trading_config.frequency,
+            manager_config.value_at_risk.clone(),
+        )
+        .map_err(|e| LiveRuntimeErrors::ConfigError(format!("{:?}", e)))?
+        .with_risk_free_rate(manager_config.risk_free_rate)
+        .with_returns_source(manager_config.returns_source);
+        let portfolio = Portfolio::from_args(&portfolio_args, Utc::now().naive_utc());
+
+        Ok(LiveRuntime {
+            strategy,
+            market,
+            portfolio,
+            position_manager: PositionManager::new(manager_config),
+            trading_config,
+            risk_config,
+            starting_capital: portfolio_args.capital,
+            poll_interval,
+            last_processed: None,
+            pending_orders: vec![],
+        })
+    }
+
+    /// Create a runtime from an already parsed [`LiveConfig`]
+    pub fn from_live_config(
+        config: LiveConfig,
+        strategy: Strategy,
+        market: M,
+    ) -> Result<Self, LiveRuntimeErrors> {
+        Self::new(
+            strategy,
+            market,
+            config.portfolio,
+            config.risk,
+            config.trading,
+        )
+    }
+
+    /// Charge fees on executed trades with the given calculator
+    ///
+    /// Replaces any fees configured under `[portfolio.fees]`.
+    pub fn with_fee_calculator<T>(mut self, fee_calculator: T) -> Self
+    where
+        T: FeeCalculator + 'static,
+    {
+        self.portfolio = std::mem::take(&mut self.portfolio).add_fee_calculator(fee_calculator);
+        self
+    }
+
+    /// Run the trading loop until Ctrl-C is received
+    pub async fn run(&mut self) -> Result<(), LiveRuntimeErrors> {
+        self.run_until(async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                warn!("Unable to listen for shutdown signal: {}", e);
+                std::future::pending::<()>().await;
+            }
+        })
+        .await
+    }
+
+    /// Run the trading loop until the given `shutdown` future completes
+    ///
+    /// Market errors are logged and the cycle is retried on the next tick. Any other error stops
+    /// the loop and is returned.
+    pub async fn run_until<F>(&mut self, shutdown: F) -> Result<(), LiveRuntimeErrors>
+    where
+        F: Future<Output = ()>,
+    {
+        let market = self.market.clone();
+        let mut trading_candles = CandleManager::new(&self.trading_config.trading_asset, &market);
+        let mut market_candles = CandleManager::new(&self.trading_config.market_asset, &market);
+
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        info!(
+            "Starting live trading of {} on {} every {:?}",
+            self.trading_config.trading_asset,
+            self.market.name(),
+            self.poll_interval
+        );
+
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => {
+                    info!("Shutdown signal received");
+                    break;
+                }
+                _ = interval.tick() => {
+                    match self.step(&mut trading_candles, &mut market_candles).await {
+                        Err(LiveRuntimeErrors::MarketError(e)) => {
+                            warn!("Error communicating with market: {}", e);
+                        }
+                        Err(e) => return Err(e),
+                        Ok(()) => {}
+                    }
+                }
+            }
+        }
+
+        info!("******************************************\nLive Trading Statistics");
+        print_portfolio(&self.portfolio, self.starting_capital);
+
+        Ok(())
+    }
+
+    /// Run the trading loop on candles received from a streaming feed until Ctrl-C is received
+    ///
+    /// See [`LiveRuntime::run_streaming_until`]
+    pub async fn run_streaming(
+        &mut self,
+        receiver: Receiver<FeedEvent>,
+    ) -> Result<(), LiveRuntimeErrors> {
+        self.run_streaming_until(receiver, async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                warn!("Unable to listen for shutdown signal: {}", e);
+                std::future::pending::<()>().await;
+            }
+        })
+        .await
+    }
+
+    /// Run the trading loop on candles received from a streaming feed until the given `shutdown`
+    /// future completes
+    ///
+    /// The candle history is fetched from the market once. Afterwards, candles are only received
+    /// from the feed, and the strategy is processed once both assets have a new candle at the
+    /// trading frequency.
+    ///
+    /// # Returns
+    /// `Err(LiveRuntimeErrors::MarketError)` if the history could not be fetched or the feed stopped
+    pub async fn run_streaming_until<F>(
+        &mut self,
+        mut receiver: Receiver<FeedEvent>,
+        shutdown: F,
+    ) -> Result<(), LiveRuntimeErrors>
+    where
+        F: Future<Output = ()>,
+    {
+        let market = self.market.clone();
+        let mut trading_candles = CandleManager::new(&self.trading_config.trading_asset, &market);
+        let mut market_candles = CandleManager::new(&self.trading_config.market_asset, &market);
+
+        self.update_candles(&mut trading_candles, &mut market_candles)
+            .await
+            .map_err(LiveRuntimeErrors::MarketError)?;
+        let frequency = self.trading_config.frequency.clone();
+        let (history, _) = align_windows(
+            trading_candles.get(&frequency).unwrap(),
+            market_candles.get(&frequency).unwrap(),
+            1,
+        );
+        self.last_processed = extract_candles_from_df(&history)
+            .unwrap()
+            .last()
+            .map(|candle| candle.time);
+
+        info!(
+            "Starting live trading of {} on {} from a streaming feed",
+            self.trading_config.trading_asset,
+            self.market.name(),
+        );
+
+        let mut result = Ok(());
+        tokio::pin!(shutdown);
+        loop {
+            let event = tokio::select! {
+                _ = &mut shutdown => {
+                    info!("Shutdown signal received");
+                    break;
+                }
+                event = receiver.recv() => event,
+            };
+            let Some(event) = event else {
+                result = Err(LiveRuntimeErrors::MarketError(MarketError::FeedError(
+                    "Market data feed stopped".to_string(),
+                )));
+                break;
+            };
+
+            if let FeedEvent::Ticker(ticker) = &event {
+                if ticker.product_id == self.trading_config.trading_asset {
+                    debug!(
+                        "{} traded at {} (bid {:?}, ask {:?}) at {}",
+                        ticker.product_id,
+                        ticker.price,
+                        ticker.best_bid,
+                        ticker.best_ask,
+                        ticker.time
+                    );
+                }
+            }
+
+            let trading = trading_candles.push_event(&event);
+            let market = market_candles.push_event(&event);
+            let closed = trading
+                .or(market)
+                .is_some_and(|(interval, _)| interval == frequency);
+            if !closed {
+                continue;
+            }
+            match self.process(&trading_candles, &market_candles).await {
+                Err(LiveRuntimeErrors::MarketError(e)) => {
+                    warn!("Error communicating with market: {}", e);
+                }
+                Err(e) => return Err(e),
+                Ok(()) => {}
+            }
+        }
+
+        info!("******************************************\nLive Trading Statistics");
+        print_portfolio(&self.portfolio, self.starting_capital);
+
+        result
+    }
+
+    /// Perform a single trading cycle
+    ///
+    /// The first call for a fresh [`CandleManager`] only loads the candle history. Subsequent calls
+    /// only process the strategy when the market returned new candles.
+    pub async fn step(
+        &mut self,
+        trading_candles: &mut CandleManager<'_, M>,
+        market_candles: &mut CandleManager<'_, M>,
+    ) -> Result<(), LiveRuntimeErrors> {
+        let new_rows = self
+            .update_candles(trading_candles, market_candles)
+            .await
+            .map_err(LiveRuntimeErrors::MarketError)?;
+
+        match new_rows {
+            None => {
+                info!(
+                    "Loaded candle history for {}",
+                    self.trading_config.trading_asset
+                );
+                return Ok(());
+            }
+            Some(rows) if rows.height() == 0 => return Ok(()),
+            Some(_) => {}
+        }
+
+        self.process(trading_candles, market_candles).await
+    }
+
+    /// Fetch the latest candles of both assets from the market
+    ///
+    /// # Returns
+    /// The new rows of the trading asset at the trading frequency. See [`CandleManager::update`].
+    async fn update_candles(
+        &self,
+        trading_candles: &mut CandleManager<'_, M>,
+        market_candles: &mut CandleManager<'_, M>,
+    ) -> Result<Option<DataFrame>, MarketError> {
+        let frequency = self.trading_config.frequency.as_str();
+
+        let new_rows = trading_candles.update(frequency).await?;
+        market_candles.update(frequency).await?;
+        for interval in self.strategy.intervals() {
+            trading_candles.update(interval).await?;
+        }
+        Ok(new_rows)
+    }
+
+    /// Pass the latest candles through the strategy and submit the resulting trade
+    ///
+    /// Only candles which both assets share are used. Nothing is done if the latest shared candle
+    /// has already been processed.
+    async fn process(
+        &mut self,
+        trading_candles: &CandleManager<'_, M>,
+        market_candles: &CandleManager<'_, M>,
+    ) -> Result<(), LiveRuntimeErrors> {
+        let frequency = self.trading_config.frequency.as_str();
+
+        // risk metrics may use a longer window than the strategy
+        let risk_window = CANDLE_TRIM_SIZE.max(self.risk_config.value_at_risk.lookback as IdxSize);
+        let (risk_trading_window, market_window) = align_windows(
+            trading_candles.get(frequency).unwrap(),
+            market_candles.get(frequency).unwrap(),
+            risk_window,
+        );
+        if risk_trading_window.height() == 0 {
+            return Ok(());
+        }
+        let trading_window = risk_trading_window.tail(Some(CANDLE_TRIM_SIZE as usize));
+
+        let trading_rows = extract_candles_from_df(&risk_trading_window).unwrap();
+        let latest = trading_rows.last().unwrap().time;
+        if self.last_processed.is_some_and(|time| time >= latest) {
+            return Ok(());
+        }
+        self.last_processed = Some(latest);
+
+        // only candles of other intervals which closed with the latest candle are visible
+        let candle_duration = frequency_to_duration(frequency)
+            .ok_or(LiveRuntimeErrors::InvalidFrequency(frequency.to_string()))?;
+        let end_time = latest + ChronoDuration::from_std(candle_duration).unwrap();
+        let timeframes = self
+            .strategy
+            .intervals()
+            .into_iter()
+            .map(|interval| {
+                let duration = frequency_to_duration(interval)
+                    .ok_or(LiveRuntimeErrors::InvalidFrequency(interval.to_string()))?;
+                let candles = trading_candles.get(interval).unwrap();
+                let closed = closed_candles(candles, end_time, duration, CANDLE_TRIM_SIZE);
+                Ok((interval.to_string(), closed))
+            })
+            .collect::<Result<HashMap<_, _>, LiveRuntimeErrors>>()?;
+
+        let signal = self
+            .strategy
+            .process_timeframes(&trading_window, &timeframes)
+            .map_err(|_| LiveRuntimeErrors::SignalExtractionError)?;
+        let market_rows = extract_candles_from_df(&market_window).unwrap();
+
+        self.resolve_pending_orders().await;
+
+        let risk = calculate_risk(
+            &self.portfolio,
+            &market_rows,
+            &trading_rows,
+            &self.risk_config,
+        )
+        .map_err(LiveRuntimeErrors::RiskCalculationError)?;
+
+        let candle = trading_rows.last().unwrap();
+        let current_price = candle.close;
+        self.portfolio.update_equity(current_price);
+
+        let decision = self
+            .position_manager
+            .make_decision(
+                &mut self.portfolio,
+                &risk,
+                &signal,
+                current_price,
+                candle.time,
+            )
+            .map_err(LiveRuntimeErrors::DecisionError)?;
+
+        let trade = match decision {
+            TradeDecision::ExecuteBuy(quantity) => {
+                FutureTrade::new(Side::Buy, current_price, quantity, candle.time)
+            }
+            TradeDecision::ExecuteSell(quantity, trade_ids) => {
+                info!("Closing positions: {:?}", trade_ids);
+                FutureTrade::new(Side::Sell, current_price, quantity, candle.time)
+            }
+            TradeDecision::DoNothing => return Ok(()),
+        }
+        .with_order_type(self.trading_config.order_type);
+
+        match self
+            .market
+            .submit_order(trade.clone(), self.trading_config.trading_asset.clone())
+            .await
+        {
+            Ok(executed) => {
+                info!("Order executed: {:?}", executed);
+                self.portfolio.add_executed_trade(executed);
+            }
+            Err(MarketError::OrderResting(order_id)) => {
+                info!("Order {} is resting on the book", order_id);
+                self.pending_orders.push((order_id, trade));
+            }
+            Err(e) => {
+                warn!("Order failed: {}", e);
+                self.portfolio
+                    .add_failed_trade(FailedTrade::with_future_trade(e.reason_code(), trade));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the orders resting on the book and record those which are done
+    ///
+    /// Orders which are still open, or could not be fetched, are checked again on the next candle.
+    async fn resolve_pending_orders(&mut self) {
+        let mut still_pending = vec![];
+        for (order_id, trade) in std::mem::take(&mut self.pending_orders) {
+            match self.market.get_order(&order_id).await {
+                Ok(order) if order.status != OrderStatus::Open => {
+                    match OrderTracker::resolve(&order, trade) {
+                        Ok(executed) => {
+                            info!("Resting order executed: {:?}", executed);
+                            self.portfolio.add_executed_trade(executed);
+                        }
+                        Err(failed) => self.portfolio.add_failed_trade(failed),
+                    }
+                }
+                Ok(_) => still_pending.push((order_id, trade)),
+                Err(e) => {
+                    warn!("Error while checking order {}: {}", order_id, e);
+                    still_pending.push((order_id, trade));
+                }
+            }
+        }
+        self.pending_orders = still_pending;
+    }
+}
+
+impl LiveRuntime<PaperMarket> {
+    /// Replay all candles in the paper market through the trading loop
+    ///
+    /// The quote currency of the trading asset is funded with the starting capital. The clock of the
+    /// market is advanced one candle at a time, without waiting for the poll interval.
+    pub async fn replay(&mut self) -> Result<(), LiveRuntimeErrors> {
+        let market = self.market.clone();
+        if let Some((_, quote)) = self.trading_config.trading_asset.split_once('-') {
+            market.set_balance(quote, self.starting_capital);
+        }
+
+        let mut trading_candles = CandleManager::new(&self.trading_config.trading_asset, &market);
+        let mut market_candles = CandleManager::new(&self.trading_config.market_asset, &market);
+
+        info!(
+            "Starting paper trading of {}",
+            self.trading_config.trading_asset
+        );
+        while market.advance().is_some() {
+            self.step(&mut trading_candles, &mut market_candles).await?;
+        }
+
+        info!("******************************************\nPaper Trading Statistics");
+        print_portfolio(&self.portfolio, self.starting_capital);
+
+        Ok(())
+    }
+}
+
+/// Sort both candle sets in ascending order, keep only shared timestamps, and trim to the last
+/// `length` rows
+fn align_windows(
+    trading: &DataFrame,
+    market: &DataFrame,
+    length: IdxSize,
+) -> (DataFrame, DataFrame) {
+    let semi = JoinArgs::new(JoinType::Semi);
+    let trading_aligned = trading
+        .join(market, ["time"], ["time"], semi.clone())
+        .unwrap();
+    let market_aligned = market.join(trading, ["time"], ["time"], semi).unwrap();
+
+    let trim = |df: DataFrame| {
+        df.sort(["time"], SortMultipleOptions::default())
+            .unwrap()
+            .tail(Some(length as usize))
+    };
+
+    (trim(trading_aligned), trim(market_aligned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::GraphProcessingError;
+    use crate::markets::SimplePercentageFee;
+    use crate::portfolio::PositionHandlers;
+    use crate::processor::CandleProcessor;
+    use crate::strategies::Consensus;
+    use crate::types::{
+        Candle, ExecutedTrade, Order, OrderType, ReasonCode, ScoredSignal, Signal, Trade,
+    };
+    use async_trait::async_trait;
+    use chrono::{Duration as ChronoDuration, NaiveDateTime};
+    use rust_decimal_macros::dec;
+    use std::sync::{Arc, Mutex};
+
+    /// Candle processor which always emits the same signal
+    struct ConstantSignal(Signal);
+
+    impl CandleProcessor for ConstantSignal {
+        type ReturnType = ScoredSignal;
+        type ErrorType = GraphProcessingError;
+
+        fn process_candle(&self, _: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
+            Ok(self.0.into())
+        }
+
+        fn get_name(&self) -> &'static str {
+            "constant"
+        }
+
+        fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+            candles.clone()
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockMarket {
+        candles: Arc<Mutex<Vec<Candle>>>,
+        orders: Arc<Mutex<Vec<FutureTrade>>>,
+        reject_orders: bool,
+        /// Stop orders are left open on the book instead of being filled
+        book: Arc<Mutex<Vec<Order>>>,
+    }
+
+    impl MockMarket {
+        fn new(reject_orders: bool) -> Self {
+            let start =
+                NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+            let candles = (0..10).map(|i| create_candle(start, i)).collect();
+            Self {
+                candles: Arc::new(Mutex::new(candles)),
+                orders: Arc::new(Mutex::new(vec![])),
+                reject_orders,
+                book: Arc::new(Mutex::new(vec![])),
+            }
+        }
+
+        fn push_candle(&self) {
+            let mut candles = self.candles.lock().unwrap();
+            let start = candles.first().unwrap().time;
+            let candle = create_candle(start, candles.len() as i64);
+            candles.push(candle);
+        }
+    }
+
+    fn create_candle(start: NaiveDateTime, i: i64) -> Candle {
+        let price = dec!(100) + Decimal::from(i % 3);
+        Candle {
+            time: start + ChronoDuration::minutes(i),
+            open: price,
+            high: price + dec!(1),
+            low: price - dec!(1),
+            close: price,
+            volume: dec!(10),
+        }
+    }
+
+    #[async_trait]
+    impl BaseMarket for MockMarket {
+        fn name(&self) -> &str {
+            "Mock"
+        }
+
+        async fn get_candles(&self, _: &str, _: &str) -> Result<Vec<Candle>, MarketError> {
+            // return in descending order to mirror the exchange
+            let mut candles = self.candles.lock().unwrap().clone();
+            candles.reverse();
+            Ok(candles)
+        }
+
+        async fn submit_order(
+            &self,
+            order: FutureTrade,
+            _: String,
+        ) -> Result<ExecutedTrade, MarketError> {
+            if self.reject_orders {
+                return Err(MarketError::OrderRejected(
+                    ReasonCode::MarketRejection,
+                    "rejected by mock".to_string(),
+                ));
+            }
+            self.orders.lock().unwrap().push(order.clone());
+            if order.get_order_type() == OrderType::Stop {
+                let mut book = self.book.lock().unwrap();
+                let id = format!("stop-{}", book.len());
+                book.push(Order {
+                    id: id.clone(),
+                    side: order.get_side(),
+                    order_type: OrderType::Stop,
+                    price: order.get_price(),
+                    quantity: order.get_quantity(),
+                    filled_quantity: Decimal::ZERO,
+                    executed_value: Decimal::ZERO,
+                    status: OrderStatus::Open,
+                    done_reason: None,
+                    reject_reason: None,
+                    created_at: *order.get_timestamp(),
+                });
+                return Err(MarketError::OrderResting(id));
+            }
+            Ok(ExecutedTrade::from_future_trade("mock".to_string(), order))
+        }
+
+        async fn get_order(&self, order_id: &str) -> Result<Order, MarketError> {
+            self.book
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|order| order.id == order_id)
+                .cloned()
+                .ok_or(MarketError::OrderNotFound(order_id.to_string()))
+        }
+
+        async fn cancel_order(&self, order_id: &str) -> Result<(), MarketError> {
+            Err(MarketError::OrderNotFound(order_id.to_string()))
+        }
+
+        async fn list_open_orders(&self, _: &str) -> Result<Vec<Order>, MarketError> {
+            Ok(vec![])
+        }
+    }
+
+    fn create_runtime(market: MockMarket, signal: Signal) -> LiveRuntime<MockMarket> {
+        let strategy = Strategy::new(vec![Box::new(ConstantSignal(signal))], Consensus::Unison);
+        let trading_config = TradingConfig {
+            frequency: "1m".to_string(),
+            trading_asset: "BTC-USD".to_string(),
+            market_asset: "ETH-USD".to_string(),
+            order_type: OrderType::default(),
+        };
+        LiveRuntime::new(
+            strategy,
+            market,
+            PortfolioArgs::default(),
+            PositionManagerConfig::default(),
+            trading_config,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_invalid_frequency() {
+        let strategy = Strategy::new(vec![], Consensus::Unison);
+        let trading_config = TradingConfig {
+            frequency: "1x".to_string(),
+            trading_asset: "BTC-USD".to_string(),
+            market_asset: "ETH-USD".to_string(),
+            order_type: OrderType::default(),
+        };
+        let runtime = LiveRuntime::new(
+            strategy,
+            MockMarket::new(false),
+            PortfolioArgs::default(),
+            PositionManagerConfig::default(),
+            trading_config,
+        );
+        assert!(matches!(
+            runtime,
+            Err(LiveRuntimeErrors::InvalidFrequency(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_step_executes_buy_on_new_candle() {
+        let market = MockMarket::new(false);
+        let mut runtime = create_runtime(market.clone(), Signal::Buy);
+
+        let local = market.clone();
+        let mut trading_candles = CandleManager::new("BTC-USD", &local);
+        let mut market_candles = CandleManager::new("ETH-USD", &local);
+
+        // the first step only loads history
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+        assert!(market.orders.lock().unwrap().is_empty());
+
+        // no new candles, so nothing is processed
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+        assert!(market.orders.lock().unwrap().is_empty());
+
+        market.push_candle();
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+
+        let orders = market.orders.lock().unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(runtime.portfolio.get_executed_trades().len(), 1);
+        assert_eq!(runtime.portfolio.get_open_positions().len(), 1);
+    }
+
+    #[test]
+    fn test_with_fee_calculator() {
+        let runtime = create_runtime(MockMarket::new(false), Signal::Buy);
+        assert_eq!(
+            runtime
+                .portfolio
+                .cost_including_fee(dec!(100), Side::Buy, OrderType::Limit),
+            dec!(100)
+        );
+
+        let runtime = runtime.with_fee_calculator(SimplePercentageFee::new(dec!(1)));
+        assert_eq!(
+            runtime
+                .portfolio
+                .cost_including_fee(dec!(100), Side::Buy, OrderType::Limit),
+            dec!(101)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_step_records_failed_trade() {
+        let market = MockMarket::new(true);
+        let mut runtime = create_runtime(market.clone(), Signal::Buy);
+
+        let local = market.clone();
+        let mut trading_candles = CandleManager::new("BTC-USD", &local);
+        let mut market_candles = CandleManager::new("ETH-USD", &local);
+
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+        market.push_candle();
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+
+        assert!(runtime.portfolio.get_executed_trades().is_empty());
+        assert_eq!(runtime.portfolio.get_failed_trades().len(), 1);
+        assert_eq!(
+            runtime.portfolio.get_failed_trades()[0].get_reason(),
+            ReasonCode::MarketRejection
+        );
+    }
+
+    #[tokio::test]
+    async fn test_step_resolves_resting_order() {
+        let market = MockMarket::new(false);
+        let mut runtime = create_runtime(market.clone(), Signal::Buy);
+        runtime.trading_config.order_type = OrderType::Stop;
+
+        let local = market.clone();
+        let mut trading_candles = CandleManager::new("BTC-USD", &local);
+        let mut market_candles = CandleManager::new("ETH-USD", &local);
+
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+        market.push_candle();
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+
+        // the stop order is neither executed nor failed while it rests on the book
+        assert_eq!(runtime.pending_orders.len(), 1);
+        assert!(runtime.portfolio.get_executed_trades().is_empty());
+        assert!(runtime.portfolio.get_failed_trades().is_empty());
+
+        // the stop is triggered and filled on the exchange
+        {
+            let mut book = market.book.lock().unwrap();
+            let order = &mut book[0];
+            order.status = OrderStatus::Done;
+            order.done_reason = Some("filled".to_string());
+            order.filled_quantity = order.quantity;
+            order.executed_value = order.quantity * order.price;
+        }
+        market.push_candle();
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+
+        let executed = runtime.portfolio.get_executed_trades();
+        let first = executed.values().next().unwrap();
+        assert_eq!(first.get_order_id(), "stop-0");
+        assert_eq!(first.get_order_type(), OrderType::Stop);
+        assert!(runtime.portfolio.get_failed_trades().is_empty());
+        assert!(!runtime.pending_orders.iter().any(|(id, _)| id == "stop-0"));
+    }
+
+    #[tokio::test]
+    async fn test_step_hold_does_nothing() {
+        let market = MockMarket::new(false);
+        let mut runtime = create_runtime(market.clone(), Signal::Hold);
+
+        let local = market.clone();
+        let mut trading_candles = CandleManager::new("BTC-USD", &local);
+        let mut market_candles = CandleManager::new("ETH-USD", &local);
+
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+        market.push_candle();
+        runtime
+            .step(&mut trading_candles, &mut market_candles)
+            .await
+            .unwrap();
+
+        assert!(market.orders.lock().unwrap().is_empty());
+        assert!(runtime.portfolio.get_executed_trades().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_until_shutdown() {
+        let market = MockMarket::new(false);
+        let mut runtime = create_runtime(market.clone(), Signal::Buy);
+        runtime.poll_interval = Duration::from_millis(10);
+
+        let result = runtime
+            .run_until(tokio::time::sleep(Duration::from_millis(50)))
+            .await;
+
+        assert!(result.is_ok());
+        // no new candles were produced, so no orders should have been placed
+        assert!(market.orders.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_streaming_processes_feed_candles() {
+        let market = MockMarket::new(false);
+        let mut runtime = create_runtime(market.clone(), Signal::Buy);
+
+        let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let candle_event = |product_id: &str| FeedEvent::Candle {
+            product_id: product_id.to_string(),
+            interval: "1m".to_string(),
+            candle: create_candle(start, 10),
+        };
+        let (sender, receiver) = tokio::sync::mpsc::channel(8);
+        sender.send(candle_event("BTC-USD")).await.unwrap();
+        sender.send(candle_event("ETH-USD")).await.unwrap();
+        // a repeated candle is not processed again
+        sender.send(candle_event("ETH-USD")).await.unwrap();
+        drop(sender);
+
+        let result = runtime
+            .run_streaming_until(receiver, std::future::pending())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(LiveRuntimeErrors::MarketError(MarketError::FeedError(_)))
+        ));
+        // the history is not processed, and each candle is processed once both assets have it
+        assert_eq!(market.orders.lock().unwrap().len(), 1);
+        assert_eq!(runtime.portfolio.get_executed_trades().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_paper_market() {
+        let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let candles = (0..20).map(|i| create_candle(start, i)).collect::<Vec<_>>();
+        let market = PaperMarket::new("1m")
+            .with_candles("BTC-USD", "1m", candles.clone())
+            .with_candles("ETH-USD", "1m", candles);
+
+        let strategy = Strategy::new(
+            vec![Box::new(ConstantSignal(Signal::Buy))],
+            Consensus::Unison,
+        );
+        let trading_config = TradingConfig {
+            frequency: "1m".to_string(),
+            trading_asset: "BTC-USD".to_string(),
+            market_asset: "ETH-USD".to_string(),
+            order_type: OrderType::Market,
+        };
+        let mut runtime = LiveRuntime::new(
+            strategy,
+            market.clone(),
+            PortfolioArgs::default(),
+            PositionManagerConfig::default(),
+            trading_config,
+        )
+        .unwrap();
+
+        runtime.replay().await.unwrap();
+
+        assert_eq!(
+            market.current_time(),
+            Some(start + ChronoDuration::minutes(19))
+        );
+        assert!(!market.get_orders().is_empty());
+        assert_eq!(
+            runtime.portfolio.get_executed_trades().len(),
+            market.get_orders().len()
+        );
+    }
+
+    /// Records the time of the last candle it receives
+    struct LastCandleTime(Arc<Mutex<Vec<NaiveDateTime>>>);
+
+    impl CandleProcessor for LastCandleTime {
+        type ReturnType = ScoredSignal;
+        type ErrorType = GraphProcessingError;
+
+        fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
+            let rows = extract_candles_from_df(candles).unwrap();
+            self.0.lock().unwrap().push(rows.last().unwrap().time);
+            Ok(Signal::Hold.into())
+        }
+
+        fn get_name(&self) -> &'static str {
+            "last_candle_time"
+        }
+
+        fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+            candles.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_multiple_intervals() {
+        let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let candles = (0..20).map(|i| create_candle(start, i)).collect::<Vec<_>>();
+        let five_minute = (0..4)
+            .map(|i| create_candle(start, i * 5))
+            .collect::<Vec<_>>();
+        let market = PaperMarket::new("1m")
+            .with_candles("BTC-USD", "1m", candles.clone())
+            .with_candles("BTC-USD", "5m", five_minute)
+            .with_candles("ETH-USD", "1m", candles);
+
+        let base_times = Arc::new(Mutex::new(vec![]));
+        let interval_times = Arc::new(Mutex::new(vec![]));
+        let strategy = Strategy::new(
+            vec![
+                Box::new(LastCandleTime(base_times.clone())),
+                Box::new(LastCandleTime(interval_times.clone())),
+            ],
+            Consensus::Unison,
+        )
+        .with_interval(1, "5m");
+        let trading_config = TradingConfig {
+            frequency: "1m".to_string(),
+            trading_asset: "BTC-USD".to_string(),
+            market_asset: "ETH-USD".to_string(),
+            order_type: OrderType::Market,
+        };
+        let mut runtime = LiveRuntime::new(
+            strategy,
+            market,
+            PortfolioArgs::default(),
+            PositionManagerConfig::default(),
+            trading_config,
+        )
+        .unwrap();
+
+        runtime.replay().await.unwrap();
+
+        // the 5m candles are only visible once closed
+        let base_times = base_times.lock().unwrap();
+        let interval_times = interval_times.lock().unwrap();
+        assert_eq!(base_times.len(), 19);
+        assert_eq!(interval_times.len(), 16);
+        for (base, interval) in base_times.iter().rev().zip(interval_times.iter().rev()) {
+            assert!(*interval + ChronoDuration::minutes(5) <= *base + ChronoDuration::minutes(1));
+            assert!(*interval + ChronoDuration::minutes(10) > *base + ChronoDuration::minutes(1));
+        }
+    }
+}
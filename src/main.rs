@@ -1,38 +1,34 @@
-use crate::backtesting::BacktestingRuntime;
-use log::info;
+use crate::cli::Cli;
+use clap::Parser;
+use log::error;
+use std::process::ExitCode;
 
 mod backtesting;
+mod cli;
 mod indicators;
+mod live;
 mod manager;
 mod markets;
+mod metrics;
 mod portfolio;
 mod processor;
 mod risk;
 mod serialization;
+mod sizing;
 mod strategies;
 mod traits;
 mod types;
 mod utils;
 
-fn main() {
+#[tokio::main]
+async fn main() -> ExitCode {
     colog::init();
 
-    let strategy = strategies::Strategy::new(
-        vec![
-            Box::new(indicators::BBands::default()),
-            Box::new(indicators::VWAP::new(5)),
-        ],
-        strategies::Consensus::Unison,
-    );
-
-    let mut runtime = BacktestingRuntime::from_config("data/backtesting_config.toml", strategy)
-        .load_candles()
-        .expect("Could not load candles");
-
-    info!("******************************************\nStarting to process");
-    runtime.run().unwrap();
-
-    // Save runtime data
-    info!("******************************************\nSaving backtesting runtime data");
-    runtime.save_data("data/backtesting");
+    match Cli::parse().run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            e.exit_code()
+        }
+    }
 }
@@ -1,6 +1,11 @@
-use crate::portfolio::{CapitalHandlers, Portfolio, PositionHandlers};
-use crate::risk::PortfolioRisk;
-use crate::types::{Signal, Trade};
+use crate::portfolio::{
+    CapitalHandlers, EquityHandlers, OpenPosition, Portfolio, PositionHandlers,
+};
+use crate::risk::{PortfolioRisk, ReturnsSource, ValueAtRiskConfig};
+use crate::sizing::{PositionSizer, SizingConfig};
+use crate::types::{OrderType, ScoredSignal, Side, Signal, Trade};
+use crate::utils::frequency_to_duration;
+use chrono::{Duration, NaiveDateTime};
 use log::{info, warn};
 /// # NOTES
 ///
@@ -28,28 +33,79 @@ pub struct PositionManagerConfig {
     // limits the total allowable capital allocated to open positions
     pub max_position_size: Decimal,
 
-    // Sets the percentage loss at which to exit a position
+    // Sets the percentage loss at which to exit a position. Checked on every candle. A value of 0 disables the stop.
     pub stop_loss_percentage: Decimal,
 
     // Sets the percentage gain at which to take profits
     pub take_profit_percentage: Decimal,
 
+    // exit a position once the price falls this fraction below the highest price since entry. Checked on every
+    // candle. A value of 0 disables the stop.
+    #[serde(default)]
+    pub trailing_stop_percentage: Decimal,
+
+    // exit a position at its entry price once it has gained this fraction. Checked on every candle. A value of 0
+    // disables the stop.
+    #[serde(default)]
+    pub break_even_percentage: Decimal,
+
+    // exit a position once it has been held this long, such as "12h" or "3d". Checked on every candle.
+    #[serde(
+        default,
+        deserialize_with = "crate::serialization::duration_label_deserializer"
+    )]
+    pub max_holding_period: Option<String>,
+
     // control the portfolio's volatility relative to the market
     pub max_beta: Decimal,
 
     // VaR limit ensures the potential loss doesn't exceed a certain threshold.
     pub var_limit: Decimal,
 
-    // Defines the maximum allowable drawdown before halting trading
-    // not used
+    // limits the average loss beyond the VaR, in the quote currency. A value of 0 disables the limit.
+    #[serde(default)]
+    pub expected_shortfall_limit: Decimal,
+
+    // confidence level, lookback, method and horizon of the VaR and expected shortfall
+    #[serde(default)]
+    pub value_at_risk: ValueAtRiskConfig,
+
+    // Defines the maximum allowable drawdown of portfolio equity before halting new buys. 0.2 is a 20% decline from
+    // the peak equity. A value of 0 disables the limit.
     #[serde(default)]
     pub max_drawdown: Decimal,
 
-    // ensure the risk-adjusted returns meet a certain threshold. Maintain balance between risk and return.
-    pub min_sharpe_ratio: Decimal,
+    // sell all open positions when the maximum drawdown is exceeded
+    #[serde(default)]
+    pub liquidate_on_max_drawdown: bool,
+
+    // ensure the risk-adjusted returns meet a certain threshold. Maintain balance between risk and return. Ratios are
+    // annualized, and each gate is disabled when unset.
+    #[serde(default)]
+    pub min_sharpe_ratio: Option<Decimal>,
+    #[serde(default)]
+    pub min_sortino_ratio: Option<Decimal>,
+    #[serde(default)]
+    pub min_calmar_ratio: Option<Decimal>,
+
+    // calculate risk metrics from the returns of the trading asset or of the portfolio equity
+    #[serde(default)]
+    pub returns_source: ReturnsSource,
+
+    // annual return of a risk-free asset subtracted from returns by the Sharpe and Sortino ratios. 0.04 is 4%.
+    #[serde(default)]
+    pub risk_free_rate: Decimal,
 
     // trigger profit-taking sells when it exceeds a certain threshold
     pub unrealized_pnl_limit: Decimal,
+
+    // scale buy and profit-taking sell quantities by the confidence of the signal
+    #[serde(default)]
+    pub scale_by_confidence: bool,
+
+    // sizes buys within the limits above. When unset, buys are as large as the limits allow.
+    #[serde(default)]
+    pub sizing: Option<SizingConfig>,
 }
 
 impl Default for PositionManagerConfig {
@@ -58,54 +114,168 @@ impl Default for PositionManagerConfig {
             max_position_size: dec!(100),
             stop_loss_percentage: dec!(0.05),
             take_profit_percentage: dec!(0.1),
+            trailing_stop_percentage: Decimal::ZERO,
+            break_even_percentage: Decimal::ZERO,
+            max_holding_period: None,
             max_beta: dec!(1.4),
             var_limit: dec!(10),
-            max_drawdown: dec!(0.2), // unused
-            min_sharpe_ratio: dec!(0.6),
+            expected_shortfall_limit: Decimal::ZERO,
+            value_at_risk: ValueAtRiskConfig::default(),
+            max_drawdown: dec!(0.2),
+            liquidate_on_max_drawdown: false,
+            min_sharpe_ratio: Some(dec!(0.6)),
+            min_sortino_ratio: None,
+            min_calmar_ratio: None,
+            returns_source: ReturnsSource::default(),
+            risk_free_rate: Decimal::ZERO,
             unrealized_pnl_limit: dec!(1.0),
+            scale_by_confidence: false,
+            sizing: None,
         }
     }
 }
 
 pub struct PositionManager {
     config: PositionManagerConfig,
+    sizer: Option<Box<dyn PositionSizer>>,
 }
 
 impl PositionManager {
     pub fn new(config: PositionManagerConfig) -> Self {
-        Self { config }
+        let sizer = config.sizing.as_ref().map(SizingConfig::build);
+        Self { config, sizer }
     }
 
     pub async fn update_config(&mut self, new_config: PositionManagerConfig) {
+        self.sizer = new_config.sizing.as_ref().map(SizingConfig::build);
         self.config = new_config;
         info!("PositionManager configuration updated");
     }
 
     /// Verifies that the current drawdown hasn't exceeded the maximum allowed
-    fn check_max_drawdown(&self) -> bool {
-        // portfolio.current_drawdown() <= self.config.max_drawdown
-        todo!("Portfolio doesn't have a drawdown method yet")
+    ///
+    /// Drawdown is measured from the equity recorded with [`EquityHandlers::update_equity`].
+    fn check_max_drawdown(&self, portfolio: &Portfolio) -> bool {
+        self.config.max_drawdown <= Decimal::ZERO
+            || portfolio.current_drawdown() <= self.config.max_drawdown
     }
 
+    /// Decide which trade, if any, to make at the current candle
+    ///
+    /// Positions are not closed here. Positions which should be sold are marked with
+    /// [`PositionHandlers::mark_for_exit`] and are closed once the sell is added to the portfolio.
     pub fn make_decision(
         &mut self,
         portfolio: &mut Portfolio,
         risk: &PortfolioRisk,
-        signal: &Signal,
+        signal: &ScoredSignal,
         current_price: Decimal,
+        current_time: NaiveDateTime,
     ) -> Result<TradeDecision, PositionManagerError> {
+        portfolio.update_high_water_marks(current_price);
+
+        // Halt buys, and optionally liquidate, once the maximum drawdown is exceeded
+        let within_max_drawdown = self.check_max_drawdown(portfolio);
+        if !within_max_drawdown {
+            warn!("Max drawdown exceeded: {}", portfolio.current_drawdown());
+            let total_quantity = portfolio.total_open_quantity();
+            if self.config.liquidate_on_max_drawdown && total_quantity > Decimal::ZERO {
+                info!("Liquidating quantity: {}", total_quantity);
+                portfolio.mark_for_exit(&[]);
+                let closed_trade_ids = portfolio.positions_to_close(total_quantity, current_price);
+                return Ok(TradeDecision::ExecuteSell(total_quantity, closed_trade_ids));
+            }
+        }
+
+        // Exit rules are checked regardless of the signal
+        if let Some(decision) = self.check_exit_rules(portfolio, current_price, current_time) {
+            return Ok(decision);
+        }
+
+        if !within_max_drawdown && signal.signal == Signal::Buy {
+            info!("Buy signal ignored: max drawdown exceeded");
+            return Ok(TradeDecision::DoNothing);
+        }
+
         // Check if we're within our risk tolerance
         if !self.is_within_risk_tolerance(&risk) {
             return Ok(TradeDecision::DoNothing);
         }
 
-        match signal {
-            Signal::Buy => self.process_buy_signal(portfolio, &risk, current_price),
-            Signal::Sell => self.process_sell_signal(portfolio, &risk, current_price),
+        let scale = self.quantity_scale(signal);
+        match signal.signal {
+            Signal::Buy => self.process_buy_signal(portfolio, &risk, current_price, scale),
+            Signal::Sell => self.process_sell_signal(portfolio, &risk, current_price, scale),
             Signal::Hold => Ok(TradeDecision::DoNothing),
         }
     }
 
+    /// Sells the positions which have hit their stop-loss, trailing stop, break-even stop or maximum holding period
+    ///
+    /// Triggered positions are marked for exit, and any earlier marks are cleared.
+    ///
+    /// # Returns
+    /// `None` if no position should be exited
+    fn check_exit_rules(
+        &self,
+        portfolio: &mut Portfolio,
+        current_price: Decimal,
+        current_time: NaiveDateTime,
+    ) -> Option<TradeDecision> {
+        let max_holding_period = self
+            .config
+            .max_holding_period
+            .as_deref()
+            .and_then(frequency_to_duration)
+            .and_then(|duration| Duration::from_std(duration).ok());
+
+        let mut sell_quantity = Decimal::ZERO;
+        let mut entry_times = Vec::new();
+        for (entry_time, position) in portfolio.get_open_positions() {
+            let held_too_long = max_holding_period
+                .is_some_and(|period| current_time - position.entry_time >= period);
+            if held_too_long || self.stop_triggered(position, current_price) {
+                info!("Exit rule triggered for position: {:?}", position);
+                sell_quantity += position.quantity;
+                entry_times.push(*entry_time);
+            }
+        }
+        portfolio.mark_for_exit(&entry_times);
+
+        if sell_quantity > Decimal::ZERO {
+            let closed_trade_ids = portfolio.positions_to_close(sell_quantity, current_price);
+            Some(TradeDecision::ExecuteSell(sell_quantity, closed_trade_ids))
+        } else {
+            None
+        }
+    }
+
+    /// Checks the stop-loss, trailing stop and break-even stop of a position
+    fn stop_triggered(&self, position: &OpenPosition, current_price: Decimal) -> bool {
+        let stop_loss = self.config.stop_loss_percentage > Decimal::ZERO
+            && current_price
+                <= position.entry_price * (Decimal::ONE - self.config.stop_loss_percentage);
+        let trailing_stop = self.config.trailing_stop_percentage > Decimal::ZERO
+            && current_price
+                <= position.high_water_mark * (Decimal::ONE - self.config.trailing_stop_percentage);
+        let break_even = self.config.break_even_percentage > Decimal::ZERO
+            && position.high_water_mark
+                >= position.entry_price * (Decimal::ONE + self.config.break_even_percentage)
+            && current_price <= position.entry_price;
+        stop_loss || trailing_stop || break_even
+    }
+
+    /// Fraction of the calculated quantity to trade for the given signal
+    ///
+    /// This is the signal confidence when `scale_by_confidence` is enabled, otherwise 1.
+    fn quantity_scale(&self, signal: &ScoredSignal) -> Decimal {
+        if self.config.scale_by_confidence {
+            Decimal::from_f64(signal.confidence).unwrap_or(Decimal::ZERO)
+        } else {
+            Decimal::ONE
+        }
+    }
+
     /// checks if the current risk profile is within tolerance using all the metrics
     fn is_within_risk_tolerance(&self, risk: &PortfolioRisk) -> bool {
         if risk.total_position_value == Decimal::ZERO {
@@ -114,30 +284,55 @@ impl PositionManager {
 
         let max_position = risk.total_position_value <= self.config.max_position_size;
         let var_limit = risk.value_at_risk <= self.config.var_limit;
+        let expected_shortfall = self.config.expected_shortfall_limit <= Decimal::ZERO
+            || risk.expected_shortfall <= self.config.expected_shortfall_limit;
         let beta = risk.beta <= self.config.max_beta;
-        let sharpe_ratio = risk.sharpe_ratio >= self.config.min_sharpe_ratio;
+        let above =
+            |ratio: Decimal, minimum: Option<Decimal>| minimum.is_none_or(|min| ratio >= min);
+        let sharpe_ratio = above(risk.sharpe_ratio, self.config.min_sharpe_ratio);
+        let sortino_ratio = above(risk.sortino_ratio, self.config.min_sortino_ratio);
+        let calmar_ratio = above(risk.calmar_ratio, self.config.min_calmar_ratio);
 
         if !max_position {
             warn!("Max position size exceeded: {}", risk.total_position_value);
         } else if !var_limit {
             warn!("VaR limit exceeded: {}", risk.value_at_risk);
+        } else if !expected_shortfall {
+            warn!(
+                "Expected shortfall limit exceeded: {}",
+                risk.expected_shortfall
+            );
         } else if !beta {
             warn!("Beta limit exceeded: {}", risk.beta);
         } else if !sharpe_ratio {
             warn!("Sharpe ratio below minimum: {}", risk.sharpe_ratio);
+        } else if !sortino_ratio {
+            warn!("Sortino ratio below minimum: {}", risk.sortino_ratio);
+        } else if !calmar_ratio {
+            warn!("Calmar ratio below minimum: {}", risk.calmar_ratio);
         }
 
-        max_position && var_limit && beta && sharpe_ratio
+        max_position
+            && var_limit
+            && expected_shortfall
+            && beta
+            && sharpe_ratio
+            && sortino_ratio
+            && calmar_ratio
     }
 
     /// calculates the available risk capacity based on the difference between the maximum allowed portfolio risk and current VaR.
     ///
     /// determines the maximum quantity that can be bought without exceeding this risk capacity.
+    ///
+    /// When a [`PositionSizer`] is configured, the quantity is also limited to its size. The quantity is multiplied
+    /// by `scale`.
     fn process_buy_signal(
         &self,
         portfolio: &Portfolio,
         risk: &PortfolioRisk,
         current_price: Decimal,
+        scale: Decimal,
     ) -> Result<TradeDecision, PositionManagerError> {
         // Check if we're within our risk tolerance
         if !self.is_within_risk_tolerance(risk) {
@@ -161,14 +356,22 @@ impl PositionManager {
             return Ok(TradeDecision::DoNothing);
         }
 
-        // Calculate the maximum quantity we can buy based on risk capacity and available capital
+        // Calculate the maximum quantity we can buy based on risk capacity and available capital.
+        // Fees are reserved from available capital so that the buy can be paid for. The taker fee is reserved since
+        // the order type is not known here.
+        let fee = portfolio.cost_including_fee(available_capital, Side::Buy, OrderType::Market)
+            - available_capital;
         let max_quantity_risk = available_risk / current_price;
-        let max_quantity_capital = available_capital / current_price;
-        let max_quantity = max_quantity_risk.min(max_quantity_capital);
+        let max_quantity_capital = (available_capital - fee) / current_price;
+        let mut max_quantity = max_quantity_risk.min(max_quantity_capital);
+        if let Some(sizer) = &self.sizer {
+            let notional = sizer.buy_notional(portfolio, risk, current_price);
+            max_quantity = max_quantity.min(notional / current_price);
+        }
 
         // Apply position size limits
         let position_limit = self.config.max_position_size / current_price;
-        let buy_quantity = max_quantity.min(position_limit);
+        let buy_quantity = max_quantity.min(position_limit) * scale;
 
         if buy_quantity > Decimal::ZERO {
             info!("Executing buy for quantity: {}", buy_quantity);
@@ -182,11 +385,14 @@ impl PositionManager {
     /// checks if the unrealized PnL has reached the profit-taking threshold.
     ///
     /// checks if the VaR exceeds the limit and calculates how much to sell to bring the risk back within limits.
+    ///
+    /// Profit-taking sells are multiplied by `scale`. Risk management sells are not scaled.
     fn process_sell_signal(
         &mut self,
         portfolio: &mut Portfolio,
         risk: &PortfolioRisk,
         current_price: Decimal,
+        scale: Decimal,
     ) -> Result<TradeDecision, PositionManagerError> {
         let total_quantity = portfolio.total_open_quantity();
 
@@ -195,13 +401,15 @@ impl PositionManager {
         }
 
         // Check if we've reached the profit-taking threshold
-        if risk.unrealized_pnl >= self.config.unrealized_pnl_limit {
+        let sell_quantity = total_quantity * scale;
+        if risk.unrealized_pnl >= self.config.unrealized_pnl_limit && sell_quantity > Decimal::ZERO
+        {
             info!(
-                "Taking profit, attempting to sell total quantity: {}",
-                total_quantity
+                "Taking profit, attempting to sell quantity: {}",
+                sell_quantity
             );
-            let closed_trade_ids = portfolio.close_positions(total_quantity, current_price);
-            return Ok(TradeDecision::ExecuteSell(total_quantity, closed_trade_ids));
+            let closed_trade_ids = portfolio.positions_to_close(sell_quantity, current_price);
+            return Ok(TradeDecision::ExecuteSell(sell_quantity, closed_trade_ids));
         }
 
         // Check if we need to reduce risk
@@ -213,33 +421,29 @@ impl PositionManager {
                 "Risk management sell, attempting to sell quantity: {}",
                 sell_quantity
             );
-            let closed_trade_ids = portfolio.close_positions(sell_quantity, current_price);
+            let closed_trade_ids = portfolio.positions_to_close(sell_quantity, current_price);
             return Ok(TradeDecision::ExecuteSell(sell_quantity, closed_trade_ids));
         }
 
-        // Check stop-loss and take-profit for individual positions
-        let open_positions = portfolio.get_open_positions().clone(); // cloned to allow borrowing as mutable
+        // Check take-profit for individual positions. Stop-losses are checked on every candle by the exit rules.
         let mut total_sell_quantity = Decimal::ZERO;
-        let mut closed_trade_ids = Vec::new();
+        let mut entry_times = Vec::new();
 
-        for (_, position) in open_positions {
-            let stop_loss =
-                position.entry_price * (Decimal::ONE - self.config.stop_loss_percentage);
+        for (entry_time, position) in portfolio.get_open_positions() {
             let take_profit =
                 position.entry_price * (Decimal::ONE + self.config.take_profit_percentage);
 
-            if current_price <= stop_loss || current_price >= take_profit {
-                info!(
-                    "Stop-loss or take-profit triggered for position: {:?}",
-                    position
-                );
-                let ids = portfolio.close_positions(position.quantity, current_price);
+            if current_price >= take_profit {
+                info!("Take-profit triggered for position: {:?}", position);
                 total_sell_quantity += position.quantity;
-                closed_trade_ids.extend(ids);
+                entry_times.push(*entry_time);
             }
         }
 
         if total_sell_quantity > Decimal::ZERO {
+            // sell the triggered positions instead of the most profitable ones
+            portfolio.mark_for_exit(&entry_times);
+            let closed_trade_ids = portfolio.positions_to_close(total_sell_quantity, current_price);
             return Ok(TradeDecision::ExecuteSell(
                 total_sell_quantity,
                 closed_trade_ids,
@@ -255,3 +459,393 @@ pub enum TradeDecision {
     ExecuteSell(Decimal, Vec<String>), // Quantity to sell
     DoNothing,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolio::TradeHandlers;
+    use crate::types::ExecutedTrade;
+    use chrono::{Duration, NaiveDateTime};
+
+    fn time() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn create_risk() -> PortfolioRisk {
+        PortfolioRisk {
+            total_position_value: Decimal::ZERO,
+            average_entry_price: Decimal::ZERO,
+            unrealized_pnl: Decimal::ZERO,
+            value_at_risk: Decimal::ZERO,
+            expected_shortfall: Decimal::ZERO,
+            beta: Decimal::ZERO,
+            sharpe_ratio: Decimal::ZERO,
+            sortino_ratio: Decimal::ZERO,
+            calmar_ratio: Decimal::ZERO,
+            volatility: Decimal::ZERO,
+            atr_volatility: Decimal::ZERO,
+        }
+    }
+
+    fn buy_quantity(config: PositionManagerConfig, signal: ScoredSignal) -> Decimal {
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), None);
+        let mut manager = PositionManager::new(config);
+        match manager
+            .make_decision(&mut portfolio, &create_risk(), &signal, dec!(10), time())
+            .unwrap()
+        {
+            TradeDecision::ExecuteBuy(quantity) => quantity,
+            _ => Decimal::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_scale_by_confidence() {
+        let signal = ScoredSignal::new(Signal::Buy, 0.5);
+
+        // quantity is limited by the VaR limit of 10
+        let quantity = buy_quantity(PositionManagerConfig::default(), signal);
+        assert_eq!(quantity, dec!(1));
+
+        let config = PositionManagerConfig {
+            scale_by_confidence: true,
+            ..Default::default()
+        };
+        assert_eq!(buy_quantity(config.clone(), signal), dec!(0.5));
+
+        let signal = ScoredSignal::new(Signal::Buy, 0.0);
+        assert_eq!(buy_quantity(config, signal), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_position_sizer() {
+        let signal = ScoredSignal::from(Signal::Buy);
+
+        // the sizer can only reduce the quantity allowed by the VaR limit of 10
+        let config = |notional| PositionManagerConfig {
+            sizing: Some(SizingConfig::FixedNotional { notional }),
+            ..Default::default()
+        };
+        assert_eq!(buy_quantity(config(dec!(5)), signal), dec!(0.5));
+        assert_eq!(buy_quantity(config(dec!(50)), signal), dec!(1));
+    }
+
+    #[test]
+    fn test_expected_shortfall_limit() {
+        let risk = PortfolioRisk {
+            total_position_value: dec!(50),
+            value_at_risk: dec!(4),
+            expected_shortfall: dec!(6),
+            ..create_risk()
+        };
+        let config = PositionManagerConfig {
+            min_sharpe_ratio: None,
+            ..Default::default()
+        };
+        assert!(PositionManager::new(config.clone()).is_within_risk_tolerance(&risk));
+
+        let manager = PositionManager::new(PositionManagerConfig {
+            expected_shortfall_limit: dec!(5),
+            ..config.clone()
+        });
+        assert!(!manager.is_within_risk_tolerance(&risk));
+
+        let manager = PositionManager::new(PositionManagerConfig {
+            expected_shortfall_limit: dec!(6),
+            ..config
+        });
+        assert!(manager.is_within_risk_tolerance(&risk));
+    }
+
+    #[test]
+    fn test_ratio_gates() {
+        let risk = PortfolioRisk {
+            total_position_value: dec!(50),
+            sharpe_ratio: dec!(1.5),
+            sortino_ratio: dec!(2),
+            calmar_ratio: dec!(0.5),
+            ..create_risk()
+        };
+        let config = PositionManagerConfig {
+            min_sharpe_ratio: None,
+            ..Default::default()
+        };
+        assert!(PositionManager::new(config.clone()).is_within_risk_tolerance(&risk));
+
+        let gates = [
+            (Some(dec!(2)), None, None),
+            (None, Some(dec!(3)), None),
+            (None, None, Some(dec!(1))),
+        ];
+        for (min_sharpe_ratio, min_sortino_ratio, min_calmar_ratio) in gates {
+            let manager = PositionManager::new(PositionManagerConfig {
+                min_sharpe_ratio,
+                min_sortino_ratio,
+                min_calmar_ratio,
+                ..config.clone()
+            });
+            assert!(!manager.is_within_risk_tolerance(&risk));
+        }
+
+        let manager = PositionManager::new(PositionManagerConfig {
+            min_sharpe_ratio: Some(dec!(1.5)),
+            min_sortino_ratio: Some(dec!(2)),
+            min_calmar_ratio: Some(dec!(0.5)),
+            ..config
+        });
+        assert!(manager.is_within_risk_tolerance(&risk));
+    }
+
+    /// Portfolio holding 10 assets bought at 10, marked at 10 and then at 7
+    fn portfolio_in_drawdown() -> Portfolio {
+        let time = time();
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), time);
+        portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+            "buy".to_string(),
+            Side::Buy,
+            dec!(10),
+            dec!(10),
+            time,
+        ));
+        portfolio.update_equity(dec!(10));
+        portfolio.update_equity(dec!(7));
+        portfolio
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        let mut portfolio = portfolio_in_drawdown();
+        // equity fell from 1000 to 970
+        assert_eq!(portfolio.current_drawdown(), dec!(0.03));
+
+        let buy = ScoredSignal::from(Signal::Buy);
+        let config = PositionManagerConfig {
+            max_drawdown: dec!(0.05),
+            stop_loss_percentage: dec!(0),
+            ..Default::default()
+        };
+        let mut manager = PositionManager::new(config.clone());
+        let decision = manager
+            .make_decision(&mut portfolio, &create_risk(), &buy, dec!(7), time())
+            .unwrap();
+        assert!(matches!(decision, TradeDecision::ExecuteBuy(_)));
+
+        // buys are halted once the drawdown exceeds the limit
+        let mut manager = PositionManager::new(PositionManagerConfig {
+            max_drawdown: dec!(0.02),
+            ..config.clone()
+        });
+        let decision = manager
+            .make_decision(&mut portfolio, &create_risk(), &buy, dec!(7), time())
+            .unwrap();
+        assert!(matches!(decision, TradeDecision::DoNothing));
+
+        // a limit of 0 is disabled
+        let mut manager = PositionManager::new(PositionManagerConfig {
+            max_drawdown: dec!(0),
+            ..config
+        });
+        let decision = manager
+            .make_decision(&mut portfolio, &create_risk(), &buy, dec!(7), time())
+            .unwrap();
+        assert!(matches!(decision, TradeDecision::ExecuteBuy(_)));
+    }
+
+    #[test]
+    fn test_liquidate_on_max_drawdown() {
+        let mut portfolio = portfolio_in_drawdown();
+        let mut manager = PositionManager::new(PositionManagerConfig {
+            max_drawdown: dec!(0.02),
+            liquidate_on_max_drawdown: true,
+            ..Default::default()
+        });
+
+        let decision = manager
+            .make_decision(
+                &mut portfolio,
+                &create_risk(),
+                &ScoredSignal::from(Signal::Hold),
+                dec!(7),
+                time(),
+            )
+            .unwrap();
+        match decision {
+            TradeDecision::ExecuteSell(quantity, ids) => {
+                assert_eq!(quantity, dec!(10));
+                assert_eq!(ids.len(), 1);
+            }
+            _ => panic!("Expected liquidation"),
+        }
+    }
+
+    /// Portfolio holding one asset bought at 10 and another bought at 20, a minute later
+    fn portfolio_with_positions() -> Portfolio {
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), time());
+        for (i, price) in [dec!(10), dec!(20)].into_iter().enumerate() {
+            portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+                i.to_string(),
+                Side::Buy,
+                price,
+                dec!(1),
+                time() + Duration::minutes(i as i64),
+            ));
+        }
+        portfolio
+    }
+
+    /// Hold decisions of the manager at each of the given prices, one minute apart
+    fn hold_decisions(
+        manager: &mut PositionManager,
+        portfolio: &mut Portfolio,
+        prices: &[Decimal],
+    ) -> Vec<TradeDecision> {
+        let hold = ScoredSignal::from(Signal::Hold);
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| {
+                let current_time = time() + Duration::minutes(2 + i as i64);
+                manager
+                    .make_decision(portfolio, &create_risk(), &hold, *price, current_time)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trailing_stop() {
+        let mut portfolio = portfolio_with_positions();
+        let mut manager = PositionManager::new(PositionManagerConfig {
+            trailing_stop_percentage: dec!(0.1),
+            ..Default::default()
+        });
+
+        // the second position is stopped below 18. The first position peaks at 15, so its stop is at 13.5.
+        let decisions = hold_decisions(
+            &mut manager,
+            &mut portfolio,
+            &[dec!(15), dec!(14), dec!(13)],
+        );
+        let sells = decisions
+            .iter()
+            .map(|decision| match decision {
+                TradeDecision::ExecuteSell(quantity, ids) => (*quantity, ids.clone()),
+                _ => panic!("Expected trailing stop"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sells[0], (dec!(1), vec!["1".to_string()]));
+        assert_eq!(sells[1], (dec!(1), vec!["1".to_string()]));
+        assert_eq!(sells[2].0, dec!(2));
+        assert_eq!(sells[2].1.len(), 2);
+    }
+
+    #[test]
+    fn test_stop_loss_on_hold() {
+        let mut portfolio = portfolio_with_positions();
+        let mut manager = PositionManager::new(PositionManagerConfig::default());
+
+        // the default 5% stop-loss is checked without a sell signal. The second position is stopped at 19 and the
+        // first at 9.5.
+        let decisions = hold_decisions(
+            &mut manager,
+            &mut portfolio,
+            &[dec!(19.5), dec!(19), dec!(9)],
+        );
+        assert!(matches!(decisions[0], TradeDecision::DoNothing));
+        match &decisions[1] {
+            TradeDecision::ExecuteSell(quantity, ids) => {
+                assert_eq!(*quantity, dec!(1));
+                assert_eq!(ids, &vec!["1".to_string()]);
+            }
+            _ => panic!("Expected stop-loss"),
+        }
+        match &decisions[2] {
+            TradeDecision::ExecuteSell(quantity, ids) => {
+                assert_eq!(*quantity, dec!(2));
+                assert_eq!(ids.len(), 2);
+            }
+            _ => panic!("Expected stop-loss"),
+        }
+    }
+
+    #[test]
+    fn test_break_even_stop() {
+        let mut portfolio = portfolio_with_positions();
+        let mut manager = PositionManager::new(PositionManagerConfig {
+            break_even_percentage: dec!(0.2),
+            stop_loss_percentage: dec!(0),
+            ..Default::default()
+        });
+
+        // only the first position gained 20%, so only it is stopped at its entry price
+        let decisions = hold_decisions(&mut manager, &mut portfolio, &[dec!(15), dec!(10)]);
+        assert!(matches!(decisions[0], TradeDecision::DoNothing));
+        match &decisions[1] {
+            TradeDecision::ExecuteSell(quantity, ids) => {
+                assert_eq!(*quantity, dec!(1));
+                assert_eq!(ids, &vec!["0".to_string()]);
+            }
+            _ => panic!("Expected break-even stop"),
+        }
+    }
+
+    #[test]
+    fn test_max_holding_period() {
+        let mut portfolio = portfolio_with_positions();
+        let mut manager = PositionManager::new(PositionManagerConfig {
+            max_holding_period: Some("3m".to_string()),
+            stop_loss_percentage: dec!(0),
+            ..Default::default()
+        });
+
+        // the first position is held for 3 minutes at the second decision
+        let decisions = hold_decisions(&mut manager, &mut portfolio, &[dec!(15), dec!(15)]);
+        assert!(matches!(decisions[0], TradeDecision::DoNothing));
+        match &decisions[1] {
+            TradeDecision::ExecuteSell(quantity, ids) => {
+                assert_eq!(*quantity, dec!(1));
+                assert_eq!(ids, &vec!["0".to_string()]);
+            }
+            _ => panic!("Expected max holding period exit"),
+        }
+
+        let config: Result<PositionManagerConfig, _> = toml::from_str(
+            &toml::to_string(&PositionManagerConfig::default())
+                .unwrap()
+                .replace(
+                    "stop_loss_percentage",
+                    "max_holding_period = \"3x\"\nstop_loss_percentage",
+                ),
+        );
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn test_exit_closes_marked_positions() {
+        let mut portfolio = portfolio_with_positions();
+        let mut manager = PositionManager::new(PositionManagerConfig {
+            max_holding_period: Some("3m".to_string()),
+            ..Default::default()
+        });
+
+        // positions are only closed once the sell is added, and the marked position is closed instead of the
+        // more profitable one
+        let decisions = hold_decisions(&mut manager, &mut portfolio, &[dec!(5), dec!(25)]);
+        assert!(matches!(decisions[1], TradeDecision::ExecuteSell(..)));
+        assert_eq!(portfolio.get_open_positions().len(), 2);
+
+        portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+            "sell".to_string(),
+            Side::Sell,
+            dec!(25),
+            dec!(1),
+            time() + Duration::minutes(3),
+        ));
+        let open_positions = portfolio.get_open_positions();
+        assert_eq!(open_positions.len(), 1);
+        assert_eq!(
+            open_positions.values().next().unwrap().entry_price,
+            dec!(20)
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use crate::markets::MarketError;
+use crate::types::{Candle, DB_PATH};
+use crate::utils::frequency_to_duration;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+use log::{info, warn};
+use rust_decimal::prelude::ToPrimitive;
+use sqlite::{Connection, State};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default time to wait between requests
+///
+/// Coinbase allows 10 requests per second to public endpoints.
+const DEFAULT_REQUEST_INTERVAL: Duration = Duration::from_millis(150);
+
+/// Default number of times a rate limited or failed request is retried
+const DEFAULT_MAX_RETRIES: usize = 5;
+
+/// A market which can return candles for a time range
+#[async_trait]
+pub trait HistoricalMarket {
+    /// Maximum number of candles returned by a single request
+    fn max_candles_per_request(&self) -> usize;
+
+    /// Returns the candles between `start` and `end` (inclusive) in ascending order
+    ///
+    /// # Arguments
+    /// * `pair` - The trading pair to get candles for
+    /// * `interval` - The interval to get candles for (ie: "1m", "1h")
+    /// * `start` - Time of the first candle
+    /// * `end` - Time of the last candle
+    async fn get_candles_between(
+        &self,
+        pair: &str,
+        interval: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<Candle>, MarketError>;
+}
+
+/// Name of the table which stores candles for a pair and interval (ie: "BTC-USD_1m")
+///
+/// The table name contains the pair and interval so that it is found by
+/// [`crate::types::MarketData::from_db_path`].
+pub fn table_name(pair: &str, interval: &str) -> String {
+    format!("{}_{}", pair, interval)
+}
+
+/// Sqlite database of candles
+///
+/// Each pair and interval is stored in a separate table using the layout read by
+/// [`crate::utils::extract_candles_from_db`]: the time in milliseconds since the unix epoch,
+/// followed by the high, low, open, close and volume.
+#[derive(Debug, Clone)]
+pub struct CandleStore {
+    path: PathBuf,
+}
+
+impl Default for CandleStore {
+    fn default() -> Self {
+        Self::new(DB_PATH)
+    }
+}
+
+impl CandleStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    fn open(&self, pair: &str, interval: &str) -> Result<Connection, MarketError> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| MarketError::StoreError(e.to_string()))?;
+            }
+        }
+        let conn = Connection::open(&self.path)?;
+        conn.execute(format!(
+            "CREATE TABLE IF NOT EXISTS \"{}\" (
+                time INTEGER PRIMARY KEY,
+                high REAL NOT NULL,
+                low REAL NOT NULL,
+                open REAL NOT NULL,
+                close REAL NOT NULL,
+                volume REAL NOT NULL
+            )",
+            table_name(pair, interval)
+        ))?;
+        Ok(conn)
+    }
+
+    /// Time of the latest stored candle
+    ///
+    /// # Returns
+    /// `None` if no candles are stored for the pair and interval
+    pub fn last_time(
+        &self,
+        pair: &str,
+        interval: &str,
+    ) -> Result<Option<NaiveDateTime>, MarketError> {
+        let conn = self.open(pair, interval)?;
+        let mut statement = conn.prepare(format!(
+            "SELECT MAX(time) FROM \"{}\"",
+            table_name(pair, interval)
+        ))?;
+        statement.next()?;
+        let time = statement.read::<Option<i64>, _>(0)?;
+        Ok(time
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|time| time.naive_utc()))
+    }
+
+    /// Write candles to the store
+    ///
+    /// Candles which are already stored are replaced.
+    ///
+    /// # Returns
+    /// The number of candles written
+    pub fn insert(
+        &self,
+        pair: &str,
+        interval: &str,
+        candles: &[Candle],
+    ) -> Result<usize, MarketError> {
+        let conn = self.open(pair, interval)?;
+        conn.execute("BEGIN")?;
+        let mut statement = conn.prepare(format!(
+            "INSERT OR REPLACE INTO \"{}\" (time, high, low, open, close, volume)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            table_name(pair, interval)
+        ))?;
+        for candle in candles {
+            let value = |x: rust_decimal::Decimal| x.to_f64().unwrap_or_default();
+            statement.reset()?;
+            statement.bind((1, candle.time.and_utc().timestamp_millis()))?;
+            statement.bind((2, value(candle.high)))?;
+            statement.bind((3, value(candle.low)))?;
+            statement.bind((4, value(candle.open)))?;
+            statement.bind((5, value(candle.close)))?;
+            statement.bind((6, value(candle.volume)))?;
+            while statement.next()? != State::Done {}
+        }
+        drop(statement);
+        conn.execute("COMMIT")?;
+        Ok(candles.len())
+    }
+}
+
+/// Downloads historical candles into a [`CandleStore`]
+///
+/// The requested range is split into pages of at most
+/// [`HistoricalMarket::max_candles_per_request`] candles. Each page is written before the next is
+/// requested, so an interrupted backfill resumes after the last stored candle.
+pub struct Backfill<'a, M: HistoricalMarket> {
+    market: &'a M,
+    store: CandleStore,
+    request_interval: Duration,
+    max_retries: usize,
+}
+
+impl<'a, M: HistoricalMarket + Sync> Backfill<'a, M> {
+    pub fn new(market: &'a M, store: CandleStore) -> Self {
+        Self {
+            market,
+            store,
+            request_interval: DEFAULT_REQUEST_INTERVAL,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    /// Builder method for the minimum time between requests
+    pub fn with_request_interval(mut self, request_interval: Duration) -> Self {
+        self.request_interval = request_interval;
+        self
+    }
+
+    /// Builder method for the number of times a failed request is retried
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Download and store candles between `start` and `end`
+    ///
+    /// If candles are already stored, the backfill starts after the latest stored candle.
+    ///
+    /// # Returns
+    /// The number of candles written
+    pub async fn run(
+        &self,
+        pair: &str,
+        interval: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<usize, MarketError> {
+        let step = frequency_to_duration(interval)
+            .and_then(|step| TimeDelta::from_std(step).ok())
+            .ok_or_else(|| {
+                MarketError::CandlesUnavailable(pair.to_string(), interval.to_string())
+            })?;
+        let page_length = step * (self.market.max_candles_per_request().max(1) as i32 - 1);
+
+        let mut start = start;
+        if let Some(last) = self.store.last_time(pair, interval)? {
+            if last >= start {
+                info!("Resuming {} {} backfill after {}", pair, interval, last);
+                start = last + step;
+            }
+        }
+
+        let mut written = 0;
+        while start <= end {
+            let page_end = (start + page_length).min(end);
+            let candles = self.request(pair, interval, start, page_end).await?;
+            let candles = candles
+                .into_iter()
+                .filter(|candle| candle.time >= start && candle.time <= page_end)
+                .collect::<Vec<_>>();
+            written += self.store.insert(pair, interval, &candles)?;
+            info!(
+                "Stored {} {} candles from {} to {}",
+                candles.len(),
+                interval,
+                start,
+                page_end
+            );
+
+            start = page_end + step;
+            if start <= end {
+                tokio::time::sleep(self.request_interval).await;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Request a page of candles, retrying with exponential backoff when rate limited
+    async fn request(
+        &self,
+        pair: &str,
+        interval: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<Candle>, MarketError> {
+        let mut attempt = 0;
+        loop {
+            match self
+                .market
+                .get_candles_between(pair, interval, start, end)
+                .await
+            {
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    let backoff = self.request_interval * 2u32.pow(attempt as u32);
+                    warn!(
+                        "Request for {} candles failed ({}). Retrying in {:?}",
+                        pair, e, backoff
+                    );
+                    tokio::time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Rate limits, server errors and connection errors are retried
+fn is_retryable(error: &MarketError) -> bool {
+    match error {
+        MarketError::ResponseError(status, _) => *status == 429 || *status >= 500,
+        MarketError::RequestError(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{create_temp_dir, extract_candles_from_db};
+    use rust_decimal::Decimal;
+    use std::sync::{Arc, Mutex};
+
+    /// Market which generates a candle for every minute and is rate limited on the first request
+    #[derive(Clone)]
+    struct GeneratedMarket {
+        requests: Arc<Mutex<Vec<(NaiveDateTime, NaiveDateTime)>>>,
+        rate_limited: Arc<Mutex<bool>>,
+    }
+
+    impl GeneratedMarket {
+        fn new(rate_limited: bool) -> Self {
+            Self {
+                requests: Arc::new(Mutex::new(vec![])),
+                rate_limited: Arc::new(Mutex::new(rate_limited)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl HistoricalMarket for GeneratedMarket {
+        fn max_candles_per_request(&self) -> usize {
+            10
+        }
+
+        async fn get_candles_between(
+            &self,
+            _: &str,
+            _: &str,
+            start: NaiveDateTime,
+            end: NaiveDateTime,
+        ) -> Result<Vec<Candle>, MarketError> {
+            let mut rate_limited = self.rate_limited.lock().unwrap();
+            if *rate_limited {
+                *rate_limited = false;
+                return Err(MarketError::ResponseError(429, "Slow down".to_string()));
+            }
+            self.requests.lock().unwrap().push((start, end));
+
+            let mut candles = vec![];
+            let mut time = start;
+            while time <= end {
+                let price = Decimal::from(time.and_utc().timestamp() / 60 % 1000);
+                candles.push(Candle {
+                    time,
+                    open: price,
+                    high: price + Decimal::ONE,
+                    low: price - Decimal::ONE,
+                    close: price,
+                    volume: Decimal::TEN,
+                });
+                time += TimeDelta::minutes(1);
+            }
+            Ok(candles)
+        }
+    }
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn store(name: &str) -> CandleStore {
+        let dir = create_temp_dir(Path::new("backfill_testing").join(name).as_path());
+        CandleStore::new(dir.join("candles.sqlite3"))
+    }
+
+    #[test]
+    fn test_store() {
+        let store = store("test_store");
+        assert_eq!(store.last_time("BTC-USD", "1m").unwrap(), None);
+
+        let candle = |t, price| Candle {
+            time: time(t),
+            open: price,
+            high: price + Decimal::ONE,
+            low: price - Decimal::ONE,
+            close: price + Decimal::TWO,
+            volume: Decimal::TEN,
+        };
+        let candles = vec![
+            candle("2024-01-01 00:00", Decimal::from(100)),
+            candle("2024-01-01 00:01", Decimal::from(101)),
+        ];
+        assert_eq!(store.insert("BTC-USD", "1m", &candles).unwrap(), 2);
+
+        // stored candles are replaced
+        let replaced = vec![candle("2024-01-01 00:01", Decimal::from(102))];
+        store.insert("BTC-USD", "1m", &replaced).unwrap();
+
+        assert_eq!(
+            store.last_time("BTC-USD", "1m").unwrap(),
+            Some(time("2024-01-01 00:01"))
+        );
+
+        let path = store.path.to_str().unwrap();
+        let extracted = extract_candles_from_db(path, &table_name("BTC-USD", "1m")).unwrap();
+        assert_eq!(extracted, vec![candles[0].clone(), replaced[0].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_run_pages() {
+        let store = store("test_run_pages");
+        let market = GeneratedMarket::new(true);
+        let backfill =
+            Backfill::new(&market, store.clone()).with_request_interval(Duration::from_millis(1));
+
+        let written = backfill
+            .run(
+                "BTC-USD",
+                "1m",
+                time("2024-01-01 00:00"),
+                time("2024-01-01 00:24"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, 25);
+
+        // the rate limited request is retried
+        let requests = market.requests.lock().unwrap().clone();
+        assert_eq!(
+            requests,
+            vec![
+                (time("2024-01-01 00:00"), time("2024-01-01 00:09")),
+                (time("2024-01-01 00:10"), time("2024-01-01 00:19")),
+                (time("2024-01-01 00:20"), time("2024-01-01 00:24")),
+            ]
+        );
+
+        let path = store.path.to_str().unwrap();
+        let extracted = extract_candles_from_db(path, &table_name("BTC-USD", "1m")).unwrap();
+        assert_eq!(extracted.len(), 25);
+        assert_eq!(extracted[0].time, time("2024-01-01 00:00"));
+        assert_eq!(extracted[24].time, time("2024-01-01 00:24"));
+    }
+
+    #[tokio::test]
+    async fn test_run_resumes() {
+        let store = store("test_run_resumes");
+        let market = GeneratedMarket::new(false);
+        let backfill =
+            Backfill::new(&market, store.clone()).with_request_interval(Duration::from_millis(1));
+
+        backfill
+            .run(
+                "BTC-USD",
+                "1m",
+                time("2024-01-01 00:00"),
+                time("2024-01-01 00:14"),
+            )
+            .await
+            .unwrap();
+        market.requests.lock().unwrap().clear();
+
+        let written = backfill
+            .run(
+                "BTC-USD",
+                "1m",
+                time("2024-01-01 00:00"),
+                time("2024-01-01 00:19"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, 5);
+        assert_eq!(
+            market.requests.lock().unwrap().clone(),
+            vec![(time("2024-01-01 00:15"), time("2024-01-01 00:19"))]
+        );
+
+        // nothing is requested when the range is already stored
+        market.requests.lock().unwrap().clear();
+        let written = backfill
+            .run(
+                "BTC-USD",
+                "1m",
+                time("2024-01-01 00:00"),
+                time("2024-01-01 00:19"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, 0);
+        assert!(market.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_invalid_interval() {
+        let market = GeneratedMarket::new(false);
+        let backfill = Backfill::new(&market, store("test_run_invalid_interval"));
+        let result = backfill
+            .run(
+                "BTC-USD",
+                "1w",
+                time("2024-01-01 00:00"),
+                time("2024-01-01 00:19"),
+            )
+            .await;
+        assert!(matches!(result, Err(MarketError::CandlesUnavailable(_, _))));
+    }
+
+    #[tokio::test]
+    async fn test_run_gives_up() {
+        let market = GeneratedMarket::new(true);
+        let backfill = Backfill::new(&market, store("test_run_gives_up"))
+            .with_request_interval(Duration::from_millis(1))
+            .with_max_retries(0);
+        let result = backfill
+            .run(
+                "BTC-USD",
+                "1m",
+                time("2024-01-01 00:00"),
+                time("2024-01-01 00:19"),
+            )
+            .await;
+        assert!(matches!(result, Err(MarketError::ResponseError(429, _))));
+    }
+}
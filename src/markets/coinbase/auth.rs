@@ -0,0 +1,220 @@
+use crate::markets::MarketError;
+use hmac::{Hmac, Mac};
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::path::Path;
+
+/// Environment variable containing the API key
+pub const API_KEY_VAR: &str = "COINBASE_API_KEY";
+/// Environment variable containing the base64 encoded API secret
+pub const API_SECRET_VAR: &str = "COINBASE_API_SECRET";
+/// Environment variable containing the API passphrase
+pub const API_PASSPHRASE_VAR: &str = "COINBASE_API_PASSPHRASE";
+
+/// API credentials used to sign requests to private Coinbase Exchange endpoints
+///
+/// Credentials can be read from environment variables or from a TOML file:
+///
+/// ```toml
+/// api_key = "..."
+/// api_secret = "..."
+/// api_passphrase = "..."
+/// ```
+#[derive(Clone, Default, Deserialize)]
+pub struct CoinbaseCredentials {
+    pub api_key: String,
+
+    /// Base64 encoded secret
+    pub api_secret: String,
+    pub api_passphrase: String,
+}
+
+impl CoinbaseCredentials {
+    pub fn new<S: Into<String>>(api_key: S, api_secret: S, api_passphrase: S) -> Self {
+        Self {
+            api_key: api_key.into(),
+            api_secret: api_secret.into(),
+            api_passphrase: api_passphrase.into(),
+        }
+    }
+
+    /// Read credentials from the `COINBASE_API_KEY`, `COINBASE_API_SECRET` and
+    /// `COINBASE_API_PASSPHRASE` environment variables
+    pub fn from_env() -> Result<Self, MarketError> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Read credentials from variables with the same names as [`CoinbaseCredentials::from_env`]
+    ///
+    /// # Arguments
+    /// * `lookup` - Returns the value of the named variable, or `None` if it is not set
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, MarketError> {
+        let var = |name: &str| {
+            lookup(name)
+                .ok_or_else(|| MarketError::InvalidCredentials(format!("{} is not set", name)))
+        };
+        Ok(Self::new(
+            var(API_KEY_VAR)?,
+            var(API_SECRET_VAR)?,
+            var(API_PASSPHRASE_VAR)?,
+        ))
+    }
+
+    /// Read credentials from a TOML file
+    pub fn from_file(path: &Path) -> Result<Self, MarketError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| MarketError::InvalidCredentials(e.to_string()))?;
+        toml::from_str(&contents).map_err(|e| MarketError::InvalidCredentials(e.to_string()))
+    }
+
+    /// Sign a request
+    ///
+    /// The signature is the base64 encoded HMAC-SHA256 of `timestamp + method + request_path + body`,
+    /// keyed with the base64 decoded secret.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Seconds since the unix epoch. Must match the `cb-access-timestamp` header.
+    /// * `method` - Uppercase HTTP method (ie: "POST")
+    /// * `request_path` - Path of the endpoint including the query string (ie: "/orders")
+    /// * `body` - The request body, or an empty string for requests without a body
+    pub fn sign(
+        &self,
+        timestamp: &str,
+        method: &str,
+        request_path: &str,
+        body: &str,
+    ) -> Result<String, MarketError> {
+        let key = base64::decode(&self.api_secret)
+            .map_err(|e| MarketError::InvalidCredentials(format!("Invalid secret: {}", e)))?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+            .map_err(|e| MarketError::InvalidCredentials(e.to_string()))?;
+        mac.update(timestamp.as_bytes());
+        mac.update(method.as_bytes());
+        mac.update(request_path.as_bytes());
+        mac.update(body.as_bytes());
+        Ok(base64::encode(mac.finalize().into_bytes()))
+    }
+
+    /// Build the authentication headers for a request
+    pub fn headers(
+        &self,
+        timestamp: &str,
+        method: &str,
+        request_path: &str,
+        body: &str,
+    ) -> Result<HeaderMap, MarketError> {
+        let signature = self.sign(timestamp, method, request_path, body)?;
+        let header = |value: &str| {
+            value
+                .parse()
+                .map_err(|_| MarketError::InvalidCredentials("Invalid header value".to_string()))
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("cb-access-key", header(&self.api_key)?);
+        headers.insert("cb-access-sign", header(&signature)?);
+        headers.insert("cb-access-passphrase", header(&self.api_passphrase)?);
+        headers.insert("cb-access-timestamp", header(timestamp)?);
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// base64 of "secret-key-for-testing"
+    const SECRET: &str = "c2VjcmV0LWtleS1mb3ItdGVzdGluZw==";
+
+    fn credentials() -> CoinbaseCredentials {
+        CoinbaseCredentials::new("key", SECRET, "passphrase")
+    }
+
+    #[test]
+    fn test_sign_without_body() {
+        let signature = credentials()
+            .sign("1700000000", "GET", "/accounts", "")
+            .unwrap();
+        assert_eq!(signature, "dgcz1T++XRhc5CRApfuPN0WYUe4852/vI9k8pDoKDvE=");
+    }
+
+    #[test]
+    fn test_sign_with_body() {
+        let body =
+            r#"{"type":"limit","side":"buy","product_id":"BTC-USD","price":100.0,"size":1.0}"#;
+        let signature = credentials()
+            .sign("1700000000", "POST", "/orders", body)
+            .unwrap();
+        assert_eq!(signature, "+COP2vA5XOfQsV9Qna9+/V3hu47AN5PP7YJbFdVwjFI=");
+    }
+
+    /// RFC 4231 test case 2, split across the signed fields
+    #[test]
+    fn test_sign_rfc4231() {
+        let credentials = CoinbaseCredentials::new("key", "SmVmZQ==", "passphrase");
+        let signature = credentials
+            .sign("what do ya want ", "for ", "nothing?", "")
+            .unwrap();
+        assert_eq!(signature, "W9zBRr9gdU5qBCQmCJV1x1oAPwidJzmDnexYuWTsOEM=");
+    }
+
+    #[test]
+    fn test_sign_invalid_secret() {
+        let credentials = CoinbaseCredentials::new("key", "not base64!", "passphrase");
+        let result = credentials.sign("1700000000", "GET", "/accounts", "");
+        assert!(matches!(result, Err(MarketError::InvalidCredentials(_))));
+    }
+
+    #[test]
+    fn test_headers() {
+        let headers = credentials()
+            .headers("1700000000", "GET", "/accounts", "")
+            .unwrap();
+        assert_eq!(headers["cb-access-key"], "key");
+        assert_eq!(
+            headers["cb-access-sign"],
+            "dgcz1T++XRhc5CRApfuPN0WYUe4852/vI9k8pDoKDvE="
+        );
+        assert_eq!(headers["cb-access-passphrase"], "passphrase");
+        assert_eq!(headers["cb-access-timestamp"], "1700000000");
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = std::env::temp_dir().join("tradr_test_coinbase_credentials.toml");
+        std::fs::write(
+            &path,
+            format!(
+                "api_key = \"key\"\napi_secret = \"{}\"\napi_passphrase = \"passphrase\"\n",
+                SECRET
+            ),
+        )
+        .unwrap();
+
+        let credentials = CoinbaseCredentials::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(credentials.api_key, "key");
+        assert_eq!(credentials.api_secret, SECRET);
+        assert_eq!(credentials.api_passphrase, "passphrase");
+
+        let result = CoinbaseCredentials::from_file(&path);
+        assert!(matches!(result, Err(MarketError::InvalidCredentials(_))));
+    }
+
+    #[test]
+    fn test_from_lookup() {
+        use std::collections::HashMap;
+
+        let mut vars = HashMap::from([(API_KEY_VAR, "key"), (API_SECRET_VAR, SECRET)]);
+        let result = CoinbaseCredentials::from_lookup(|name| vars.get(name).map(|v| v.to_string()));
+        assert!(matches!(result, Err(MarketError::InvalidCredentials(_))));
+
+        vars.insert(API_PASSPHRASE_VAR, "passphrase");
+        let credentials =
+            CoinbaseCredentials::from_lookup(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(credentials.api_key, "key");
+        assert_eq!(credentials.api_secret, SECRET);
+        assert_eq!(credentials.api_passphrase, "passphrase");
+    }
+}
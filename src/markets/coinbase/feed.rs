@@ -0,0 +1,378 @@
+use crate::markets::feed::{CandleAggregator, FeedEvent, Ticker};
+use crate::markets::manager::VALID_INTERVALS;
+use crate::markets::MarketError;
+use crate::types::Candle;
+use chrono::{NaiveDateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+const FEED_URL: &str = "wss://ws-feed.exchange.coinbase.com";
+
+/// Format of timestamps sent by the websocket feed
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.fZ";
+
+/// Messages received from the Coinbase websocket feed
+///
+/// Only the fields which are used are deserialized.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CoinbaseFeedMessage {
+    /// A trade between two orders
+    Match(CoinbaseMatch),
+    /// The most recent trade, sent once after subscribing to the matches channel
+    LastMatch(CoinbaseMatch),
+    Ticker(CoinbaseTicker),
+    Error {
+        message: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    /// Subscription confirmations and heartbeats
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct CoinbaseMatch {
+    product_id: String,
+    price: Decimal,
+    size: Decimal,
+    time: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CoinbaseTicker {
+    product_id: String,
+    price: Decimal,
+    #[serde(default)]
+    best_bid: Option<Decimal>,
+    #[serde(default)]
+    best_ask: Option<Decimal>,
+
+    /// Not sent with the first ticker message after subscribing
+    #[serde(default)]
+    time: Option<String>,
+}
+
+fn parse_time(time: &str) -> Result<NaiveDateTime, MarketError> {
+    NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+        .map_err(|e| MarketError::FeedError(format!("Invalid time '{}': {}", time, e)))
+}
+
+/// Streams real-time market data from the Coinbase Exchange websocket feed
+///
+/// Subscribes to the `ticker` and `matches` channels. Matches are aggregated into candles for each
+/// interval, and closed candles are sent as [`FeedEvent::Candle`]. Ticker messages are sent as
+/// [`FeedEvent::Ticker`] and also close any candles which have ended, so that candles are emitted
+/// during periods without trades.
+pub struct CoinbaseFeed {
+    url: String,
+    product_ids: Vec<String>,
+    intervals: Vec<String>,
+}
+
+impl CoinbaseFeed {
+    /// Create a feed for the given products using all intervals in [`VALID_INTERVALS`]
+    pub fn new(product_ids: &[&str]) -> Self {
+        Self {
+            url: FEED_URL.to_string(),
+            product_ids: product_ids.iter().map(|id| id.to_string()).collect(),
+            intervals: VALID_INTERVALS.iter().map(|i| i.to_string()).collect(),
+        }
+    }
+
+    /// Only build candles for the given intervals
+    pub fn with_intervals(mut self, intervals: &[&str]) -> Self {
+        self.intervals = intervals.iter().map(|i| i.to_string()).collect();
+        self
+    }
+
+    fn subscribe_message(&self) -> String {
+        serde_json::json!({
+            "type": "subscribe",
+            "product_ids": self.product_ids,
+            "channels": ["ticker", "matches"],
+        })
+        .to_string()
+    }
+
+    /// Connect to the feed and send events until the connection is closed
+    ///
+    /// # Arguments
+    /// * `sender` - Channel to send events to. The feed stops when the receiver is dropped.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the server closed the connection or the receiver was dropped
+    /// * `Err(MarketError::FeedError)` - If the connection failed or the server sent an error
+    pub async fn run(self, sender: Sender<FeedEvent>) -> Result<(), MarketError> {
+        let (mut stream, _) = connect_async(self.url.as_str())
+            .await
+            .map_err(|e| MarketError::FeedError(e.to_string()))?;
+        info!("Connected to {}", self.url);
+
+        stream
+            .send(Message::Text(self.subscribe_message()))
+            .await
+            .map_err(|e| MarketError::FeedError(e.to_string()))?;
+
+        let intervals = self
+            .intervals
+            .iter()
+            .map(|i| i.as_str())
+            .collect::<Vec<_>>();
+        let mut aggregator = CandleAggregator::new(&intervals);
+
+        while let Some(message) = stream.next().await {
+            let text = match message.map_err(|e| MarketError::FeedError(e.to_string()))? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+
+            let message = match serde_json::from_str::<CoinbaseFeedMessage>(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("Could not parse feed message: {}", e);
+                    continue;
+                }
+            };
+
+            let events = match message {
+                CoinbaseFeedMessage::Match(trade) | CoinbaseFeedMessage::LastMatch(trade) => {
+                    let time = parse_time(&trade.time)?;
+                    let closed =
+                        aggregator.add_trade(&trade.product_id, trade.price, trade.size, time);
+                    candle_events(&trade.product_id, closed)
+                }
+                CoinbaseFeedMessage::Ticker(ticker) => {
+                    let time = match ticker.time {
+                        Some(time) => parse_time(&time)?,
+                        None => Utc::now().naive_utc(),
+                    };
+                    let closed = aggregator.close(&ticker.product_id, time);
+                    let mut events = vec![FeedEvent::Ticker(Ticker {
+                        product_id: ticker.product_id.clone(),
+                        price: ticker.price,
+                        best_bid: ticker.best_bid,
+                        best_ask: ticker.best_ask,
+                        time,
+                    })];
+                    events.extend(candle_events(&ticker.product_id, closed));
+                    events
+                }
+                CoinbaseFeedMessage::Error { message, reason } => {
+                    return Err(MarketError::FeedError(match reason {
+                        Some(reason) => format!("{}: {}", message, reason),
+                        None => message,
+                    }))
+                }
+                CoinbaseFeedMessage::Other => {
+                    debug!("Ignoring feed message: {}", text);
+                    continue;
+                }
+            };
+
+            for event in events {
+                if sender.send(event).await.is_err() {
+                    info!("Feed receiver was dropped");
+                    return Ok(());
+                }
+            }
+        }
+
+        info!("Feed connection closed");
+        Ok(())
+    }
+}
+
+fn candle_events(product_id: &str, closed: Vec<(String, Candle)>) -> Vec<FeedEvent> {
+    closed
+        .into_iter()
+        .map(|(interval, candle)| FeedEvent::Candle {
+            product_id: product_id.to_string(),
+            interval,
+            candle,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Start a local websocket server which waits for a subscribe message, sends `messages` and
+    /// closes the connection
+    ///
+    /// # Returns
+    /// The url of the server and a handle resolving to the subscribe message
+    async fn stand_in_server(messages: Vec<String>) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = tokio_tungstenite::accept_async(socket).await.unwrap();
+
+            let subscribe = match stream.next().await.unwrap().unwrap() {
+                Message::Text(text) => text,
+                message => panic!("Unexpected message: {:?}", message),
+            };
+            for message in messages {
+                stream.send(Message::Text(message)).await.unwrap();
+            }
+            stream.close(None).await.unwrap();
+            subscribe
+        });
+
+        (url, handle)
+    }
+
+    fn match_message(price: &str, size: &str, time: &str) -> String {
+        format!(
+            r#"{{"type":"match","trade_id":1,"sequence":1,"maker_order_id":"a","taker_order_id":"b","time":"{}","product_id":"BTC-USD","size":"{}","price":"{}","side":"buy"}}"#,
+            time, size, price
+        )
+    }
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let message: CoinbaseFeedMessage = serde_json::from_str(&match_message(
+            "100.5",
+            "0.25",
+            "2024-01-01T00:00:05.123456Z",
+        ))
+        .unwrap();
+        match message {
+            CoinbaseFeedMessage::Match(trade) => {
+                assert_eq!(trade.price, dec!(100.5));
+                assert_eq!(trade.size, dec!(0.25));
+                assert_eq!(
+                    parse_time(&trade.time).unwrap(),
+                    NaiveDateTime::parse_from_str(
+                        "2024-01-01 00:00:05.123456",
+                        "%Y-%m-%d %H:%M:%S%.f"
+                    )
+                    .unwrap()
+                );
+            }
+            _ => panic!("Expected a match message"),
+        }
+
+        let message: CoinbaseFeedMessage =
+            serde_json::from_str(r#"{"type":"heartbeat","sequence":1}"#).unwrap();
+        assert!(matches!(message, CoinbaseFeedMessage::Other));
+
+        let message: CoinbaseFeedMessage =
+            serde_json::from_str(r#"{"type":"ticker","product_id":"BTC-USD","price":"10"}"#)
+                .unwrap();
+        assert!(matches!(message, CoinbaseFeedMessage::Ticker(_)));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let messages = vec![
+            r#"{"type":"subscriptions","channels":[]}"#.to_string(),
+            match_message("100", "1", "2024-01-01T00:00:05.000000Z"),
+            match_message("110", "2", "2024-01-01T00:00:30.000000Z"),
+            match_message("90", "1", "2024-01-01T00:00:59.000000Z"),
+            match_message("95", "1", "2024-01-01T00:01:10.000000Z"),
+            r#"{"type":"ticker","sequence":2,"product_id":"BTC-USD","price":"96","best_bid":"95.5","best_ask":"96","time":"2024-01-01T00:02:00.000000Z"}"#.to_string(),
+        ];
+        let (url, server) = stand_in_server(messages).await;
+
+        let (sender, mut receiver) = mpsc::channel(16);
+        let feed = CoinbaseFeed {
+            url,
+            ..CoinbaseFeed::new(&["BTC-USD"])
+        }
+        .with_intervals(&["1m"]);
+        feed.run(sender).await.unwrap();
+
+        let subscribe: serde_json::Value = serde_json::from_str(&server.await.unwrap()).unwrap();
+        assert_eq!(subscribe["type"], "subscribe");
+        assert_eq!(subscribe["product_ids"][0], "BTC-USD");
+        assert_eq!(subscribe["channels"][1], "matches");
+
+        let mut events = vec![];
+        while let Some(event) = receiver.recv().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 3);
+
+        // the first candle is closed by the trade in the next minute
+        match &events[0] {
+            FeedEvent::Candle {
+                product_id,
+                interval,
+                candle,
+            } => {
+                assert_eq!(product_id, "BTC-USD");
+                assert_eq!(interval, "1m");
+                assert_eq!(candle.time, time("2024-01-01 00:00:00"));
+                assert_eq!(candle.open, dec!(100));
+                assert_eq!(candle.high, dec!(110));
+                assert_eq!(candle.low, dec!(90));
+                assert_eq!(candle.close, dec!(90));
+                assert_eq!(candle.volume, dec!(4));
+            }
+            event => panic!("Expected a candle, got {:?}", event),
+        }
+
+        // the ticker closes the second candle
+        match &events[1] {
+            FeedEvent::Ticker(ticker) => {
+                assert_eq!(ticker.price, dec!(96));
+                assert_eq!(ticker.best_bid, Some(dec!(95.5)));
+                assert_eq!(ticker.time, time("2024-01-01 00:02:00"));
+            }
+            event => panic!("Expected a ticker, got {:?}", event),
+        }
+        match &events[2] {
+            FeedEvent::Candle { candle, .. } => {
+                assert_eq!(candle.time, time("2024-01-01 00:01:00"));
+                assert_eq!(candle.close, dec!(95));
+            }
+            event => panic!("Expected a candle, got {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_error() {
+        let messages =
+            vec![r#"{"type":"error","message":"Failed to subscribe","reason":"BAD-USD is not a valid product"}"#.to_string()];
+        let (url, _server) = stand_in_server(messages).await;
+
+        let (sender, _receiver) = mpsc::channel(16);
+        let result = CoinbaseFeed {
+            url,
+            ..CoinbaseFeed::new(&["BAD-USD"])
+        }
+        .run(sender)
+        .await;
+        assert!(matches!(result, Err(MarketError::FeedError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_run_connection_failed() {
+        let (sender, _receiver) = mpsc::channel(16);
+        let result = CoinbaseFeed {
+            url: "ws://127.0.0.1:1".to_string(),
+            ..CoinbaseFeed::new(&["BTC-USD"])
+        }
+        .run(sender)
+        .await;
+        assert!(matches!(result, Err(MarketError::FeedError(_))));
+    }
+}
@@ -1,15 +1,38 @@
+mod auth;
+mod feed;
 mod order;
 
+pub use auth::CoinbaseCredentials;
+pub use feed::CoinbaseFeed;
+
+use crate::markets::backfill::HistoricalMarket;
 use crate::markets::coinbase::order::{CoinbaseOrderRequest, CoinbaseOrderResponse};
 use crate::markets::BaseMarket;
-use crate::markets::{FeeCalculator, Market, SimplePercentageFee};
-use crate::types::{Candle, ExecutedTrade, FutureTrade};
+use crate::markets::{FeeCalculator, MakerTakerFee, Market, MarketError, OrderTracker};
+use crate::types::{Candle, ExecutedTrade, FutureTrade, Order, ReasonCode};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::{Method, StatusCode};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 const BASE_URL: &str = "https://api.exchange.coinbase.com";
 
+/// Maker fee percentage for the lowest Coinbase Exchange volume tier
+const DEFAULT_MAKER_FEE: Decimal = dec!(0.4);
+
+/// Taker fee percentage for the lowest Coinbase Exchange volume tier
+const DEFAULT_TAKER_FEE: Decimal = dec!(0.6);
+
+/// Maximum number of candles returned by the candles endpoint
+const MAX_CANDLES_PER_REQUEST: usize = 300;
+
+/// Format of the `start` and `end` parameters of the candles endpoint
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
 const VALID_INTERVALS: [[&str; 2]; 6] = [
     ["1m", "60"],
     ["5m", "300"],
@@ -40,13 +63,42 @@ pub struct TradingPairInfo {
     pub status_message: Option<String>,
 }
 
+/// A candle as returned by the Coinbase candles endpoint
+///
+/// Candles are arrays of `[time, low, high, open, close, volume]`, where time is in seconds.
+#[derive(Deserialize, Debug)]
+struct CoinbaseCandle(i64, Decimal, Decimal, Decimal, Decimal, Decimal);
+
+impl From<CoinbaseCandle> for Candle {
+    fn from(candle: CoinbaseCandle) -> Self {
+        let CoinbaseCandle(time, low, high, open, close, volume) = candle;
+        Candle {
+            time: DateTime::from_timestamp(time, 0).unwrap().naive_utc(),
+            open,
+            high,
+            low,
+            close,
+            volume,
+        }
+    }
+}
+
+/// Granularity in seconds for an interval label (ie: "1m" is "60")
+fn granularity(pair: &str, interval: &str) -> Result<&'static str, MarketError> {
+    VALID_INTERVALS
+        .iter()
+        .find(|x| x[0] == interval)
+        .map(|x| x[1])
+        .ok_or_else(|| MarketError::CandlesUnavailable(pair.to_string(), interval.to_string()))
+}
+
 #[derive(Clone)]
 pub struct CoinbaseClient {
-    api_key: String,
-    api_secret: String,
-    api_passphrase: String,
+    credentials: CoinbaseCredentials,
 
     client: reqwest::Client,
+    fee_calculator: MakerTakerFee,
+    order_tracker: OrderTracker,
 
     enable_trades: bool,
 }
@@ -62,18 +114,80 @@ impl CoinbaseClient {
             .build()
             .unwrap();
         Self {
-            api_key: "".to_string(),
-            api_secret: "".to_string(),
-            api_passphrase: "".to_string(),
+            credentials: CoinbaseCredentials::default(),
             client,
+            fee_calculator: MakerTakerFee::new(DEFAULT_MAKER_FEE, DEFAULT_TAKER_FEE),
+            order_tracker: OrderTracker::default(),
             enable_trades: true,
         }
     }
 
+    /// Builder method for the credentials used to sign private requests
+    pub fn with_credentials(mut self, credentials: CoinbaseCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Create a client with credentials read from environment variables
+    ///
+    /// See [`CoinbaseCredentials::from_env`]
+    pub fn from_env() -> Result<Self, MarketError> {
+        Ok(Self::new().with_credentials(CoinbaseCredentials::from_env()?))
+    }
+
+    /// Create a client with credentials read from a TOML file
+    ///
+    /// See [`CoinbaseCredentials::from_file`]
+    pub fn from_credentials_file(path: &Path) -> Result<Self, MarketError> {
+        Ok(Self::new().with_credentials(CoinbaseCredentials::from_file(path)?))
+    }
+
+    /// The maker/taker fees charged by Coinbase
+    pub fn fee_calculator(&self) -> MakerTakerFee {
+        self.fee_calculator.clone()
+    }
+
     pub fn disable_trades(mut self) -> Self {
         self.enable_trades = false;
         self
     }
+
+    /// Send a signed request to a private endpoint and parse the response
+    ///
+    /// # Arguments
+    /// * `method` - The HTTP method
+    /// * `request_path` - Path of the endpoint including the query string (ie: "/orders")
+    /// * `body` - The request body, or an empty string for requests without a body
+    async fn send_signed<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        request_path: &str,
+        body: String,
+    ) -> Result<T, MarketError> {
+        let url = format!("{}{}", BASE_URL, request_path);
+        let timestamp = Utc::now().timestamp().to_string();
+        let headers = self
+            .credentials
+            .headers(&timestamp, method.as_str(), request_path, &body)?;
+
+        let response = self
+            .client
+            .request(method, &url)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Err(MarketError::OrderNotFound(request_path.to_string()));
+        }
+        if !status.is_success() {
+            let message = response.text().await?;
+            return Err(MarketError::ResponseError(status.as_u16(), message));
+        }
+        Ok(response.json::<T>().await?)
+    }
 }
 
 #[async_trait]
@@ -82,13 +196,13 @@ impl BaseMarket for CoinbaseClient {
         "Coinbase"
     }
 
-    async fn get_candles(&self, pair: &str, interval: &str) -> Result<Vec<Candle>, reqwest::Error> {
-        assert!(VALID_INTERVALS.iter().any(|x| x[0] == interval));
-
+    async fn get_candles(&self, pair: &str, interval: &str) -> Result<Vec<Candle>, MarketError> {
         // build url
         let url = format!(
             "{}/products/{}/candles?granularity={}",
-            BASE_URL, pair, interval
+            BASE_URL,
+            pair,
+            granularity(pair, interval)?
         );
 
         // send request and parse response
@@ -97,68 +211,86 @@ impl BaseMarket for CoinbaseClient {
             .get(&url)
             .send()
             .await?
-            .json::<Vec<Candle>>()
+            .json::<Vec<CoinbaseCandle>>()
             .await?;
-        Ok(response)
+        Ok(response.into_iter().map(Candle::from).collect())
     }
 
     /// Submits an order to the exchange and returns the executed trade.
     ///
-    /// This method will only submit FOK orders. Therefore, if the order cannot be filled immediately,
-    /// it will be cancelled.
+    /// The order type is taken from the trade. Limit orders are submitted as FOK orders. Therefore, if
+    /// the order cannot be filled immediately, it will be cancelled. The order is then tracked until it
+    /// is done, and orders which are still open after the tracker timeout are cancelled.
     ///
     /// # Arguments
     /// * `order` - A proposed order to submit to the exchange.
     ///
     /// # Returns
-    /// * `ExecutedTrade` - The executed trade returned by the exchange.
-    /// * `reqwest::Error` - If there was an error parsing the order
+    /// * `ExecutedTrade` - The filled quantity of the order.
+    /// * `MarketError` - If there was an error submitting the order or nothing was filled. Stop orders
+    ///   which have not been triggered return `MarketError::OrderResting` with the order id.
     async fn submit_order(
         &self,
         order: FutureTrade,
         product_id: String,
-    ) -> Result<ExecutedTrade, reqwest::Error> {
+    ) -> Result<ExecutedTrade, MarketError> {
         if !self.enable_trades {
             let trade = ExecutedTrade::from_future_trade("mock".to_string(), order);
             return Ok(trade);
         }
-        let request = CoinbaseOrderRequest::with_future_trade(order, product_id);
-
-        let url = format!("{}/orders", BASE_URL);
+        let request = CoinbaseOrderRequest::with_future_trade(order.clone(), product_id);
+
+        let body = serde_json::to_string(&request).expect("Order request should serialize");
+        let response: CoinbaseOrderResponse =
+            self.send_signed(Method::POST, "/orders", body).await?;
+
+        self.order_tracker
+            .track(self, &response.id, order)
+            .await
+            .map_err(|failed| match failed.get_reason() {
+                ReasonCode::Resting => MarketError::OrderResting(response.id.clone()),
+                reason => MarketError::OrderRejected(
+                    reason,
+                    format!("Order {} was not filled", response.id),
+                ),
+            })
+    }
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("cb-access-key", self.api_key.parse().unwrap());
-        headers.insert(
-            "cb-access-sign",
-            base64::encode(self.api_secret.as_bytes()).parse().unwrap(),
-        );
-        headers.insert("cb-access-passphrase", self.api_passphrase.parse().unwrap());
-        headers.insert("cb-access-timestamp", Utc::now().timestamp().into());
+    async fn get_order(&self, order_id: &str) -> Result<Order, MarketError> {
+        let request_path = format!("/orders/{}", order_id);
+        let response: CoinbaseOrderResponse = self
+            .send_signed(Method::GET, &request_path, String::new())
+            .await?;
+        Order::try_from(response)
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .headers(headers)
-            .send()
-            .await?
-            .json::<CoinbaseOrderResponse>()
+    async fn cancel_order(&self, order_id: &str) -> Result<(), MarketError> {
+        let request_path = format!("/orders/{}", order_id);
+        let _: String = self
+            .send_signed(Method::DELETE, &request_path, String::new())
             .await?;
+        Ok(())
+    }
 
-        Ok(response.into())
+    async fn list_open_orders(&self, product_id: &str) -> Result<Vec<Order>, MarketError> {
+        let request_path = format!("/orders?status=open&product_id={}", product_id);
+        let response: Vec<CoinbaseOrderResponse> = self
+            .send_signed(Method::GET, &request_path, String::new())
+            .await?;
+        response.into_iter().map(Order::try_from).collect()
     }
 }
 
 #[async_trait]
 impl Market for CoinbaseClient {
     type PairType = TradingPairInfo;
-    type FeeCalculator = SimplePercentageFee;
+    type FeeCalculator = MakerTakerFee;
 
     async fn get_fee_calculator(&self) -> Option<&dyn FeeCalculator> {
-        todo!()
+        Some(&self.fee_calculator)
     }
 
-    async fn get_trading_pair_info(&self) -> Result<Vec<Self::PairType>, reqwest::Error> {
+    async fn get_trading_pair_info(&self) -> Result<Vec<Self::PairType>, MarketError> {
         let url = format!("{}/products/", BASE_URL);
 
         let response = self
@@ -172,17 +304,103 @@ impl Market for CoinbaseClient {
     }
 }
 
+#[async_trait]
+impl HistoricalMarket for CoinbaseClient {
+    fn max_candles_per_request(&self) -> usize {
+        MAX_CANDLES_PER_REQUEST
+    }
+
+    async fn get_candles_between(
+        &self,
+        pair: &str,
+        interval: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<Candle>, MarketError> {
+        let url = format!(
+            "{}/products/{}/candles?granularity={}&start={}&end={}",
+            BASE_URL,
+            pair,
+            granularity(pair, interval)?,
+            start.format(TIME_FORMAT),
+            end.format(TIME_FORMAT)
+        );
+
+        let response = self.client.get(&url).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await?;
+            return Err(MarketError::ResponseError(status.as_u16(), message));
+        }
+
+        // candles are returned in descending order
+        let mut candles = response
+            .json::<Vec<CoinbaseCandle>>()
+            .await?
+            .into_iter()
+            .map(Candle::from)
+            .collect::<Vec<_>>();
+        candles.sort_by_key(|candle| candle.time);
+        Ok(candles)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::markets::{FeeContext, Liquidity};
     use crate::types::Side;
-    use rust_decimal_macros::dec;
 
     #[test]
     fn test_new() {
         let client = CoinbaseClient::new();
-        assert_eq!(client.api_key, "".to_string());
-        assert_eq!(client.api_secret, "".to_string());
+        assert_eq!(client.credentials.api_key, "".to_string());
+        assert_eq!(client.credentials.api_secret, "".to_string());
+    }
+
+    #[test]
+    fn test_granularity() {
+        assert_eq!(granularity("BTC-USD", "1m").unwrap(), "60");
+        assert_eq!(granularity("BTC-USD", "6h").unwrap(), "21600");
+        assert!(matches!(
+            granularity("BTC-USD", "2m"),
+            Err(MarketError::CandlesUnavailable(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_coinbase_candle() {
+        let candles: Vec<CoinbaseCandle> =
+            serde_json::from_str("[[1704067200, 95.5, 110.25, 100, 105, 12.5]]").unwrap();
+        let candle = Candle::from(candles.into_iter().next().unwrap());
+        assert_eq!(
+            candle.time,
+            DateTime::from_timestamp(1704067200, 0).unwrap().naive_utc()
+        );
+        assert_eq!(candle.low, dec!(95.5));
+        assert_eq!(candle.high, dec!(110.25));
+        assert_eq!(candle.open, dec!(100));
+        assert_eq!(candle.close, dec!(105));
+        assert_eq!(candle.volume, dec!(12.5));
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_calculator() {
+        let client = CoinbaseClient::new();
+        let fee_calculator = client.get_fee_calculator().await.unwrap();
+        assert_eq!(
+            fee_calculator.cost_including_fee(dec!(100.0), &FeeContext::taker(Side::Buy)),
+            dec!(100.6)
+        );
+
+        let context = FeeContext {
+            liquidity: Liquidity::Maker,
+            ..FeeContext::taker(Side::Buy)
+        };
+        assert_eq!(
+            fee_calculator.cost_including_fee(dec!(100.0), &context),
+            dec!(100.4)
+        );
     }
 
     #[tokio::test]
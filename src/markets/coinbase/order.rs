@@ -1,10 +1,11 @@
 use chrono::NaiveDateTime;
-use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::types::{ExecutedTrade, FutureTrade, Side, Trade};
+use crate::markets::MarketError;
+use crate::types::{ExecutedTrade, FutureTrade, Order, OrderStatus, OrderType, Side, Trade};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum CoinbaseMarketOrderType {
@@ -54,6 +55,8 @@ impl<'de> Deserialize<'de> for CoinbaseMarketOrderType {
 /// - Market: A market order provides no pricing guarantees and are subject to the market. They just provide an easier way
 ///     to buy or sell cryptocurrency at the current market price. Market orders execute immediately and not part of the market
 ///     order book, and are therefore always considered "takers". When placing a market order you can specify funds and/or size.
+///
+/// - Stop: A limit order which is only placed on the book once the last trade price reaches `stop_price`.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CoinbaseOrderRequest {
     pub profile_id: Option<String>,
@@ -95,6 +98,12 @@ pub struct CoinbaseOrderRequest {
 
     /// Use this to show how much to show
     pub max_floor: Option<String>,
+
+    /// Possible values 'loss' or 'entry'. Required for stop orders.
+    pub stop: Option<String>,
+
+    /// Price at which a stop order is triggered
+    pub stop_price: Option<f64>,
 }
 
 impl CoinbaseOrderRequest {
@@ -112,16 +121,48 @@ impl CoinbaseOrderRequest {
             post_only: None,
             client_oid: None,
             max_floor: None,
+            stop: None,
+            stop_price: None,
         }
     }
 
+    /// Create a market order for the given quantity
+    pub fn new_market_order(side: Side, product_id: String, size: f64) -> Self {
+        Self {
+            r#type: CoinbaseMarketOrderType::Market,
+            price: None,
+            time_in_force: None,
+            ..Self::new_limit_order(side, product_id, 0.0, size)
+        }
+    }
+
+    /// Create a stop order which places a limit order at `price` once the price reaches `price`
+    ///
+    /// Sells are stop-loss orders and buys are stop-entry orders.
+    pub fn new_stop_order(side: Side, product_id: String, price: f64, size: f64) -> Self {
+        let stop = match side {
+            Side::Buy => "entry",
+            Side::Sell => "loss",
+        };
+        Self {
+            r#type: CoinbaseMarketOrderType::Stop,
+            time_in_force: Some("GTC".to_string()),
+            stop: Some(stop.to_string()),
+            stop_price: Some(price),
+            ..Self::new_limit_order(side, product_id, price, size)
+        }
+    }
+
+    /// Create an order from a trade using the order type of the trade
     pub fn with_future_trade(trade: FutureTrade, product_id: String) -> Self {
-        Self::new_limit_order(
-            trade.get_side(),
-            product_id,
-            trade.get_price().to_f64().unwrap(),
-            trade.get_quantity().to_f64().unwrap(),
-        )
+        let side = trade.get_side();
+        let price = trade.get_price().to_f64().unwrap();
+        let size = trade.get_quantity().to_f64().unwrap();
+        match trade.get_order_type() {
+            OrderType::Limit => Self::new_limit_order(side, product_id, price, size),
+            OrderType::Market => Self::new_market_order(side, product_id, size),
+            OrderType::Stop => Self::new_stop_order(side, product_id, price, size),
+        }
     }
 
     pub fn set_client_oid(mut self, client_oid: String) -> Self {
@@ -131,16 +172,20 @@ impl CoinbaseOrderRequest {
 }
 
 /// Coinbase order response.
+///
+/// Coinbase encodes amounts as strings, such as `"0.01000000"`, so they are deserialized as decimals from strings.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CoinbaseOrderResponse {
     /// Order id.
     pub id: String,
 
-    /// Price per unit of base currency.
-    pub price: f64,
+    /// Price per unit of base currency. Not provided for market orders.
+    #[serde(default, with = "rust_decimal::serde::str")]
+    pub price: Decimal,
 
     /// Amount of base currency to buy or sell.
-    pub size: f64,
+    #[serde(default, with = "rust_decimal::serde::str")]
+    pub size: Decimal,
 
     /// Book the order belongs to.
     pub product_id: String,
@@ -152,10 +197,12 @@ pub struct CoinbaseOrderResponse {
     pub side: Side,
 
     /// amount of quote currency to spend (for market orders)
-    pub funds: Option<f64>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub funds: Option<Decimal>,
 
     /// funds with fees
-    pub specified_funds: Option<f64>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub specified_funds: Option<Decimal>,
 
     /// Possible values are `limit`, `market`, or `stop`.
     pub r#type: CoinbaseMarketOrderType,
@@ -182,12 +229,15 @@ pub struct CoinbaseOrderResponse {
     pub reject_reason: Option<String>,
 
     /// fees paid by current order amount
-    pub fill_fees: f64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub fill_fees: Decimal,
 
     /// amount (in base currency) of current order filled
-    pub filled_size: f64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub filled_size: Decimal,
 
-    pub executed_value: f64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub executed_value: Decimal,
 
     /// Possible values: [open, pending, rejected, done, active, received, all]
     pub status: String,
@@ -220,14 +270,53 @@ impl Into<ExecutedTrade> for CoinbaseOrderResponse {
         ExecutedTrade::new(
             self.id.to_string(),
             self.side,
-            Decimal::from_f64(self.price).unwrap(),
-            Decimal::from_f64(self.size).unwrap(),
-            Decimal::from_f64(self.filled_size).unwrap(),
+            self.price,
+            self.size,
+            self.filled_size,
             point,
         )
     }
 }
 
+impl TryFrom<CoinbaseOrderResponse> for Order {
+    type Error = MarketError;
+
+    fn try_from(response: CoinbaseOrderResponse) -> Result<Self, Self::Error> {
+        let created_at =
+            NaiveDateTime::parse_from_str(&response.created_at, "%Y-%m-%dT%H:%M:%S%.fZ").map_err(
+                |e| {
+                    MarketError::ParseError(format!(
+                        "Invalid created_at '{}': {}",
+                        response.created_at, e
+                    ))
+                },
+            )?;
+        let order_type = match (&response.r#type, &response.stop) {
+            (CoinbaseMarketOrderType::Market, _) => OrderType::Market,
+            (CoinbaseMarketOrderType::Stop, _) | (_, Some(_)) => OrderType::Stop,
+            (CoinbaseMarketOrderType::Limit, None) => OrderType::Limit,
+        };
+        let status = match response.status.as_str() {
+            "done" => OrderStatus::Done,
+            "rejected" => OrderStatus::Rejected,
+            _ => OrderStatus::Open,
+        };
+        Ok(Order {
+            id: response.id,
+            side: response.side,
+            order_type,
+            price: response.price,
+            quantity: response.size,
+            filled_quantity: response.filled_size,
+            executed_value: response.executed_value,
+            status,
+            done_reason: response.done_reason,
+            reject_reason: response.reject_reason,
+            created_at,
+        })
+    }
+}
+
 #[cfg(test)]
 mod order_type_tests {
     /// Tests that the `CoinbaseMarketOrderType` enum can be serialized and deserialized correctly.
@@ -337,22 +426,55 @@ mod order_request_tests {
         let order = order.set_client_oid("test".to_string());
         assert_eq!(order.client_oid, Some("test".to_string()));
     }
+
+    #[test]
+    fn test_with_future_trade_order_types() {
+        use super::{CoinbaseMarketOrderType, OrderType};
+
+        let trade = FutureTrade::new(
+            super::Side::Buy,
+            dec!(100.0),
+            dec!(1.0),
+            chrono::Utc::now().naive_utc(),
+        );
+
+        let order = CoinbaseOrderRequest::with_future_trade(
+            trade.clone().with_order_type(OrderType::Market),
+            "BTC-USD".to_string(),
+        );
+        assert_eq!(order.r#type, CoinbaseMarketOrderType::Market);
+        assert_eq!(order.price, None);
+        assert_eq!(order.size, Some(1.0));
+        assert_eq!(order.time_in_force, None);
+
+        let order = CoinbaseOrderRequest::with_future_trade(
+            trade.with_order_type(OrderType::Stop),
+            "BTC-USD".to_string(),
+        );
+        assert_eq!(order.r#type, CoinbaseMarketOrderType::Stop);
+        assert_eq!(order.price, Some(100.0));
+        assert_eq!(order.stop, Some("entry".to_string()));
+        assert_eq!(order.stop_price, Some(100.0));
+        assert_eq!(order.time_in_force, Some("GTC".to_string()));
+    }
 }
 
 #[cfg(test)]
 mod order_response_tests {
-    use super::{CoinbaseMarketOrderType, CoinbaseOrderResponse};
+    use super::{
+        CoinbaseMarketOrderType, CoinbaseOrderResponse, MarketError, Order, OrderStatus, OrderType,
+    };
     use crate::types::Side;
     use crate::types::{ExecutedTrade, Trade};
     use chrono::NaiveDateTime;
-    use rust_decimal::prelude::ToPrimitive;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_order_response_into_executed_trade() {
         let order = CoinbaseOrderResponse {
             id: "uuid".to_string(),
-            price: 100.0,
-            size: 1.0,
+            price: dec!(100),
+            size: dec!(1),
             product_id: "BTC-USD".to_string(),
             profile_id: None,
             side: Side::Buy,
@@ -366,9 +488,9 @@ mod order_response_tests {
             done_at: None,
             done_reason: None,
             reject_reason: None,
-            fill_fees: 0.0,
-            filled_size: 0.0,
-            executed_value: 0.0,
+            fill_fees: dec!(0),
+            filled_size: dec!(0),
+            executed_value: dec!(0),
             status: "open".to_string(),
             settled: false,
             stop: None,
@@ -381,11 +503,85 @@ mod order_response_tests {
         let trade: ExecutedTrade = order.clone().into();
         assert_eq!(trade.get_order_id(), &order.id.to_string());
         assert_eq!(trade.get_side(), order.side);
-        assert_eq!(trade.get_price().to_f64().unwrap(), order.price);
-        assert_eq!(trade.get_quantity().to_f64().unwrap(), order.size);
+        assert_eq!(trade.get_price(), order.price);
+        assert_eq!(trade.get_quantity(), order.size);
         assert_eq!(
             *trade.get_timestamp(),
             NaiveDateTime::parse_from_str(&order.created_at, "%Y-%m-%dT%H:%M:%S%.fZ").unwrap()
         );
     }
+
+    /// Response to a filled market buy, as returned by the Coinbase Exchange API
+    const FILLED_MARKET_ORDER: &str = r#"{
+        "id": "68e6a28f-ae28-4788-8d4f-5ab4e5e5ae08",
+        "size": "1.00000000",
+        "product_id": "BTC-USD",
+        "side": "buy",
+        "stp": "dc",
+        "funds": "9.9750623400000000",
+        "specified_funds": "10.0000000000000000",
+        "type": "market",
+        "post_only": false,
+        "created_at": "2016-12-08T20:09:05.508883Z",
+        "done_at": "2016-12-08T20:09:05.527Z",
+        "done_reason": "filled",
+        "fill_fees": "0.0249376391550000",
+        "filled_size": "0.01291771",
+        "executed_value": "9.9750556620000000",
+        "status": "done",
+        "settled": true
+    }"#;
+
+    /// Response to an open limit buy, as returned by the Coinbase Exchange API
+    const OPEN_LIMIT_ORDER: &str = r#"{
+        "id": "d0c5340b-6d6c-49d9-b567-48c4bfca13d2",
+        "price": "0.10000000",
+        "size": "0.01000000",
+        "product_id": "BTC-USD",
+        "side": "buy",
+        "stp": "dc",
+        "type": "limit",
+        "time_in_force": "GTC",
+        "post_only": false,
+        "created_at": "2016-12-08T20:02:28.53864Z",
+        "fill_fees": "0.0000000000000000",
+        "filled_size": "0.00000000",
+        "executed_value": "0.0000000000000000",
+        "status": "open",
+        "settled": false
+    }"#;
+
+    #[test]
+    fn test_order_response_into_order() {
+        let response: CoinbaseOrderResponse = serde_json::from_str(FILLED_MARKET_ORDER).unwrap();
+        assert_eq!(response.fill_fees, dec!(0.024937639155));
+        assert_eq!(response.funds, Some(dec!(9.97506234)));
+        let order = Order::try_from(response.clone()).unwrap();
+        assert_eq!(order.id, "68e6a28f-ae28-4788-8d4f-5ab4e5e5ae08");
+        assert_eq!(order.order_type, OrderType::Market);
+        assert_eq!(order.status, OrderStatus::Done);
+        assert_eq!(order.done_reason, Some("filled".to_string()));
+        assert_eq!(order.price, dec!(0));
+        assert_eq!(order.quantity, dec!(1));
+        assert_eq!(order.filled_quantity, dec!(0.01291771));
+        assert_eq!(order.average_price(), dec!(9.975055662) / dec!(0.01291771));
+
+        let response: CoinbaseOrderResponse = serde_json::from_str(OPEN_LIMIT_ORDER).unwrap();
+        let order = Order::try_from(response.clone()).unwrap();
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.price, dec!(0.1));
+        assert_eq!(order.quantity, dec!(0.01));
+        assert_eq!(order.filled_quantity, dec!(0));
+        assert_eq!(order.average_price(), dec!(0.1));
+
+        let invalid = CoinbaseOrderResponse {
+            created_at: "yesterday".to_string(),
+            ..response
+        };
+        assert!(matches!(
+            Order::try_from(invalid),
+            Err(MarketError::ParseError(_))
+        ));
+    }
 }
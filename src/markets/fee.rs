@@ -1,6 +1,63 @@
-use crate::types::Side;
+use crate::types::{OrderType, Side};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+/// Number of days of trading volume used to determine volume-tiered fees
+pub const FEE_VOLUME_DAYS: i64 = 30;
+
+/// Whether an order adds liquidity to the order book or removes it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Liquidity {
+    /// Resting orders which are filled by other orders
+    Maker,
+    /// Orders which are filled immediately against resting orders
+    #[default]
+    Taker,
+}
+
+impl From<OrderType> for Liquidity {
+    /// Limit orders are submitted fill-or-kill, so like market and stop orders they are filled immediately and take
+    /// liquidity. Only markets which rest unfilled orders on the book, such as the paper market, charge the maker fee.
+    fn from(order_type: OrderType) -> Self {
+        match order_type {
+            OrderType::Limit | OrderType::Market | OrderType::Stop => Liquidity::Taker,
+        }
+    }
+}
+
+/// The currency that a fee is deducted from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeCurrency {
+    /// Fees are added to the cost of buys and subtracted from the proceeds of sells
+    #[default]
+    Quote,
+    /// Fees are converted to the base asset and deducted from the traded quantity
+    Base,
+}
+
+/// Details about an order which determine the fee that is charged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeContext {
+    pub side: Side,
+    pub liquidity: Liquidity,
+
+    /// Notional value traded over the trailing [`FEE_VOLUME_DAYS`], excluding the current order
+    pub volume_30d: Decimal,
+}
+
+impl FeeContext {
+    /// Context for a taker order with no trading history
+    pub fn taker(side: Side) -> Self {
+        Self {
+            side,
+            liquidity: Liquidity::Taker,
+            volume_30d: Decimal::ZERO,
+        }
+    }
+}
 
 /// A trait for calculating the amounts of fees to be deducted from a trade.
 ///
@@ -11,7 +68,21 @@ use rust_decimal_macros::dec;
 /// For buy trades, the fee is added to the cost of the buy order. For sell trades, the fee is
 /// subtracted from the amount of quote currency yielded by the trade.
 pub trait FeeCalculator {
-    fn cost_including_fee(&self, cost: Decimal, side: Side) -> Decimal;
+    /// The fee for an order with the given notional value, denominated in quote currency
+    fn calculate_fee(&self, cost: Decimal, context: &FeeContext) -> Decimal;
+
+    /// The currency that the fee is deducted from
+    fn fee_currency(&self) -> FeeCurrency {
+        FeeCurrency::Quote
+    }
+
+    fn cost_including_fee(&self, cost: Decimal, context: &FeeContext) -> Decimal {
+        let fee = self.calculate_fee(cost, context);
+        match context.side {
+            Side::Buy => cost + fee,
+            Side::Sell => cost - fee,
+        }
+    }
 }
 
 /// A simple fee calculator that has a fixed percentage fee.
@@ -24,6 +95,7 @@ pub trait FeeCalculator {
 /// fee is subtracted from the amount of quote currency yielded by the trade.
 ///
 /// This fee calculator assumes that the fee is the same for both buy and sell orders.
+#[derive(Debug, Clone)]
 pub struct SimplePercentageFee {
     taker_fee: Decimal,
 }
@@ -37,19 +109,221 @@ impl SimplePercentageFee {
 }
 
 impl FeeCalculator for SimplePercentageFee {
-    fn cost_including_fee(&self, cost: Decimal, side: Side) -> Decimal {
-        let fee = cost * self.taker_fee;
-        match side {
-            Side::Buy => cost + fee,
-            Side::Sell => cost - fee,
+    fn calculate_fee(&self, cost: Decimal, _context: &FeeContext) -> Decimal {
+        cost * self.taker_fee
+    }
+}
+
+/// A fee calculator with separate percentages for maker and taker orders
+#[derive(Debug, Clone)]
+pub struct MakerTakerFee {
+    maker_fee: Decimal,
+    taker_fee: Decimal,
+}
+
+impl MakerTakerFee {
+    /// # Arguments
+    /// * `maker_percentage` - The fee for maker orders. `0.4` is 0.4%.
+    /// * `taker_percentage` - The fee for taker orders. `0.6` is 0.6%.
+    pub fn new(maker_percentage: Decimal, taker_percentage: Decimal) -> Self {
+        Self {
+            maker_fee: maker_percentage / dec!(100.0),
+            taker_fee: taker_percentage / dec!(100.0),
+        }
+    }
+}
+
+impl FeeCalculator for MakerTakerFee {
+    fn calculate_fee(&self, cost: Decimal, context: &FeeContext) -> Decimal {
+        match context.liquidity {
+            Liquidity::Maker => cost * self.maker_fee,
+            Liquidity::Taker => cost * self.taker_fee,
+        }
+    }
+}
+
+/// A single tier of a [`TieredFee`] schedule
+///
+/// Fees are given as percentages. `0.6` is 0.6%.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeeTier {
+    /// Minimum 30-day notional volume required for this tier
+    pub min_volume: Decimal,
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+}
+
+/// A maker/taker fee schedule where the rates depend on the trailing 30-day volume
+///
+/// The tier with the highest `min_volume` which does not exceed the 30-day volume is used. If the
+/// volume is below every tier, the lowest tier is used. Tiers may be given in any order.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TieredFee {
+    tiers: Vec<FeeTier>,
+}
+
+impl TieredFee {
+    pub fn new(tiers: Vec<FeeTier>) -> Self {
+        Self { tiers }
+    }
+
+    fn get_tier(&self, volume: Decimal) -> Option<&FeeTier> {
+        self.tiers
+            .iter()
+            .filter(|tier| tier.min_volume <= volume)
+            .max_by_key(|tier| tier.min_volume)
+            .or_else(|| self.tiers.iter().min_by_key(|tier| tier.min_volume))
+    }
+}
+
+impl FeeCalculator for TieredFee {
+    fn calculate_fee(&self, cost: Decimal, context: &FeeContext) -> Decimal {
+        let Some(tier) = self.get_tier(context.volume_30d) else {
+            return Decimal::ZERO;
+        };
+        let percentage = match context.liquidity {
+            Liquidity::Maker => tier.maker_fee,
+            Liquidity::Taker => tier.taker_fee,
+        };
+        cost * percentage / dec!(100.0)
+    }
+}
+
+/// Wraps a [`FeeCalculator`] and charges at least a flat fee per order
+#[derive(Debug, Clone)]
+pub struct MinimumFee<F: FeeCalculator> {
+    inner: F,
+    minimum: Decimal,
+}
+
+impl<F: FeeCalculator> MinimumFee<F> {
+    pub fn new(inner: F, minimum: Decimal) -> Self {
+        Self { inner, minimum }
+    }
+}
+
+impl<F: FeeCalculator> FeeCalculator for MinimumFee<F> {
+    fn calculate_fee(&self, cost: Decimal, context: &FeeContext) -> Decimal {
+        self.inner.calculate_fee(cost, context).max(self.minimum)
+    }
+
+    fn fee_currency(&self) -> FeeCurrency {
+        self.inner.fee_currency()
+    }
+}
+
+/// Wraps a [`FeeCalculator`] so that fees are deducted from the base asset
+#[derive(Debug, Clone)]
+pub struct BaseAssetFee<F: FeeCalculator> {
+    inner: F,
+}
+
+impl<F: FeeCalculator> BaseAssetFee<F> {
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<F: FeeCalculator> FeeCalculator for BaseAssetFee<F> {
+    fn calculate_fee(&self, cost: Decimal, context: &FeeContext) -> Decimal {
+        self.inner.calculate_fee(cost, context)
+    }
+
+    fn fee_currency(&self) -> FeeCurrency {
+        FeeCurrency::Base
+    }
+}
+
+/// Fee model selection
+///
+/// Meant to be read from a TOML config file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeModel {
+    Percentage {
+        fee: Decimal,
+    },
+    MakerTaker {
+        maker_fee: Decimal,
+        taker_fee: Decimal,
+    },
+    /// Tiers are given as `[[tiers]]` tables
+    Tiered {
+        tiers: Vec<FeeTier>,
+    },
+}
+
+/// Fee configuration
+///
+/// Meant to be read from the `[portfolio.fees]` section of a TOML config file.
+///
+/// ```toml
+/// [portfolio.fees]
+/// type = "maker_taker"
+/// maker_fee = 0.4
+/// taker_fee = 0.6
+/// minimum_fee = 0.01
+/// fee_currency = "quote"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeConfig {
+    #[serde(flatten)]
+    pub model: FeeModel,
+
+    /// Flat minimum fee charged per order
+    #[serde(default)]
+    pub minimum_fee: Option<Decimal>,
+
+    #[serde(default)]
+    pub fee_currency: FeeCurrency,
+}
+
+impl FeeConfig {
+    /// Create the configured [`FeeCalculator`]
+    pub fn build(&self) -> Box<dyn FeeCalculator + Send + Sync> {
+        let calculator: Box<dyn FeeCalculator + Send + Sync> = match &self.model {
+            FeeModel::Percentage { fee } => Box::new(SimplePercentageFee::new(*fee)),
+            FeeModel::MakerTaker {
+                maker_fee,
+                taker_fee,
+            } => Box::new(MakerTakerFee::new(*maker_fee, *taker_fee)),
+            FeeModel::Tiered { tiers } => Box::new(TieredFee::new(tiers.clone())),
+        };
+
+        let calculator: Box<dyn FeeCalculator + Send + Sync> = match self.minimum_fee {
+            Some(minimum) => Box::new(MinimumFee::new(calculator, minimum)),
+            None => calculator,
+        };
+
+        match self.fee_currency {
+            FeeCurrency::Quote => calculator,
+            FeeCurrency::Base => Box::new(BaseAssetFee::new(calculator)),
         }
     }
 }
 
+impl<T: FeeCalculator + ?Sized> FeeCalculator for Box<T> {
+    fn calculate_fee(&self, cost: Decimal, context: &FeeContext) -> Decimal {
+        (**self).calculate_fee(cost, context)
+    }
+
+    fn fee_currency(&self) -> FeeCurrency {
+        (**self).fee_currency()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn context(liquidity: Liquidity, volume_30d: Decimal) -> FeeContext {
+        FeeContext {
+            side: Side::Buy,
+            liquidity,
+            volume_30d,
+        }
+    }
+
     #[test]
     fn test_percentage_taker_fee_calculator() {
         let trade_price = dec!(100.0);
@@ -59,11 +333,135 @@ mod tests {
         assert_eq!(fee_calculator.taker_fee, dec!(0.008));
 
         // assert that the fee for a buy trade is calculated correctly
-        let fee = fee_calculator.cost_including_fee(trade_price, Side::Buy);
+        let fee = fee_calculator.cost_including_fee(trade_price, &FeeContext::taker(Side::Buy));
         assert_eq!(fee, dec!(100.8));
 
         // assert that the fee for a sell trade is calculated correctly
-        let fee = fee_calculator.cost_including_fee(trade_price, Side::Sell);
+        let fee = fee_calculator.cost_including_fee(trade_price, &FeeContext::taker(Side::Sell));
         assert_eq!(fee, dec!(99.2));
     }
+
+    #[test]
+    fn test_maker_taker_fee_calculator() {
+        let fee_calculator = MakerTakerFee::new(dec!(0.4), dec!(0.6));
+
+        let fee = fee_calculator.calculate_fee(dec!(100.0), &context(Liquidity::Maker, dec!(0)));
+        assert_eq!(fee, dec!(0.4));
+
+        let fee = fee_calculator.calculate_fee(dec!(100.0), &context(Liquidity::Taker, dec!(0)));
+        assert_eq!(fee, dec!(0.6));
+    }
+
+    #[test]
+    fn test_liquidity_from_order_type() {
+        assert_eq!(Liquidity::from(OrderType::Limit), Liquidity::Taker);
+        assert_eq!(Liquidity::from(OrderType::Market), Liquidity::Taker);
+        assert_eq!(Liquidity::from(OrderType::Stop), Liquidity::Taker);
+    }
+
+    #[test]
+    fn test_tiered_fee_calculator() {
+        let fee_calculator = TieredFee::new(vec![
+            FeeTier {
+                min_volume: dec!(10000),
+                maker_fee: dec!(0.25),
+                taker_fee: dec!(0.4),
+            },
+            FeeTier {
+                min_volume: dec!(0),
+                maker_fee: dec!(0.4),
+                taker_fee: dec!(0.6),
+            },
+        ]);
+
+        let fee = fee_calculator.calculate_fee(dec!(100.0), &context(Liquidity::Taker, dec!(9999)));
+        assert_eq!(fee, dec!(0.6));
+
+        let fee =
+            fee_calculator.calculate_fee(dec!(100.0), &context(Liquidity::Taker, dec!(10000)));
+        assert_eq!(fee, dec!(0.4));
+
+        let fee =
+            fee_calculator.calculate_fee(dec!(100.0), &context(Liquidity::Maker, dec!(50000)));
+        assert_eq!(fee, dec!(0.25));
+    }
+
+    #[test]
+    fn test_tiered_fee_from_toml() {
+        // tiers are deliberately out of order
+        let schedule = r#"
+            [[tiers]]
+            min_volume = 10000
+            maker_fee = 0.25
+            taker_fee = 0.4
+
+            [[tiers]]
+            min_volume = 0
+            maker_fee = 0.4
+            taker_fee = 0.6
+        "#;
+        let fee_calculator: TieredFee = toml::from_str(schedule).unwrap();
+
+        let fee =
+            fee_calculator.calculate_fee(dec!(100.0), &context(Liquidity::Taker, dec!(20000)));
+        assert_eq!(fee, dec!(0.4));
+
+        let fee = fee_calculator.calculate_fee(dec!(100.0), &context(Liquidity::Taker, dec!(9999)));
+        assert_eq!(fee, dec!(0.6));
+    }
+
+    #[test]
+    fn test_minimum_fee_calculator() {
+        let fee_calculator = MinimumFee::new(SimplePercentageFee::new(dec!(0.5)), dec!(1.0));
+
+        let fee = fee_calculator.calculate_fee(dec!(100.0), &FeeContext::taker(Side::Buy));
+        assert_eq!(fee, dec!(1.0));
+
+        let fee = fee_calculator.calculate_fee(dec!(1000.0), &FeeContext::taker(Side::Buy));
+        assert_eq!(fee, dec!(5.0));
+        assert_eq!(fee_calculator.fee_currency(), FeeCurrency::Quote);
+    }
+
+    #[test]
+    fn test_base_asset_fee_calculator() {
+        let fee_calculator = BaseAssetFee::new(SimplePercentageFee::new(dec!(0.5)));
+
+        let fee = fee_calculator.calculate_fee(dec!(100.0), &FeeContext::taker(Side::Buy));
+        assert_eq!(fee, dec!(0.5));
+        assert_eq!(fee_calculator.fee_currency(), FeeCurrency::Base);
+    }
+
+    #[test]
+    fn test_fee_config() {
+        let config = r#"
+            type = "maker_taker"
+            maker_fee = 0.4
+            taker_fee = 0.6
+            minimum_fee = 1.0
+            fee_currency = "base"
+        "#;
+        let config: FeeConfig = toml::from_str(config).unwrap();
+        let fee_calculator = config.build();
+
+        let fee = fee_calculator.calculate_fee(dec!(1000.0), &context(Liquidity::Maker, dec!(0)));
+        assert_eq!(fee, dec!(4.0));
+
+        let fee = fee_calculator.calculate_fee(dec!(10.0), &context(Liquidity::Maker, dec!(0)));
+        assert_eq!(fee, dec!(1.0));
+        assert_eq!(fee_calculator.fee_currency(), FeeCurrency::Base);
+
+        let config = r#"
+            type = "tiered"
+
+            [[tiers]]
+            min_volume = 0
+            maker_fee = 0.4
+            taker_fee = 0.6
+        "#;
+        let config: FeeConfig = toml::from_str(config).unwrap();
+        let fee_calculator = config.build();
+        let fee = fee_calculator.calculate_fee(dec!(100.0), &FeeContext::taker(Side::Sell));
+        assert_eq!(fee, dec!(0.6));
+        assert_eq!(fee_calculator.fee_currency(), FeeCurrency::Quote);
+    }
 }
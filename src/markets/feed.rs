@@ -0,0 +1,254 @@
+use crate::types::Candle;
+use crate::utils::frequency_to_duration;
+use chrono::{NaiveDateTime, TimeDelta};
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Messages emitted by a streaming market data feed
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedEvent {
+    /// A candle which has closed
+    Candle {
+        product_id: String,
+        interval: String,
+        candle: Candle,
+    },
+    Ticker(Ticker),
+}
+
+/// The latest price of a product
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ticker {
+    pub product_id: String,
+    pub price: Decimal,
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub time: NaiveDateTime,
+}
+
+/// Builds candles from a stream of trades
+///
+/// Trades are placed into buckets aligned to the unix epoch for every configured interval. A candle
+/// is only emitted once it is closed, either by a trade in a later bucket or by [`Self::close`].
+/// Intervals without any trades do not produce a candle.
+pub struct CandleAggregator {
+    /// Interval labels with their length
+    intervals: Vec<(String, TimeDelta)>,
+
+    /// Open candle keyed by product id and interval
+    open: HashMap<(String, String), Candle>,
+
+    /// Start of the last closed candle keyed by product id and interval
+    closed: HashMap<(String, String), NaiveDateTime>,
+}
+
+impl CandleAggregator {
+    /// Create an aggregator for the given interval labels (ie: "1m", "1h")
+    ///
+    /// Invalid intervals are ignored.
+    pub fn new(intervals: &[&str]) -> Self {
+        let intervals = intervals
+            .iter()
+            .filter_map(|interval| {
+                let duration = frequency_to_duration(interval)
+                    .and_then(|duration| TimeDelta::from_std(duration).ok());
+                if duration.is_none() {
+                    warn!("Ignoring invalid interval: {}", interval);
+                }
+                duration.map(|duration| (interval.to_string(), duration))
+            })
+            .collect();
+        Self {
+            intervals,
+            open: HashMap::new(),
+            closed: HashMap::new(),
+        }
+    }
+
+    /// Add a trade to the open candles
+    ///
+    /// Trades which are older than the open candle or belong to an already closed candle are ignored.
+    ///
+    /// # Returns
+    /// Candles closed by this trade as `(interval, candle)` pairs
+    pub fn add_trade(
+        &mut self,
+        product_id: &str,
+        price: Decimal,
+        size: Decimal,
+        time: NaiveDateTime,
+    ) -> Vec<(String, Candle)> {
+        let mut closed = vec![];
+        for (interval, duration) in self.intervals.iter() {
+            let start = bucket_start(time, *duration);
+            let key = (product_id.to_string(), interval.clone());
+
+            if self.closed.get(&key).is_some_and(|last| start <= *last) {
+                warn!(
+                    "Ignoring trade for closed candle of {} at {}",
+                    product_id, time
+                );
+                continue;
+            }
+
+            match self.open.get_mut(&key) {
+                Some(candle) if candle.time == start => {
+                    candle.high = candle.high.max(price);
+                    candle.low = candle.low.min(price);
+                    candle.close = price;
+                    candle.volume += size;
+                    continue;
+                }
+                Some(candle) if candle.time > start => {
+                    warn!("Ignoring out of order trade for {} at {}", product_id, time);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let candle = Candle {
+                time: start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: size,
+            };
+            if let Some(previous) = self.open.insert(key.clone(), candle) {
+                self.closed.insert(key, previous.time);
+                closed.push((interval.clone(), previous));
+            }
+        }
+        closed
+    }
+
+    /// Close all open candles for a product which end at or before `time`
+    ///
+    /// # Returns
+    /// The closed candles as `(interval, candle)` pairs
+    pub fn close(&mut self, product_id: &str, time: NaiveDateTime) -> Vec<(String, Candle)> {
+        let mut closed = vec![];
+        for (interval, duration) in self.intervals.iter() {
+            let key = (product_id.to_string(), interval.clone());
+            let ended = self
+                .open
+                .get(&key)
+                .is_some_and(|candle| candle.time + *duration <= time);
+            if ended {
+                let candle = self.open.remove(&key).unwrap();
+                self.closed.insert(key, candle.time);
+                closed.push((interval.clone(), candle));
+            }
+        }
+        closed
+    }
+}
+
+/// Start of the interval which contains `time`
+fn bucket_start(time: NaiveDateTime, duration: TimeDelta) -> NaiveDateTime {
+    let timestamp = time.and_utc().timestamp();
+    let seconds = duration.num_seconds();
+    let start = timestamp - timestamp.rem_euclid(seconds);
+    chrono::DateTime::from_timestamp(start, 0)
+        .unwrap()
+        .naive_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const PRODUCT: &str = "BTC-USD";
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_bucket_start() {
+        let start = bucket_start(time("2024-01-01 10:07:31"), TimeDelta::minutes(5));
+        assert_eq!(start, time("2024-01-01 10:05:00"));
+
+        let start = bucket_start(time("2024-01-01 10:07:31"), TimeDelta::days(1));
+        assert_eq!(start, time("2024-01-01 00:00:00"));
+    }
+
+    #[test]
+    fn test_add_trade() {
+        let mut aggregator = CandleAggregator::new(&["1m", "5m"]);
+
+        let trades = [
+            (dec!(100), dec!(1), "2024-01-01 00:00:05"),
+            (dec!(105), dec!(2), "2024-01-01 00:00:20"),
+            (dec!(95), dec!(1), "2024-01-01 00:00:40"),
+            (dec!(101), dec!(3), "2024-01-01 00:00:59"),
+        ];
+        for (price, size, t) in trades {
+            assert!(aggregator
+                .add_trade(PRODUCT, price, size, time(t))
+                .is_empty());
+        }
+
+        // a trade in the next minute closes the 1m candle
+        let closed = aggregator.add_trade(PRODUCT, dec!(102), dec!(1), time("2024-01-01 00:01:00"));
+        assert_eq!(closed.len(), 1);
+        let (interval, candle) = &closed[0];
+        assert_eq!(interval, "1m");
+        assert_eq!(
+            candle,
+            &Candle {
+                time: time("2024-01-01 00:00:00"),
+                open: dec!(100),
+                high: dec!(105),
+                low: dec!(95),
+                close: dec!(101),
+                volume: dec!(7),
+            }
+        );
+
+        // late trades are ignored by the 1m candle which has closed, but not by the open 5m candle
+        let closed = aggregator.add_trade(PRODUCT, dec!(102), dec!(1), time("2024-01-01 00:00:30"));
+        assert!(closed.is_empty());
+
+        // a trade after a gap closes both candles
+        let closed = aggregator.add_trade(PRODUCT, dec!(110), dec!(1), time("2024-01-01 00:07:00"));
+        assert_eq!(closed.len(), 2);
+        let (interval, candle) = &closed[1];
+        assert_eq!(interval, "5m");
+        assert_eq!(candle.time, time("2024-01-01 00:00:00"));
+        assert_eq!(candle.close, dec!(102));
+        assert_eq!(candle.volume, dec!(9));
+    }
+
+    #[test]
+    fn test_close() {
+        let mut aggregator = CandleAggregator::new(&["1m", "5m", "invalid"]);
+        aggregator.add_trade(PRODUCT, dec!(100), dec!(1), time("2024-01-01 00:00:05"));
+
+        assert!(aggregator
+            .close(PRODUCT, time("2024-01-01 00:00:59"))
+            .is_empty());
+
+        let closed = aggregator.close(PRODUCT, time("2024-01-01 00:01:00"));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].0, "1m");
+
+        // candles are only closed once
+        assert!(aggregator
+            .close(PRODUCT, time("2024-01-01 00:02:00"))
+            .is_empty());
+
+        // a late trade does not reopen a closed candle
+        let closed = aggregator.add_trade(PRODUCT, dec!(101), dec!(1), time("2024-01-01 00:00:50"));
+        assert!(closed.is_empty());
+        let closed = aggregator.close(PRODUCT, time("2024-01-01 00:05:00"));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].0, "5m");
+        assert_eq!(closed[0].1.volume, dec!(2));
+        assert!(aggregator
+            .close(PRODUCT, time("2024-01-01 00:10:00"))
+            .is_empty());
+    }
+}
@@ -1,5 +1,7 @@
-use crate::markets::BaseMarket;
+use crate::markets::feed::FeedEvent;
+use crate::markets::{BaseMarket, MarketError};
 use crate::traits::AsDataFrame;
+use crate::types::Candle;
 use crate::utils::extract_new_rows;
 use polars::error::PolarsResult;
 use polars::frame::{DataFrame, UniqueKeepStrategy};
@@ -99,9 +101,22 @@ where
         self.candles.get(&interval.to_string())
     }
 
-    pub async fn update(&mut self, interval: &str) -> Option<DataFrame> {
-        let candles = self.market.get_candles(&self.pair, interval).await.unwrap();
-        let df = candles.as_dataframe();
+    /// Fetch the latest candles from the market and merge them into the stored candles
+    ///
+    /// # Returns
+    /// * `Ok(Some)` - The rows which were not previously stored
+    /// * `Ok(None)` - If this was the first update for the given interval
+    /// * `Err` - If the market request failed
+    pub async fn update(&mut self, interval: &str) -> Result<Option<DataFrame>, MarketError> {
+        let candles = self.market.get_candles(&self.pair, interval).await?;
+        Ok(self.merge(interval, candles.as_dataframe()))
+    }
+
+    /// Merge candles into the stored candles for an interval
+    ///
+    /// # Returns
+    /// The rows which were not previously stored, or `None` if nothing was stored for the interval
+    fn merge(&mut self, interval: &str, df: DataFrame) -> Option<DataFrame> {
         match self.candles.get(interval) {
             Some(existing) => {
                 let updated = append_candles(existing, df).unwrap();
@@ -116,10 +131,41 @@ where
         }
     }
 
-    pub async fn update_all(&mut self) {
+    /// Add closed candles received from a streaming feed
+    ///
+    /// # Returns
+    /// The rows which were not previously stored
+    pub fn push_candles(&mut self, interval: &str, candles: Vec<Candle>) -> DataFrame {
+        let df = candles.as_dataframe();
+        self.merge(interval, df.clone()).unwrap_or(df)
+    }
+
+    /// Store a candle received from a streaming feed
+    ///
+    /// Events for other pairs and ticker events are ignored.
+    ///
+    /// # Returns
+    /// * `Some((interval, new_rows))` - The interval of the received candle and the new rows
+    /// * `None` - If the event was ignored
+    pub fn push_event(&mut self, event: &FeedEvent) -> Option<(String, DataFrame)> {
+        match event {
+            FeedEvent::Candle {
+                product_id,
+                interval,
+                candle,
+            } if *product_id == self.pair => {
+                let new_rows = self.push_candles(interval, vec![candle.clone()]);
+                Some((interval.clone(), new_rows))
+            }
+            _ => None,
+        }
+    }
+
+    pub async fn update_all(&mut self) -> Result<(), MarketError> {
         for interval in VALID_INTERVALS.iter() {
-            self.update(interval).await;
+            self.update(interval).await?;
         }
+        Ok(())
     }
 
     pub fn save(&mut self, path: &Path) -> Result<(), Error> {
@@ -144,6 +190,7 @@ where
 mod tests {
     use crate::markets::manager::{load_candles, CandleManager, VALID_INTERVALS};
     use crate::markets::CoinbaseClient;
+    use crate::traits::AsDataFrame;
     use crate::utils::create_temp_dir;
     use polars::frame::DataFrame;
     use polars::prelude::*;
@@ -306,4 +353,69 @@ mod tests {
             5.0
         );
     }
+
+    #[test]
+    fn test_push_event() {
+        use crate::markets::feed::{FeedEvent, Ticker};
+        use crate::types::Candle;
+        use chrono::NaiveDateTime;
+        use rust_decimal_macros::dec;
+
+        let time = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        let candle = |t| Candle {
+            time: time(t),
+            open: dec!(1),
+            high: dec!(2),
+            low: dec!(0.5),
+            close: dec!(1.5),
+            volume: dec!(10),
+        };
+        let candle_event = |product_id: &str, t| FeedEvent::Candle {
+            product_id: product_id.to_string(),
+            interval: "1m".to_string(),
+            candle: candle(t),
+        };
+
+        let market = build_market();
+        let mut manager = CandleManager::new("BTC-USD", &market);
+
+        // ticker events and other pairs are ignored
+        let ticker = FeedEvent::Ticker(Ticker {
+            product_id: "BTC-USD".to_string(),
+            price: dec!(1),
+            best_bid: None,
+            best_ask: None,
+            time: time("2024-01-01 00:00"),
+        });
+        assert!(manager.push_event(&ticker).is_none());
+        assert!(manager
+            .push_event(&candle_event("ETH-USD", "2024-01-01 00:00"))
+            .is_none());
+        assert!(manager.get("1m").is_none());
+
+        let (interval, new_rows) = manager
+            .push_event(&candle_event("BTC-USD", "2024-01-01 00:00"))
+            .unwrap();
+        assert_eq!(interval, "1m");
+        assert_eq!(new_rows.shape(), (1, 6));
+
+        let (_, new_rows) = manager
+            .push_event(&candle_event("BTC-USD", "2024-01-01 00:01"))
+            .unwrap();
+        assert_eq!(new_rows.shape(), (1, 6));
+        assert_eq!(manager.get("1m").unwrap().shape(), (2, 6));
+
+        // candles are stored in descending order
+        let expected = candle("2024-01-01 00:01").as_dataframe();
+        assert_eq!(
+            manager
+                .get("1m")
+                .unwrap()
+                .column("time")
+                .unwrap()
+                .get(0)
+                .unwrap(),
+            expected.column("time").unwrap().get(0).unwrap()
+        );
+    }
 }
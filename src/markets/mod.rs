@@ -1,15 +1,73 @@
+pub mod backfill;
 mod coinbase;
 mod fee;
+pub mod feed;
 pub mod manager;
+mod paper;
+mod tracker;
 pub mod utils;
 
 use async_trait::async_trait;
+use thiserror::Error;
 
-pub use coinbase::CoinbaseClient;
+pub use coinbase::{CoinbaseClient, CoinbaseFeed};
 
-pub use fee::{FeeCalculator, SimplePercentageFee};
+pub use fee::{
+    FeeCalculator, FeeConfig, FeeContext, FeeCurrency, Liquidity, MakerTakerFee,
+    SimplePercentageFee, FEE_VOLUME_DAYS,
+};
+pub use paper::PaperMarket;
+pub use tracker::OrderTracker;
 
-use crate::types::{Candle, ExecutedTrade, FutureTrade};
+use crate::types::{Candle, ExecutedTrade, FutureTrade, Order, ReasonCode};
+
+#[derive(Error, Debug)]
+pub enum MarketError {
+    #[error("Request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Order rejected ({0:?}): {1}")]
+    OrderRejected(ReasonCode, String),
+    #[error("Candles not available for {0} at interval {1}")]
+    CandlesUnavailable(String, String),
+    #[error("Invalid credentials: {0}")]
+    InvalidCredentials(String),
+    #[error("Order not found: {0}")]
+    OrderNotFound(String),
+    #[error("Market responded with status {0}: {1}")]
+    ResponseError(u16, String),
+    #[error("Market data feed error: {0}")]
+    FeedError(String),
+    #[error("Candle store error: {0}")]
+    StoreError(String),
+    #[error("Could not parse market response: {0}")]
+    ParseError(String),
+    #[error("Order {0} is resting on the book")]
+    OrderResting(String),
+}
+
+impl From<sqlite::Error> for MarketError {
+    fn from(error: sqlite::Error) -> Self {
+        MarketError::StoreError(error.to_string())
+    }
+}
+
+impl MarketError {
+    /// The [`ReasonCode`] to record when an order fails with this error
+    pub fn reason_code(&self) -> ReasonCode {
+        match self {
+            MarketError::RequestError(_) => ReasonCode::PostError,
+            MarketError::OrderRejected(reason, _) => *reason,
+            MarketError::CandlesUnavailable(_, _) => ReasonCode::Unknown,
+            MarketError::InvalidCredentials(_) => ReasonCode::PostError,
+            MarketError::OrderNotFound(_) => ReasonCode::Unknown,
+            MarketError::ResponseError(_, _) => ReasonCode::PostError,
+            MarketError::FeedError(_) => ReasonCode::Unknown,
+            MarketError::StoreError(_) => ReasonCode::Unknown,
+            MarketError::ParseError(_) => ReasonCode::ParseError,
+            MarketError::OrderResting(_) => ReasonCode::Resting,
+        }
+    }
+}
 
 /// A minimum interface for interacting with cryptocurrency exchanges.
 ///
@@ -24,7 +82,7 @@ pub trait BaseMarket: Clone {
     /// # Arguments
     /// * `pair` - The trading pair to get candles for. This is market specific.
     /// * `interval` - The interval to get candles for. This is market specific.
-    async fn get_candles(&self, pair: &str, interval: &str) -> Result<Vec<Candle>, reqwest::Error>;
+    async fn get_candles(&self, pair: &str, interval: &str) -> Result<Vec<Candle>, MarketError>;
 
     /// Submits an order to the exchange and returns the executed trade.
     ///
@@ -34,11 +92,32 @@ pub trait BaseMarket: Clone {
     ///
     /// # Returns
     /// * `ExecutedTrade` - The executed trade returned by the exchange if the order was filled.
+    /// * `MarketError` - If the order could not be submitted or was rejected by the exchange.
     async fn submit_order(
         &self,
         order: FutureTrade,
         product_id: String,
-    ) -> Result<ExecutedTrade, reqwest::Error>;
+    ) -> Result<ExecutedTrade, MarketError>;
+
+    /// Returns the current state of an order
+    ///
+    /// # Arguments
+    /// * `order_id` - The id assigned to the order by the exchange
+    async fn get_order(&self, order_id: &str) -> Result<Order, MarketError>;
+
+    /// Cancels an open order
+    ///
+    /// Any quantity which has already been filled is not reverted.
+    ///
+    /// # Arguments
+    /// * `order_id` - The id assigned to the order by the exchange
+    async fn cancel_order(&self, order_id: &str) -> Result<(), MarketError>;
+
+    /// Returns all orders which are open for the given product
+    ///
+    /// # Arguments
+    /// * `product_id` - The product id to list orders for. This is market specific.
+    async fn list_open_orders(&self, product_id: &str) -> Result<Vec<Order>, MarketError>;
 }
 
 /// A common interface for interacting with cryptocurrency exchanges.
@@ -59,5 +138,5 @@ pub trait Market: BaseMarket {
     async fn get_fee_calculator(&self) -> Option<&dyn FeeCalculator>;
 
     /// Returns a list of trading pairs and their info supported by the exchange.
-    async fn get_trading_pair_info(&self) -> Result<Vec<Self::PairType>, reqwest::Error>;
+    async fn get_trading_pair_info(&self) -> Result<Vec<Self::PairType>, MarketError>;
 }
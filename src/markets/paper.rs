@@ -0,0 +1,853 @@
+use crate::markets::utils::read_candles;
+use crate::markets::{
+    BaseMarket, FeeCalculator, FeeContext, FeeCurrency, Liquidity, Market, MarketError,
+    SimplePercentageFee, FEE_VOLUME_DAYS,
+};
+use crate::types::{
+    Candle, ExecutedTrade, FutureTrade, MarketData, Order, OrderStatus, OrderType, ReasonCode,
+    Side, Trade,
+};
+use crate::utils::extract_candles_from_df;
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime};
+use polars::prelude::PolarsResult;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of candles returned by `get_candles`. Mirrors the Coinbase candles endpoint.
+const MAX_CANDLES: usize = 300;
+
+/// Mutable state shared between all clones of a [`PaperMarket`]
+struct PaperMarketState {
+    /// Candles keyed by pair and interval. Sorted by time in ascending order.
+    candles: HashMap<(String, String), Vec<Candle>>,
+
+    /// The time of the most recently closed candle. `None` until the replay has started.
+    time: Option<NaiveDateTime>,
+
+    /// Available balance keyed by currency (ie: "BTC", "USD")
+    balances: HashMap<String, Decimal>,
+
+    /// All filled orders
+    orders: Vec<ExecutedTrade>,
+
+    /// Limit and stop orders which were not filled when submitted, with the pair each order was placed for. Orders
+    /// are kept once done so that their final state can be queried.
+    book: Vec<(String, Order)>,
+
+    /// Number of orders which have been submitted. Used to assign order ids.
+    order_count: usize,
+}
+
+impl PaperMarketState {
+    fn next_order_id(&mut self) -> String {
+        self.order_count += 1;
+        format!("paper-{}", self.order_count)
+    }
+}
+
+/// A simulated exchange which replays historical candles
+///
+/// Candles are replayed by calling [`PaperMarket::advance`], which moves the internal clock to the
+/// next candle at the replay interval. [`BaseMarket::get_candles`] only returns candles which have
+/// closed at the current time.
+///
+/// Market orders, limit orders which cross the current close price, and stop orders whose stop price has been
+/// reached are filled immediately at the close price of the current candle. Other limit and stop orders rest on a
+/// simulated order book and are reported with [`MarketError::OrderResting`]. Every time the clock advances, resting
+/// orders which the new candle crossed are filled at their price, or at the open price if the market gapped past
+/// it. Resting orders can be queried with [`BaseMarket::get_order`] and [`BaseMarket::list_open_orders`], and
+/// removed with [`BaseMarket::cancel_order`].
+///
+/// An order is rejected with [`ReasonCode::MarketRejection`] when:
+/// - the quantity exceeds the available liquidity (a fraction of the candle volume)
+/// - there is not enough balance to pay for the order, including fees
+///
+/// Resting orders stay on the book while liquidity is insufficient, and are rejected if the balance is insufficient
+/// when they are filled. All clones share the same clock, balances and order book.
+#[derive(Clone)]
+pub struct PaperMarket {
+    state: Arc<Mutex<PaperMarketState>>,
+    fee_calculator: Arc<dyn FeeCalculator + Send + Sync>,
+
+    /// Interval used to advance the clock and to price orders
+    interval: String,
+
+    /// Fraction of the candle volume which is available to fill a single order
+    liquidity_fraction: Decimal,
+}
+
+impl PaperMarket {
+    /// Create an empty market which is replayed at the given interval
+    pub fn new<S: Into<String>>(interval: S) -> Self {
+        let state = PaperMarketState {
+            candles: HashMap::new(),
+            time: None,
+            balances: HashMap::new(),
+            orders: Vec::new(),
+            book: Vec::new(),
+            order_count: 0,
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            fee_calculator: Arc::new(SimplePercentageFee::new(dec!(0))),
+            interval: interval.into(),
+            liquidity_fraction: Decimal::ONE,
+        }
+    }
+
+    /// Builder method for setting the starting balance of a currency
+    pub fn with_balance<S: Into<String>>(self, currency: S, amount: Decimal) -> Self {
+        self.set_balance(currency, amount);
+        self
+    }
+
+    /// Set the available balance of a currency
+    pub fn set_balance<S: Into<String>>(&self, currency: S, amount: Decimal) {
+        self.state
+            .lock()
+            .unwrap()
+            .balances
+            .insert(currency.into(), amount);
+    }
+
+    /// Builder method for the fee calculator applied to every filled order
+    pub fn with_fee_calculator<T>(mut self, fee_calculator: T) -> Self
+    where
+        T: FeeCalculator + Send + Sync + 'static,
+    {
+        self.fee_calculator = Arc::new(fee_calculator);
+        self
+    }
+
+    /// Builder method for the fraction of candle volume available to a single order
+    pub fn with_liquidity_fraction(mut self, fraction: Decimal) -> Self {
+        self.liquidity_fraction = fraction;
+        self
+    }
+
+    /// Add candles to be replayed for the given pair and interval
+    pub fn with_candles<S: Into<String>>(
+        self,
+        pair: S,
+        interval: S,
+        mut candles: Vec<Candle>,
+    ) -> Self {
+        candles.sort_by_key(|candle| candle.time);
+        self.state
+            .lock()
+            .unwrap()
+            .candles
+            .insert((pair.into(), interval.into()), candles);
+        self
+    }
+
+    /// Add candles from a CSV file written by [`crate::markets::utils::save_candles`]
+    pub fn with_csv<S: Into<String>>(
+        self,
+        pair: S,
+        interval: S,
+        path: &Path,
+    ) -> PolarsResult<Self> {
+        let candles = extract_candles_from_df(&read_candles(path)?)?;
+        Ok(self.with_candles(pair, interval, candles))
+    }
+
+    /// Add every frequency available in the sqlite candle store for the given pair
+    pub fn with_market_data<S: Into<String>>(
+        mut self,
+        pair: S,
+        market_data: &MarketData,
+    ) -> PolarsResult<Self> {
+        let pair = pair.into();
+        for (interval, df) in market_data.candles.iter() {
+            let candles = extract_candles_from_df(df)?;
+            self = self.with_candles(pair.clone(), interval.clone(), candles);
+        }
+        Ok(self)
+    }
+
+    /// Move the clock to the next candle at the replay interval and fill the resting orders it crossed
+    ///
+    /// # Returns
+    /// * `Some` - The new time of the clock
+    /// * `None` - If all candles have been replayed
+    pub fn advance(&self) -> Option<NaiveDateTime> {
+        let mut state = self.state.lock().unwrap();
+        let current = state.time;
+        let next = state
+            .candles
+            .iter()
+            .filter(|((_, interval), _)| *interval == self.interval)
+            .filter_map(|(_, candles)| {
+                candles
+                    .iter()
+                    .map(|candle| candle.time)
+                    .find(|time| current.is_none_or(|current| *time > current))
+            })
+            .min();
+
+        if let Some(time) = next {
+            state.time = next;
+            self.match_orders(&mut state, time);
+        }
+        next
+    }
+
+    /// Fill the resting orders which were crossed by the candles at `time`
+    ///
+    /// Resting limit orders add liquidity, so they are charged the maker fee. Stop orders are charged the taker fee.
+    fn match_orders(&self, state: &mut PaperMarketState, time: NaiveDateTime) {
+        for index in 0..state.book.len() {
+            let (product_id, order) = state.book[index].clone();
+            if order.status != OrderStatus::Open {
+                continue;
+            }
+            let Some(candle) = state
+                .candles
+                .get(&(product_id.clone(), self.interval.clone()))
+                .and_then(|candles| candles.iter().find(|candle| candle.time == time))
+                .cloned()
+            else {
+                continue;
+            };
+
+            let price = match (order.order_type, order.side) {
+                (OrderType::Limit, Side::Buy) if candle.low <= order.price => {
+                    order.price.min(candle.open)
+                }
+                (OrderType::Limit, Side::Sell) if candle.high >= order.price => {
+                    order.price.max(candle.open)
+                }
+                (OrderType::Stop, Side::Buy) if candle.high >= order.price => {
+                    order.price.max(candle.open)
+                }
+                (OrderType::Stop, Side::Sell) if candle.low <= order.price => {
+                    order.price.min(candle.open)
+                }
+                _ => continue,
+            };
+            if order.quantity > candle.volume * self.liquidity_fraction {
+                continue;
+            }
+
+            let liquidity = match order.order_type {
+                OrderType::Limit => Liquidity::Maker,
+                _ => Liquidity::Taker,
+            };
+            let result = self.fill(state, &order, &product_id, price, liquidity, time);
+            let resting = &mut state.book[index].1;
+            match result {
+                Ok(trade) => {
+                    resting.status = OrderStatus::Done;
+                    resting.done_reason = Some("filled".to_string());
+                    resting.filled_quantity = trade.get_quantity();
+                    resting.executed_value = trade.get_notional_value();
+                }
+                Err(reason) => {
+                    resting.status = OrderStatus::Rejected;
+                    resting.reject_reason = Some(reason.to_string());
+                }
+            }
+        }
+    }
+
+    /// Settle a fill of the whole order quantity against the balances and record it
+    ///
+    /// # Returns
+    /// The reason the fill was rejected if the pair is invalid, or there is not enough balance to pay for the order
+    /// including fees
+    fn fill(
+        &self,
+        state: &mut PaperMarketState,
+        order: &Order,
+        product_id: &str,
+        price: Decimal,
+        liquidity: Liquidity,
+        time: NaiveDateTime,
+    ) -> Result<ExecutedTrade, &'static str> {
+        let side = order.side;
+        let quantity = order.quantity;
+
+        let (base, quote) = split_pair(product_id).ok_or("Invalid product id")?;
+        let base_balance = state.balances.get(base).cloned().unwrap_or_default();
+        let quote_balance = state.balances.get(quote).cloned().unwrap_or_default();
+
+        let notional_value = price * quantity;
+        let context = FeeContext {
+            side,
+            liquidity,
+            volume_30d: trailing_volume(&state.orders, time),
+        };
+        let fee = self.fee_calculator.calculate_fee(notional_value, &context);
+        let (base_fee, quote_fee) = match self.fee_calculator.fee_currency() {
+            FeeCurrency::Quote => (Decimal::ZERO, fee),
+            FeeCurrency::Base => (fee / price, Decimal::ZERO),
+        };
+
+        let (base_balance, quote_balance) = match side {
+            Side::Buy if notional_value + quote_fee > quote_balance => {
+                return Err("Insufficient funds")
+            }
+            Side::Buy => (
+                base_balance + quantity - base_fee,
+                quote_balance - notional_value - quote_fee,
+            ),
+            Side::Sell if quantity + base_fee > base_balance => return Err("Insufficient funds"),
+            Side::Sell => (
+                base_balance - quantity - base_fee,
+                quote_balance + notional_value - quote_fee,
+            ),
+        };
+        state.balances.insert(base.to_string(), base_balance);
+        state.balances.insert(quote.to_string(), quote_balance);
+
+        let trade = ExecutedTrade::new(
+            order.id.clone(),
+            side,
+            price,
+            quantity,
+            notional_value,
+            time,
+        )
+        .with_order_type(order.order_type);
+        state.orders.push(trade.clone());
+        Ok(trade)
+    }
+
+    /// Get the current time of the replay
+    pub fn current_time(&self) -> Option<NaiveDateTime> {
+        self.state.lock().unwrap().time
+    }
+
+    /// Get the available balance for a currency
+    pub fn get_balance(&self, currency: &str) -> Decimal {
+        self.state
+            .lock()
+            .unwrap()
+            .balances
+            .get(currency)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Get all filled orders
+    pub fn get_orders(&self) -> Vec<ExecutedTrade> {
+        self.state.lock().unwrap().orders.clone()
+    }
+}
+
+/// Split a pair (ie: "BTC-USD") into the base and quote currencies
+fn split_pair(pair: &str) -> Option<(&str, &str)> {
+    pair.split_once('-')
+}
+
+/// Notional value of the orders filled in the [`FEE_VOLUME_DAYS`] up to `time`
+fn trailing_volume(orders: &[ExecutedTrade], time: NaiveDateTime) -> Decimal {
+    let start = time - Duration::days(FEE_VOLUME_DAYS);
+    orders
+        .iter()
+        .filter(|order| *order.get_timestamp() > start)
+        .map(|order| order.get_notional_value())
+        .sum()
+}
+
+fn rejection<S: Into<String>>(message: S) -> MarketError {
+    MarketError::OrderRejected(ReasonCode::MarketRejection, message.into())
+}
+
+#[async_trait]
+impl BaseMarket for PaperMarket {
+    fn name(&self) -> &str {
+        "Paper"
+    }
+
+    async fn get_candles(&self, pair: &str, interval: &str) -> Result<Vec<Candle>, MarketError> {
+        let state = self.state.lock().unwrap();
+        let candles = state
+            .candles
+            .get(&(pair.to_string(), interval.to_string()))
+            .ok_or(MarketError::CandlesUnavailable(
+                pair.to_string(),
+                interval.to_string(),
+            ))?;
+
+        let Some(time) = state.time else {
+            return Ok(vec![]);
+        };
+
+        // candles are returned in descending order to mirror the exchange
+        Ok(candles
+            .iter()
+            .rev()
+            .skip_while(|candle| candle.time > time)
+            .take(MAX_CANDLES)
+            .cloned()
+            .collect())
+    }
+
+    async fn submit_order(
+        &self,
+        order: FutureTrade,
+        product_id: String,
+    ) -> Result<ExecutedTrade, MarketError> {
+        let mut state = self.state.lock().unwrap();
+        let time = state
+            .time
+            .ok_or_else(|| rejection("Market has not started"))?;
+
+        let candle = state
+            .candles
+            .get(&(product_id.clone(), self.interval.clone()))
+            .and_then(|candles| candles.iter().rev().find(|candle| candle.time <= time))
+            .ok_or_else(|| rejection(format!("No market data for {}", product_id)))?;
+        let price = candle.close;
+        let liquidity = candle.volume * self.liquidity_fraction;
+
+        let side = order.get_side();
+        let quantity = order.get_quantity();
+        if quantity <= Decimal::ZERO {
+            return Err(rejection("Order quantity must be positive"));
+        }
+
+        let crossed = match (order.get_order_type(), side) {
+            (OrderType::Market, _) => true,
+            (OrderType::Limit, Side::Buy) => order.get_price() >= price,
+            (OrderType::Limit, Side::Sell) => order.get_price() <= price,
+            (OrderType::Stop, Side::Buy) => order.get_price() <= price,
+            (OrderType::Stop, Side::Sell) => order.get_price() >= price,
+        };
+        let id = state.next_order_id();
+        let order = Order {
+            id: id.clone(),
+            side,
+            order_type: order.get_order_type(),
+            price: order.get_price(),
+            quantity,
+            filled_quantity: Decimal::ZERO,
+            executed_value: Decimal::ZERO,
+            status: OrderStatus::Open,
+            done_reason: None,
+            reject_reason: None,
+            created_at: time,
+        };
+
+        if !crossed {
+            state.book.push((product_id, order));
+            return Err(MarketError::OrderResting(id));
+        }
+        if quantity > liquidity {
+            return Err(rejection("Insufficient liquidity"));
+        }
+        let liquidity = Liquidity::from(order.order_type);
+        self.fill(&mut state, &order, &product_id, price, liquidity, time)
+            .map_err(rejection)
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<Order, MarketError> {
+        let state = self.state.lock().unwrap();
+        if let Some((_, order)) = state.book.iter().find(|(_, order)| order.id == order_id) {
+            return Ok(order.clone());
+        }
+        let trade = state
+            .orders
+            .iter()
+            .find(|trade| trade.get_order_id() == order_id)
+            .ok_or_else(|| MarketError::OrderNotFound(order_id.to_string()))?;
+        Ok(Order {
+            id: order_id.to_string(),
+            side: trade.get_side(),
+            order_type: trade.get_order_type(),
+            price: trade.get_price(),
+            quantity: trade.get_quantity(),
+            filled_quantity: trade.get_quantity(),
+            executed_value: trade.get_notional_value(),
+            status: OrderStatus::Done,
+            done_reason: Some("filled".to_string()),
+            reject_reason: None,
+            created_at: *trade.get_timestamp(),
+        })
+    }
+
+    /// Cancel an order resting on the book
+    ///
+    /// Orders which were filled immediately, or are no longer open, cannot be cancelled.
+    async fn cancel_order(&self, order_id: &str) -> Result<(), MarketError> {
+        let mut state = self.state.lock().unwrap();
+        let filled = state
+            .orders
+            .iter()
+            .any(|trade| trade.get_order_id() == order_id);
+        let resting = state
+            .book
+            .iter_mut()
+            .find(|(_, order)| order.id == order_id);
+        match resting {
+            Some((_, order)) if order.status == OrderStatus::Open => {
+                order.status = OrderStatus::Done;
+                order.done_reason = Some("canceled".to_string());
+                Ok(())
+            }
+            Some(_) => Err(rejection("Order is already done")),
+            None if filled => Err(rejection("Order is already done")),
+            None => Err(MarketError::OrderNotFound(order_id.to_string())),
+        }
+    }
+
+    async fn list_open_orders(&self, product_id: &str) -> Result<Vec<Order>, MarketError> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .book
+            .iter()
+            .filter(|(pair, order)| pair == product_id && order.status == OrderStatus::Open)
+            .map(|(_, order)| order.clone())
+            .collect())
+    }
+}
+#[async_trait]
+impl Market for PaperMarket {
+    type PairType = String;
+    type FeeCalculator = Arc<dyn FeeCalculator + Send + Sync>;
+
+    async fn get_fee_calculator(&self) -> Option<&dyn FeeCalculator> {
+        Some(self.fee_calculator.as_ref())
+    }
+
+    async fn get_trading_pair_info(&self) -> Result<Vec<Self::PairType>, MarketError> {
+        let state = self.state.lock().unwrap();
+        let mut pairs = state
+            .candles
+            .keys()
+            .map(|(pair, _)| pair.clone())
+            .collect::<Vec<_>>();
+        pairs.sort();
+        pairs.dedup();
+        Ok(pairs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markets::utils::save_candles;
+    use crate::markets::MakerTakerFee;
+    use crate::traits::AsDataFrame;
+    use crate::utils::create_temp_dir;
+    use chrono::Duration;
+    use std::fs::remove_dir_all;
+
+    const PAIR: &str = "BTC-USD";
+
+    fn create_candles(n: i64) -> Vec<Candle> {
+        let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        (0..n)
+            .map(|i| Candle {
+                time: start + Duration::minutes(i),
+                open: dec!(100),
+                high: dec!(110),
+                low: dec!(90),
+                close: dec!(100) + Decimal::from(i),
+                volume: dec!(10),
+            })
+            .collect()
+    }
+
+    fn create_market() -> PaperMarket {
+        PaperMarket::new("1m")
+            .with_candles(PAIR, "1m", create_candles(5))
+            .with_balance("USD", dec!(1000))
+    }
+
+    fn buy(price: Decimal, quantity: Decimal) -> FutureTrade {
+        FutureTrade::new(Side::Buy, price, quantity, NaiveDateTime::default())
+    }
+
+    fn sell(price: Decimal, quantity: Decimal) -> FutureTrade {
+        FutureTrade::new(Side::Sell, price, quantity, NaiveDateTime::default())
+    }
+
+    #[tokio::test]
+    async fn test_get_candles_replay() {
+        let market = create_market();
+
+        // nothing is visible before the replay starts
+        assert!(market.get_candles(PAIR, "1m").await.unwrap().is_empty());
+
+        market.advance();
+        market.advance();
+        let candles = market.get_candles(PAIR, "1m").await.unwrap();
+        assert_eq!(candles.len(), 2);
+        // descending order
+        assert!(candles[0].time > candles[1].time);
+        assert_eq!(candles[0].time, market.current_time().unwrap());
+
+        for _ in 0..3 {
+            assert!(market.advance().is_some());
+        }
+        assert!(market.advance().is_none());
+        assert_eq!(market.get_candles(PAIR, "1m").await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_get_candles_unavailable() {
+        let market = create_market();
+        let result = market.get_candles("ETH-USD", "1m").await;
+        assert!(matches!(result, Err(MarketError::CandlesUnavailable(_, _))));
+    }
+
+    #[tokio::test]
+    async fn test_buy_and_sell() {
+        let market = create_market().with_fee_calculator(SimplePercentageFee::new(dec!(1)));
+        market.advance();
+
+        let trade = market
+            .submit_order(buy(dec!(100), dec!(2)), PAIR.to_string())
+            .await
+            .unwrap();
+        assert_eq!(trade.get_price(), dec!(100));
+        assert_eq!(trade.get_quantity(), dec!(2));
+        assert_eq!(market.get_balance("BTC"), dec!(2));
+        assert_eq!(market.get_balance("USD"), dec!(798));
+
+        // price moves to 101
+        market.advance();
+        market
+            .submit_order(sell(dec!(101), dec!(2)), PAIR.to_string())
+            .await
+            .unwrap();
+        assert_eq!(market.get_balance("BTC"), dec!(0));
+        assert_eq!(market.get_balance("USD"), dec!(798) + dec!(199.98));
+        assert_eq!(market.get_orders().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reject_insufficient_funds() {
+        let market = create_market();
+        market.advance();
+
+        let result = market
+            .submit_order(buy(dec!(100), dec!(10.1)), PAIR.to_string())
+            .await;
+        let error = result.unwrap_err();
+        assert_eq!(error.reason_code(), ReasonCode::MarketRejection);
+
+        let result = market
+            .submit_order(sell(dec!(100), dec!(1)), PAIR.to_string())
+            .await;
+        assert!(matches!(
+            result,
+            Err(MarketError::OrderRejected(ReasonCode::MarketRejection, _))
+        ));
+
+        // balances are untouched
+        assert_eq!(market.get_balance("USD"), dec!(1000));
+        assert!(market.get_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reject_insufficient_liquidity() {
+        let market = create_market()
+            .with_balance("USD", dec!(100000))
+            .with_liquidity_fraction(dec!(0.5));
+        market.advance();
+
+        let result = market
+            .submit_order(buy(dec!(100), dec!(6)), PAIR.to_string())
+            .await;
+        assert!(matches!(
+            result,
+            Err(MarketError::OrderRejected(ReasonCode::MarketRejection, _))
+        ));
+
+        let result = market
+            .submit_order(buy(dec!(100), dec!(5)), PAIR.to_string())
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_limit_price() {
+        let market = create_market();
+        market.advance();
+        market.advance();
+
+        // market price is 101, so the order rests on the book
+        let result = market
+            .submit_order(buy(dec!(100), dec!(1)), PAIR.to_string())
+            .await;
+        assert!(matches!(result, Err(MarketError::OrderResting(_))));
+
+        let result = market
+            .submit_order(buy(dec!(102), dec!(1)), PAIR.to_string())
+            .await;
+        assert_eq!(result.unwrap().get_price(), dec!(101));
+    }
+
+    #[tokio::test]
+    async fn test_market_and_stop_orders() {
+        let market = create_market().with_balance("BTC", dec!(5));
+        market.advance();
+        market.advance();
+
+        // market price is 101. Market orders ignore the trade price.
+        let trade = market
+            .submit_order(
+                buy(dec!(50), dec!(1)).with_order_type(OrderType::Market),
+                PAIR.to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(trade.get_price(), dec!(101));
+        let order = market.get_order(trade.get_order_id()).await.unwrap();
+        assert_eq!(order.order_type, OrderType::Market);
+
+        // stop-loss is not triggered until the price falls to the stop price
+        let result = market
+            .submit_order(
+                sell(dec!(100), dec!(1)).with_order_type(OrderType::Stop),
+                PAIR.to_string(),
+            )
+            .await;
+        assert!(matches!(result, Err(MarketError::OrderResting(_))));
+
+        let trade = market
+            .submit_order(
+                sell(dec!(102), dec!(1)).with_order_type(OrderType::Stop),
+                PAIR.to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(trade.get_price(), dec!(101));
+    }
+
+    #[tokio::test]
+    async fn test_resting_orders() {
+        let market = create_market()
+            .with_balance("BTC", dec!(1))
+            .with_fee_calculator(MakerTakerFee::new(dec!(1), dec!(2)));
+        market.advance();
+
+        // market price is 100, so none of the orders cross
+        let resting = |result: Result<ExecutedTrade, MarketError>| match result {
+            Err(MarketError::OrderResting(id)) => id,
+            result => panic!("Expected resting order: {:?}", result),
+        };
+        let limit = resting(
+            market
+                .submit_order(buy(dec!(95), dec!(2)), PAIR.to_string())
+                .await,
+        );
+        let stop = resting(
+            market
+                .submit_order(
+                    sell(dec!(95), dec!(1)).with_order_type(OrderType::Stop),
+                    PAIR.to_string(),
+                )
+                .await,
+        );
+        let far = resting(
+            market
+                .submit_order(buy(dec!(50), dec!(1)), PAIR.to_string())
+                .await,
+        );
+        assert_eq!(market.list_open_orders(PAIR).await.unwrap().len(), 3);
+        assert!(market.list_open_orders("ETH-USD").await.unwrap().is_empty());
+        assert_eq!(market.get_balance("USD"), dec!(1000));
+
+        // the next candle trades down to 90, which fills both orders at their price
+        market.advance();
+        let order = market.get_order(&limit).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Done);
+        assert_eq!(order.filled_quantity, dec!(2));
+        assert_eq!(order.average_price(), dec!(95));
+        let order = market.get_order(&stop).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Done);
+        assert_eq!(order.order_type, OrderType::Stop);
+        assert_eq!(order.average_price(), dec!(95));
+
+        // the resting limit order pays the maker fee and the stop order the taker fee
+        assert_eq!(market.get_balance("BTC"), dec!(2));
+        assert_eq!(
+            market.get_balance("USD"),
+            dec!(1000) - dec!(190) - dec!(1.9) + dec!(95) - dec!(1.9)
+        );
+        assert_eq!(market.get_orders().len(), 2);
+
+        // orders which are not crossed stay open until cancelled
+        let open = market.list_open_orders(PAIR).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, far);
+        market.cancel_order(&far).await.unwrap();
+        assert!(market.list_open_orders(PAIR).await.unwrap().is_empty());
+        let order = market.get_order(&far).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Done);
+        assert_eq!(order.done_reason, Some("canceled".to_string()));
+        assert_eq!(order.filled_quantity, dec!(0));
+        assert!(market.cancel_order(&far).await.is_err());
+        assert!(market.cancel_order(&limit).await.is_err());
+
+        // orders which cannot be paid for when crossed are rejected
+        let unfunded = resting(
+            market
+                .submit_order(buy(dec!(95), dec!(9.9)), PAIR.to_string())
+                .await,
+        );
+        market.advance();
+        let order = market.get_order(&unfunded).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Rejected);
+        assert_eq!(order.reject_reason, Some("Insufficient funds".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_get_order() {
+        let market = create_market();
+        market.advance();
+
+        let trade = market
+            .submit_order(buy(dec!(100), dec!(1)), PAIR.to_string())
+            .await
+            .unwrap();
+        let order = market.get_order(trade.get_order_id()).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Done);
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.filled_quantity, dec!(1));
+        assert_eq!(order.average_price(), dec!(100));
+
+        assert!(market.cancel_order(trade.get_order_id()).await.is_err());
+        assert!(market.list_open_orders(PAIR).await.unwrap().is_empty());
+        assert!(matches!(
+            market.get_order("unknown").await,
+            Err(MarketError::OrderNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_with_csv() {
+        let path = create_temp_dir(Path::new("paper_market_testing"));
+        let file_path = path.join("candles.csv");
+
+        let candles = create_candles(5);
+        save_candles(&mut candles.as_dataframe(), file_path.to_str().unwrap()).unwrap();
+
+        let market = PaperMarket::new("1m")
+            .with_csv(PAIR, "1m", &file_path)
+            .unwrap();
+        while market.advance().is_some() {}
+
+        let mut loaded = market.get_candles(PAIR, "1m").await.unwrap();
+        loaded.reverse();
+        assert_eq!(loaded, candles);
+
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_trading_pair_info() {
+        let market = create_market().with_candles("ETH-USD", "1m", create_candles(1));
+        let pairs = market.get_trading_pair_info().await.unwrap();
+        assert_eq!(pairs, vec!["BTC-USD".to_string(), "ETH-USD".to_string()]);
+    }
+}
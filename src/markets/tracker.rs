@@ -0,0 +1,326 @@
+use crate::markets::BaseMarket;
+use crate::types::{
+    ExecutedTrade, FailedTrade, FutureTrade, Order, OrderStatus, OrderType, ReasonCode,
+};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use std::time::{Duration, Instant};
+
+/// Default time between polls of the order state
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default time to wait for an order to be done before it is cancelled
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Follows a submitted order until it is done
+///
+/// The market is polled with [`BaseMarket::get_order`] until the order is done or rejected. Orders
+/// which are still open after the timeout are cancelled. The final state of the order is mapped to
+/// an [`ExecutedTrade`] for the filled quantity, or a [`FailedTrade`] if nothing was filled.
+///
+/// Stop orders wait on the book until their price is reached, so they are polled once and never
+/// cancelled.
+#[derive(Debug, Clone)]
+pub struct OrderTracker {
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl Default for OrderTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_POLL_INTERVAL, DEFAULT_TIMEOUT)
+    }
+}
+
+impl OrderTracker {
+    pub fn new(poll_interval: Duration, timeout: Duration) -> Self {
+        Self {
+            poll_interval,
+            timeout,
+        }
+    }
+
+    /// Poll the market until the order is done
+    ///
+    /// Errors while polling are logged and retried until the timeout.
+    ///
+    /// # Arguments
+    /// * `market` - The market the order was submitted to
+    /// * `order_id` - The id assigned to the order by the market
+    /// * `trade` - The trade the order was created from. Used to record failed trades.
+    ///
+    /// # Returns
+    /// A [`FailedTrade`] with [`ReasonCode::Resting`] if a stop order has not been triggered
+    pub async fn track<M: BaseMarket + Sync>(
+        &self,
+        market: &M,
+        order_id: &str,
+        trade: FutureTrade,
+    ) -> Result<ExecutedTrade, FailedTrade> {
+        if trade.get_order_type() == OrderType::Stop {
+            return match market.get_order(order_id).await {
+                Ok(order) if order.status != OrderStatus::Open => Self::resolve(&order, trade),
+                _ => {
+                    info!("Order {} is resting until its stop price", order_id);
+                    Err(FailedTrade::with_future_trade(ReasonCode::Resting, trade))
+                }
+            };
+        }
+
+        let start = Instant::now();
+        loop {
+            match market.get_order(order_id).await {
+                Ok(order) if order.status != OrderStatus::Open => {
+                    return Self::resolve(&order, trade)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Error while polling order {}: {}", order_id, e),
+            }
+            if start.elapsed() >= self.timeout {
+                break;
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        info!("Cancelling order {} after timeout", order_id);
+        if let Err(e) = market.cancel_order(order_id).await {
+            warn!("Error while cancelling order {}: {}", order_id, e);
+        }
+
+        // the order may have been partially filled before it was cancelled
+        match market.get_order(order_id).await {
+            Ok(order) if order.filled_quantity > Decimal::ZERO => Self::resolve(&order, trade),
+            _ => Err(FailedTrade::with_future_trade(
+                ReasonCode::MarketRejection,
+                trade,
+            )),
+        }
+    }
+
+    /// Map the state of an order to an executed or failed trade
+    ///
+    /// Any filled quantity is returned as an [`ExecutedTrade`] at the average fill price, regardless
+    /// of `done_reason`. Rejections are mapped to a [`ReasonCode`] using `reject_reason`.
+    pub fn resolve(order: &Order, trade: FutureTrade) -> Result<ExecutedTrade, FailedTrade> {
+        if order.filled_quantity > Decimal::ZERO {
+            return Ok(ExecutedTrade::new(
+                order.id.clone(),
+                order.side,
+                order.average_price(),
+                order.filled_quantity,
+                order.executed_value,
+                order.created_at,
+            )
+            .with_order_type(order.order_type));
+        }
+
+        let reason = match order.status {
+            OrderStatus::Rejected => reject_reason_code(order.reject_reason.as_deref()),
+            _ => match order.done_reason.as_deref() {
+                Some("rejected") => reject_reason_code(order.reject_reason.as_deref()),
+                _ => ReasonCode::MarketRejection,
+            },
+        };
+        info!("Order {} was not filled: {:?}", order.id, reason);
+        Err(FailedTrade::with_future_trade(reason, trade))
+    }
+}
+
+/// Map a market provided reject reason to a [`ReasonCode`]
+fn reject_reason_code(reject_reason: Option<&str>) -> ReasonCode {
+    match reject_reason {
+        Some(reason) if reason.to_lowercase().contains("insufficient") => {
+            ReasonCode::InsufficientFunds
+        }
+        _ => ReasonCode::MarketRejection,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markets::MarketError;
+    use crate::types::{Candle, OrderType, Side, Trade};
+    use async_trait::async_trait;
+    use chrono::NaiveDateTime;
+    use rust_decimal_macros::dec;
+    use std::sync::{Arc, Mutex};
+
+    /// Market which reports a fixed sequence of order states
+    #[derive(Clone)]
+    struct SequenceMarket {
+        states: Arc<Mutex<Vec<Order>>>,
+        cancelled: Arc<Mutex<bool>>,
+    }
+
+    impl SequenceMarket {
+        fn new(states: Vec<Order>) -> Self {
+            Self {
+                states: Arc::new(Mutex::new(states)),
+                cancelled: Arc::new(Mutex::new(false)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl BaseMarket for SequenceMarket {
+        fn name(&self) -> &str {
+            "Sequence"
+        }
+
+        async fn get_candles(&self, _: &str, _: &str) -> Result<Vec<Candle>, MarketError> {
+            Ok(vec![])
+        }
+
+        async fn submit_order(
+            &self,
+            _: FutureTrade,
+            _: String,
+        ) -> Result<ExecutedTrade, MarketError> {
+            Err(MarketError::OrderRejected(
+                ReasonCode::MarketRejection,
+                "Orders cannot be submitted".to_string(),
+            ))
+        }
+
+        async fn get_order(&self, order_id: &str) -> Result<Order, MarketError> {
+            let mut states = self.states.lock().unwrap();
+            match states.len() {
+                0 => Err(MarketError::OrderNotFound(order_id.to_string())),
+                1 => Ok(states[0].clone()),
+                _ => Ok(states.remove(0)),
+            }
+        }
+
+        async fn cancel_order(&self, _: &str) -> Result<(), MarketError> {
+            *self.cancelled.lock().unwrap() = true;
+            Ok(())
+        }
+
+        async fn list_open_orders(&self, _: &str) -> Result<Vec<Order>, MarketError> {
+            Ok(vec![])
+        }
+    }
+
+    fn time() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn trade() -> FutureTrade {
+        FutureTrade::new(Side::Buy, dec!(100), dec!(2), time())
+    }
+
+    fn order(status: OrderStatus, filled_quantity: Decimal) -> Order {
+        Order {
+            id: "order".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: dec!(100),
+            quantity: dec!(2),
+            filled_quantity,
+            executed_value: filled_quantity * dec!(99),
+            status,
+            done_reason: None,
+            reject_reason: None,
+            created_at: time(),
+        }
+    }
+
+    fn tracker() -> OrderTracker {
+        OrderTracker::new(Duration::from_millis(1), Duration::from_millis(50))
+    }
+
+    #[tokio::test]
+    async fn test_track_filled() {
+        let filled = Order {
+            done_reason: Some("filled".to_string()),
+            ..order(OrderStatus::Done, dec!(2))
+        };
+        let market = SequenceMarket::new(vec![
+            order(OrderStatus::Open, dec!(0)),
+            order(OrderStatus::Open, dec!(1)),
+            filled,
+        ]);
+
+        let executed = tracker().track(&market, "order", trade()).await.unwrap();
+        assert_eq!(executed.get_order_id(), "order");
+        assert_eq!(executed.get_quantity(), dec!(2));
+        assert_eq!(executed.get_price(), dec!(99));
+        assert_eq!(executed.get_notional_value(), dec!(198));
+        assert!(!*market.cancelled.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_track_rejected() {
+        let rejected = Order {
+            reject_reason: Some("Insufficient funds".to_string()),
+            ..order(OrderStatus::Rejected, dec!(0))
+        };
+        let market = SequenceMarket::new(vec![rejected]);
+
+        let failed = tracker()
+            .track(&market, "order", trade())
+            .await
+            .unwrap_err();
+        assert_eq!(failed.get_reason(), ReasonCode::InsufficientFunds);
+        assert_eq!(failed.get_quantity(), dec!(2));
+    }
+
+    #[tokio::test]
+    async fn test_track_cancelled_by_market() {
+        let cancelled = Order {
+            done_reason: Some("canceled".to_string()),
+            ..order(OrderStatus::Done, dec!(0))
+        };
+        let market = SequenceMarket::new(vec![cancelled]);
+
+        let failed = tracker()
+            .track(&market, "order", trade())
+            .await
+            .unwrap_err();
+        assert_eq!(failed.get_reason(), ReasonCode::MarketRejection);
+    }
+
+    #[tokio::test]
+    async fn test_track_timeout_cancels_order() {
+        let market = SequenceMarket::new(vec![order(OrderStatus::Open, dec!(0))]);
+
+        let failed = tracker()
+            .track(&market, "order", trade())
+            .await
+            .unwrap_err();
+        assert_eq!(failed.get_reason(), ReasonCode::MarketRejection);
+        assert!(*market.cancelled.lock().unwrap());
+
+        // partial fills are kept when the order is cancelled
+        let market = SequenceMarket::new(vec![order(OrderStatus::Open, dec!(1))]);
+        let executed = tracker().track(&market, "order", trade()).await.unwrap();
+        assert_eq!(executed.get_quantity(), dec!(1));
+        assert!(*market.cancelled.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_track_stop_order_is_not_cancelled() {
+        let stop = trade().with_order_type(OrderType::Stop);
+        let market = SequenceMarket::new(vec![order(OrderStatus::Open, dec!(0))]);
+
+        // the order is left on the book without waiting for the timeout
+        let tracker = OrderTracker::new(Duration::from_secs(1), Duration::from_secs(60));
+        let failed = tracker
+            .track(&market, "order", stop.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(failed.get_reason(), ReasonCode::Resting);
+        assert!(!*market.cancelled.lock().unwrap());
+
+        // stops which were triggered immediately are resolved
+        let filled = Order {
+            done_reason: Some("filled".to_string()),
+            ..order(OrderStatus::Done, dec!(2))
+        };
+        let market = SequenceMarket::new(vec![filled]);
+        let executed = tracker.track(&market, "order", stop).await.unwrap();
+        assert_eq!(executed.get_quantity(), dec!(2));
+        assert!(!*market.cancelled.lock().unwrap());
+    }
+}
@@ -1,4 +1,5 @@
 use polars::prelude::*;
+use std::path::Path;
 
 pub fn save_candles(candles: &mut DataFrame, path: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = std::fs::File::create(path).unwrap();
@@ -6,3 +7,26 @@ pub fn save_candles(candles: &mut DataFrame, path: &str) -> Result<(), Box<dyn s
 
     Ok(())
 }
+
+/// Read candles previously written by [`save_candles`]
+///
+/// The "time" column is parsed as a millisecond datetime and all price and volume columns are
+/// cast to `f64` so that the output is compatible with [`crate::utils::extract_candles_from_df`].
+pub fn read_candles(path: &Path) -> PolarsResult<DataFrame> {
+    let df = CsvReadOptions::default()
+        .with_has_header(true)
+        .with_parse_options(CsvParseOptions::default().with_try_parse_dates(true))
+        .try_into_reader_with_file_path(Some(path.into()))?
+        .finish()?;
+
+    df.lazy()
+        .select([
+            col("time").cast(DataType::Datetime(TimeUnit::Milliseconds, None)),
+            col("open").cast(DataType::Float64),
+            col("high").cast(DataType::Float64),
+            col("low").cast(DataType::Float64),
+            col("close").cast(DataType::Float64),
+            col("volume").cast(DataType::Float64),
+        ])
+        .collect()
+}
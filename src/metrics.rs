@@ -0,0 +1,182 @@
+/// Performance metrics of returns and equity curves
+///
+/// Shared by risk calculation, backtesting and the portfolio so that every ratio and drawdown is measured the same
+/// way. Drawdowns are fractions of the peak, so `0.2` is a 20% decline.
+use rust_decimal::prelude::*;
+
+/// Simple returns between consecutive values
+///
+/// The return following a value of zero is zero, so there is a return for every pair of values.
+pub fn returns(values: &[Decimal]) -> Vec<Decimal> {
+    values
+        .windows(2)
+        .map(|window| {
+            let [previous, current] = window else {
+                unreachable!()
+            };
+            if previous.is_zero() {
+                Decimal::ZERO
+            } else {
+                (current - previous) / previous
+            }
+        })
+        .collect()
+}
+
+/// Decline of `value` from `peak`
+///
+/// Zero when the value is at or above the peak, or the peak is not positive.
+pub fn drawdown(peak: Decimal, value: Decimal) -> Decimal {
+    if peak > Decimal::ZERO {
+        ((peak - value) / peak).max(Decimal::ZERO)
+    } else {
+        Decimal::ZERO
+    }
+}
+
+/// Drawdown of every value of an equity curve from the highest value up to that point
+pub fn drawdowns(equity: &[Decimal]) -> Vec<Decimal> {
+    let mut peak = Decimal::MIN;
+    equity
+        .iter()
+        .map(|value| {
+            peak = peak.max(*value);
+            drawdown(peak, *value)
+        })
+        .collect()
+}
+
+/// Largest decline of equity from a previous peak
+pub fn max_drawdown(equity: &[Decimal]) -> Decimal {
+    drawdowns(equity).into_iter().max().unwrap_or(Decimal::ZERO)
+}
+
+/// Annualized Sharpe ratio of per-period returns
+///
+/// The mean return in excess of `risk_free_return` over the sample standard deviation of returns, annualized by
+/// the square root of `periods_per_year`. Zero with fewer than two returns or no volatility.
+///
+/// # Arguments
+/// * `returns` - Return of each period
+/// * `risk_free_return` - Risk-free return over a single period
+/// * `periods_per_year` - Number of periods in a year
+pub fn sharpe_ratio(
+    returns: &[Decimal],
+    risk_free_return: Decimal,
+    periods_per_year: Decimal,
+) -> Decimal {
+    if returns.len() < 2 {
+        return Decimal::ZERO;
+    }
+    let mean = returns.iter().sum::<Decimal>() / Decimal::from(returns.len());
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<Decimal>()
+        / Decimal::from(returns.len() - 1);
+    let std_dev = variance.sqrt().unwrap_or_default();
+
+    if std_dev.is_zero() {
+        Decimal::ZERO
+    } else {
+        (mean - risk_free_return) / std_dev * periods_per_year.sqrt().unwrap_or_default()
+    }
+}
+
+/// Annualized Sortino ratio of per-period returns
+///
+/// Like [`sharpe_ratio`], but only returns below `risk_free_return` count as volatility. The downside deviation is
+/// the root mean square of the shortfalls over all returns. Zero with fewer than two returns or no shortfall.
+pub fn sortino_ratio(
+    returns: &[Decimal],
+    risk_free_return: Decimal,
+    periods_per_year: Decimal,
+) -> Decimal {
+    if returns.len() < 2 {
+        return Decimal::ZERO;
+    }
+    let mean_excess =
+        returns.iter().sum::<Decimal>() / Decimal::from(returns.len()) - risk_free_return;
+    let downside_variance = returns
+        .iter()
+        .map(|r| (r - risk_free_return).min(Decimal::ZERO).powi(2))
+        .sum::<Decimal>()
+        / Decimal::from(returns.len());
+    let downside_deviation = downside_variance.sqrt().unwrap_or_default();
+
+    if downside_deviation.is_zero() {
+        Decimal::ZERO
+    } else {
+        mean_excess / downside_deviation * periods_per_year.sqrt().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_returns() {
+        assert_eq!(
+            returns(&[dec!(100), dec!(110), dec!(0), dec!(50)]),
+            vec![dec!(0.1), dec!(-1), dec!(0)]
+        );
+        assert!(returns(&[dec!(100)]).is_empty());
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        let equity = [
+            dec!(100),
+            dec!(120),
+            dec!(90),
+            dec!(100),
+            dec!(130),
+            dec!(125),
+        ];
+        assert_eq!(max_drawdown(&equity), dec!(0.25));
+        assert_eq!(drawdowns(&equity)[3], dec!(1) - dec!(100) / dec!(120));
+        assert_eq!(drawdowns(&equity)[4], dec!(0));
+        assert_eq!(max_drawdown(&[dec!(100), dec!(110)]), dec!(0));
+        assert_eq!(max_drawdown(&[]), dec!(0));
+        assert_eq!(drawdown(dec!(0), dec!(-10)), dec!(0));
+    }
+
+    #[test]
+    fn test_sharpe_ratio() {
+        let returns = [dec!(0.01), dec!(-0.01), dec!(0.02), dec!(0.02)];
+        let per_period = sharpe_ratio(&returns, dec!(0), dec!(1));
+        assert!(per_period > dec!(0));
+
+        // annualized by the square root of the periods in a year
+        let annualized = sharpe_ratio(&returns, dec!(0), dec!(4));
+        assert!((annualized - per_period * dec!(2)).abs() < dec!(0.000001));
+
+        // excess returns over the risk-free return
+        assert!(sharpe_ratio(&returns, dec!(0.005), dec!(1)) < per_period);
+
+        assert_eq!(sharpe_ratio(&[dec!(0.01)], dec!(0), dec!(1)), dec!(0));
+        assert_eq!(
+            sharpe_ratio(&[dec!(0.01), dec!(0.01)], dec!(0), dec!(1)),
+            dec!(0)
+        );
+    }
+
+    #[test]
+    fn test_sortino_ratio() {
+        let returns = [dec!(0.01), dec!(-0.01), dec!(0.02), dec!(0.02)];
+        // only the loss counts as volatility
+        let sortino = sortino_ratio(&returns, dec!(0), dec!(1));
+        assert!((sortino - dec!(2)).abs() < dec!(0.000001));
+        assert!(sortino > sharpe_ratio(&returns, dec!(0), dec!(1)));
+
+        // shortfalls are measured against the risk-free return
+        assert!(sortino_ratio(&returns, dec!(0.015), dec!(1)) < dec!(0));
+
+        let annualized = sortino_ratio(&returns, dec!(0), dec!(4));
+        assert!((annualized - sortino * dec!(2)).abs() < dec!(0.000001));
+
+        assert_eq!(
+            sortino_ratio(&[dec!(0.01), dec!(0.02)], dec!(0), dec!(1)),
+            dec!(0)
+        );
+    }
+}
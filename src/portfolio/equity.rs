@@ -0,0 +1,114 @@
+use crate::metrics;
+use crate::portfolio::{AssetHandlers, CapitalHandlers, Portfolio};
+use crate::types::Candle;
+use rust_decimal::Decimal;
+
+/// Interface methods for the mark-to-market equity of a portfolio.
+///
+/// Equity is the available capital plus the value of assets at the latest price. It is recorded with
+/// [`EquityHandlers::update_equity`], usually once per candle. Drawdowns are fractions of the peak equity, so
+/// `0.2` is a 20% decline.
+pub trait EquityHandlers {
+    fn update_equity(&mut self, price: Decimal) -> Decimal;
+    fn current_drawdown(&self) -> Decimal;
+    fn equity_at(&self, candles: &[Candle]) -> Vec<Decimal>;
+}
+
+impl EquityHandlers for Portfolio {
+    /// Record the equity at the given asset price
+    ///
+    /// # Arguments
+    /// * `price` - Current price of the asset
+    ///
+    /// # Returns
+    /// The recorded equity
+    fn update_equity(&mut self, price: Decimal) -> Decimal {
+        let equity = self.available_capital() + self.get_assets() * price;
+        self.current_equity = Some(equity);
+        let peak = self.peak_equity.map_or(equity, |peak| peak.max(equity));
+        self.peak_equity = Some(peak);
+        equity
+    }
+
+    /// Decline of the current equity from the peak equity
+    ///
+    /// Zero when no equity has been recorded.
+    fn current_drawdown(&self) -> Decimal {
+        match (self.current_equity, self.peak_equity) {
+            (Some(current), Some(peak)) => metrics::drawdown(peak, current),
+            _ => Decimal::ZERO,
+        }
+    }
+
+    /// Mark-to-market equity at the close of each candle
+    ///
+    /// Unlike the recorded equity, this is rebuilt from the capital and assets held at the time of each candle, so
+    /// it does not depend on [`EquityHandlers::update_equity`] being called.
+    ///
+    /// # Arguments
+    /// * `candles` - Candles in ascending order
+    fn equity_at(&self, candles: &[Candle]) -> Vec<Decimal> {
+        let points = candles.iter().map(|candle| candle.time).collect::<Vec<_>>();
+        let capital = self.capital_ts.values_at(&points);
+        let assets = self.assets_ts.values_at(&points);
+
+        candles
+            .iter()
+            .zip(capital.iter().zip(assets.iter()))
+            .map(|(candle, (capital, assets))| capital + assets * candle.close)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+    use crate::portfolio::TradeHandlers;
+    use crate::types::{ExecutedTrade, Side};
+    use crate::utils::extract_candles_from_df;
+    use chrono::Duration;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_update_equity() {
+        let mut portfolio = Portfolio::new(dec!(2), dec!(100), None);
+        assert_eq!(portfolio.current_drawdown(), dec!(0));
+
+        assert_eq!(portfolio.update_equity(dec!(50)), dec!(200));
+        assert_eq!(portfolio.current_drawdown(), dec!(0));
+
+        assert_eq!(portfolio.update_equity(dec!(25)), dec!(150));
+        assert_eq!(portfolio.current_drawdown(), dec!(0.25));
+
+        // drawdowns are measured from the peak equity
+        portfolio.update_equity(dec!(45));
+        assert_eq!(portfolio.current_drawdown(), dec!(0.05));
+
+        // a new peak ends the drawdown
+        portfolio.update_equity(dec!(60));
+        assert_eq!(portfolio.current_drawdown(), dec!(0));
+        portfolio.update_equity(dec!(55));
+        assert_eq!(portfolio.current_drawdown(), dec!(10) / dec!(220));
+    }
+
+    #[test]
+    fn test_equity_at() {
+        let candles = extract_candles_from_df(&create_candles(&[10.0, 12.0, 11.0, 9.0])).unwrap();
+
+        // buy 10 assets at the close of the second candle
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), candles[0].time);
+        portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+            "buy".to_string(),
+            Side::Buy,
+            dec!(12),
+            dec!(10),
+            candles[0].time + Duration::minutes(1),
+        ));
+
+        assert_eq!(
+            portfolio.equity_at(&candles),
+            vec![dec!(1000), dec!(1000), dec!(990), dec!(970)]
+        );
+    }
+}
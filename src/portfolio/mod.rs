@@ -1,19 +1,23 @@
 mod assets;
 mod capital;
+mod equity;
 mod position;
 mod tracked;
 mod trade;
 
 pub use assets::AssetHandlers;
 pub use capital::CapitalHandlers;
+pub use equity::EquityHandlers;
 pub use position::PositionHandlers;
 use std::collections::{BTreeMap, HashMap};
 pub use trade::TradeHandlers;
 
-use crate::markets::FeeCalculator;
+use crate::markets::{
+    FeeCalculator, FeeConfig, FeeContext, FeeCurrency, Liquidity, FEE_VOLUME_DAYS,
+};
 use crate::portfolio::tracked::TrackedValue;
-use crate::types::{ExecutedTrade, FailedTrade};
-use chrono::{NaiveDateTime, Utc};
+use crate::types::{ExecutedTrade, FailedTrade, OrderType, Side, Trade};
+use chrono::{Duration, NaiveDateTime, Utc};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
@@ -36,8 +40,7 @@ pub const DEFAULT_THRESHOLD: Decimal = dec!(0.5);
 ///    assets: 0.0,
 ///    capital: 100.0,
 ///    threshold: 0.25,
-///    open_positions_limit: 2,
-///    timeout: 60 * 2,
+///    fees: None,
 /// };
 ///
 /// // create a new Portfolio using the `from_args` constructor
@@ -61,6 +64,12 @@ pub struct PortfolioArgs {
     pub assets: Decimal,
     pub capital: Decimal,
     pub threshold: Decimal,
+
+    /// Fees charged on every executed trade. Read from the `[portfolio.fees]` table.
+    ///
+    /// No fees are charged when this is not provided.
+    #[serde(default)]
+    pub fees: Option<FeeConfig>,
 }
 impl Default for PortfolioArgs {
     fn default() -> Self {
@@ -68,6 +77,7 @@ impl Default for PortfolioArgs {
             assets: dec!(0.0),
             capital: dec!(100.0),
             threshold: DEFAULT_THRESHOLD,
+            fees: None,
         }
     }
 }
@@ -78,6 +88,12 @@ pub struct OpenPosition {
     pub quantity: Decimal,
     pub entry_time: NaiveDateTime,
     pub order_id: String,
+
+    /// Highest price seen since the position was opened
+    pub high_water_mark: Decimal,
+
+    /// Position was selected to be closed by the next sell
+    pub exit_pending: bool,
 }
 
 /// This struct is used to manage an entire portfolio for a given asset.
@@ -93,6 +109,15 @@ pub struct Portfolio {
     assets_ts: TrackedValue,
     capital_ts: TrackedValue,
 
+    /// Cumulative fees paid for executed trades
+    fees_ts: TrackedValue,
+    /// Fee paid for each executed trade, keyed by the trade timestamp
+    trade_fees: HashMap<NaiveDateTime, Decimal>,
+
+    /// Latest and highest mark-to-market equity recorded by [`EquityHandlers::update_equity`]
+    current_equity: Option<Decimal>,
+    peak_equity: Option<Decimal>,
+
     total_position_notional_value: Decimal,
     average_entry_price: Decimal,
 
@@ -109,6 +134,11 @@ impl Default for Portfolio {
             threshold: DEFAULT_THRESHOLD,
             assets_ts: TrackedValue::default(),
             capital_ts: TrackedValue::default(),
+            fees_ts: TrackedValue::with_initial(dec!(0), None),
+            trade_fees: HashMap::new(),
+
+            current_equity: None,
+            peak_equity: None,
 
             total_position_notional_value: dec!(0),
             average_entry_price: dec!(0),
@@ -130,16 +160,22 @@ impl Portfolio {
         Portfolio {
             assets_ts: TrackedValue::with_initial(assets, point),
             capital_ts: TrackedValue::with_initial(capital, point),
+            fees_ts: TrackedValue::with_initial(dec!(0), point),
             ..Default::default()
         }
     }
 
     pub fn from_args(args: &PortfolioArgs, start_time: NaiveDateTime) -> Self {
+        let fee_calculator = args
+            .fees
+            .as_ref()
+            .map(|fees| fees.build() as Box<dyn FeeCalculator>);
         Self {
             threshold: args.threshold,
             assets_ts: TrackedValue::with_initial(args.assets, start_time),
             capital_ts: TrackedValue::with_initial(args.capital, start_time),
-            fee_calculator: None,
+            fees_ts: TrackedValue::with_initial(dec!(0), start_time),
+            fee_calculator,
             ..Default::default()
         }
     }
@@ -181,14 +217,92 @@ impl Portfolio {
     pub fn set_threshold(&mut self, threshold: Decimal) {
         self.threshold = threshold;
     }
+
+    /// Calculate the fee charged for a trade with the given notional value
+    ///
+    /// The fee is denominated in quote currency. The liquidity of the trade is determined by the
+    /// order type, and the trailing volume is measured up to the most recent executed trade.
+    ///
+    /// Returns zero if there is no fee calculator.
+    pub fn calculate_fee(
+        &self,
+        notional_value: Decimal,
+        side: Side,
+        order_type: OrderType,
+    ) -> Decimal {
+        let point = self.executed_trades.keys().max().copied();
+        self.calculate_fee_at(notional_value, side, order_type, point)
+    }
+
+    /// Calculate the fee charged for a trade executed at `point`
+    pub(crate) fn calculate_fee_at(
+        &self,
+        notional_value: Decimal,
+        side: Side,
+        order_type: OrderType,
+        point: Option<NaiveDateTime>,
+    ) -> Decimal {
+        match &self.fee_calculator {
+            Some(calculator) => {
+                let context = FeeContext {
+                    side,
+                    liquidity: Liquidity::from(order_type),
+                    volume_30d: point.map_or(Decimal::ZERO, |point| self.trailing_volume(point)),
+                };
+                calculator.calculate_fee(notional_value, &context)
+            }
+            None => Decimal::ZERO,
+        }
+    }
+
+    /// The amount of quote currency a buy costs or a sell yields, including fees
+    ///
+    /// Fees which are deducted from the base asset do not affect the quote currency amount.
+    pub fn cost_including_fee(
+        &self,
+        notional_value: Decimal,
+        side: Side,
+        order_type: OrderType,
+    ) -> Decimal {
+        if self.fee_currency() == FeeCurrency::Base {
+            return notional_value;
+        }
+        let fee = self.calculate_fee(notional_value, side, order_type);
+        match side {
+            Side::Buy => notional_value + fee,
+            Side::Sell => notional_value - fee,
+        }
+    }
+
+    /// The currency fees are deducted from
+    pub fn fee_currency(&self) -> FeeCurrency {
+        self.fee_calculator
+            .as_ref()
+            .map_or(FeeCurrency::Quote, |calculator| calculator.fee_currency())
+    }
+
+    /// Notional value of trades executed in the [`FEE_VOLUME_DAYS`] before `point`
+    pub fn trailing_volume(&self, point: NaiveDateTime) -> Decimal {
+        let start = point - Duration::days(FEE_VOLUME_DAYS);
+        self.executed_trades
+            .iter()
+            .filter(|(time, _)| **time > start && **time <= point)
+            .map(|(_, trade)| trade.get_notional_value())
+            .sum()
+    }
+
+    /// Total fees paid for all executed trades
+    pub fn total_fees(&self) -> Decimal {
+        self.fees_ts.get_last_value()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::Duration;
     use super::*;
     use crate::portfolio::{assets::AssetHandlers, capital::CapitalHandlers};
     use crate::types::{ExecutedTrade, FailedTrade, FutureTrade, ReasonCode, Side};
+    use chrono::Duration;
     #[test]
     fn test_with_data() {
         use crate::types::Side;
@@ -266,6 +380,33 @@ mod tests {
         assert!(portfolio.fee_calculator.is_some());
     }
 
+    #[test]
+    fn test_from_args_fees() {
+        let fees = toml::from_str("type = \"percentage\"\nfee = 0.5").unwrap();
+        let args = PortfolioArgs {
+            fees: Some(fees),
+            ..Default::default()
+        };
+        let portfolio = Portfolio::from_args(&args, Utc::now().naive_utc());
+        assert!(portfolio.fee_calculator.is_some());
+        assert_eq!(portfolio.total_fees(), dec!(0));
+        assert_eq!(
+            portfolio.calculate_fee(dec!(100), Side::Buy, OrderType::Market),
+            dec!(0.5)
+        );
+        assert_eq!(
+            portfolio.calculate_fee(dec!(100), Side::Sell, OrderType::Market),
+            dec!(0.5)
+        );
+
+        let portfolio = Portfolio::from_args(&PortfolioArgs::default(), Utc::now().naive_utc());
+        assert!(portfolio.fee_calculator.is_none());
+        assert_eq!(
+            portfolio.calculate_fee(dec!(100), Side::Buy, OrderType::Market),
+            dec!(0)
+        );
+    }
+
     #[test]
     fn test_set_threshold() {
         let mut portfolio = Portfolio::new(dec!(100.0), dec!(100.0), None);
@@ -12,6 +12,9 @@ pub trait PositionHandlers {
     fn get_open_positions_as_trades(&self) -> Option<Vec<&ExecutedTrade>>;
     fn get_open_positions(&self) -> &BTreeMap<NaiveDateTime, OpenPosition>;
     fn close_positions(&mut self, quantity: Decimal, close_price: Decimal) -> Vec<String>;
+    fn positions_to_close(&self, quantity: Decimal, close_price: Decimal) -> Vec<String>;
+    fn mark_for_exit(&mut self, entry_times: &[NaiveDateTime]);
+    fn update_high_water_marks(&mut self, price: Decimal);
     fn update_position_metrics(&mut self);
     fn total_open_quantity(&self) -> Decimal;
     fn average_entry_price(&self) -> Decimal;
@@ -38,6 +41,8 @@ impl PositionHandlers for Portfolio {
             quantity: trade.get_quantity(),
             entry_time: *trade.get_timestamp(),
             order_id: trade.get_order_id().to_string(),
+            high_water_mark: trade.get_price(),
+            exit_pending: false,
         };
 
         self.open_positions.insert(*trade.get_timestamp(), position);
@@ -66,8 +71,8 @@ impl PositionHandlers for Portfolio {
 
     /// Close open positions by quantity and close price
     ///
-    /// First, profitable positions are closed first, from most-profitable to least. Then, non-profitable positions are
-    /// closed in a FIFO order.
+    /// Positions marked with [`PositionHandlers::mark_for_exit`] are closed first. Then, profitable positions are
+    /// closed, from most-profitable to least. Then, non-profitable positions are closed in a FIFO order.
     ///
     /// Returns the order ids of the fully closed positions.
     fn close_positions(&mut self, quantity: Decimal, close_price: Decimal) -> Vec<String> {
@@ -76,17 +81,7 @@ impl PositionHandlers for Portfolio {
         let mut positions_to_remove = Vec::new();
         let mut positions_to_update = Vec::new();
 
-        // Sort positions by profitability (most profitable first)
-        let mut sorted_positions: Vec<_> = self.open_positions.iter().collect();
-        sorted_positions.sort_by(|a, b| {
-            let profit_a = close_price - a.1.entry_price;
-            let profit_b = close_price - b.1.entry_price;
-            profit_b
-                .partial_cmp(&profit_a)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        for (timestamp, position) in sorted_positions {
+        for (timestamp, position) in self.closing_order(close_price) {
             if remaining_quantity <= Decimal::ZERO {
                 break;
             }
@@ -118,6 +113,41 @@ impl PositionHandlers for Portfolio {
         closed_trade_ids
     }
 
+    /// Order ids of the positions which would be fully closed by [`PositionHandlers::close_positions`]
+    ///
+    /// Positions are not modified.
+    fn positions_to_close(&self, quantity: Decimal, close_price: Decimal) -> Vec<String> {
+        let mut remaining_quantity = quantity;
+        let mut closed_trade_ids = Vec::new();
+        for (_, position) in self.closing_order(close_price) {
+            if remaining_quantity < position.quantity {
+                break;
+            }
+            remaining_quantity -= position.quantity;
+            closed_trade_ids.push(position.order_id.clone());
+        }
+        closed_trade_ids
+    }
+
+    /// Select the positions to close first when the next sell is executed
+    ///
+    /// Replaces any earlier selection.
+    ///
+    /// # Arguments
+    /// * `entry_times` - Entry times of the selected positions
+    fn mark_for_exit(&mut self, entry_times: &[NaiveDateTime]) {
+        for (timestamp, position) in self.open_positions.iter_mut() {
+            position.exit_pending = entry_times.contains(timestamp);
+        }
+    }
+
+    /// Raise the high-water mark of every open position to the given price
+    fn update_high_water_marks(&mut self, price: Decimal) {
+        for position in self.open_positions.values_mut() {
+            position.high_water_mark = position.high_water_mark.max(price);
+        }
+    }
+
     /// Update the average entry price and total notional value of open positions
     fn update_position_metrics(&mut self) {
         let (total_value, total_cost, total_quantity) = self.open_positions.values().fold(
@@ -155,6 +185,24 @@ impl PositionHandlers for Portfolio {
     }
 }
 
+impl Portfolio {
+    /// Open positions in the order they are closed at the given price
+    fn closing_order(&self, close_price: Decimal) -> Vec<(&NaiveDateTime, &OpenPosition)> {
+        // Sort positions marked for exit first, then by profitability (most profitable first)
+        let mut sorted_positions: Vec<_> = self.open_positions.iter().collect();
+        sorted_positions.sort_by(|a, b| {
+            let profit_a = close_price - a.1.entry_price;
+            let profit_b = close_price - b.1.entry_price;
+            b.1.exit_pending.cmp(&a.1.exit_pending).then(
+                profit_b
+                    .partial_cmp(&profit_a)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+        sorted_positions
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -209,6 +257,8 @@ mod tests {
                 quantity: dec!(10),
                 entry_time: timestamp1,
                 order_id: "1".to_string(),
+                high_water_mark: dec!(100),
+                exit_pending: false,
             },
         );
         portfolio.open_positions.insert(
@@ -218,6 +268,8 @@ mod tests {
                 quantity: dec!(5),
                 entry_time: timestamp2,
                 order_id: "2".to_string(),
+                high_water_mark: dec!(110),
+                exit_pending: false,
             },
         );
 
@@ -250,6 +302,8 @@ mod tests {
                 quantity: dec!(10),
                 entry_time: timestamp1,
                 order_id: "1".to_string(),
+                high_water_mark: dec!(100),
+                exit_pending: false,
             },
         );
         portfolio.open_positions.insert(
@@ -259,6 +313,8 @@ mod tests {
                 quantity: dec!(5),
                 entry_time: timestamp2,
                 order_id: "2".to_string(),
+                high_water_mark: dec!(110),
+                exit_pending: false,
             },
         );
         portfolio.open_positions.insert(
@@ -268,6 +324,8 @@ mod tests {
                 quantity: dec!(8),
                 entry_time: timestamp3,
                 order_id: "3".to_string(),
+                high_water_mark: dec!(90),
+                exit_pending: false,
             },
         );
 
@@ -301,6 +359,8 @@ mod tests {
                 quantity: dec!(10),
                 entry_time: timestamp,
                 order_id: "1".to_string(),
+                high_water_mark: dec!(100),
+                exit_pending: false,
             },
         );
 
@@ -336,6 +396,8 @@ mod tests {
                 quantity: dec!(10),
                 entry_time: timestamp1,
                 order_id: "1".to_string(),
+                high_water_mark: dec!(100),
+                exit_pending: false,
             },
         );
         portfolio.open_positions.insert(
@@ -345,6 +407,8 @@ mod tests {
                 quantity: dec!(5),
                 entry_time: timestamp2,
                 order_id: "2".to_string(),
+                high_water_mark: dec!(110),
+                exit_pending: false,
             },
         );
 
@@ -362,4 +426,55 @@ mod tests {
         assert_eq!(portfolio.total_position_notional_value, dec!(330)); // 110 * 3
         assert_eq!(portfolio.average_entry_price, dec!(110));
     }
+
+    #[test]
+    fn test_close_marked_positions() {
+        let mut portfolio = Portfolio::default();
+        let timestamp1 = NaiveDate::from_ymd_opt(2023, 1, 1)
+            .unwrap()
+            .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap());
+        let timestamp2 = NaiveDate::from_ymd_opt(2023, 1, 2)
+            .unwrap()
+            .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap());
+
+        for (timestamp, price, order_id) in
+            [(timestamp1, dec!(100), "1"), (timestamp2, dec!(110), "2")]
+        {
+            portfolio.add_open_position(&ExecutedTrade::with_calculated_notional(
+                order_id.to_string(),
+                Side::Buy,
+                price,
+                dec!(5),
+                timestamp,
+            ));
+        }
+
+        portfolio.update_high_water_marks(dec!(105));
+        assert_eq!(
+            portfolio.open_positions[&timestamp1].high_water_mark,
+            dec!(105)
+        );
+        assert_eq!(
+            portfolio.open_positions[&timestamp2].high_water_mark,
+            dec!(110)
+        );
+
+        // unmarked positions are closed most profitable first
+        assert_eq!(
+            portfolio.positions_to_close(dec!(5), dec!(120)),
+            vec!["1".to_string()]
+        );
+        // `closing_order` closes marked positions first, whatever their profitability
+        portfolio.mark_for_exit(&[timestamp2]);
+        assert_eq!(
+            portfolio.positions_to_close(dec!(5), dec!(120)),
+            vec!["2".to_string()]
+        );
+        assert_eq!(portfolio.open_positions.len(), 2);
+
+        let closed_trade_ids = portfolio.close_positions(dec!(5), dec!(120));
+        assert_eq!(closed_trade_ids, vec!["2".to_string()]);
+        assert_eq!(portfolio.open_positions.len(), 1);
+        assert!(portfolio.open_positions.contains_key(&timestamp1));
+    }
 }
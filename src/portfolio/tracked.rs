@@ -92,6 +92,50 @@ impl TrackedValue {
         }
     }
 
+    /// Get the value at each of the given points in time
+    ///
+    /// The value at a point is the most recent value recorded at or before it. Points before the first recorded
+    /// value use the first value. Values recorded at the same time are resolved in the order they were added.
+    ///
+    /// # Arguments
+    /// * `points` - Points in time in ascending order
+    pub fn values_at(&self, points: &[NaiveDateTime]) -> Vec<Decimal> {
+        let sorted = self
+            .0
+            .sort(
+                ["timestamp"],
+                SortMultipleOptions::default().with_maintain_order(true),
+            )
+            .unwrap();
+        let timestamps = sorted.column("timestamp").unwrap().datetime().unwrap();
+        let time_unit = timestamps.time_unit();
+        let rows = timestamps
+            .into_iter()
+            .zip(sorted.column("value").unwrap().f64().unwrap())
+            .filter_map(|(timestamp, value)| Some((timestamp?, value?)))
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            return vec![Decimal::ZERO; points.len()];
+        }
+
+        let mut index = 0;
+        points
+            .iter()
+            .map(|point| {
+                let point = point.and_utc();
+                let timestamp = match time_unit {
+                    TimeUnit::Milliseconds => point.timestamp_millis(),
+                    TimeUnit::Microseconds => point.timestamp_micros(),
+                    TimeUnit::Nanoseconds => point.timestamp_nanos_opt().unwrap(),
+                };
+                while index + 1 < rows.len() && rows[index + 1].0 <= timestamp {
+                    index += 1;
+                }
+                Decimal::from_f64(rows[index].1).unwrap()
+            })
+            .collect()
+    }
+
     /// Decrement the tracked value by the given amount
     ///
     /// # Arguments
@@ -184,6 +228,33 @@ mod tests {
         assert_eq!(last_value, expected);
     }
 
+    #[test]
+    fn test_values_at() {
+        let start_time =
+            NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let mut chart = TrackedValue::with_initial(dec!(1.0), start_time);
+        chart.increment(dec!(1.0), start_time + Duration::minutes(2));
+        chart.increment(dec!(1.0), start_time + Duration::minutes(2));
+        chart.decrement(dec!(2.0), start_time + Duration::minutes(4));
+
+        let points = (-1..6)
+            .map(|i| start_time + Duration::minutes(i))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chart.values_at(&points),
+            vec![
+                dec!(1.0),
+                dec!(1.0),
+                dec!(1.0),
+                dec!(3.0),
+                dec!(3.0),
+                dec!(1.0),
+                dec!(1.0)
+            ]
+        );
+        assert!(TrackedValue::default().values_at(&points[..1]) == vec![Decimal::ZERO]);
+    }
+
     #[test]
     fn test_add_row() {
         // starting value and added value
@@ -1,3 +1,4 @@
+use crate::markets::FeeCurrency;
 use crate::portfolio::assets::AssetHandlers;
 use crate::portfolio::capital::CapitalHandlers;
 use crate::portfolio::position::PositionHandlers;
@@ -6,11 +7,12 @@ use crate::types::{Candle, ExecutedTrade, FailedTrade, FutureTrade, Side, Trade}
 use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 /// Interface methods for storing and retrieving trades, and determining when to trade
 pub trait TradeHandlers: PositionHandlers + AssetHandlers + CapitalHandlers {
     fn get_executed_trades(&self) -> &HashMap<NaiveDateTime, ExecutedTrade>;
+    fn get_failed_trades(&self) -> &Vec<FailedTrade>;
     fn add_failed_trade(&mut self, trade: FailedTrade);
     fn add_executed_trade(&mut self, trade: ExecutedTrade);
 
@@ -21,6 +23,7 @@ pub trait TradeHandlers: PositionHandlers + AssetHandlers + CapitalHandlers {
     #[deprecated(note = "Responsibility is moving to crate::PositionManager")]
     fn get_buy_cost(&self) -> Decimal;
     fn get_last_trade(&self) -> Option<&ExecutedTrade>;
+    fn closed_trade_pnl(&self) -> Vec<Decimal>;
     #[deprecated(note = "Responsibility is moving to crate::PositionManager")]
     fn able_to_buy(&self) -> bool;
 }
@@ -31,6 +34,11 @@ impl TradeHandlers for Portfolio {
         &self.executed_trades
     }
 
+    /// Get the failed trades
+    fn get_failed_trades(&self) -> &Vec<FailedTrade> {
+        &self.failed_trades
+    }
+
     /// Add a failed trade to the portfolio
     ///
     /// Storing "failed trades" is only intended for debugging and backtesting purposes.
@@ -43,21 +51,49 @@ impl TradeHandlers for Portfolio {
 
     /// Add an executed trade to the portfolio
     ///
-    /// Adding an executed trade will update the capital and assets of the portfolio.
+    /// Adding an executed trade will update the capital and assets of the portfolio. If a fee calculator
+    /// is set, the fee is recorded in the cumulative fee series. Fees in quote currency are added to the
+    /// cost of buys and subtracted from the proceeds of sells. Fees in the base asset are converted at
+    /// the trade price and deducted from the assets, reducing the size of the opened position on buys.
     ///
     /// # Arguments
     /// * `trade` - The executed trade to add
     fn add_executed_trade(&mut self, trade: ExecutedTrade) {
+        let point = *trade.get_timestamp();
+        let notional_value = trade.get_notional_value();
+        let fee = self.calculate_fee_at(
+            notional_value,
+            trade.get_side(),
+            trade.get_order_type(),
+            Some(point),
+        );
+
+        let (quote_fee, base_fee) = match self.fee_currency() {
+            FeeCurrency::Quote => (fee, Decimal::ZERO),
+            FeeCurrency::Base if trade.get_price().is_zero() => (Decimal::ZERO, Decimal::ZERO),
+            FeeCurrency::Base => (Decimal::ZERO, fee / trade.get_price()),
+        };
+
         if trade.get_side() == Side::Buy {
-            self.decrease_capital(trade.get_notional_value(), *trade.get_timestamp());
-            self.increase_assets(trade.get_quantity(), *trade.get_timestamp());
+            self.decrease_capital(notional_value + quote_fee, point);
+            self.increase_assets(trade.get_quantity() - base_fee, point);
             self.add_open_position(&trade);
+            if !base_fee.is_zero() {
+                if let Some(position) = self.open_positions.get_mut(&point) {
+                    position.quantity -= base_fee;
+                }
+                self.update_position_metrics();
+            }
         } else {
-            self.increase_capital(trade.get_notional_value(), *trade.get_timestamp());
-            self.decrease_assets(trade.get_quantity(), *trade.get_timestamp());
-            self.close_positions(trade.get_quantity(), trade.get_notional_value());
+            self.increase_capital(notional_value - quote_fee, point);
+            self.decrease_assets(trade.get_quantity() + base_fee, point);
+            self.close_positions(trade.get_quantity() + base_fee, trade.get_price());
         }
-        self.executed_trades.insert(*trade.get_timestamp(), trade);
+        if !fee.is_zero() {
+            self.fees_ts.increment(fee, point);
+            self.trade_fees.insert(point, fee);
+        }
+        self.executed_trades.insert(point, trade);
     }
 
     fn generate_sell_opt(&self, candle: &Candle) -> Option<FutureTrade> {
@@ -94,6 +130,58 @@ impl TradeHandlers for Portfolio {
         }
     }
 
+    /// Profit or loss of every sell which closed earlier buys
+    ///
+    /// Sells are matched against buys first-in-first-out. Sells of assets which were not bought by the portfolio are
+    /// ignored. The fees of the sell and of the matched buys are deducted, in proportion to the quantity matched.
+    ///
+    /// # Returns
+    /// The profit or loss of each closing sell in the order they were executed
+    fn closed_trade_pnl(&self) -> Vec<Decimal> {
+        let mut trades = self.executed_trades.values().collect::<Vec<_>>();
+        trades.sort_by_key(|trade| *trade.get_timestamp());
+
+        let mut lots = VecDeque::new();
+        let mut closed = vec![];
+        for trade in trades {
+            let fee = self
+                .trade_fees
+                .get(trade.get_timestamp())
+                .copied()
+                .unwrap_or_default();
+            let fee_per_unit = if trade.get_quantity().is_zero() {
+                Decimal::ZERO
+            } else {
+                fee / trade.get_quantity()
+            };
+            if trade.get_side() == Side::Buy {
+                lots.push_back((trade.get_price(), trade.get_quantity(), fee_per_unit));
+                continue;
+            }
+
+            let mut remaining = trade.get_quantity();
+            let mut matched = false;
+            let mut pnl = Decimal::ZERO;
+            while remaining > Decimal::ZERO {
+                let Some((price, quantity, buy_fee_per_unit)) = lots.front_mut() else {
+                    break;
+                };
+                let filled = remaining.min(*quantity);
+                pnl += (trade.get_price() - *price - *buy_fee_per_unit - fee_per_unit) * filled;
+                remaining -= filled;
+                *quantity -= filled;
+                matched = true;
+                if quantity.is_zero() {
+                    lots.pop_front();
+                }
+            }
+            if matched {
+                closed.push(pnl);
+            }
+        }
+        closed
+    }
+
     /// Get a boolean indicating whether or not the portfolio is able to buy.
     ///
     /// # Conditions
@@ -121,8 +209,8 @@ fn calculate_buy_rate(candle: &Candle) -> Decimal {
 #[cfg(test)]
 mod tests {
     use crate::portfolio::{AssetHandlers, CapitalHandlers, Portfolio, TradeHandlers};
-    use crate::types::{ExecutedTrade, FailedTrade, ReasonCode, Side, Trade};
-    use chrono::{Duration, NaiveDateTime, Utc};
+    use crate::types::{ExecutedTrade, FailedTrade, OrderType, ReasonCode, Side, Trade};
+    use chrono::{DateTime, Duration, NaiveDateTime, Utc};
     use rust_decimal_macros::dec;
 
     /// Test that a failed trade is correctly added to the portfolio storage.
@@ -139,7 +227,9 @@ mod tests {
             Side::Buy,
             dec!(100.0),
             dec!(1.0),
-            NaiveDateTime::from_timestamp_opt(Utc::now().timestamp(), 0).unwrap(),
+            DateTime::from_timestamp(Utc::now().timestamp(), 0)
+                .unwrap()
+                .naive_utc(),
         );
         portfolio.add_failed_trade(trade);
         assert_eq!(portfolio.failed_trades.len(), 1);
@@ -150,7 +240,9 @@ mod tests {
             Side::Sell,
             dec!(100.0),
             dec!(1.0),
-            NaiveDateTime::from_timestamp_opt(Utc::now().timestamp(), 0).unwrap(),
+            DateTime::from_timestamp(Utc::now().timestamp(), 0)
+                .unwrap()
+                .naive_utc(),
         );
         portfolio.add_failed_trade(trade);
         assert_eq!(portfolio.failed_trades.len(), 2);
@@ -168,7 +260,9 @@ mod tests {
             Side::Buy,
             dec!(100.0),
             dec!(1.0),
-            NaiveDateTime::from_timestamp_opt(Utc::now().timestamp(), 0).unwrap(),
+            DateTime::from_timestamp(Utc::now().timestamp(), 0)
+                .unwrap()
+                .naive_utc(),
         );
         assert!(portfolio.executed_trades.is_empty());
         assert_eq!(portfolio.available_capital(), dec!(200.0));
@@ -199,6 +293,127 @@ mod tests {
         assert_eq!(portfolio.open_positions.len(), 0);
     }
 
+    /// Test that fees are deducted from capital and tracked when a fee calculator is set
+    #[test]
+    fn test_add_executed_trade_with_fees() {
+        use crate::markets::SimplePercentageFee;
+
+        let mut portfolio = Portfolio::new(dec!(0.0), dec!(200.0), None)
+            .add_fee_calculator(SimplePercentageFee::new(dec!(1.0)));
+        let time = DateTime::from_timestamp(Utc::now().timestamp(), 0)
+            .unwrap()
+            .naive_utc();
+
+        let trade = ExecutedTrade::with_calculated_notional(
+            "buy".to_string(),
+            Side::Buy,
+            dec!(100.0),
+            dec!(1.0),
+            time,
+        );
+        portfolio.add_executed_trade(trade);
+        assert_eq!(portfolio.available_capital(), dec!(99.0));
+        assert_eq!(portfolio.total_fees(), dec!(1.0));
+
+        let trade = ExecutedTrade::with_calculated_notional(
+            "sell".to_string(),
+            Side::Sell,
+            dec!(110.0),
+            dec!(1.0),
+            time + Duration::seconds(1),
+        );
+        portfolio.add_executed_trade(trade);
+        assert_eq!(portfolio.available_capital(), dec!(207.9));
+        assert_eq!(portfolio.total_fees(), dec!(2.1));
+        assert_eq!(portfolio.get_assets(), dec!(0.0));
+    }
+
+    /// Test that fees deducted from the base asset reduce the assets and position size
+    #[test]
+    fn test_add_executed_trade_with_base_asset_fees() {
+        use crate::markets::FeeConfig;
+        use crate::portfolio::PositionHandlers;
+
+        let fees: FeeConfig =
+            toml::from_str("type = \"percentage\"\nfee = 1.0\nfee_currency = \"base\"").unwrap();
+        let mut portfolio =
+            Portfolio::new(dec!(0.0), dec!(200.0), None).add_fee_calculator(fees.build());
+        let time = DateTime::from_timestamp(Utc::now().timestamp(), 0)
+            .unwrap()
+            .naive_utc();
+
+        let trade = ExecutedTrade::with_calculated_notional(
+            "buy".to_string(),
+            Side::Buy,
+            dec!(100.0),
+            dec!(1.0),
+            time,
+        );
+        portfolio.add_executed_trade(trade);
+        assert_eq!(portfolio.available_capital(), dec!(100.0));
+        assert_eq!(portfolio.get_assets(), dec!(0.99));
+        assert_eq!(portfolio.total_open_quantity(), dec!(0.99));
+        assert_eq!(portfolio.total_fees(), dec!(1.0));
+    }
+
+    /// Test that volume-tiered fees use the trailing trading volume
+    #[test]
+    fn test_add_executed_trade_with_tiered_fees() {
+        use crate::markets::FeeConfig;
+
+        let fees: FeeConfig = toml::from_str(
+            r#"
+            type = "tiered"
+
+            [[tiers]]
+            min_volume = 0
+            maker_fee = 0.5
+            taker_fee = 1.0
+
+            [[tiers]]
+            min_volume = 100
+            maker_fee = 0.25
+            taker_fee = 0.5
+        "#,
+        )
+        .unwrap();
+        let mut portfolio =
+            Portfolio::new(dec!(0.0), dec!(1000.0), None).add_fee_calculator(fees.build());
+        let time = DateTime::from_timestamp(Utc::now().timestamp(), 0)
+            .unwrap()
+            .naive_utc();
+
+        let trade = ExecutedTrade::with_calculated_notional(
+            "buy".to_string(),
+            Side::Buy,
+            dec!(100.0),
+            dec!(1.0),
+            time,
+        )
+        .with_order_type(OrderType::Market);
+        portfolio.add_executed_trade(trade);
+        assert_eq!(portfolio.total_fees(), dec!(1.0));
+        assert_eq!(portfolio.trailing_volume(time), dec!(100.0));
+
+        // the second trade qualifies for the lower tier, and fill-or-kill limit orders are charged the taker fee
+        let trade = ExecutedTrade::with_calculated_notional(
+            "sell".to_string(),
+            Side::Sell,
+            dec!(100.0),
+            dec!(1.0),
+            time + Duration::days(1),
+        )
+        .with_order_type(OrderType::Limit);
+        portfolio.add_executed_trade(trade);
+        assert_eq!(portfolio.total_fees(), dec!(1.5));
+
+        // trades older than 30 days no longer count towards the volume
+        assert_eq!(
+            portfolio.trailing_volume(time + Duration::days(30)),
+            dec!(100.0)
+        );
+    }
+
     #[test]
     fn test_last_trade() {
         let mut portfolio = Portfolio::new(dec!(200.0), dec!(200.0), None);
@@ -237,4 +452,60 @@ mod tests {
             time.timestamp_millis()
         );
     }
+
+    #[test]
+    fn test_closed_trade_pnl() {
+        let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), start);
+        let trades = [
+            (Side::Buy, dec!(10), dec!(2)),
+            (Side::Buy, dec!(20), dec!(2)),
+            // closes the first buy and half of the second
+            (Side::Sell, dec!(15), dec!(3)),
+            (Side::Sell, dec!(30), dec!(1)),
+            // nothing left to close
+            (Side::Sell, dec!(30), dec!(1)),
+        ];
+        for (i, (side, price, quantity)) in trades.into_iter().enumerate() {
+            portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+                i.to_string(),
+                side,
+                price,
+                quantity,
+                start + Duration::minutes(i as i64),
+            ));
+        }
+        assert_eq!(portfolio.closed_trade_pnl(), vec![dec!(5), dec!(10)]);
+    }
+
+    #[test]
+    fn test_closed_trade_pnl_with_fees() {
+        use crate::markets::SimplePercentageFee;
+
+        let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), start)
+            .add_fee_calculator(SimplePercentageFee::new(dec!(1.0)));
+        let trades = [
+            (Side::Buy, dec!(100), dec!(2)),
+            // a gross profit of 2 is a net loss after 2 of buy fees and 1.02 of sell fees
+            (Side::Sell, dec!(102), dec!(1)),
+            (Side::Sell, dec!(120), dec!(1)),
+        ];
+        for (i, (side, price, quantity)) in trades.into_iter().enumerate() {
+            portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+                i.to_string(),
+                side,
+                price,
+                quantity,
+                start + Duration::minutes(i as i64),
+            ));
+        }
+        assert_eq!(
+            portfolio.closed_trade_pnl(),
+            vec![
+                dec!(2) - dec!(1) - dec!(1.02),
+                dec!(20) - dec!(1) - dec!(1.2)
+            ]
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::types::Candle;
 use polars::prelude::DataFrame;
 
 /// Common interface for objects which process candle data
@@ -13,4 +14,21 @@ pub trait CandleProcessor {
     ///
     /// Strictly meant for debugging and graphing.
     fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame;
+
+    /// Ingest a single new candle and update internal state incrementally
+    ///
+    /// Candles must be passed once each and in ascending order. The returned value is the same as calling
+    /// `process_candle` with all candles ingested so far.
+    ///
+    /// Returns `None` when incremental updates are not supported, in which case `process_candle` should be
+    /// used instead.
+    fn process_new_candle(
+        &mut self,
+        _candle: &Candle,
+    ) -> Option<Result<Self::ReturnType, Self::ErrorType>> {
+        None
+    }
+
+    /// Clear any state accumulated by `process_new_candle`
+    fn reset(&mut self) {}
 }
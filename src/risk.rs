@@ -1,10 +1,16 @@
-use crate::portfolio::{Portfolio, PositionHandlers};
-use crate::types::{Candle, Trade};
+use crate::metrics;
+use crate::portfolio::{EquityHandlers, Portfolio, PositionHandlers};
+use crate::types::{candles_per_year, Candle, Trade};
+use crate::utils::frequency_to_duration;
 /// Functions for calculating risk metrics for a portfolio
 ///
 /// The primary function is [`calculate_risk`], which accepts a [`Portfolio`] and market data as input and returns a [`PortfolioRisk`] struct.
-use rust_decimal::{Decimal, MathematicalOps};
+use rust_decimal::prelude::*;
 use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+/// Number of candles averaged by the average true range
+const ATR_PERIOD: usize = 14;
 
 #[derive(Debug)]
 pub enum RiskCalculationErrors {
@@ -12,6 +18,163 @@ pub enum RiskCalculationErrors {
     CandleDataNotAligned,
 
     CouldNotExtract,
+
+    /// The risk configuration has an invalid value
+    InvalidConfig(String),
+}
+
+/// Method used to estimate the loss distribution for Value at Risk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueAtRiskMethod {
+    /// Empirical percentile of the observed returns
+    #[default]
+    Historical,
+    /// Normal distribution fitted to the mean and standard deviation of returns
+    Parametric,
+    /// Normal quantile adjusted for the skewness and excess kurtosis of returns
+    CornishFisher,
+}
+
+/// Source of the returns that risk metrics are calculated from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnsSource {
+    /// Close-to-close returns of the trading asset
+    #[default]
+    Asset,
+    /// Returns of the mark-to-market equity of the portfolio, so that metrics reflect the positions actually held
+    Portfolio,
+}
+
+/// Settings for Value at Risk and expected shortfall
+///
+/// Read from the `[risk.value_at_risk]` table of the config file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValueAtRiskConfig {
+    /// Probability that losses do not exceed the VaR. `0.95` is 95%.
+    pub confidence: f64,
+
+    /// Number of the most recent candles used to estimate returns
+    pub lookback: usize,
+
+    pub method: ValueAtRiskMethod,
+
+    /// Period that losses are estimated over, as a frequency label (ie: "1h", "1d")
+    ///
+    /// Defaults to a single candle of the trading frequency. Losses are scaled from a single candle by the square
+    /// root of the number of candles in the horizon.
+    pub horizon: Option<String>,
+}
+
+impl Default for ValueAtRiskConfig {
+    fn default() -> Self {
+        Self {
+            confidence: 0.95,
+            lookback: 100,
+            method: ValueAtRiskMethod::default(),
+            horizon: None,
+        }
+    }
+}
+
+/// Settings used by [`calculate_risk`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskConfig {
+    pub value_at_risk: ValueAtRiskConfig,
+
+    /// Number of candles in the VaR horizon
+    horizon_candles: f64,
+
+    /// Number of candles in a year, used to annualize ratios
+    periods_per_year: Decimal,
+
+    /// Annual return of a risk-free asset. `0.04` is 4%.
+    risk_free_rate: Decimal,
+
+    returns_source: ReturnsSource,
+}
+
+impl RiskConfig {
+    /// Validate the VaR settings for candles of the given frequency
+    ///
+    /// # Arguments
+    /// * `frequency` - Frequency label of the candles passed to [`calculate_risk`]
+    /// * `value_at_risk` - Value at Risk settings
+    ///
+    /// # Errors
+    /// - [`RiskCalculationErrors::InvalidConfig`] - A frequency which is not an intraday frequency, an unknown
+    ///   horizon, a confidence outside of `(0, 1)` or a lookback of less than 3 candles
+    pub fn new(
+        frequency: &str,
+        value_at_risk: ValueAtRiskConfig,
+    ) -> Result<Self, RiskCalculationErrors> {
+        if !(value_at_risk.confidence > 0.0 && value_at_risk.confidence < 1.0) {
+            return Err(RiskCalculationErrors::InvalidConfig(format!(
+                "VaR confidence must be between 0 and 1, got {}",
+                value_at_risk.confidence
+            )));
+        }
+        if value_at_risk.lookback < 3 {
+            return Err(RiskCalculationErrors::InvalidConfig(format!(
+                "VaR lookback must be at least 3 candles, got {}",
+                value_at_risk.lookback
+            )));
+        }
+
+        let duration = |label: &str| {
+            frequency_to_duration(label).ok_or(RiskCalculationErrors::InvalidConfig(format!(
+                "Invalid frequency '{}'",
+                label
+            )))
+        };
+        let candle_duration = duration(frequency)?;
+        let periods_per_year = candles_per_year(frequency).ok_or(
+            RiskCalculationErrors::InvalidConfig(format!("Unsupported frequency '{}'", frequency)),
+        )?;
+        let horizon_candles = match &value_at_risk.horizon {
+            Some(horizon) => duration(horizon)?.as_secs_f64() / candle_duration.as_secs_f64(),
+            None => 1.0,
+        };
+
+        Ok(Self {
+            value_at_risk,
+            horizon_candles,
+            periods_per_year: Decimal::from(periods_per_year),
+            risk_free_rate: Decimal::ZERO,
+            returns_source: ReturnsSource::default(),
+        })
+    }
+
+    /// Set the annual risk-free rate subtracted from returns by the Sharpe and Sortino ratios
+    pub fn with_risk_free_rate(mut self, risk_free_rate: Decimal) -> Self {
+        self.risk_free_rate = risk_free_rate;
+        self
+    }
+
+    /// Set whether metrics are calculated from asset or portfolio returns
+    pub fn with_returns_source(mut self, returns_source: ReturnsSource) -> Self {
+        self.returns_source = returns_source;
+        self
+    }
+
+    /// Risk-free return over a single candle
+    fn risk_free_return(&self) -> Decimal {
+        self.risk_free_rate / self.periods_per_year
+    }
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            value_at_risk: ValueAtRiskConfig::default(),
+            horizon_candles: 1.0,
+            periods_per_year: Decimal::from(candles_per_year("1m").unwrap()),
+            risk_free_rate: Decimal::ZERO,
+            returns_source: ReturnsSource::default(),
+        }
+    }
 }
 
 /// Calculate risk metrics for a portfolio against market data, and historical data for the asset.
@@ -20,6 +183,11 @@ pub enum RiskCalculationErrors {
 /// - `portfolio` - The portfolio to calculate risk metrics for
 /// - `market_data` - Historical market data for the asset
 /// - `historical_data` - Historical data for the asset
+/// - `config` - Settings for the risk metrics
+///
+/// With [`ReturnsSource::Portfolio`], VaR, beta and the ratios are calculated from the equity of the portfolio
+/// at the close of each candle in `historical_data`, and VaR is a fraction of the current equity instead of the
+/// position value.
 ///
 /// # Returns
 ///
@@ -32,6 +200,7 @@ pub fn calculate_risk(
     portfolio: &Portfolio,
     market_data: &[Candle],
     historical_data: &[Candle],
+    config: &RiskConfig,
 ) -> Result<PortfolioRisk, RiskCalculationErrors> {
     // ensure that the market data and historical data are sorted by timestamp
     let market_data_index = market_data
@@ -50,23 +219,43 @@ pub fn calculate_risk(
     let current_price = get_current_price(historical_data);
     let (total_position_value, average_entry_price, unrealized_pnl) =
         calculate_position_metrics(portfolio, current_price);
-    let returns = calculate_returns(historical_data);
+    let asset_returns = calculate_returns(historical_data);
+    let (returns, exposure) = match config.returns_source {
+        ReturnsSource::Asset => (asset_returns.clone(), total_position_value),
+        ReturnsSource::Portfolio => {
+            let equity = portfolio.equity_at(historical_data);
+            let current_equity = equity.last().copied().unwrap_or_default();
+            (metrics::returns(&equity), current_equity)
+        }
+    };
 
-    let value_at_risk = if total_position_value == Decimal::ZERO {
-        Decimal::ZERO
+    let (value_at_risk, expected_shortfall) = if total_position_value == Decimal::ZERO {
+        (Decimal::ZERO, Decimal::ZERO)
     } else {
-        calculate_value_at_risk(&returns, total_position_value)
+        let lookback = historical_data.len().min(config.value_at_risk.lookback);
+        let window = &returns[returns.len() + 1 - lookback..];
+        let (var, es) = calculate_value_at_risk(window, config);
+        (exposure * var, exposure * es)
     };
     let beta = calculate_beta(market_data, &returns);
-    let sharpe_ratio = calculate_sharpe_ratio(&returns);
+    let sharpe_ratio = calculate_sharpe_ratio(&returns, config);
+    let sortino_ratio = calculate_sortino_ratio(&returns, config);
+    let calmar_ratio = calculate_calmar_ratio(&returns, config);
+    let volatility = calculate_volatility(&asset_returns, config);
+    let atr_volatility = calculate_atr_volatility(historical_data, config);
 
     Ok(PortfolioRisk {
         total_position_value,
         average_entry_price,
         unrealized_pnl,
         value_at_risk,
+        expected_shortfall,
         beta,
         sharpe_ratio,
+        sortino_ratio,
+        calmar_ratio,
+        volatility,
+        atr_volatility,
     })
 }
 
@@ -104,18 +293,151 @@ fn calculate_position_metrics(
     (total_position_value, average_entry_price, unrealized_pnl)
 }
 
-/// Quantifies the level of financial risk over a specific time frame with a given confidence interval.
+/// Estimate the Value at Risk and expected shortfall of the returns
 ///
-/// Defaults to a 95% confidence interval, which means that there is a 5% chance that the portfolio
-/// will lose more than the VaR estimate over the defined period.
-fn calculate_value_at_risk(returns: &[Decimal], total_position_value: Decimal) -> Decimal {
-    let mut sorted_returns = returns.to_vec();
-    sorted_returns.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+/// Both are returned as positive fractions of the position value, and are scaled to the configured horizon with
+/// the square root of time. Gains are not reported as negative losses, so neither is less than zero.
+///
+/// # Returns
+/// A tuple of the VaR and the expected shortfall
+fn calculate_value_at_risk(returns: &[Decimal], config: &RiskConfig) -> (Decimal, Decimal) {
+    let returns = returns
+        .iter()
+        .filter_map(|r| r.to_f64())
+        .collect::<Vec<_>>();
+    if returns.len() < 2 {
+        return (Decimal::ZERO, Decimal::ZERO);
+    }
+
+    let alpha = 1.0 - config.value_at_risk.confidence;
+    let (var, es) = match config.value_at_risk.method {
+        ValueAtRiskMethod::Historical => {
+            let mut sorted = returns;
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+            let index = ((sorted.len() as f64 * alpha) as usize).min(sorted.len() - 1);
+            let tail = &sorted[..=index];
+            (
+                -sorted[index],
+                -tail.iter().sum::<f64>() / tail.len() as f64,
+            )
+        }
+        ValueAtRiskMethod::Parametric => {
+            let (mean, std_dev) = mean_std_dev(&returns);
+            let z = normal_quantile(alpha);
+            (
+                -(mean + z * std_dev),
+                -(mean - std_dev * normal_density(z) / alpha),
+            )
+        }
+        ValueAtRiskMethod::CornishFisher => {
+            let (mean, std_dev) = mean_std_dev(&returns);
+            let (skewness, kurtosis) = skewness_kurtosis(&returns, mean);
+            let quantile = |p: f64| cornish_fisher_quantile(normal_quantile(p), skewness, kurtosis);
+
+            // expected shortfall averages the adjusted quantiles over the tail
+            const TAIL_STEPS: usize = 100;
+            let tail = (0..TAIL_STEPS)
+                .map(|i| quantile(alpha * (i as f64 + 0.5) / TAIL_STEPS as f64))
+                .sum::<f64>()
+                / TAIL_STEPS as f64;
+            (
+                -(mean + quantile(alpha) * std_dev),
+                -(mean + tail * std_dev),
+            )
+        }
+    };
+
+    let scale = config.horizon_candles.sqrt();
+    let to_decimal = |loss: f64| Decimal::from_f64(loss.max(0.0) * scale).unwrap_or_default();
+    (to_decimal(var), to_decimal(es))
+}
+
+/// Sample mean and standard deviation
+fn mean_std_dev(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+/// Skewness and excess kurtosis
+///
+/// Both are zero when the values have no variance.
+fn skewness_kurtosis(values: &[f64], mean: f64) -> (f64, f64) {
+    let n = values.len() as f64;
+    let moment = |k: i32| values.iter().map(|v| (v - mean).powi(k)).sum::<f64>() / n;
+    let variance = moment(2);
+    if variance == 0.0 {
+        return (0.0, 0.0);
+    }
+    (
+        moment(3) / variance.powf(1.5),
+        moment(4) / variance.powi(2) - 3.0,
+    )
+}
+
+/// Adjust a standard normal quantile for skewness and excess kurtosis
+fn cornish_fisher_quantile(z: f64, skewness: f64, kurtosis: f64) -> f64 {
+    z + (z.powi(2) - 1.0) * skewness / 6.0 + (z.powi(3) - 3.0 * z) * kurtosis / 24.0
+        - (2.0 * z.powi(3) - 5.0 * z) * skewness.powi(2) / 36.0
+}
+
+/// Probability density of the standard normal distribution
+fn normal_density(z: f64) -> f64 {
+    (-0.5 * z * z).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// Inverse of the standard normal cumulative distribution
+///
+/// Uses Acklam's rational approximation, which is accurate to about 5 significant digits.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549671636502937e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const P_LOW: f64 = 0.02425;
 
-    let var_index = (sorted_returns.len() as f64 * 0.05) as usize;
-    let var_95 = sorted_returns.get(var_index).cloned().unwrap_or_default();
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
 
-    total_position_value * var_95
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    }
 }
 
 /// Measure the volatility of an asset compared against the market
@@ -142,24 +464,43 @@ fn calculate_beta(market_data: &[Candle], asset_returns: &[Decimal]) -> Decimal
 
 /// Measure additional return for the volatility endured for holding a riskier asset
 ///
-/// This does not account for the risk-free rate, which is a common simplification for algo trading
-/// because it should be negligible for short-term trading.
-fn calculate_sharpe_ratio(returns: &[Decimal]) -> Decimal {
-    if returns.len() < 2 {
+/// The per-candle ratio of excess returns over the risk-free rate is annualized by the square root of the number
+/// of candles in a year. See [`metrics::sharpe_ratio`].
+fn calculate_sharpe_ratio(returns: &[Decimal], config: &RiskConfig) -> Decimal {
+    metrics::sharpe_ratio(returns, config.risk_free_return(), config.periods_per_year)
+}
+
+/// Measure additional return for the downside volatility endured for holding a riskier asset
+///
+/// Like the Sharpe ratio, but only returns below the risk-free rate count as volatility. See
+/// [`metrics::sortino_ratio`].
+fn calculate_sortino_ratio(returns: &[Decimal], config: &RiskConfig) -> Decimal {
+    metrics::sortino_ratio(returns, config.risk_free_return(), config.periods_per_year)
+}
+
+/// Measure the annualized return relative to the maximum drawdown of the returns
+///
+/// Zero when there is no drawdown.
+fn calculate_calmar_ratio(returns: &[Decimal], config: &RiskConfig) -> Decimal {
+    if returns.is_empty() {
         return dec!(0);
     }
-    let mean_return = returns.iter().sum::<Decimal>() / Decimal::from(returns.len());
-    let variance = returns
-        .iter()
-        .map(|&r| (r - mean_return) * (r - mean_return))
-        .sum::<Decimal>()
-        / Decimal::from(returns.len() - 1);
-    let std_dev = variance.sqrt().unwrap();
+    let annual_return =
+        returns.iter().sum::<Decimal>() / Decimal::from(returns.len()) * config.periods_per_year;
 
-    if std_dev.is_zero() {
+    // compound the returns into an equity curve starting at one
+    let equity = std::iter::once(Decimal::ONE)
+        .chain(returns.iter().scan(Decimal::ONE, |value, r| {
+            *value *= Decimal::ONE + r;
+            Some(*value)
+        }))
+        .collect::<Vec<_>>();
+    let max_drawdown = metrics::max_drawdown(&equity);
+
+    if max_drawdown.is_zero() {
         dec!(0)
     } else {
-        mean_return / std_dev
+        annual_return / max_drawdown
     }
 }
 
@@ -179,6 +520,47 @@ fn calculate_returns(candles: &[Candle]) -> Vec<Decimal> {
         .collect()
 }
 
+/// Annualized standard deviation of returns
+fn calculate_volatility(returns: &[Decimal], config: &RiskConfig) -> Decimal {
+    if returns.len() < 2 {
+        return dec!(0);
+    }
+    let mean_return = returns.iter().sum::<Decimal>() / Decimal::from(returns.len());
+    let variance = returns
+        .iter()
+        .map(|&r| (r - mean_return) * (r - mean_return))
+        .sum::<Decimal>()
+        / Decimal::from(returns.len() - 1);
+
+    variance.sqrt().unwrap() * config.periods_per_year.sqrt().unwrap()
+}
+
+/// Annualized average true range as a fraction of the current price
+///
+/// The true range of a candle is the largest of its high-low range and the distance of its high and low from the
+/// previous close. The last [`ATR_PERIOD`] true ranges are averaged.
+fn calculate_atr_volatility(candles: &[Candle], config: &RiskConfig) -> Decimal {
+    let true_ranges = candles
+        .windows(2)
+        .map(|window| {
+            let [previous, current] = window else {
+                unreachable!()
+            };
+            (current.high - current.low)
+                .max((current.high - previous.close).abs())
+                .max((current.low - previous.close).abs())
+        })
+        .collect::<Vec<_>>();
+    let current_price = get_current_price(candles);
+    if true_ranges.is_empty() || current_price.is_zero() {
+        return dec!(0);
+    }
+
+    let window = &true_ranges[true_ranges.len().saturating_sub(ATR_PERIOD)..];
+    let average_true_range = window.iter().sum::<Decimal>() / Decimal::from(window.len());
+    average_true_range / current_price * config.periods_per_year.sqrt().unwrap()
+}
+
 /// Risk metrics for a portfolio
 ///
 /// # Measurements
@@ -190,7 +572,14 @@ fn calculate_returns(candles: &[Candle]) -> Vec<Decimal> {
 /// Quantifies the level of financial risk over a specific time frame with a given confidence interval.
 ///
 /// For example, a 95% confidence interval, means that there is a 5% chance that the portfolio will
-/// lose more than the VaR estimate over the defined period.
+/// lose more than the VaR estimate over the defined period. It is reported as a positive loss in the quote
+/// currency, and is estimated as configured by [`ValueAtRiskConfig`].
+///
+///
+/// ## Expected Shortfall (CVaR)
+///
+/// The average loss over the same period in the cases where the loss exceeds the VaR. Always at least as large as
+/// the VaR, and more sensitive to the shape of the tail of the return distribution.
 ///
 ///
 /// ## Beta
@@ -208,7 +597,9 @@ fn calculate_returns(candles: &[Candle]) -> Vec<Decimal> {
 ///
 /// ## Sharpe Ratio
 ///
-/// Measures the additional return for the volatility endured for holding a riskier asset.
+/// Measures the additional return for the volatility endured for holding a riskier asset. Returns are in excess of
+/// the risk-free rate, and the ratio is annualized for the candle frequency so that ratios of different
+/// frequencies are comparable.
 ///
 /// ### Interpretation
 ///
@@ -228,12 +619,234 @@ fn calculate_returns(candles: &[Candle]) -> Vec<Decimal> {
 ///   allocation/distribution. Strategies with higher Sharpe ratios might receive more capital.
 /// - **Robustness Check:** A consistently high Sharpe ratio across different market conditions can indicate
 /// a robust trading strategy.
+///
+///
+/// ## Sortino Ratio
+///
+/// Like the Sharpe ratio, but only penalizes volatility from returns below the risk-free rate. Also annualized.
+///
+///
+/// ## Calmar Ratio
+///
+/// The annualized return divided by the maximum drawdown over the same candles. Favors strategies which recover
+/// from losses without deep drawdowns.
+///
+///
+/// ## Volatility
+///
+/// The annualized standard deviation of the trading asset's returns, and the annualized average true range as a
+/// fraction of the current price. Both measure the asset regardless of [`ReturnsSource`], and are used to size
+/// positions to a target volatility.
 #[derive(Debug, Clone)]
 pub struct PortfolioRisk {
     pub total_position_value: Decimal,
     pub average_entry_price: Decimal,
     pub unrealized_pnl: Decimal,
     pub value_at_risk: Decimal,
+    pub expected_shortfall: Decimal,
     pub beta: Decimal,
     pub sharpe_ratio: Decimal,
+    pub sortino_ratio: Decimal,
+    pub calmar_ratio: Decimal,
+    pub volatility: Decimal,
+    pub atr_volatility: Decimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::create_candles;
+    use crate::portfolio::TradeHandlers;
+    use crate::types::{ExecutedTrade, Side};
+    use crate::utils::extract_candles_from_df;
+    use chrono::NaiveDateTime;
+
+    /// Returns from -10% to 9% in steps of 1%
+    fn uniform_returns() -> Vec<Decimal> {
+        (-10..10).map(|i| Decimal::new(i, 2)).collect()
+    }
+
+    fn config(method: ValueAtRiskMethod) -> RiskConfig {
+        RiskConfig::new(
+            "1m",
+            ValueAtRiskConfig {
+                method,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_normal_quantile() {
+        assert!(normal_quantile(0.5).abs() < 1e-9);
+        assert!((normal_quantile(0.05) + 1.644854).abs() < 1e-4);
+        assert!((normal_quantile(0.975) - 1.959964).abs() < 1e-4);
+        assert!((normal_quantile(0.001) + 3.090232).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_value_at_risk_methods() {
+        let returns = uniform_returns();
+
+        // the second worst of 20 returns, and the average of the two worst
+        let (var, es) = calculate_value_at_risk(&returns, &config(ValueAtRiskMethod::Historical));
+        assert_eq!(var, dec!(0.09));
+        assert_eq!(es, dec!(0.095));
+
+        for method in [
+            ValueAtRiskMethod::Parametric,
+            ValueAtRiskMethod::CornishFisher,
+        ] {
+            let (var, es) = calculate_value_at_risk(&returns, &config(method));
+            assert!(
+                var > dec!(0.05) && var < dec!(0.15),
+                "{:?}: {}",
+                method,
+                var
+            );
+            assert!(es > var, "{:?}: {} <= {}", method, es, var);
+        }
+
+        // losses are never negative
+        let gains = vec![dec!(0.01); 10];
+        let (var, es) = calculate_value_at_risk(&gains, &config(ValueAtRiskMethod::Parametric));
+        assert_eq!((var, es), (Decimal::ZERO, Decimal::ZERO));
+    }
+
+    #[test]
+    fn test_horizon_scaling() {
+        let returns = uniform_returns();
+        let (var, es) = calculate_value_at_risk(&returns, &config(ValueAtRiskMethod::Historical));
+
+        // 4 candles in the horizon doubles the loss
+        let config = RiskConfig::new(
+            "15m",
+            ValueAtRiskConfig {
+                horizon: Some("1h".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            calculate_value_at_risk(&returns, &config),
+            (var * dec!(2), es * dec!(2))
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let invalid = [
+            ValueAtRiskConfig {
+                confidence: 1.0,
+                ..Default::default()
+            },
+            ValueAtRiskConfig {
+                lookback: 2,
+                ..Default::default()
+            },
+            ValueAtRiskConfig {
+                horizon: Some("1w".to_string()),
+                ..Default::default()
+            },
+        ];
+        for value_at_risk in invalid {
+            assert!(matches!(
+                RiskConfig::new("1m", value_at_risk),
+                Err(RiskCalculationErrors::InvalidConfig(_))
+            ));
+        }
+        assert!(RiskConfig::new("1x", ValueAtRiskConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_annualized_ratios() {
+        let returns = vec![dec!(0.2), dec!(-0.1), dec!(0.2)];
+        let daily = RiskConfig::new("1d", ValueAtRiskConfig::default()).unwrap();
+        let hourly = RiskConfig::new("1h", ValueAtRiskConfig::default()).unwrap();
+
+        // per-candle mean of 0.1 and standard deviation of sqrt(0.03)
+        let per_candle = dec!(0.1) / dec!(0.03).sqrt().unwrap();
+        let sharpe = calculate_sharpe_ratio(&returns, &daily);
+        assert!((sharpe - per_candle * dec!(365).sqrt().unwrap()).abs() < dec!(0.0001));
+        let sharpe_hourly = calculate_sharpe_ratio(&returns, &hourly);
+        assert!((sharpe_hourly - per_candle * dec!(8760).sqrt().unwrap()).abs() < dec!(0.0001));
+
+        // only the loss counts towards the downside deviation
+        assert!(calculate_sortino_ratio(&returns, &daily) > sharpe);
+
+        // annual return of 36.5 over a drawdown of 10%
+        assert_eq!(calculate_calmar_ratio(&returns, &daily), dec!(365));
+        assert_eq!(calculate_calmar_ratio(&[dec!(0.1)], &daily), Decimal::ZERO);
+
+        let risk_free = daily.clone().with_risk_free_rate(dec!(0.365));
+        assert!(calculate_sharpe_ratio(&returns, &risk_free) < sharpe);
+        assert!(
+            calculate_sortino_ratio(&returns, &risk_free)
+                < calculate_sortino_ratio(&returns, &daily)
+        );
+
+        assert!(RiskConfig::new("30m", ValueAtRiskConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_portfolio_returns() {
+        let candles =
+            extract_candles_from_df(&create_candles(&[100.0, 50.0, 100.0, 90.0, 99.0])).unwrap();
+
+        // the position is only opened after the large swings
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), candles[0].time);
+        portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+            "buy".to_string(),
+            Side::Buy,
+            dec!(90),
+            dec!(10),
+            candles[3].time,
+        ));
+
+        let asset = calculate_risk(&portfolio, &candles, &candles, &RiskConfig::default()).unwrap();
+        assert_eq!(asset.value_at_risk, dec!(495));
+
+        let config = RiskConfig::default().with_returns_source(ReturnsSource::Portfolio);
+        let risk = calculate_risk(&portfolio, &candles, &candles, &config).unwrap();
+        assert_eq!(risk.total_position_value, asset.total_position_value);
+        assert_eq!(risk.value_at_risk, Decimal::ZERO);
+        assert!(risk.sharpe_ratio > Decimal::ZERO);
+        assert!(risk.sharpe_ratio != asset.sharpe_ratio);
+        assert!(risk.beta < asset.beta);
+    }
+
+    #[test]
+    fn test_calculate_risk_lookback() {
+        let candles =
+            extract_candles_from_df(&create_candles(&[100.0, 50.0, 100.0, 90.0, 99.0])).unwrap();
+
+        let time = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), time);
+        portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+            "buy".to_string(),
+            Side::Buy,
+            dec!(10),
+            dec!(10),
+            time,
+        ));
+
+        // the 50% loss is the worst return
+        let risk = calculate_risk(&portfolio, &candles, &candles, &RiskConfig::default()).unwrap();
+        assert_eq!(risk.total_position_value, dec!(990));
+        assert_eq!(risk.value_at_risk, dec!(495));
+
+        // only the last two returns are within the lookback
+        let config = RiskConfig::new(
+            "1m",
+            ValueAtRiskConfig {
+                lookback: 3,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let risk = calculate_risk(&portfolio, &candles, &candles, &config).unwrap();
+        assert_eq!(risk.value_at_risk, dec!(99));
+        assert_eq!(risk.expected_shortfall, dec!(99));
+    }
 }
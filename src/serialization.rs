@@ -21,3 +21,22 @@ where
     let timestamp = i64::deserialize(deserializer)?;
     Ok(NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap())
 }
+
+/// Deserialize an optional duration label, such as "12h", rejecting labels which are not a valid duration
+///
+/// The label is kept as a string so that it is serialized unchanged.
+pub fn duration_label_deserializer<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let label = Option::<String>::deserialize(deserializer)?;
+    if let Some(label) = &label {
+        if crate::utils::frequency_to_duration(label).is_none() {
+            return Err(serde::de::Error::custom(format!(
+                "invalid duration '{}'",
+                label
+            )));
+        }
+    }
+    Ok(label)
+}
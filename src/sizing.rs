@@ -0,0 +1,401 @@
+use crate::portfolio::{AssetHandlers, CapitalHandlers, Portfolio, TradeHandlers};
+use crate::risk::PortfolioRisk;
+/// Position sizing strategies for buys
+///
+/// A [`PositionSizer`] decides how much to buy. The [`PositionManager`](crate::manager::PositionManager) still caps
+/// the size with the VaR limit, the available capital and `max_position_size`.
+use rust_decimal::prelude::*;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+/// Determines the notional value of a buy
+pub trait PositionSizer: Send + Sync {
+    /// Notional value to buy at the current price
+    ///
+    /// # Arguments
+    /// * `portfolio` - The portfolio which is buying
+    /// * `risk` - Current risk metrics of the portfolio
+    /// * `current_price` - Current price of the asset
+    fn buy_notional(
+        &self,
+        portfolio: &Portfolio,
+        risk: &PortfolioRisk,
+        current_price: Decimal,
+    ) -> Decimal;
+}
+
+/// Mark-to-market value of the capital and assets of the portfolio
+fn equity(portfolio: &Portfolio, current_price: Decimal) -> Decimal {
+    portfolio.available_capital() + portfolio.get_assets() * current_price
+}
+
+/// Buy a fixed fraction of the portfolio equity
+pub struct FixedFractional {
+    fraction: Decimal,
+}
+
+impl FixedFractional {
+    pub fn new(fraction: Decimal) -> Self {
+        Self { fraction }
+    }
+}
+
+impl PositionSizer for FixedFractional {
+    fn buy_notional(
+        &self,
+        portfolio: &Portfolio,
+        _risk: &PortfolioRisk,
+        current_price: Decimal,
+    ) -> Decimal {
+        equity(portfolio, current_price) * self.fraction
+    }
+}
+
+/// Buy a fixed notional value
+pub struct FixedNotional {
+    notional: Decimal,
+}
+
+impl FixedNotional {
+    pub fn new(notional: Decimal) -> Self {
+        Self { notional }
+    }
+}
+
+impl PositionSizer for FixedNotional {
+    fn buy_notional(
+        &self,
+        _portfolio: &Portfolio,
+        _risk: &PortfolioRisk,
+        _price: Decimal,
+    ) -> Decimal {
+        self.notional
+    }
+}
+
+/// Measure of asset volatility used by [`VolatilityTarget`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolatilityMeasure {
+    /// Standard deviation of returns
+    #[default]
+    StdDev,
+    /// Average true range
+    Atr,
+}
+
+/// Size positions so that their volatility is a target fraction of the portfolio equity
+///
+/// The notional value is the equity scaled by the ratio of the target to the annualized volatility of the asset,
+/// so positions shrink as the asset becomes more volatile. The full equity is used when the asset has no
+/// measurable volatility.
+pub struct VolatilityTarget {
+    target: Decimal,
+    measure: VolatilityMeasure,
+}
+
+impl VolatilityTarget {
+    pub fn new(target: Decimal, measure: VolatilityMeasure) -> Self {
+        Self { target, measure }
+    }
+}
+
+impl PositionSizer for VolatilityTarget {
+    fn buy_notional(
+        &self,
+        portfolio: &Portfolio,
+        risk: &PortfolioRisk,
+        current_price: Decimal,
+    ) -> Decimal {
+        let equity = equity(portfolio, current_price);
+        let volatility = match self.measure {
+            VolatilityMeasure::StdDev => risk.volatility,
+            VolatilityMeasure::Atr => risk.atr_volatility,
+        };
+        if volatility.is_zero() {
+            equity
+        } else {
+            equity * self.target / volatility
+        }
+    }
+}
+
+/// Size positions with a fraction of the Kelly criterion
+///
+/// The Kelly fraction is `W - (1 - W) / R`, where `W` is the win rate and `R` is the ratio of the average win to
+/// the average loss of closed trades. Until `min_trades` trades have closed, `initial_fraction` of the equity is
+/// bought instead. Nothing is bought while the Kelly fraction is not positive.
+pub struct FractionalKelly {
+    fraction: Decimal,
+    min_trades: usize,
+    initial_fraction: Decimal,
+}
+
+impl FractionalKelly {
+    pub fn new(fraction: Decimal, min_trades: usize, initial_fraction: Decimal) -> Self {
+        Self {
+            fraction,
+            min_trades,
+            initial_fraction,
+        }
+    }
+
+    /// Kelly fraction of the closed trade profits and losses, between 0 and 1
+    fn kelly_fraction(pnl: &[Decimal]) -> Decimal {
+        let wins = pnl
+            .iter()
+            .filter(|p| **p > Decimal::ZERO)
+            .collect::<Vec<_>>();
+        let losses = pnl
+            .iter()
+            .filter(|p| **p < Decimal::ZERO)
+            .collect::<Vec<_>>();
+        if wins.is_empty() {
+            return Decimal::ZERO;
+        }
+        if losses.is_empty() {
+            return Decimal::ONE;
+        }
+
+        let average = |values: &[&Decimal]| {
+            values.iter().copied().sum::<Decimal>() / Decimal::from(values.len())
+        };
+        let win_rate = Decimal::from(wins.len()) / Decimal::from(pnl.len());
+        let payoff = average(&wins) / -average(&losses);
+        (win_rate - (Decimal::ONE - win_rate) / payoff).clamp(Decimal::ZERO, Decimal::ONE)
+    }
+}
+
+impl PositionSizer for FractionalKelly {
+    fn buy_notional(
+        &self,
+        portfolio: &Portfolio,
+        _risk: &PortfolioRisk,
+        current_price: Decimal,
+    ) -> Decimal {
+        let pnl = portfolio.closed_trade_pnl();
+        let fraction = if pnl.len() < self.min_trades {
+            self.initial_fraction
+        } else {
+            Self::kelly_fraction(&pnl) * self.fraction
+        };
+        equity(portfolio, current_price) * fraction
+    }
+}
+
+fn default_kelly_fraction() -> Decimal {
+    dec!(0.5)
+}
+
+fn default_min_trades() -> usize {
+    10
+}
+
+fn default_initial_fraction() -> Decimal {
+    dec!(0.05)
+}
+
+/// Position sizing selection
+///
+/// Meant to be read from the `[risk.sizing]` section of a TOML config file. Fractions are of the portfolio equity,
+/// so `0.1` is 10%.
+///
+/// ```toml
+/// [risk.sizing]
+/// type = "volatility_target"
+/// target = 0.2
+/// measure = "atr"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SizingConfig {
+    FixedFractional {
+        fraction: Decimal,
+    },
+    FixedNotional {
+        notional: Decimal,
+    },
+    /// `target` is the annualized volatility of a position as a fraction of equity
+    VolatilityTarget {
+        target: Decimal,
+        #[serde(default)]
+        measure: VolatilityMeasure,
+    },
+    Kelly {
+        #[serde(default = "default_kelly_fraction")]
+        fraction: Decimal,
+        #[serde(default = "default_min_trades")]
+        min_trades: usize,
+        #[serde(default = "default_initial_fraction")]
+        initial_fraction: Decimal,
+    },
+}
+
+impl SizingConfig {
+    /// Create the configured [`PositionSizer`]
+    pub fn build(&self) -> Box<dyn PositionSizer> {
+        match self {
+            SizingConfig::FixedFractional { fraction } => Box::new(FixedFractional::new(*fraction)),
+            SizingConfig::FixedNotional { notional } => Box::new(FixedNotional::new(*notional)),
+            SizingConfig::VolatilityTarget { target, measure } => {
+                Box::new(VolatilityTarget::new(*target, *measure))
+            }
+            SizingConfig::Kelly {
+                fraction,
+                min_trades,
+                initial_fraction,
+            } => Box::new(FractionalKelly::new(
+                *fraction,
+                *min_trades,
+                *initial_fraction,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ExecutedTrade, Side};
+    use chrono::{Duration, NaiveDateTime};
+
+    fn risk(volatility: Decimal, atr_volatility: Decimal) -> PortfolioRisk {
+        PortfolioRisk {
+            total_position_value: Decimal::ZERO,
+            average_entry_price: Decimal::ZERO,
+            unrealized_pnl: Decimal::ZERO,
+            value_at_risk: Decimal::ZERO,
+            expected_shortfall: Decimal::ZERO,
+            beta: Decimal::ZERO,
+            sharpe_ratio: Decimal::ZERO,
+            sortino_ratio: Decimal::ZERO,
+            calmar_ratio: Decimal::ZERO,
+            volatility,
+            atr_volatility,
+        }
+    }
+
+    /// Portfolio with 500 in capital and 5 assets
+    fn portfolio() -> Portfolio {
+        Portfolio::new(dec!(5), dec!(500), None)
+    }
+
+    #[test]
+    fn test_fixed_sizers() {
+        let risk = risk(Decimal::ZERO, Decimal::ZERO);
+        // equity of 1000 at a price of 100
+        let sizer = FixedFractional::new(dec!(0.1));
+        assert_eq!(
+            sizer.buy_notional(&portfolio(), &risk, dec!(100)),
+            dec!(100)
+        );
+
+        let sizer = FixedNotional::new(dec!(250));
+        assert_eq!(
+            sizer.buy_notional(&portfolio(), &risk, dec!(100)),
+            dec!(250)
+        );
+    }
+
+    #[test]
+    fn test_volatility_target() {
+        let risk = risk(dec!(0.4), dec!(0.8));
+
+        let sizer = VolatilityTarget::new(dec!(0.2), VolatilityMeasure::StdDev);
+        assert_eq!(
+            sizer.buy_notional(&portfolio(), &risk, dec!(100)),
+            dec!(500)
+        );
+
+        let sizer = VolatilityTarget::new(dec!(0.2), VolatilityMeasure::Atr);
+        assert_eq!(
+            sizer.buy_notional(&portfolio(), &risk, dec!(100)),
+            dec!(250)
+        );
+
+        // no volatility uses the full equity
+        let risk = self::risk(Decimal::ZERO, Decimal::ZERO);
+        assert_eq!(
+            sizer.buy_notional(&portfolio(), &risk, dec!(100)),
+            dec!(1000)
+        );
+    }
+
+    #[test]
+    fn test_kelly_fraction() {
+        // win rate of 0.5 and payoff of 2
+        let pnl = [dec!(20), dec!(-10), dec!(20), dec!(-10)];
+        assert_eq!(FractionalKelly::kelly_fraction(&pnl), dec!(0.25));
+
+        assert_eq!(FractionalKelly::kelly_fraction(&[dec!(-1)]), Decimal::ZERO);
+        assert_eq!(FractionalKelly::kelly_fraction(&[dec!(1)]), Decimal::ONE);
+        // losing edge
+        let pnl = [dec!(10), dec!(-10), dec!(-10)];
+        assert_eq!(FractionalKelly::kelly_fraction(&pnl), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_fractional_kelly() {
+        let start = NaiveDateTime::parse_from_str("2024-01-01 00:00", "%Y-%m-%d %H:%M").unwrap();
+        let mut portfolio = Portfolio::new(dec!(0), dec!(1000), start);
+        let risk = risk(Decimal::ZERO, Decimal::ZERO);
+        let sizer = FractionalKelly::new(dec!(0.5), 2, dec!(0.01));
+
+        // too few closed trades
+        assert_eq!(sizer.buy_notional(&portfolio, &risk, dec!(10)), dec!(10));
+
+        let trades = [(10, 30), (10, 5)];
+        for (i, (buy, sell)) in trades.into_iter().enumerate() {
+            for (j, (side, price)) in [(Side::Buy, buy), (Side::Sell, sell)]
+                .into_iter()
+                .enumerate()
+            {
+                portfolio.add_executed_trade(ExecutedTrade::with_calculated_notional(
+                    format!("{}-{}", i, j),
+                    side,
+                    Decimal::from(price),
+                    dec!(1),
+                    start + Duration::minutes((i * 2 + j) as i64),
+                ));
+            }
+        }
+
+        // win rate of 0.5 and payoff of 4 gives a Kelly fraction of 0.375
+        let equity = portfolio.available_capital();
+        assert_eq!(
+            sizer.buy_notional(&portfolio, &risk, dec!(10)),
+            equity * dec!(0.375) * dec!(0.5)
+        );
+    }
+
+    #[test]
+    fn test_sizing_config() {
+        let config: SizingConfig = toml::from_str(
+            r#"
+            type = "volatility_target"
+            target = 0.2
+            measure = "atr"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            SizingConfig::VolatilityTarget {
+                target: dec!(0.2),
+                measure: VolatilityMeasure::Atr
+            }
+        );
+
+        let config: SizingConfig = toml::from_str(r#"type = "kelly""#).unwrap();
+        assert_eq!(
+            config,
+            SizingConfig::Kelly {
+                fraction: dec!(0.5),
+                min_trades: 10,
+                initial_fraction: dec!(0.05)
+            }
+        );
+
+        assert!(toml::from_str::<SizingConfig>(r#"type = "martingale""#).is_err());
+    }
+}
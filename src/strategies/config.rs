@@ -0,0 +1,859 @@
+use crate::indicators::{
+    BBands, GraphProcessingError, MovingAverageCrossover, Stochastic, ATR, MACD, RSI, VWAP,
+};
+use crate::markets::manager::VALID_INTERVALS;
+use crate::processor::CandleProcessor;
+use crate::strategies::{Consensus, Strategy};
+use crate::types::ScoredSignal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Candle columns which may be used as an indicator source column
+const SOURCE_COLUMNS: [&str; 5] = ["open", "high", "low", "close", "volume"];
+
+/// An indicator which may be stored in a [`Strategy`]
+pub type BoxedIndicator =
+    Box<dyn CandleProcessor<ErrorType = GraphProcessingError, ReturnType = ScoredSignal>>;
+
+/// Creates an indicator from its config
+///
+/// Parameters not listed in the [`IndicatorRegistry`] entry are rejected before the constructor is called.
+pub type IndicatorConstructor = fn(&IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError>;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum StrategyConfigError {
+    #[error("Strategy has no indicators")]
+    NoIndicators,
+    #[error("Unknown indicator '{0}'. Available indicators: {1}")]
+    UnknownIndicator(String, String),
+    #[error("Indicator '{indicator}' does not accept parameter '{parameter}'")]
+    UnsupportedParameter {
+        indicator: String,
+        parameter: &'static str,
+    },
+    #[error("Invalid value for parameter '{parameter}' of indicator '{indicator}': {reason}")]
+    InvalidParameter {
+        indicator: String,
+        parameter: &'static str,
+        reason: String,
+    },
+    #[error("Invalid consensus: {0}")]
+    InvalidConsensus(String),
+}
+
+/// Declarative definition of a [`Strategy`]
+///
+/// Meant to be read from the `[strategy]` section of a TOML config file:
+///
+/// ```toml
+/// [strategy]
+/// consensus = { weighted = { threshold = 0.5 } }
+///
+/// [[strategy.indicators]]
+/// name = "bbands"
+/// period = 20
+/// multiplier = 2.0
+/// threshold = 0.99
+/// weight = 2.0
+///
+/// [[strategy.indicators]]
+/// name = "vwap"
+/// window = 5
+///
+/// [[strategy.indicators]]
+/// name = "ema_crossover"
+/// interval = "1h"
+/// ```
+///
+/// Indicators use candles of the trading frequency unless an `interval` is given.
+///
+/// When the section is omitted, Bollinger Bands and VWAP with default parameters must agree in unison.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+    #[serde(default)]
+    pub consensus: Consensus,
+    pub indicators: Vec<IndicatorConfig>,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            consensus: Consensus::Unison,
+            indicators: vec![IndicatorConfig::new("bbands"), IndicatorConfig::new("vwap")],
+        }
+    }
+}
+
+impl StrategyConfig {
+    /// Create the configured [`Strategy`] using the default [`IndicatorRegistry`]
+    pub fn build(&self) -> Result<Strategy, StrategyConfigError> {
+        self.build_with(&IndicatorRegistry::default())
+    }
+
+    /// Create the configured [`Strategy`] using indicators from the given registry
+    pub fn build_with(
+        &self,
+        registry: &IndicatorRegistry,
+    ) -> Result<Strategy, StrategyConfigError> {
+        if self.indicators.is_empty() {
+            return Err(StrategyConfigError::NoIndicators);
+        }
+        let consensus = self.build_consensus()?;
+        let indicators = self
+            .indicators
+            .iter()
+            .map(|indicator| registry.build(indicator))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut strategy = Strategy::new(indicators, consensus);
+        for (idx, indicator) in self.indicators.iter().enumerate() {
+            if let Some(interval) = indicator.interval()? {
+                strategy = strategy.with_interval(idx, interval);
+            }
+        }
+        Ok(strategy)
+    }
+
+    /// Validate the consensus and set indicator weights
+    fn build_consensus(&self) -> Result<Consensus, StrategyConfigError> {
+        let invalid = |reason: String| Err(StrategyConfigError::InvalidConsensus(reason));
+
+        if let Some(indicator) = self.indicators.iter().find(|indicator| {
+            indicator
+                .weight
+                .is_some_and(|weight| !weight.is_finite() || weight < 0.0)
+        }) {
+            return Err(indicator.invalid("weight", "must be a number of at least 0"));
+        }
+
+        match &self.consensus {
+            Consensus::Weighted { threshold, .. } => {
+                if !(*threshold > 0.0 && *threshold <= 1.0) {
+                    return invalid(
+                        "weighted threshold must be greater than 0 and at most 1".into(),
+                    );
+                }
+                let weights = self
+                    .indicators
+                    .iter()
+                    .map(|indicator| indicator.weight.unwrap_or(1.0))
+                    .collect::<Vec<_>>();
+                if weights.iter().sum::<f64>() <= 0.0 {
+                    return invalid("at least one indicator must have a weight above 0".into());
+                }
+                return Ok(Consensus::Weighted {
+                    weights,
+                    threshold: *threshold,
+                });
+            }
+            Consensus::Veto { indicators } => {
+                if let Some(name) = indicators
+                    .iter()
+                    .find(|name| !self.indicators.iter().any(|i| &i.name == *name))
+                {
+                    return invalid(format!("veto indicator '{}' is not in the strategy", name));
+                }
+            }
+            Consensus::Quorum(n) => {
+                if *n == 0 || *n > self.indicators.len() {
+                    return invalid(format!(
+                        "quorum must be between 1 and the number of indicators ({})",
+                        self.indicators.len()
+                    ));
+                }
+            }
+            Consensus::Unison | Consensus::Majority => {}
+        }
+
+        if self
+            .indicators
+            .iter()
+            .any(|indicator| indicator.weight.is_some())
+        {
+            return invalid("indicator weights are only used by the weighted consensus".into());
+        }
+        Ok(self.consensus.clone())
+    }
+}
+
+/// Name and parameters of a single indicator
+///
+/// Parameters which are omitted use the indicator's defaults.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct IndicatorConfig {
+    pub name: String,
+
+    /// Weight of the indicator for [`Consensus::Weighted`]. Defaults to 1.
+    pub weight: Option<f64>,
+    /// Candle interval processed by the indicator (ie: "1h"). Defaults to the trading frequency.
+    pub interval: Option<String>,
+
+    pub period: Option<usize>,
+    pub multiplier: Option<f64>,
+    pub threshold: Option<f64>,
+    pub source_column: Option<String>,
+    pub window: Option<usize>,
+    pub fast_period: Option<usize>,
+    pub slow_period: Option<usize>,
+    pub signal_period: Option<usize>,
+    pub oversold: Option<f64>,
+    pub overbought: Option<f64>,
+}
+
+impl IndicatorConfig {
+    /// Create a config for the named indicator with default parameters
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            weight: None,
+            interval: None,
+            period: None,
+            multiplier: None,
+            threshold: None,
+            source_column: None,
+            window: None,
+            fast_period: None,
+            slow_period: None,
+            signal_period: None,
+            oversold: None,
+            overbought: None,
+        }
+    }
+
+    /// Names of the parameters which are set
+    fn parameters(&self) -> Vec<&'static str> {
+        [
+            ("period", self.period.is_some()),
+            ("multiplier", self.multiplier.is_some()),
+            ("threshold", self.threshold.is_some()),
+            ("source_column", self.source_column.is_some()),
+            ("window", self.window.is_some()),
+            ("fast_period", self.fast_period.is_some()),
+            ("slow_period", self.slow_period.is_some()),
+            ("signal_period", self.signal_period.is_some()),
+            ("oversold", self.oversold.is_some()),
+            ("overbought", self.overbought.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, is_set)| is_set.then_some(name))
+        .collect()
+    }
+
+    fn invalid(&self, parameter: &'static str, reason: &str) -> StrategyConfigError {
+        StrategyConfigError::InvalidParameter {
+            indicator: self.name.clone(),
+            parameter,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Validate that an integer parameter is at least 1
+    fn positive_integer(
+        &self,
+        parameter: &'static str,
+        value: Option<usize>,
+    ) -> Result<Option<usize>, StrategyConfigError> {
+        match value {
+            Some(0) => Err(self.invalid(parameter, "must be at least 1")),
+            value => Ok(value),
+        }
+    }
+
+    /// Validate that a float parameter is finite and greater than 0
+    fn positive_float(
+        &self,
+        parameter: &'static str,
+        value: Option<f64>,
+    ) -> Result<Option<f64>, StrategyConfigError> {
+        match value {
+            Some(value) if !value.is_finite() || value <= 0.0 => {
+                Err(self.invalid(parameter, "must be a number greater than 0"))
+            }
+            value => Ok(value),
+        }
+    }
+
+    /// Validate that an oscillator level is between 0 and 100
+    fn level(
+        &self,
+        parameter: &'static str,
+        value: Option<f64>,
+    ) -> Result<Option<f64>, StrategyConfigError> {
+        match value {
+            Some(value) if !(value > 0.0 && value < 100.0) => {
+                Err(self.invalid(parameter, "must be between 0 and 100"))
+            }
+            value => Ok(value),
+        }
+    }
+
+    /// Validate that the oversold level is below the overbought level
+    fn check_levels(&self, oversold: f64, overbought: f64) -> Result<(), StrategyConfigError> {
+        if oversold >= overbought {
+            return Err(self.invalid(
+                "oversold",
+                &format!("must be below the overbought level ({})", overbought),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validate that the fast period is shorter than the slow period
+    fn check_periods(
+        &self,
+        fast_period: usize,
+        slow_period: usize,
+    ) -> Result<(), StrategyConfigError> {
+        if fast_period >= slow_period {
+            return Err(self.invalid(
+                "fast_period",
+                &format!("must be shorter than the slow period ({})", slow_period),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validate that the interval is a supported candle interval
+    fn interval(&self) -> Result<Option<&str>, StrategyConfigError> {
+        match self.interval.as_deref() {
+            Some(interval) if !VALID_INTERVALS.contains(&interval) => Err(self.invalid(
+                "interval",
+                &format!("must be one of {}", VALID_INTERVALS.join(", ")),
+            )),
+            interval => Ok(interval),
+        }
+    }
+
+    /// Validate that the source column is a candle column
+    fn source_column(&self) -> Result<Option<String>, StrategyConfigError> {
+        match &self.source_column {
+            Some(column) if !SOURCE_COLUMNS.contains(&column.as_str()) => Err(self.invalid(
+                "source_column",
+                &format!("must be one of {}", SOURCE_COLUMNS.join(", ")),
+            )),
+            column => Ok(column.clone()),
+        }
+    }
+}
+
+struct RegistryEntry {
+    parameters: &'static [&'static str],
+    constructor: IndicatorConstructor,
+}
+
+/// Maps indicator names used in config files to indicator constructors
+///
+/// The default registry contains all indicators in [`crate::indicators`].
+pub struct IndicatorRegistry {
+    entries: HashMap<&'static str, RegistryEntry>,
+}
+
+impl IndicatorRegistry {
+    /// Create a registry without any indicators
+    pub fn empty() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Register an indicator
+    ///
+    /// # Arguments
+    /// * `name` - Name used in the config file
+    /// * `parameters` - Parameters accepted by the indicator. Any other parameter is rejected.
+    /// * `constructor` - Creates the indicator from its config
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        parameters: &'static [&'static str],
+        constructor: IndicatorConstructor,
+    ) {
+        self.entries.insert(
+            name,
+            RegistryEntry {
+                parameters,
+                constructor,
+            },
+        );
+    }
+
+    /// Names of all registered indicators in alphabetical order
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = self.entries.keys().copied().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Create an indicator from its config
+    pub fn build(&self, config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+        let entry = self.entries.get(config.name.as_str()).ok_or_else(|| {
+            StrategyConfigError::UnknownIndicator(config.name.clone(), self.names().join(", "))
+        })?;
+
+        if let Some(parameter) = config
+            .parameters()
+            .into_iter()
+            .find(|parameter| !entry.parameters.contains(parameter))
+        {
+            return Err(StrategyConfigError::UnsupportedParameter {
+                indicator: config.name.clone(),
+                parameter,
+            });
+        }
+
+        (entry.constructor)(config)
+    }
+}
+
+impl Default for IndicatorRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(
+            "bbands",
+            &["period", "multiplier", "threshold", "source_column"],
+            build_bbands,
+        );
+        registry.register("vwap", &["window"], build_vwap);
+        registry.register(
+            "rsi",
+            &["period", "oversold", "overbought", "source_column"],
+            build_rsi,
+        );
+        registry.register(
+            "macd",
+            &[
+                "fast_period",
+                "slow_period",
+                "signal_period",
+                "source_column",
+            ],
+            build_macd,
+        );
+        registry.register(
+            "ema_crossover",
+            &["fast_period", "slow_period", "source_column"],
+            build_ema_crossover,
+        );
+        registry.register(
+            "sma_crossover",
+            &["fast_period", "slow_period", "source_column"],
+            build_sma_crossover,
+        );
+        registry.register("atr", &["period", "multiplier"], build_atr);
+        registry.register(
+            "stochastic",
+            &["period", "signal_period", "oversold", "overbought"],
+            build_stochastic,
+        );
+        registry
+    }
+}
+
+fn build_bbands(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    let mut bbands = BBands::default();
+    if let Some(period) = config.positive_integer("period", config.period)? {
+        bbands = bbands.with_period(period);
+    }
+    if let Some(multiplier) = config.positive_float("multiplier", config.multiplier)? {
+        bbands = bbands.with_multiplier(multiplier);
+    }
+    if let Some(threshold) = config.positive_float("threshold", config.threshold)? {
+        bbands = bbands.with_threshold(threshold);
+    }
+    if let Some(source_column) = config.source_column()? {
+        bbands = bbands.with_source_column(source_column);
+    }
+    Ok(Box::new(bbands))
+}
+
+fn build_vwap(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    let vwap = match config.positive_integer("window", config.window)? {
+        Some(window) => VWAP::new(window),
+        None => VWAP::default(),
+    };
+    Ok(Box::new(vwap))
+}
+
+fn build_rsi(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    let mut rsi = match config.positive_integer("period", config.period)? {
+        Some(period) => RSI::new(period),
+        None => RSI::default(),
+    };
+    if let Some(oversold) = config.level("oversold", config.oversold)? {
+        rsi = rsi.with_oversold(oversold);
+    }
+    if let Some(overbought) = config.level("overbought", config.overbought)? {
+        rsi = rsi.with_overbought(overbought);
+    }
+    config.check_levels(rsi.oversold(), rsi.overbought())?;
+    if let Some(source_column) = config.source_column()? {
+        rsi = rsi.with_source_column(source_column);
+    }
+    Ok(Box::new(rsi))
+}
+
+fn build_macd(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    let mut macd = MACD::default();
+    if let Some(fast_period) = config.positive_integer("fast_period", config.fast_period)? {
+        macd = macd.with_fast_period(fast_period);
+    }
+    if let Some(slow_period) = config.positive_integer("slow_period", config.slow_period)? {
+        macd = macd.with_slow_period(slow_period);
+    }
+    if let Some(signal_period) = config.positive_integer("signal_period", config.signal_period)? {
+        macd = macd.with_signal_period(signal_period);
+    }
+    config.check_periods(macd.fast_period(), macd.slow_period())?;
+    if let Some(source_column) = config.source_column()? {
+        macd = macd.with_source_column(source_column);
+    }
+    Ok(Box::new(macd))
+}
+
+fn build_crossover(
+    config: &IndicatorConfig,
+    mut crossover: MovingAverageCrossover,
+) -> Result<BoxedIndicator, StrategyConfigError> {
+    if let Some(fast_period) = config.positive_integer("fast_period", config.fast_period)? {
+        crossover = crossover.with_fast_period(fast_period);
+    }
+    if let Some(slow_period) = config.positive_integer("slow_period", config.slow_period)? {
+        crossover = crossover.with_slow_period(slow_period);
+    }
+    config.check_periods(crossover.fast_period(), crossover.slow_period())?;
+    if let Some(source_column) = config.source_column()? {
+        crossover = crossover.with_source_column(source_column);
+    }
+    Ok(Box::new(crossover))
+}
+
+fn build_ema_crossover(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    build_crossover(config, MovingAverageCrossover::ema())
+}
+
+fn build_sma_crossover(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    build_crossover(config, MovingAverageCrossover::sma())
+}
+
+fn build_atr(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    let mut atr = match config.positive_integer("period", config.period)? {
+        Some(period) => ATR::new(period),
+        None => ATR::default(),
+    };
+    if let Some(multiplier) = config.positive_float("multiplier", config.multiplier)? {
+        atr = atr.with_multiplier(multiplier);
+    }
+    Ok(Box::new(atr))
+}
+
+fn build_stochastic(config: &IndicatorConfig) -> Result<BoxedIndicator, StrategyConfigError> {
+    let mut stochastic = Stochastic::default();
+    if let Some(period) = config.positive_integer("period", config.period)? {
+        stochastic = stochastic.with_period(period);
+    }
+    if let Some(signal_period) = config.positive_integer("signal_period", config.signal_period)? {
+        stochastic = stochastic.with_signal_period(signal_period);
+    }
+    if let Some(oversold) = config.level("oversold", config.oversold)? {
+        stochastic = stochastic.with_oversold(oversold);
+    }
+    if let Some(overbought) = config.level("overbought", config.overbought)? {
+        stochastic = stochastic.with_overbought(overbought);
+    }
+    config.check_levels(stochastic.oversold(), stochastic.overbought())?;
+    Ok(Box::new(stochastic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(config: &str) -> Result<Strategy, StrategyConfigError> {
+        toml::from_str::<StrategyConfig>(config).unwrap().build()
+    }
+
+    #[test]
+    fn test_build() {
+        let config = r#"
+            consensus = "majority"
+
+            [[indicators]]
+            name = "bbands"
+            period = 10
+            multiplier = 1.5
+            threshold = 0.9
+            source_column = "open"
+
+            [[indicators]]
+            name = "vwap"
+            window = 3
+        "#;
+        let strategy = build(config).unwrap();
+        let names = strategy
+            .indicators
+            .iter()
+            .map(|indicator| indicator.get_name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["bbands", "vwap"]);
+    }
+
+    #[test]
+    fn test_default() {
+        let config: StrategyConfig = toml::from_str("[[indicators]]\nname = \"vwap\"").unwrap();
+        assert_eq!(config.consensus, Consensus::Unison);
+
+        let strategy = StrategyConfig::default().build().unwrap();
+        assert_eq!(strategy.indicators.len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_indicator() {
+        let error = build("[[indicators]]\nname = \"adx\"").err().unwrap();
+        assert_eq!(
+            error,
+            StrategyConfigError::UnknownIndicator(
+                "adx".to_string(),
+                "atr, bbands, ema_crossover, macd, rsi, sma_crossover, stochastic, vwap"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_bad_parameters() {
+        let error = build("[[indicators]]\nname = \"vwap\"\nperiod = 5")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            StrategyConfigError::UnsupportedParameter {
+                indicator: "vwap".to_string(),
+                parameter: "period"
+            }
+        );
+
+        let error = build("[[indicators]]\nname = \"vwap\"\nwindow = 0")
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            StrategyConfigError::InvalidParameter {
+                parameter: "window",
+                ..
+            }
+        ));
+
+        let error = build("[[indicators]]\nname = \"bbands\"\nmultiplier = -1.0")
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            StrategyConfigError::InvalidParameter {
+                parameter: "multiplier",
+                ..
+            }
+        ));
+
+        let error = build("[[indicators]]\nname = \"bbands\"\nsource_column = \"price\"")
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            StrategyConfigError::InvalidParameter {
+                parameter: "source_column",
+                ..
+            }
+        ));
+
+        // misspelled parameters are rejected when parsing
+        assert!(
+            toml::from_str::<StrategyConfig>("[[indicators]]\nname = \"vwap\"\nwindw = 5").is_err()
+        );
+
+        assert_eq!(
+            build("indicators = []").err().unwrap(),
+            StrategyConfigError::NoIndicators
+        );
+    }
+
+    #[test]
+    fn test_consensus() {
+        let config = r#"
+            consensus = { weighted = { threshold = 0.5 } }
+
+            [[indicators]]
+            name = "bbands"
+            weight = 3.0
+
+            [[indicators]]
+            name = "vwap"
+        "#;
+        let config = toml::from_str::<StrategyConfig>(config).unwrap();
+        assert_eq!(
+            config.build_consensus().unwrap(),
+            Consensus::Weighted {
+                weights: vec![3.0, 1.0],
+                threshold: 0.5
+            }
+        );
+
+        let config =
+            "consensus = { veto = { indicators = [\"vwap\"] } }\n[[indicators]]\nname = \"vwap\"";
+        assert!(build(config).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_consensus() {
+        let invalid = |config: &str| {
+            matches!(
+                build(config).err().unwrap(),
+                StrategyConfigError::InvalidConsensus(_)
+            )
+        };
+        assert!(invalid(
+            "consensus = { weighted = { threshold = 1.5 } }\n[[indicators]]\nname = \"vwap\""
+        ));
+        assert!(invalid(
+            "consensus = { weighted = { threshold = 0.5 } }\n[[indicators]]\nname = \"vwap\"\nweight = 0.0"
+        ));
+        assert!(invalid(
+            "consensus = { veto = { indicators = [\"bbands\"] } }\n[[indicators]]\nname = \"vwap\""
+        ));
+        assert!(invalid(
+            "consensus = { quorum = 2 }\n[[indicators]]\nname = \"vwap\""
+        ));
+        assert!(invalid("[[indicators]]\nname = \"vwap\"\nweight = 2.0"));
+
+        let error = build(
+            "consensus = { weighted = { threshold = 0.5 } }\n[[indicators]]\nname = \"vwap\"\nweight = -1.0",
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            error,
+            StrategyConfigError::InvalidParameter {
+                parameter: "weight",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_build_all_indicators() {
+        let config = r#"
+            consensus = "majority"
+
+            [[indicators]]
+            name = "rsi"
+            period = 10
+            oversold = 25.0
+            overbought = 75.0
+
+            [[indicators]]
+            name = "macd"
+            fast_period = 5
+            slow_period = 10
+            signal_period = 4
+
+            [[indicators]]
+            name = "ema_crossover"
+            fast_period = 5
+
+            [[indicators]]
+            name = "sma_crossover"
+            slow_period = 50
+            source_column = "open"
+
+            [[indicators]]
+            name = "atr"
+            period = 10
+            multiplier = 1.5
+
+            [[indicators]]
+            name = "stochastic"
+            period = 10
+            signal_period = 3
+        "#;
+        let strategy = build(config).unwrap();
+        let names = strategy
+            .indicators
+            .iter()
+            .map(|indicator| indicator.get_name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "rsi",
+                "macd",
+                "ema_crossover",
+                "sma_crossover",
+                "atr",
+                "stochastic"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_levels_and_periods() {
+        let invalid_parameter = |config: &str, expected: &str| match build(config).err().unwrap() {
+            StrategyConfigError::InvalidParameter { parameter, .. } => parameter == expected,
+            _ => false,
+        };
+
+        assert!(invalid_parameter(
+            "[[indicators]]\nname = \"rsi\"\noversold = 120.0",
+            "oversold"
+        ));
+        assert!(invalid_parameter(
+            "[[indicators]]\nname = \"stochastic\"\noversold = 50.0\noverbought = 40.0",
+            "oversold"
+        ));
+        // the default overbought level of the RSI is 70
+        assert!(invalid_parameter(
+            "[[indicators]]\nname = \"rsi\"\noversold = 80.0",
+            "oversold"
+        ));
+        // the default fast period of the MACD is 12
+        assert!(invalid_parameter(
+            "[[indicators]]\nname = \"macd\"\nslow_period = 10",
+            "fast_period"
+        ));
+        assert!(invalid_parameter(
+            "[[indicators]]\nname = \"sma_crossover\"\nfast_period = 0",
+            "fast_period"
+        ));
+    }
+
+    #[test]
+    fn test_interval() {
+        let config = r#"
+            [[indicators]]
+            name = "rsi"
+
+            [[indicators]]
+            name = "ema_crossover"
+            interval = "1h"
+
+            [[indicators]]
+            name = "vwap"
+            interval = "1h"
+        "#;
+        let strategy = build(config).unwrap();
+        assert_eq!(strategy.intervals(), ["1h"]);
+
+        let error = build("[[indicators]]\nname = \"vwap\"\ninterval = \"2h\"")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            StrategyConfigError::InvalidParameter {
+                indicator: "vwap".to_string(),
+                parameter: "interval",
+                reason: "must be one of 1m, 5m, 15m, 1h, 6h, 1d".to_string(),
+            }
+        );
+    }
+}
@@ -1,14 +1,47 @@
-use crate::types::Signal;
+use crate::types::{ScoredSignal, Signal};
+use serde::Deserialize;
 
 /// The [`Consensus`] enum is used to define how a [`Strategy`] should reach a consensus
 /// between multiple [`Indicator`] objects.
+///
+/// Read from the `consensus` key of the `[strategy]` config section:
+///
+/// ```toml
+/// consensus = "majority"
+/// consensus = { weighted = { threshold = 0.5 } }
+/// consensus = { veto = { indicators = ["vwap"] } }
+/// consensus = { quorum = 2 }
+/// ```
+///
+/// All modes accept scored signals in the range `[-1, 1]`. Positive scores are buy signals and negative
+/// scores are sell signals. Only [`Consensus::Weighted`] uses the strength of a score.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum Consensus {
     /// The [`Strategy`] will produce a [`FutureTrade`] if all [`Signal`]s returned by
     /// [`Indicator`] objects are the same.
+    #[default]
     Unison,
     /// The [`Strategy`] will produce a [`FutureTrade`] if the majority of [`Signal`]s
     /// returned by [`Indicator`] objects are the same.
     Majority,
+    /// The [`Strategy`] will produce a [`FutureTrade`] if the weighted average score reaches `threshold`.
+    ///
+    /// Scores are averaged using `weights`, which are ordered the same as the indicators. Indicators without
+    /// a weight have a weight of 1. A buy is produced when the average is at least `threshold` and a sell
+    /// when the average is at most `-threshold`.
+    Weighted {
+        /// Set from the `weight` of each indicator in the `[strategy]` config section
+        #[serde(skip)]
+        weights: Vec<f64>,
+        threshold: f64,
+    },
+    /// The majority [`Signal`] is used unless one of the named [`Indicator`] objects reports the
+    /// opposite direction.
+    Veto { indicators: Vec<String> },
+    /// The [`Strategy`] will produce a [`FutureTrade`] if at least `n` [`Signal`]s agree and fewer
+    /// than `n` [`Signal`]s are in the opposite direction.
+    Quorum(usize),
 }
 
 impl Consensus {
@@ -17,41 +50,107 @@ impl Consensus {
         match self {
             Consensus::Unison => "unison",
             Consensus::Majority => "majority",
+            Consensus::Weighted { .. } => "weighted",
+            Consensus::Veto { .. } => "veto",
+            Consensus::Quorum(_) => "quorum",
         }
     }
 
-    /// Accepts an iterator of [`Signal`]s and returns a [`Signal`] based on the consensus type
+    /// Reduce unnamed [`Signal`]s with full confidence
+    ///
+    /// Since signals are not named, [`Consensus::Veto`] reduces to [`Consensus::Majority`].
+    #[cfg(test)]
+    fn reduce(&self, signals: impl Iterator<Item = Signal>) -> Signal {
+        self.reduce_scored(signals.map(|signal| ("", ScoredSignal::new(signal, 1.0))))
+            .signal
+    }
+
+    /// Accepts an iterator of indicator names and [`ScoredSignal`]s and returns a [`ScoredSignal`] based on
+    /// the consensus type
+    ///
+    /// The direction is determined by [`Consensus::reduce_scores`]. For [`Consensus::Weighted`] the confidence
+    /// is the magnitude of the weighted average score. Otherwise, the confidence is the average confidence of
+    /// the signals which agree with the consensus.
     ///
     /// # Arguments
-    /// * `signals` - An iterator of [`Signal`]s
+    /// * `signals` - Pairs of indicator name and [`ScoredSignal`]
+    pub fn reduce_scored<'a>(
+        &self,
+        signals: impl Iterator<Item = (&'a str, ScoredSignal)>,
+    ) -> ScoredSignal {
+        let signals = signals.collect::<Vec<_>>();
+        let direction =
+            self.reduce_scores(signals.iter().map(|(name, signal)| (*name, signal.score())));
+
+        let confidence = match self {
+            Consensus::Weighted { weights, .. } => {
+                weighted_average(weights, signals.iter().map(|(_, signal)| signal.score())).abs()
+            }
+            _ => {
+                let agreeing = signals
+                    .iter()
+                    .filter(|(_, signal)| signal.signal == direction)
+                    .map(|(_, signal)| signal.confidence)
+                    .collect::<Vec<_>>();
+                if agreeing.is_empty() {
+                    0.0
+                } else {
+                    agreeing.iter().sum::<f64>() / agreeing.len() as f64
+                }
+            }
+        };
+        ScoredSignal::new(direction, confidence)
+    }
+
+    /// Accepts an iterator of indicator names and scores and returns a [`Signal`] based on the consensus type
+    ///
+    /// # Arguments
+    /// * `scores` - Pairs of indicator name and score. Scores are clamped to the range `[-1, 1]`.
     ///
     /// # Returns
-    /// A [`Signal`] based on the consensus type
-    pub fn reduce(&self, signals: impl Iterator<Item = Signal>) -> Signal {
-        let mut iter = signals.into_iter();
+    /// A [`Signal`] based on the consensus type. [`Signal::Hold`] is returned when there are no scores.
+    pub fn reduce_scores<'a>(&self, scores: impl Iterator<Item = (&'a str, f64)>) -> Signal {
+        let scores = scores
+            .map(|(name, score)| (name, score.clamp(-1.0, 1.0)))
+            .collect::<Vec<_>>();
+        let signals = scores.iter().map(|(_, score)| Signal::from_score(*score));
+
         match self {
             Consensus::Unison => {
-                let first = iter.next().unwrap();
-                if iter.all(|x| x == first) {
-                    first
+                let mut iter = signals;
+                match iter.next() {
+                    Some(first) if iter.all(|x| x == first) => first,
+                    _ => Signal::Hold,
+                }
+            }
+            Consensus::Majority => majority(signals),
+            Consensus::Weighted { weights, threshold } => {
+                let average = weighted_average(weights, scores.iter().map(|(_, score)| *score));
+                if average != 0.0 && average >= *threshold {
+                    Signal::Buy
+                } else if average != 0.0 && average <= -threshold {
+                    Signal::Sell
                 } else {
                     Signal::Hold
                 }
             }
-            Consensus::Majority => {
-                let mut buy = 0;
-                let mut sell = 0;
-                let mut hold = 0;
-                for signal in iter {
-                    match signal {
-                        Signal::Buy => buy += 1,
-                        Signal::Sell => sell += 1,
-                        Signal::Hold => hold += 1,
-                    }
+            Consensus::Veto { indicators } => {
+                let signal = majority(signals);
+                let vetoed = scores.iter().any(|(name, score)| {
+                    indicators.iter().any(|indicator| indicator == name)
+                        && signal.score() * score < 0.0
+                });
+                if vetoed {
+                    Signal::Hold
+                } else {
+                    signal
                 }
-                if buy > sell && buy > hold {
+            }
+            Consensus::Quorum(n) => {
+                let (buy, sell) = count(signals);
+                if buy >= *n && sell < *n {
                     Signal::Buy
-                } else if sell > buy && sell > hold {
+                } else if sell >= *n && buy < *n {
                     Signal::Sell
                 } else {
                     Signal::Hold
@@ -61,6 +160,54 @@ impl Consensus {
     }
 }
 
+/// Average of the scores using the weights. Scores without a weight have a weight of 1.
+///
+/// Returns 0 when the total weight is not positive.
+fn weighted_average(weights: &[f64], scores: impl Iterator<Item = f64>) -> f64 {
+    let (total, total_weight) =
+        scores
+            .enumerate()
+            .fold((0.0, 0.0), |(total, total_weight), (idx, score)| {
+                let weight = weights.get(idx).copied().unwrap_or(1.0);
+                (total + weight * score, total_weight + weight)
+            });
+    if total_weight <= 0.0 {
+        0.0
+    } else {
+        total / total_weight
+    }
+}
+
+/// Count the buy and sell signals
+fn count(signals: impl Iterator<Item = Signal>) -> (usize, usize) {
+    signals.fold((0, 0), |(buy, sell), signal| match signal {
+        Signal::Buy => (buy + 1, sell),
+        Signal::Sell => (buy, sell + 1),
+        Signal::Hold => (buy, sell),
+    })
+}
+
+/// Returns the most common [`Signal`], or [`Signal::Hold`] when there is no single most common signal
+fn majority(signals: impl Iterator<Item = Signal>) -> Signal {
+    let mut buy = 0;
+    let mut sell = 0;
+    let mut hold = 0;
+    for signal in signals {
+        match signal {
+            Signal::Buy => buy += 1,
+            Signal::Sell => sell += 1,
+            Signal::Hold => hold += 1,
+        }
+    }
+    if buy > sell && buy > hold {
+        Signal::Buy
+    } else if sell > buy && sell > hold {
+        Signal::Sell
+    } else {
+        Signal::Hold
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -132,4 +279,147 @@ mod tests {
         let signals = vec![Signal::Buy, Signal::Sell, Signal::Hold];
         assert_eq!(consensus.reduce(signals.into_iter()), Signal::Hold);
     }
+
+    #[test]
+    fn test_consensus_reduce_empty() {
+        use super::*;
+        let consensuses = [
+            Consensus::Unison,
+            Consensus::Majority,
+            Consensus::Weighted {
+                weights: vec![],
+                threshold: 0.5,
+            },
+            Consensus::Veto { indicators: vec![] },
+            Consensus::Quorum(1),
+        ];
+        for consensus in consensuses {
+            assert_eq!(consensus.reduce(std::iter::empty()), Signal::Hold);
+        }
+    }
+
+    #[test]
+    fn test_consensus_reduce_weighted() {
+        use super::*;
+        let consensus = Consensus::Weighted {
+            weights: vec![3.0, 1.0],
+            threshold: 0.5,
+        };
+
+        // (3 * 1 - 1) / 4 = 0.5
+        let signals = vec![Signal::Buy, Signal::Sell];
+        assert_eq!(consensus.reduce(signals.into_iter()), Signal::Buy);
+
+        // (3 * -1 + 1) / 4 = -0.5
+        let signals = vec![Signal::Sell, Signal::Buy];
+        assert_eq!(consensus.reduce(signals.into_iter()), Signal::Sell);
+
+        // (3 * 0.2 + 1) / 4 = 0.4
+        let scores = vec![("bbands", 0.2), ("vwap", 1.0)];
+        assert_eq!(consensus.reduce_scores(scores.into_iter()), Signal::Hold);
+
+        // indicators without a weight have a weight of 1: (3 * 0.5 + 1 + 1) / 5 = 0.7
+        let scores = vec![("bbands", 0.5), ("vwap", 1.0), ("rsi", 1.0)];
+        assert_eq!(consensus.reduce_scores(scores.into_iter()), Signal::Buy);
+
+        // scores are clamped: (3 * -1 + 1) / 4 = -0.5
+        let scores = vec![("bbands", -5.0), ("vwap", 1.0)];
+        assert_eq!(consensus.reduce_scores(scores.into_iter()), Signal::Sell);
+    }
+
+    #[test]
+    fn test_consensus_reduce_veto() {
+        use super::*;
+        let consensus = Consensus::Veto {
+            indicators: vec!["vwap".to_string()],
+        };
+
+        let scores = vec![("bbands", 1.0), ("rsi", 1.0), ("vwap", 0.0)];
+        assert_eq!(consensus.reduce_scores(scores.into_iter()), Signal::Buy);
+
+        // vwap blocks the buy
+        let scores = vec![("bbands", 1.0), ("rsi", 1.0), ("vwap", -0.1)];
+        assert_eq!(consensus.reduce_scores(scores.into_iter()), Signal::Hold);
+
+        // other indicators cannot block
+        let scores = vec![("bbands", -1.0), ("rsi", -1.0), ("macd", 1.0)];
+        assert_eq!(consensus.reduce_scores(scores.into_iter()), Signal::Sell);
+    }
+
+    #[test]
+    fn test_consensus_reduce_quorum() {
+        use super::*;
+        let consensus = Consensus::Quorum(2);
+
+        let signals = vec![Signal::Buy, Signal::Buy, Signal::Hold, Signal::Hold];
+        assert_eq!(consensus.reduce(signals.into_iter()), Signal::Buy);
+
+        let signals = vec![Signal::Sell, Signal::Sell, Signal::Buy];
+        assert_eq!(consensus.reduce(signals.into_iter()), Signal::Sell);
+
+        let signals = vec![Signal::Buy, Signal::Hold, Signal::Hold];
+        assert_eq!(consensus.reduce(signals.into_iter()), Signal::Hold);
+
+        // both directions reach the quorum
+        let signals = vec![Signal::Buy, Signal::Buy, Signal::Sell, Signal::Sell];
+        assert_eq!(consensus.reduce(signals.into_iter()), Signal::Hold);
+    }
+
+    #[test]
+    fn test_consensus_deserialize() {
+        use super::*;
+
+        #[derive(Deserialize)]
+        struct Config {
+            consensus: Consensus,
+        }
+        let parse = |s: &str| toml::from_str::<Config>(s).unwrap().consensus;
+
+        assert_eq!(parse("consensus = \"unison\""), Consensus::Unison);
+        assert_eq!(parse("consensus = { quorum = 3 }"), Consensus::Quorum(3));
+        assert_eq!(
+            parse("consensus = { weighted = { threshold = 0.5 } }"),
+            Consensus::Weighted {
+                weights: vec![],
+                threshold: 0.5
+            }
+        );
+        assert_eq!(
+            parse("consensus = { veto = { indicators = [\"vwap\"] } }"),
+            Consensus::Veto {
+                indicators: vec!["vwap".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_consensus_reduce_scored() {
+        use super::*;
+        let signals = || {
+            vec![
+                ("bbands", ScoredSignal::new(Signal::Buy, 0.8)),
+                ("vwap", ScoredSignal::new(Signal::Buy, 0.4)),
+                ("rsi", ScoredSignal::new(Signal::Sell, 0.2)),
+            ]
+            .into_iter()
+        };
+
+        let signal = Consensus::Majority.reduce_scored(signals());
+        assert_eq!(signal.signal, Signal::Buy);
+        assert!((signal.confidence - 0.6).abs() < 1e-9);
+
+        assert_eq!(
+            Consensus::Unison.reduce_scored(signals()),
+            ScoredSignal::from(Signal::Hold)
+        );
+
+        // (0.8 + 0.4 - 0.2) / 3
+        let consensus = Consensus::Weighted {
+            weights: vec![],
+            threshold: 0.3,
+        };
+        let signal = consensus.reduce_scored(signals());
+        assert_eq!(signal.signal, Signal::Buy);
+        assert!((signal.confidence - 1.0 / 3.0).abs() < 1e-9);
+    }
 }
@@ -1,22 +1,26 @@
+mod config;
 mod consensus;
 
 use crate::indicators::GraphProcessingError;
 use crate::markets::utils::save_candles;
 use crate::processor::CandleProcessor;
+pub use crate::strategies::config::{BoxedIndicator, StrategyConfig, StrategyConfigError};
 pub use crate::strategies::consensus::Consensus;
-use crate::types::Signal;
+use crate::types::{Candle, ScoredSignal, Signal};
 use log::info;
 use polars::prelude::*;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub enum StrategyError {
     IndicatorError(GraphProcessingError),
+    /// Raised when no candles are given for the interval an indicator is bound to
+    IntervalNotFound(String),
 }
 
 /// A [`IndicatorContainer`] is a collection of [`CandleProcessor`] objects.
-type IndicatorContainer =
-    Vec<Box<dyn CandleProcessor<ErrorType = GraphProcessingError, ReturnType = Signal>>>;
+type IndicatorContainer = Vec<BoxedIndicator>;
 
 /// A [`Strategy`] is a facade for interfacing with more than one [`CandleProcessor`] objects.
 ///
@@ -24,14 +28,20 @@ type IndicatorContainer =
 /// and generating a consensus [`Signal`] among all [`CandleProcessor`] objects.
 ///
 /// `Strategy::process_historical_candles` is used for bootstrapping the indicators with historical data.
-/// `Strategy::process_new_candles` is used for ingesting new candle data and generating a consensus signal.
+/// `Strategy::process_new_candle` is used for ingesting new candle data and generating a consensus signal.
+///
+/// Indicators may be bound to a candle interval other than the trading frequency with
+/// `Strategy::with_interval`. Candles for those intervals are passed to `Strategy::process_timeframes`.
 pub struct Strategy {
     pub indicators: IndicatorContainer,
     consensus: Consensus,
+
+    /// Candle interval of indicators, keyed by index. Unbound indicators use the trading frequency.
+    intervals: HashMap<usize, String>,
 }
 
 impl CandleProcessor for Strategy {
-    type ReturnType = Signal;
+    type ReturnType = ScoredSignal;
     type ErrorType = StrategyError;
 
     /// Process all historical data
@@ -39,15 +49,10 @@ impl CandleProcessor for Strategy {
     /// The internal state of all [`CandleProcessor`] objects is updated with the historical data. Any existing
     /// data in the indicators is overwritten.
     ///
-    /// This method is used upon initial load, or during backtesting.
+    /// This method is used upon initial load, or during backtesting. Indicators bound to an interval raise
+    /// [`StrategyError::IntervalNotFound`]; use `Strategy::process_timeframes` instead.
     fn process_candle(&self, candles: &DataFrame) -> Result<Self::ReturnType, Self::ErrorType> {
-        let results = self.indicators.iter().map(|indicator| {
-            indicator
-                .process_candle(candles)
-                .map_err(|x| StrategyError::IndicatorError(x))
-                .unwrap()
-        });
-        Ok(self.consensus.reduce(results))
+        self.process_timeframes(candles, &HashMap::new())
     }
 
     fn get_name(&self) -> &'static str {
@@ -81,6 +86,41 @@ impl CandleProcessor for Strategy {
 
         df
     }
+
+    /// Ingest a new candle in every indicator
+    ///
+    /// All indicators are updated so that their state stays aligned. Returns `None` if any indicator does not
+    /// support incremental updates.
+    fn process_new_candle(
+        &mut self,
+        candle: &Candle,
+    ) -> Option<Result<Self::ReturnType, Self::ErrorType>> {
+        // candles of other intervals are not passed to bound indicators
+        if !self.intervals.is_empty() {
+            return None;
+        }
+
+        let mut results = Vec::with_capacity(self.indicators.len());
+        let mut supported = true;
+        for indicator in self.indicators.iter_mut() {
+            match indicator.process_new_candle(candle) {
+                Some(Ok(signal)) => results.push((indicator.get_name(), signal)),
+                Some(Err(e)) => return Some(Err(StrategyError::IndicatorError(e))),
+                None => supported = false,
+            }
+        }
+
+        if !supported {
+            return None;
+        }
+        Some(Ok(self.consensus.reduce_scored(results.into_iter())))
+    }
+
+    fn reset(&mut self) {
+        self.indicators
+            .iter_mut()
+            .for_each(|indicator| indicator.reset());
+    }
 }
 
 impl Strategy {
@@ -88,9 +128,64 @@ impl Strategy {
         Self {
             indicators,
             consensus,
+            intervals: HashMap::new(),
         }
     }
 
+    /// Bind the indicator at `index` to a candle interval (ie: "1h")
+    pub fn with_interval<S: Into<String>>(mut self, index: usize, interval: S) -> Self {
+        self.intervals.insert(index, interval.into());
+        self
+    }
+
+    /// Get the distinct candle intervals that indicators are bound to
+    pub fn intervals(&self) -> Vec<&str> {
+        let mut intervals = self
+            .intervals
+            .values()
+            .map(|interval| interval.as_str())
+            .collect::<Vec<_>>();
+        intervals.sort();
+        intervals.dedup();
+        intervals
+    }
+
+    /// Process candles of the trading frequency and of every interval that indicators are bound to
+    ///
+    /// Indicators bound to an interval hold until `timeframes` contains at least one candle for it. Only
+    /// closed candles should be passed in `timeframes` to avoid look-ahead. See [`crate::utils::closed_candles`].
+    ///
+    /// # Arguments
+    /// * `candles` - Candles of the trading frequency
+    /// * `timeframes` - Candles keyed by interval
+    pub fn process_timeframes(
+        &self,
+        candles: &DataFrame,
+        timeframes: &HashMap<String, DataFrame>,
+    ) -> Result<ScoredSignal, StrategyError> {
+        let results = self
+            .indicators
+            .iter()
+            .enumerate()
+            .map(|(idx, indicator)| {
+                let candles = match self.intervals.get(&idx) {
+                    Some(interval) => timeframes
+                        .get(interval)
+                        .ok_or_else(|| StrategyError::IntervalNotFound(interval.clone()))?,
+                    None => candles,
+                };
+                if candles.height() == 0 {
+                    return Ok((indicator.get_name(), Signal::Hold.into()));
+                }
+                indicator
+                    .process_candle(candles)
+                    .map(|signal| (indicator.get_name(), signal))
+                    .map_err(StrategyError::IndicatorError)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.consensus.reduce_scored(results.into_iter()))
+    }
+
     pub fn save_indicators<P: Into<PathBuf>>(&self, candles: &DataFrame, path: P) {
         let path = path.into();
 
@@ -132,33 +227,157 @@ mod strategy_tests {
         .unwrap()
     }
 
+    /// Indicator which always returns the same signal
+    struct FixedSignal(Signal);
+
+    impl CandleProcessor for FixedSignal {
+        type ReturnType = ScoredSignal;
+        type ErrorType = GraphProcessingError;
+
+        fn process_candle(&self, _: &DataFrame) -> Result<ScoredSignal, GraphProcessingError> {
+            Ok(self.0.into())
+        }
+
+        fn get_name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn get_raw_dataframe(&self, candles: &DataFrame) -> DataFrame {
+            candles.clone()
+        }
+    }
+
+    fn process_signals(signals: &[Signal]) -> Signal {
+        let indicators = signals
+            .iter()
+            .map(|signal| Box::new(FixedSignal(*signal)) as BoxedIndicator)
+            .collect();
+        Strategy::new(indicators, Consensus::Majority)
+            .process_candle(&create_dataframe())
+            .unwrap()
+            .signal
+    }
+
     #[test]
     fn combined_signals_with_unanimous_buy_signals() {
-        todo!()
+        let signal = process_signals(&[Signal::Buy, Signal::Buy, Signal::Buy]);
+        assert_eq!(signal, Signal::Buy);
     }
 
     #[test]
     fn combined_signals_with_unanimous_sell_signals() {
-        todo!()
+        let signal = process_signals(&[Signal::Sell, Signal::Sell, Signal::Sell]);
+        assert_eq!(signal, Signal::Sell);
     }
 
     #[test]
     fn combined_signals_with_mixed_signals_majority_buy() {
-        todo!()
+        let signal = process_signals(&[Signal::Buy, Signal::Buy, Signal::Sell]);
+        assert_eq!(signal, Signal::Buy);
     }
 
     #[test]
     fn combined_signals_with_mixed_signals_majority_sell() {
-        todo!()
+        let signal = process_signals(&[Signal::Sell, Signal::Hold, Signal::Sell]);
+        assert_eq!(signal, Signal::Sell);
     }
 
     #[test]
     fn combined_signals_with_no_signals_returns_hold() {
-        todo!()
+        assert_eq!(process_signals(&[]), Signal::Hold);
     }
 
     #[test]
     fn combined_signals_with_equal_buy_and_sell_signals_returns_hold() {
-        todo!()
+        let signal = process_signals(&[Signal::Buy, Signal::Sell]);
+        assert_eq!(signal, Signal::Hold);
+    }
+
+    #[test]
+    fn test_get_raw_dataframe() {
+        use crate::indicators::{create_candles, MACD, RSI};
+
+        let closes = (0..30).map(|i| 100.0 + (i % 7) as f64).collect::<Vec<_>>();
+        let candles = create_candles(&closes);
+        let strategy = Strategy::new(
+            vec![Box::new(RSI::new(5)), Box::new(MACD::new(3, 6, 3))],
+            Consensus::Majority,
+        );
+
+        let df = strategy.get_raw_dataframe(&candles);
+        assert_eq!(
+            df.get_column_names(),
+            ["time", "rsi", "macd", "macd_signal", "macd_histogram"]
+        );
+        assert_eq!(df.height(), candles.height());
+    }
+
+    #[test]
+    fn test_process_new_candle() {
+        use crate::indicators::{create_candles, RSI, VWAP};
+        use crate::utils::extract_candles_from_df;
+
+        let closes = (0..40)
+            .map(|i| 100.0 + (i as f64 * 0.6).sin() * 4.0)
+            .collect::<Vec<_>>();
+        let candles = create_candles(&closes);
+        let mut strategy = Strategy::new(
+            vec![Box::new(BBands::new(10, 1.0)), Box::new(VWAP::new(5))],
+            Consensus::Unison,
+        );
+
+        for (i, candle) in extract_candles_from_df(&candles)
+            .unwrap()
+            .iter()
+            .enumerate()
+        {
+            let streamed = strategy.process_new_candle(candle).unwrap().unwrap();
+            let batch = strategy.process_candle(&candles.head(Some(i + 1))).unwrap();
+            assert_eq!(streamed.signal, batch.signal);
+            assert!((streamed.confidence - batch.confidence).abs() < 1e-9);
+        }
+
+        // indicators without incremental updates fall back to `process_candle`
+        let mut strategy = Strategy::new(
+            vec![Box::new(BBands::default()), Box::new(RSI::default())],
+            Consensus::Unison,
+        );
+        let candle = extract_candles_from_df(&candles).unwrap().remove(0);
+        assert!(strategy.process_new_candle(&candle).is_none());
+    }
+
+    #[test]
+    fn test_process_timeframes() {
+        use crate::indicators::{create_candles, RSI};
+
+        let falling = (0..20).map(|i| 100.0 - i as f64).collect::<Vec<_>>();
+        let rising = (0..20).map(|i| 100.0 + i as f64).collect::<Vec<_>>();
+
+        // RSI is oversold on the trading frequency, but overbought on the hourly candles
+        let strategy = Strategy::new(
+            vec![Box::new(RSI::new(5)), Box::new(RSI::new(5))],
+            Consensus::Majority,
+        )
+        .with_interval(1, "1h");
+        assert_eq!(strategy.intervals(), ["1h"]);
+
+        let candles = create_candles(&falling);
+        let timeframes = HashMap::from([("1h".to_string(), create_candles(&rising))]);
+        let signal = strategy.process_timeframes(&candles, &timeframes).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+
+        let timeframes = HashMap::from([("1h".to_string(), create_candles(&falling))]);
+        let signal = strategy.process_timeframes(&candles, &timeframes).unwrap();
+        assert_eq!(signal.signal, Signal::Buy);
+
+        // bound indicators hold until a candle of their interval has closed
+        let timeframes = HashMap::from([("1h".to_string(), candles.clear())]);
+        let signal = strategy.process_timeframes(&candles, &timeframes).unwrap();
+        assert_eq!(signal.signal, Signal::Hold);
+
+        assert!(matches!(
+            strategy.process_candle(&candles),
+            Err(StrategyError::IntervalNotFound(_))
+        ));
     }
 }
@@ -7,7 +7,7 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 /// Abstracts a candlestick
-#[derive(Serialize, Debug, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct Candle {
     #[serde(serialize_with = "crate::serialization::naive_dt_serializer")]
     #[serde(deserialize_with = "crate::serialization::naive_dt_deserializer")]
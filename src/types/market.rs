@@ -1,21 +1,39 @@
 use crate::traits::AsDataFrame;
 use crate::utils;
-use polars::prelude::DataFrame;
+use chrono::NaiveDateTime;
+use polars::prelude::*;
 use sqlite::Connection;
 use std::collections::HashMap;
+use std::path::Path;
 
-/// Path to the database file
-const DB_PATH: &str = "data/candle_data.sqlite3";
+/// Default path to the sqlite candle store
+pub const DB_PATH: &str = "data/candle_data.sqlite3";
 
 /// Intraday frequency names ordered by priority
 const INTRADAY_FREQUENCIES: [&str; 6] = ["1m", "5m", "15m", "1h", "6h", "1d"];
 
+/// Number of candles of an intraday frequency in a year
+///
+/// Markets trade around the clock, so a year is 365 full days. Used to annualize per-candle ratios.
+///
+/// # Returns
+/// `None` if the frequency is not one of the intraday frequencies
+pub fn candles_per_year(frequency: &str) -> Option<u64> {
+    if !INTRADAY_FREQUENCIES.contains(&frequency) {
+        return None;
+    }
+    let duration = utils::frequency_to_duration(frequency)?;
+    Some(365 * 24 * 60 * 60 / duration.as_secs())
+}
+
 #[derive(Debug)]
 pub enum MarketDataError {
     FrequencyNotFound,
+    /// Raised when the candle database cannot be read
+    DatabaseError(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MarketData {
     /// Used to identify the asset
     pub asset_name: String,
@@ -23,15 +41,33 @@ pub struct MarketData {
 }
 
 impl MarketData {
-    /// Create a new [`MarketData`] instance from the database
-    pub fn from_db<S: Into<String>>(asset_name: S) -> Self {
+    /// Create a new [`MarketData`] instance from the database at the given path
+    ///
+    /// # Arguments
+    /// * `db_path` - Path to the sqlite candle store
+    /// * `asset_name` - Used to find the tables which store candles for the asset
+    pub fn from_db_path<P: AsRef<Path>, S: Into<String>>(
+        db_path: P,
+        asset_name: S,
+    ) -> Result<Self, MarketDataError> {
+        let db_path = db_path.as_ref();
+        if !db_path.is_file() {
+            return Err(MarketDataError::DatabaseError(format!(
+                "{} does not exist",
+                db_path.display()
+            )));
+        }
+        let db_path = db_path
+            .to_str()
+            .ok_or(MarketDataError::DatabaseError("Invalid path".to_string()))?;
+
         let asset_name = asset_name.into();
-        let table_names = get_relevant_table_names(&asset_name);
+        let table_names = get_relevant_table_names(db_path, &asset_name)?;
 
         let candles = table_names
             .into_iter()
             .map(|table_name| {
-                let df = utils::extract_candles_from_db(DB_PATH, &table_name)
+                let df = utils::extract_candles_from_db(db_path, &table_name)
                     .unwrap()
                     .as_dataframe();
 
@@ -40,10 +76,29 @@ impl MarketData {
             })
             .collect();
 
-        MarketData {
+        Ok(MarketData {
             asset_name,
             candles,
+        })
+    }
+
+    /// Only keep candles between `start` and `end` (inclusive)
+    pub fn with_date_range(
+        mut self,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Self {
+        for df in self.candles.values_mut() {
+            let mut filtered = df.clone().lazy();
+            if let Some(start) = start {
+                filtered = filtered.filter(col("time").gt_eq(lit(start)));
+            }
+            if let Some(end) = end {
+                filtered = filtered.filter(col("time").lt_eq(lit(end)));
+            }
+            *df = filtered.collect().unwrap();
         }
+        self
     }
 
     pub fn get_candles(&self, frequency: &String) -> Result<&DataFrame, MarketDataError> {
@@ -58,10 +113,15 @@ impl MarketData {
 /// Retrieves all table names that contain the given substring.
 ///
 /// Used to find all tables relevant to a given asset name
-fn get_relevant_table_names(substring: &String) -> Vec<String> {
-    let conn = Connection::open(DB_PATH).unwrap();
-    conn.prepare("SELECT name FROM sqlite_master WHERE type='table'")
-        .unwrap()
+fn get_relevant_table_names(
+    db_path: &str,
+    substring: &String,
+) -> Result<Vec<String>, MarketDataError> {
+    let conn =
+        Connection::open(db_path).map_err(|e| MarketDataError::DatabaseError(e.to_string()))?;
+    let tables = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type='table'")
+        .map_err(|e| MarketDataError::DatabaseError(e.to_string()))?
         .into_iter()
         .map(|row| {
             let data = row.unwrap();
@@ -72,14 +132,76 @@ fn get_relevant_table_names(substring: &String) -> Vec<String> {
             table.to_lowercase().contains(lowercase_substring.as_str())
         })
         .map(|table| table.to_string())
-        .collect()
+        .collect();
+    Ok(tables)
 }
 
 /// Extracts the frequency from the table name
+///
+/// The longest matching frequency is used so that "15m" is not mistaken for "5m".
 fn extract_frequency_from_table_name(table_name: &String) -> String {
     INTRADAY_FREQUENCIES
         .iter()
-        .find(|&freq| table_name.contains(freq))
+        .filter(|&freq| table_name.contains(freq))
+        .max_by_key(|freq| freq.len())
         .expect("Could not extract frequency from table name")
         .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_frequency_from_table_name() {
+        let frequency = |name: &str| extract_frequency_from_table_name(&name.to_string());
+        assert_eq!(frequency("BTC-USD_1m"), "1m");
+        assert_eq!(frequency("BTC-USD_5m"), "5m");
+        assert_eq!(frequency("BTC-USD_15m"), "15m");
+        assert_eq!(frequency("BTC-USD_1d"), "1d");
+    }
+
+    #[test]
+    fn test_candles_per_year() {
+        assert_eq!(candles_per_year("1m"), Some(525_600));
+        assert_eq!(candles_per_year("6h"), Some(1460));
+        assert_eq!(candles_per_year("1d"), Some(365));
+        assert_eq!(candles_per_year("30m"), None);
+    }
+
+    #[test]
+    fn test_from_db_path_missing() {
+        let result = MarketData::from_db_path("does/not/exist.sqlite3", "BTC-USD");
+        assert!(matches!(result, Err(MarketDataError::DatabaseError(_))));
+    }
+
+    #[test]
+    fn test_with_date_range() {
+        let time = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        let times = ["2024-01-01 00:00", "2024-01-01 00:01", "2024-01-01 00:02"];
+        let df = df!(
+            "time" => times.iter().map(|t| time(t)).collect::<Vec<_>>(),
+            "close" => &[1.0, 2.0, 3.0]
+        )
+        .unwrap();
+        let market_data = MarketData {
+            asset_name: "BTC-USD".to_string(),
+            candles: HashMap::from([("1m".to_string(), df)]),
+        };
+
+        let filtered = market_data.with_date_range(Some(time("2024-01-01 00:01")), None);
+        assert_eq!(filtered.candles["1m"].height(), 2);
+
+        let filtered = filtered.with_date_range(None, Some(time("2024-01-01 00:01")));
+        assert_eq!(filtered.candles["1m"].height(), 1);
+        assert_eq!(
+            filtered.candles["1m"]
+                .column("close")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .get(0),
+            Some(2.0)
+        );
+    }
+}
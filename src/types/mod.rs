@@ -1,11 +1,13 @@
 mod candles;
 mod market;
+mod order;
 mod reason_code;
 mod signals;
 mod trades;
 
 pub use candles::Candle;
-pub use market::{MarketData, MarketDataError};
+pub use market::{candles_per_year, MarketData, MarketDataError, DB_PATH};
+pub use order::{Order, OrderStatus, OrderType};
 pub use reason_code::ReasonCode;
-pub use signals::{Side, Signal};
+pub use signals::{ScoredSignal, Side, Signal};
 pub use trades::{ExecutedTrade, FailedTrade, FutureTrade, Trade};
@@ -0,0 +1,74 @@
+use crate::types::signals::Side;
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// The type of order used to submit a trade to a market
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    /// Fill-or-kill limit order at the trade price
+    #[default]
+    Limit,
+    /// Order filled immediately at the market price. The trade price is ignored.
+    Market,
+    /// Order which is only placed once the market reaches the trade price
+    ///
+    /// Sells are triggered when the price falls to the trade price (stop-loss). Buys are triggered
+    /// when the price rises to the trade price (stop-entry).
+    Stop,
+}
+
+/// The state of an order on a market
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderStatus {
+    /// The order has been received and is not done. It may be partially filled.
+    Open,
+    /// The order is no longer on the book. See [`Order::done_reason`].
+    Done,
+    /// The order was rejected by the market. See [`Order::reject_reason`].
+    Rejected,
+}
+
+/// An order which has been placed on a market
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub id: String,
+    pub side: Side,
+    pub order_type: OrderType,
+
+    /// Limit or stop price. Zero for market orders.
+    pub price: Decimal,
+
+    /// Requested quantity of the base asset
+    pub quantity: Decimal,
+
+    /// Quantity of the base asset which has been filled
+    pub filled_quantity: Decimal,
+
+    /// Value of the filled quantity in quote currency
+    pub executed_value: Decimal,
+
+    pub status: OrderStatus,
+
+    /// Why the order is done (ie: "filled", "canceled")
+    pub done_reason: Option<String>,
+
+    /// Why the order was rejected
+    pub reject_reason: Option<String>,
+
+    pub created_at: NaiveDateTime,
+}
+
+impl Order {
+    /// Average price of the filled quantity
+    ///
+    /// Returns the order price if nothing has been filled.
+    pub fn average_price(&self) -> Decimal {
+        if self.filled_quantity.is_zero() {
+            self.price
+        } else {
+            self.executed_value / self.filled_quantity
+        }
+    }
+}
@@ -14,4 +14,6 @@ pub enum ReasonCode {
     ParseError = 4,
     /// Insufficient funds to complete trade
     InsufficientFunds = 5,
+    /// Order was placed and is waiting on the book, such as a stop order which has not been triggered
+    Resting = 6,
 }
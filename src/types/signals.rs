@@ -44,6 +44,68 @@ impl Display for Signal {
     }
 }
 
+impl Signal {
+    /// Convert a score in the range `[-1, 1]` to a [`Signal`]
+    ///
+    /// Positive scores are [`Signal::Buy`], negative scores are [`Signal::Sell`] and zero is [`Signal::Hold`].
+    pub fn from_score(score: f64) -> Self {
+        if score > 0.0 {
+            Signal::Buy
+        } else if score < 0.0 {
+            Signal::Sell
+        } else {
+            Signal::Hold
+        }
+    }
+
+    /// Score of the signal in the range `[-1, 1]`
+    ///
+    /// Discrete signals have full strength: `1` for [`Signal::Buy`] and `-1` for [`Signal::Sell`].
+    pub fn score(&self) -> f64 {
+        match self {
+            Signal::Buy => 1.0,
+            Signal::Hold => 0.0,
+            Signal::Sell => -1.0,
+        }
+    }
+}
+
+/// A [`Signal`] with the confidence of the indicator which produced it
+///
+/// Confidence is in the range `[0, 1]` and is used to weigh signals when reaching a consensus and to
+/// scale the size of trades.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoredSignal {
+    pub signal: Signal,
+    pub confidence: f64,
+}
+
+impl ScoredSignal {
+    /// Create a new [`ScoredSignal`]
+    ///
+    /// Confidence is clamped to the range `[0, 1]`. [`Signal::Hold`] always has a confidence of 0.
+    pub fn new(signal: Signal, confidence: f64) -> Self {
+        let confidence = match signal {
+            Signal::Hold => 0.0,
+            _ if confidence.is_nan() => 0.0,
+            _ => confidence.clamp(0.0, 1.0),
+        };
+        Self { signal, confidence }
+    }
+
+    /// Score of the signal in the range `[-1, 1]`
+    pub fn score(&self) -> f64 {
+        self.signal.score() * self.confidence
+    }
+}
+
+impl From<Signal> for ScoredSignal {
+    /// Discrete signals have full confidence
+    fn from(signal: Signal) -> Self {
+        Self::new(signal, 1.0)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 /// Abstracts types of trades
 pub enum Side {
@@ -112,6 +174,27 @@ impl From<i8> for Side {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_signal_score() {
+        assert_eq!(Signal::from_score(0.3), Signal::Buy);
+        assert_eq!(Signal::from_score(-0.3), Signal::Sell);
+        assert_eq!(Signal::from_score(0.0), Signal::Hold);
+
+        for signal in [Signal::Buy, Signal::Hold, Signal::Sell] {
+            assert_eq!(Signal::from_score(signal.score()), signal);
+        }
+    }
+
+    #[test]
+    fn test_scored_signal() {
+        let signal = ScoredSignal::new(Signal::Sell, 0.25);
+        assert_eq!(signal.score(), -0.25);
+
+        assert_eq!(ScoredSignal::new(Signal::Buy, 1.5).confidence, 1.0);
+        assert_eq!(ScoredSignal::new(Signal::Hold, 0.5).confidence, 0.0);
+        assert_eq!(ScoredSignal::from(Signal::Buy).score(), 1.0);
+    }
+
     #[test]
     fn test_side_from_i8() {
         assert_eq!(Side::from(1), Side::Buy);
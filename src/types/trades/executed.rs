@@ -1,3 +1,4 @@
+use crate::types::order::OrderType;
 use crate::types::signals::Side;
 use crate::types::trades::future::FutureTrade;
 use crate::types::trades::{calc_notional_value, Trade};
@@ -13,6 +14,7 @@ pub struct ExecutedTrade {
     quantity: Decimal,
     notional_value: Decimal,
     timestamp: NaiveDateTime,
+    order_type: OrderType,
 }
 
 impl ExecutedTrade {
@@ -31,6 +33,7 @@ impl ExecutedTrade {
             quantity,
             notional_value,
             timestamp,
+            order_type: OrderType::default(),
         }
     }
 
@@ -53,6 +56,7 @@ impl ExecutedTrade {
             quantity,
             notional_value,
             timestamp,
+            order_type: OrderType::default(),
         }
     }
 
@@ -64,12 +68,24 @@ impl ExecutedTrade {
             quantity: trade.get_quantity(),
             notional_value: trade.get_notional_value(),
             timestamp: trade.get_timestamp().clone(),
+            order_type: trade.get_order_type(),
         }
     }
 
+    /// Builder method for the `order_type` field
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
     pub fn get_order_id(&self) -> &String {
         &self.order_id
     }
+
+    /// The type of order which was filled. Used to determine the liquidity of the fee.
+    pub fn get_order_type(&self) -> OrderType {
+        self.order_type
+    }
 }
 
 impl Trade for ExecutedTrade {
@@ -169,7 +185,8 @@ mod test {
             execution_price,
             execution_quantity,
             execution_timestamp.clone(),
-        );
+        )
+        .with_order_type(OrderType::Market);
 
         let executed_trade = ExecutedTrade::from_future_trade(order_id.clone(), future_trade);
 
@@ -179,5 +196,6 @@ mod test {
         assert_eq!(executed_trade.quantity, execution_quantity);
         assert_eq!(executed_trade.notional_value, notional_value);
         assert_eq!(executed_trade.timestamp, execution_timestamp);
+        assert_eq!(executed_trade.get_order_type(), OrderType::Market);
     }
 }
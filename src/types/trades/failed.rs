@@ -6,6 +6,7 @@ use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
 
 /// Represents a trade that has been rejected by the market or otherwise failed
+#[derive(Debug, Clone, PartialEq)]
 pub struct FailedTrade {
     reason: ReasonCode,
     side: Side,
@@ -44,6 +45,10 @@ impl FailedTrade {
             point: trade.get_timestamp().clone(),
         }
     }
+
+    pub fn get_reason(&self) -> ReasonCode {
+        self.reason
+    }
 }
 
 impl Trade for FailedTrade {
@@ -1,3 +1,4 @@
+use crate::types::order::OrderType;
 use crate::types::signals::Side;
 use crate::types::trades::{calc_notional_value, Trade};
 use chrono::NaiveDateTime;
@@ -12,6 +13,7 @@ pub struct FutureTrade {
     cost: Decimal,
     /// The time at which the trade was identified
     point: NaiveDateTime,
+    order_type: OrderType,
 }
 
 impl FutureTrade {
@@ -24,6 +26,7 @@ impl FutureTrade {
             quantity,
             cost,
             point,
+            order_type: OrderType::default(),
         }
     }
 
@@ -40,8 +43,19 @@ impl FutureTrade {
             quantity,
             cost,
             point,
+            order_type: OrderType::default(),
         }
     }
+
+    /// Builder method for the order type used to submit the trade
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    pub fn get_order_type(&self) -> OrderType {
+        self.order_type
+    }
 }
 
 impl Trade for FutureTrade {
@@ -68,6 +82,7 @@ impl Trade for FutureTrade {
 
 #[cfg(test)]
 mod tests {
+    use crate::types::order::OrderType;
     use crate::types::signals::Side;
     use crate::types::trades::future::FutureTrade;
     use crate::types::trades::Trade;
@@ -88,5 +103,14 @@ mod tests {
         assert_eq!(trade.get_quantity(), quantity);
         assert_eq!(trade.get_notional_value(), price * quantity);
         assert_eq!(trade.get_timestamp(), &point);
+        assert_eq!(trade.get_order_type(), OrderType::Limit);
+    }
+
+    #[test]
+    fn test_with_order_type() {
+        let point = Utc::now().naive_utc();
+        let trade = FutureTrade::new(Side::Sell, dec!(1.0), dec!(2.0), point)
+            .with_order_type(OrderType::Stop);
+        assert_eq!(trade.get_order_type(), OrderType::Stop);
     }
 }
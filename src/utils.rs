@@ -9,6 +9,7 @@ use sqlite::Connection;
 use std::env::temp_dir;
 use std::fs::{create_dir_all, remove_dir_all};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// create temp dir for testing
 pub fn create_temp_dir(dir: &Path) -> PathBuf {
@@ -50,7 +51,7 @@ pub fn extract_new_rows(updated: &DataFrame, data: &DataFrame) -> DataFrame {
 pub fn extract_candles_from_db(db_path: &str, table_name: &str) -> Result<Vec<Candle>, ()> {
     let conn = Connection::open(db_path).unwrap();
 
-    let query = format!("SELECT * FROM {}", table_name);
+    let query = format!("SELECT * FROM \"{}\"", table_name);
     let results = conn
         .prepare(query)
         .unwrap()
@@ -147,6 +148,22 @@ pub fn print_candle_statistics(candles: &DataFrame) {
     info!("Candles range: {:?} - {:?}", candle_start, candle_end);
 }
 
+/// Convert a candle frequency label (ie: "1m", "6h", "1d") into a [`Duration`]
+///
+/// Returns `None` if the label is not a positive number followed by one of `m`, `h` or `d`.
+pub fn frequency_to_duration(frequency: &str) -> Option<Duration> {
+    let unit = frequency.chars().last()?;
+    let amount = &frequency[..frequency.len() - unit.len_utf8()];
+    let amount = amount.parse::<u64>().ok().filter(|x| *x > 0)?;
+    let seconds = match unit {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 60 * 60 * 24,
+        _ => return None,
+    };
+    Some(Duration::from_secs(amount * seconds))
+}
+
 #[derive(Debug)]
 pub enum AlignmentError {
     DifferentLengths,
@@ -182,10 +199,94 @@ pub fn trim_candles(candles: &DataFrame, end_time: NaiveDateTime, length: IdxSiz
         .unwrap()
 }
 
+/// Keep the last `length` candles which have closed by `end_time`
+///
+/// A candle opened at `time` closes at `time + interval`. Candles which are still forming at `end_time` are
+/// dropped so that higher timeframe candles can be aligned with lower timeframe candles without look-ahead.
+///
+/// # Arguments
+/// * `candles` - Candles of a single interval in any order
+/// * `end_time` - Candles closing after this time are dropped
+/// * `interval` - Duration of a single candle
+/// * `length` - Maximum number of candles to return
+///
+/// # Returns
+/// The closed candles sorted in ascending order
+pub fn closed_candles(
+    candles: &DataFrame,
+    end_time: NaiveDateTime,
+    interval: Duration,
+    length: IdxSize,
+) -> DataFrame {
+    let last_open = end_time - chrono::Duration::from_std(interval).unwrap();
+    candles
+        .clone()
+        .lazy()
+        .filter(col("time").lt_eq(lit(last_open)))
+        .sort(["time"], SortMultipleOptions::default())
+        .tail(length)
+        .collect()
+        .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::extract_new_rows;
+    use crate::utils::{closed_candles, extract_new_rows, frequency_to_duration};
+    use chrono::NaiveDateTime;
     use polars::prelude::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_closed_candles() {
+        let time = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        // descending, as stored by `CandleManager`
+        let candles = df!(
+            "time" => &[time("2024-01-01 02:00"), time("2024-01-01 01:00"), time("2024-01-01 00:00")],
+            "close" => &[3.0, 2.0, 1.0]
+        )
+        .unwrap();
+        let hour = Duration::from_secs(3600);
+
+        // the 01:00 candle is still forming at 01:59
+        let closed = closed_candles(&candles, time("2024-01-01 01:59"), hour, 100);
+        assert_eq!(closed.height(), 1);
+
+        // the 01:00 candle closes at 02:00
+        let closed = closed_candles(&candles, time("2024-01-01 02:00"), hour, 100);
+        assert_eq!(
+            closed.column("close").unwrap().f64().unwrap().to_vec(),
+            [Some(1.0), Some(2.0)]
+        );
+
+        let closed = closed_candles(&candles, time("2024-01-01 03:00"), hour, 2);
+        assert_eq!(
+            closed.column("close").unwrap().f64().unwrap().to_vec(),
+            [Some(2.0), Some(3.0)]
+        );
+
+        let closed = closed_candles(&candles, time("2024-01-01 00:59"), hour, 100);
+        assert_eq!(closed.height(), 0);
+    }
+
+    #[test]
+    fn test_frequency_to_duration() {
+        assert_eq!(frequency_to_duration("1m"), Some(Duration::from_secs(60)));
+        assert_eq!(frequency_to_duration("15m"), Some(Duration::from_secs(900)));
+        assert_eq!(
+            frequency_to_duration("6h"),
+            Some(Duration::from_secs(21600))
+        );
+        assert_eq!(
+            frequency_to_duration("1d"),
+            Some(Duration::from_secs(86400))
+        );
+
+        assert_eq!(frequency_to_duration(""), None);
+        assert_eq!(frequency_to_duration("0m"), None);
+        assert_eq!(frequency_to_duration("1w"), None);
+        assert_eq!(frequency_to_duration("m"), None);
+    }
 
     /// Test that extract_new_rows() returns the correct rows
     #[test]